@@ -0,0 +1,44 @@
+<?php
+
+// Benchmarks of array and string operations. Run with
+// `php -d extension=<path to libskel> bench.php`.
+
+// Compares converting a list of floats through the packed array fast path with the
+// generic conversion.
+
+$values = array_map('floatval', range(1, 100000));
+$iterations = 100;
+
+foreach (['skel_sum_packed', 'skel_sum_generic'] as $fn) {
+    $start = hrtime(true);
+    for ($i = 0; $i < $iterations; $i++) {
+        $sum = $fn($values);
+    }
+    $elapsed = (hrtime(true) - $start) / 1e6 / $iterations;
+
+    printf("%-16s %.3f ms per call (sum = %.0f)\n", $fn, $elapsed, $sum);
+}
+
+// Compares building a list of one million integers with and without allocating the array
+// up front.
+$n = 1000000;
+foreach (['growing' => 0, 'preallocated' => $n] as $name => $capacity) {
+    $start = hrtime(true);
+    for ($i = 0; $i < 10; $i++) {
+        $list = skel_build_list($n, $capacity);
+    }
+    $elapsed = (hrtime(true) - $start) / 1e6 / 10;
+
+    printf("%-16s %.3f ms per call (count = %d)\n", $name, $elapsed, count($list));
+}
+
+// Compares building a 2 MB string by appending to it in place with copying the whole string
+// for each append.
+$chunk = str_repeat('x', 1024);
+foreach (['append', 'naive'] as $mode) {
+    $start = hrtime(true);
+    $string = skel_build_string($chunk, 2048, $mode);
+    $elapsed = (hrtime(true) - $start) / 1e6;
+
+    printf("%-16s %.3f ms per call (length = %d)\n", $mode, $elapsed, strlen($string));
+}
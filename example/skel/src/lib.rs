@@ -1,23 +1,48 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt::Write,
+    panic::{self, AssertUnwindSafe},
+};
+
 use ext_php_rs::{
-    call_user_func, info_table_end, info_table_row, info_table_start,
+    call_user_func,
+    errors::Error,
     php::{
-        args::{Arg, ArgParser},
-        class::ClassBuilder,
-        enums::DataType,
+        args::{Arg, ArgError, ArgParser, RefArg},
+        class::{ClassBuilder, ClassEntry},
+        enums::{DataType, KnownString},
+        errors,
         execution_data::ExecutionData,
-        flags::MethodFlags,
+        flags::{ConstantFlags, MethodFlags, PropertyFlags, SortFlags},
         function::FunctionBuilder,
-        module::{ModuleBuilder, ModuleEntry},
-        types::{array::ZendHashTable, long::ZendLong, object::ZendClassObject, zval::Zval},
+        globals::{ExecutorGlobals, ModuleGlobals},
+        info::ModuleInfo,
+        ini::{ini_get_bool, ini_get_long, IniBuilder},
+        module::ModuleBuilder,
+        serde::{from_zval, to_zval},
+        serialize::{AllowedClasses, UnserializeOptions},
+        types::{
+            array::{ArrayBuilder, ArrayKey, ZendHashTable},
+            long::ZendLong,
+            object::ZendClassObject,
+            smart_str::SmartStr,
+            string::ZendString,
+            zval::{IntoZval, Zval},
+        },
     },
-    ZendObjectHandler,
+    php_function, php_module, ZendObjectHandler,
 };
+use serde::{Deserialize, Serialize};
 
-#[no_mangle]
-pub extern "C" fn php_module_info(_module: *mut ModuleEntry) {
-    info_table_start!();
-    info_table_row!("skeleton extension", "enabled");
-    info_table_end!();
+fn module_info(info: &mut ModuleInfo) {
+    info.table()
+        .header(&["skeleton extension", "enabled"])
+        .row("version", "0.1.0")
+        .row("requests", GLOBALS.get().requests)
+        .row("motto", "<Rust> & \"PHP\"")
+        .end();
+    info.print_box("Built with ext-php-rs");
 }
 
 #[derive(Debug, ZendObjectHandler)]
@@ -53,6 +78,34 @@ impl Test {
         dbg!(x.a);
     }
 
+    /// Counts the calls in the `touched` property of `$this`, returning the class of `$this`.
+    pub extern "C" fn touch(execute_data: &mut ExecutionData, retval: &mut Zval) {
+        let this = match execute_data.get_this_mut() {
+            Some(this) => this,
+            None => return,
+        };
+
+        let touched = this.get_property::<ZendLong>("touched").unwrap_or_default();
+        if this.set_property("touched", touched + 1).is_ok() {
+            let _ = retval.set_string(this.class_name());
+        }
+    }
+
+    /// Returns the value of a property of `$this`, or `<missing>` if it does not exist.
+    pub extern "C" fn prop(execute_data: &mut ExecutionData, retval: &mut Zval) {
+        let (name,) = match execute_data.parse_args().arg::<String>("name").finish() {
+            Ok(args) => args,
+            Err(_) => return,
+        };
+
+        match execute_data.get_self_property(&name) {
+            Some(val) => *retval = val,
+            None => {
+                let _ = retval.set_string("<missing>");
+            }
+        }
+    }
+
     pub extern "C" fn call(execute_data: &mut ExecutionData, _retval: &mut Zval) {
         let mut _fn = Arg::new("fn", DataType::Callable);
         let result = ArgParser::new(execute_data).arg(&mut _fn).parse();
@@ -63,8 +116,13 @@ impl Test {
 
         let result = call_user_func!(_fn, "Hello", 5);
 
-        if let Some(r) = result {
-            println!("{}", r.string().unwrap());
+        match result {
+            Ok(r) => println!("{}", r.coerce_to_string().unwrap_or_default()),
+            Err(Error::Exception(e)) => {
+                println!("Callable threw {}: {} ({})", e.class, e.message, e.code);
+                ExecutorGlobals::clear_exception();
+            }
+            Err(e) => println!("Call failed: {}", e),
         }
 
         println!("Ready for call!");
@@ -77,10 +135,22 @@ impl Default for Test {
     }
 }
 
+/// Lookup table built when the module starts, which is shared by every request.
+static mut LOOKUP: Option<ZendHashTable> = None;
+
 #[no_mangle]
 pub extern "C" fn module_init(_type: i32, _module_number: i32) -> i32 {
     // object_handlers_init!(Test);
 
+    let mut lookup = ZendHashTable::new_persistent();
+    for (key, val) in [("rust", "Persistent string"), ("php", "Survives requests")] {
+        let mut zv = Zval::from(());
+        let _ = zv.set_persistent_string(val);
+        let _ = lookup.insert(key, zv);
+    }
+    let _ = lookup.insert("answer", 42);
+    unsafe { LOOKUP = Some(lookup) };
+
     ClassBuilder::new("TestClass")
         .method(
             FunctionBuilder::constructor(Test::constructor).build(),
@@ -94,6 +164,24 @@ pub extern "C" fn module_init(_type: i32, _module_number: i32) -> i32 {
             FunctionBuilder::new("get", Test::get).build(),
             MethodFlags::Public,
         )
+        .method(
+            FunctionBuilder::new("touch", Test::touch)
+                .returns(DataType::String, false, false)
+                .build(),
+            MethodFlags::Public,
+        )
+        .method(
+            FunctionBuilder::new("prop", Test::prop)
+                .arg(Arg::new("name", DataType::String))
+                .build(),
+            MethodFlags::Public,
+        )
+        .method(
+            FunctionBuilder::new("describe", skeleton_describe)
+                .returns(DataType::String, false, false)
+                .build(),
+            MethodFlags::Public | MethodFlags::Static,
+        )
         .method(
             FunctionBuilder::new("call", Test::call)
                 .arg(Arg::new("fn", DataType::Callable))
@@ -102,14 +190,512 @@ pub extern "C" fn module_init(_type: i32, _module_number: i32) -> i32 {
         )
         // .property("value", "world", PropertyFlags::Protected)
         .constant("TEST", "Hello world")
+        .unwrap()
         .object_override::<Test>()
         .build();
 
     0
 }
 
-#[no_mangle]
-pub extern "C" fn get_module() -> *mut ext_php_rs::php::module::ModuleEntry {
+/// Frees the lookup table built when the module started.
+fn module_shutdown(_module_number: i32) -> Result<(), Error> {
+    unsafe { LOOKUP = None };
+    Ok(())
+}
+
+/// `skel_add(int $a, ?int $b = null): int`
+#[php_function(name = "skel_add")]
+pub fn add(a: ZendLong, b: Option<ZendLong>) -> ZendLong {
+    a + b.unwrap_or(1)
+}
+
+/// `skel_increment(&$value, int $by = 1): void`
+#[php_function(name = "skel_increment", by_ref(value))]
+pub fn increment(mut value: RefArg, by: Option<ZendLong>) {
+    let next = value.val::<ZendLong>().unwrap_or(0) + by.unwrap_or(1);
+    let _ = value.set(next);
+}
+
+/// `skel_lists(array $strings, array $longs, array $doubles, array $nested): array`, converting
+/// each list into a vector and back into a PHP array.
+#[php_function]
+pub fn skel_lists(
+    strings: Vec<String>,
+    longs: Vec<ZendLong>,
+    doubles: Vec<f64>,
+    nested: Vec<Vec<ZendLong>>,
+) -> Option<Vec<Zval>> {
+    Some(vec![
+        Zval::try_from(strings).ok()?,
+        Zval::try_from(longs).ok()?,
+        Zval::try_from(doubles).ok()?,
+        Zval::try_from(nested).ok()?,
+    ])
+}
+
+/// `skel_index_keys(array $keys): array`, mapping each key to its position in the list.
+#[php_function]
+pub fn skel_index_keys(keys: Vec<String>) -> HashMap<String, ZendLong> {
+    keys.into_iter()
+        .enumerate()
+        .map(|(i, key)| (key, i as ZendLong))
+        .collect()
+}
+
+/// `skel_entries(array $array): array`, returning the `[$key, $value]` pairs of the array in
+/// the order they are walked by [`ZendHashTable::iter`].
+#[php_function]
+pub fn skel_entries(array: &Zval) -> Option<Vec<(Zval, Zval)>> {
+    array
+        .array()?
+        .iter()
+        .map(|(key, val)| {
+            let mut zv = Zval::new();
+            match key {
+                ArrayKey::Long(idx) => zv.set_long(idx as ZendLong),
+                ArrayKey::Str(key) => zv.set_binary(key).ok()?,
+            }
+            Some((zv, val.shallow_clone()))
+        })
+        .collect()
+}
+
+/// `skel_words(string $text): array`, collecting the words of the text into a list.
+#[php_function]
+pub fn skel_words(text: String) -> Option<Zval> {
+    Zval::try_from_iter(text.split_whitespace().map(String::from)).ok()
+}
+
+/// `skel_word_lengths(string $text): array`, mapping each word of the text to its length.
+#[php_function]
+pub fn skel_word_lengths(text: String) -> Option<Zval> {
+    Zval::try_from_assoc_iter(
+        text.split_whitespace()
+            .map(|word| (word, word.len() as u64)),
+    )
+    .ok()
+}
+
+/// `skel_chars(string $text): array`, collecting the characters of the text into a list.
+#[php_function]
+pub fn skel_chars(text: String) -> Zval {
+    text.chars().collect()
+}
+
+/// The globals of the extension, which each thread has a copy of when PHP is built with
+/// thread safety.
+#[derive(Default)]
+pub struct SkelGlobals {
+    requests: ZendLong,
+    initialized: bool,
+}
+
+static GLOBALS: ModuleGlobals<SkelGlobals> = ModuleGlobals::new();
+
+fn globals_init(globals: &mut SkelGlobals) {
+    globals.initialized = true;
+}
+
+fn request_startup(_module_number: i32) -> Result<(), Error> {
+    unsafe { GLOBALS.get_mut() }.requests += 1;
+    Ok(())
+}
+
+fn register_skel_limits() -> Result<(), Error> {
+    ClassBuilder::new("SkelLimits")
+        .constant("MAX", ZendLong::MAX)?
+        .constant("NAME", "limits")?
+        .constant("MODE_FAST", 1)?
+        .constant("RATIO", 0.5)?
+        .constant("STRICT", true)?
+        .constant("NOTHING", ())?
+        .constant_with_flags("STEP", 10, ConstantFlags::Protected)?
+        .constant_with_flags("SECRET", "hidden", ConstantFlags::Private)?
+        .build();
+    Ok(())
+}
+
+/// `Skel\Util\Counter::increment(): int`, adding `$this->step` to the total.
+pub extern "C" fn counter_increment(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let this = match execute_data.get_this_mut() {
+        Some(this) => this,
+        None => return,
+    };
+
+    let step = this.get_property::<ZendLong>("step").unwrap_or(1);
+    let total = this.get_property::<ZendLong>("total").unwrap_or_default() + step;
+
+    if this.set_property("total", total).is_ok() {
+        retval.set_long(total);
+    }
+}
+
+/// `Skel\Util\Counter::count(): int`
+pub extern "C" fn counter_count(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if let Some(this) = execute_data.get_this_mut() {
+        retval.set_long(this.get_property::<ZendLong>("total").unwrap_or_default());
+    }
+}
+
+/// `Skel\Util\Counter::__toString(): string`
+pub extern "C" fn counter_to_string(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if let Some(this) = execute_data.get_this_mut() {
+        let label = this.get_property::<String>("label").unwrap_or_default();
+        let total = this.get_property::<ZendLong>("total").unwrap_or_default();
+
+        let _ = retval.set_string(format!("{}: {}", label, total));
+    }
+}
+
+/// `Skel\Util\Counter::jsonSerialize(): mixed`, encoding the counter as its total.
+pub extern "C" fn counter_json_serialize(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    counter_count(execute_data, retval);
+}
+
+fn register_counter() -> Result<(), Error> {
+    let countable = ClassEntry::countable().ok_or(Error::InvalidPointer)?;
+    let stringable = ClassEntry::stringable().ok_or(Error::InvalidPointer)?;
+
+    ClassBuilder::new("Skel\\Util\\Counter")
+        .implements(countable)
+        .implements(stringable)
+        .implements_named("JsonSerializable")?
+        .property("step", 1, PropertyFlags::Public)?
+        .property("label", "counter", PropertyFlags::Public)?
+        .property("total", 0, PropertyFlags::Protected)?
+        .method(
+            FunctionBuilder::new("increment", counter_increment)
+                .returns(DataType::Long, false, false)
+                .build(),
+            MethodFlags::Public,
+        )
+        .method(
+            FunctionBuilder::new("count", counter_count)
+                .returns(DataType::Long, false, false)
+                .build(),
+            MethodFlags::Public,
+        )
+        .method(
+            FunctionBuilder::new("__toString", counter_to_string)
+                .returns(DataType::String, false, false)
+                .build(),
+            MethodFlags::Public,
+        )
+        .method(
+            FunctionBuilder::new("jsonSerialize", counter_json_serialize)
+                .returns(DataType::Mixed, false, false)
+                .build(),
+            MethodFlags::Public,
+        )
+        .build();
+    Ok(())
+}
+
+/// `Skel\Util\Incomplete`, which implements `Countable` without a `count()` method.
+fn register_incomplete() -> Result<(), Error> {
+    ClassBuilder::new("Skel\\Util\\Incomplete")
+        .implements_named("countable")?
+        .build();
+    Ok(())
+}
+
+/// `Skel\Util\Settings`, a class with typed and static properties and array defaults.
+fn register_settings() -> Result<(), Error> {
+    let tags = ArrayBuilder::new()
+        .push("rust")
+        .insert("nested", vec![1, 2, 3])
+        .build()?;
+
+    ClassBuilder::new("Skel\\Util\\Settings")
+        .typed_property(
+            "name",
+            DataType::String,
+            false,
+            "skel",
+            PropertyFlags::Public,
+        )?
+        .typed_property("owner", DataType::String, true, (), PropertyFlags::Public)?
+        .typed_property("ratio", DataType::Double, false, 1, PropertyFlags::Public)?
+        .typed_property("tags", DataType::Array, false, tags, PropertyFlags::Public)?
+        .uninitialized_property("id", DataType::Long, false, PropertyFlags::Public)?
+        .property("extra", vec!["a", "b"], PropertyFlags::Protected)?
+        .static_property("defaults", vec![1, 2], PropertyFlags::Public)?
+        .typed_property(
+            "instances",
+            DataType::Long,
+            false,
+            0,
+            PropertyFlags::Public | PropertyFlags::Static,
+        )?
+        .build();
+    Ok(())
+}
+
+/// `Skel\Util\Shape::create(): string`, a static method returning the name of the class it
+/// was called on.
+pub extern "C" fn shape_create(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let called = execute_data
+        .get_called_scope()
+        .and_then(|ce| unsafe { ce.name.as_ref() })
+        .map(String::from)
+        .unwrap_or_default();
+
+    // Static methods are called without `$this`.
+    if execute_data.is_static_call() {
+        let _ = retval.set_string(called);
+    }
+}
+
+/// `Skel\Util\Shape::describe(): string`, a final method.
+pub extern "C" fn shape_describe(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if let Some(this) = execute_data.get_this() {
+        let _ = retval.set_string(format!("{} shape", this.class_name()));
+    }
+}
+
+/// `Skel\Util\Shape::helper(): string` and `Skel\Util\Shape::secret(): string`, which are
+/// protected and private.
+pub extern "C" fn shape_name(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let _ = retval.set_string(execute_data.function_name().unwrap_or_default());
+}
+
+fn register_shape() -> Result<(), Error> {
+    let string = |name, handler| {
+        FunctionBuilder::new(name, handler)
+            .returns(DataType::String, false, false)
+            .build()
+    };
+
+    ClassBuilder::new("Skel\\Util\\Shape")
+        .method(
+            FunctionBuilder::new_abstract("area")
+                .returns(DataType::Double, false, false)
+                .build(),
+            MethodFlags::Public | MethodFlags::Abstract,
+        )
+        .method(string("create", shape_create), MethodFlags::Static)
+        .method(
+            string("describe", shape_describe),
+            MethodFlags::Public | MethodFlags::Final,
+        )
+        .method(string("helper", shape_name), MethodFlags::Protected)
+        .method(string("secret", shape_name), MethodFlags::Private)
+        .build();
+    Ok(())
+}
+
+/// `Skel\Util\SkelException`, the exception thrown by the extension.
+fn register_exception() -> Result<(), Error> {
+    let exception = ClassEntry::exception().ok_or(Error::InvalidPointer)?;
+
+    ClassBuilder::new("Skel\\Util\\SkelException")
+        .extends(exception)
+        .constant("NOT_FOUND", 404)?
+        .build();
+    Ok(())
+}
+
+/// `skel_fail(string $message, int $code): void`, throwing a `Skel\Util\SkelException`.
+#[php_function]
+pub fn skel_fail(message: String, code: i64) {
+    if let Some(class) = ClassEntry::try_find("Skel\\Util\\SkelException") {
+        errors::throw(class, &message, code);
+    }
+}
+
+/// `skel_throw(string $class, string $message): ?string`, throwing an internal class. Returns
+/// the error if the class cannot be thrown.
+#[php_function]
+pub fn skel_throw(class: String, message: String) -> Option<String> {
+    let class = ClassEntry::try_find(&class)?;
+
+    match errors::throw(class, &message, 0) {
+        Error::Exception(_) => None,
+        e => Some(e.to_string()),
+    }
+}
+
+/// `skel_coerce_string(mixed $value): ?string`, converting the value as a `(string)` cast
+/// would. Returns `null` for strings which are not valid UTF-8.
+#[php_function]
+pub fn skel_coerce_string(value: &Zval) -> Option<String> {
+    value.coerce_to_string().ok()
+}
+
+/// `skel_coerce_long(mixed $value): ?int`, converting the value as an `(int)` cast would.
+#[php_function]
+pub fn skel_coerce_long(value: &Zval) -> Option<ZendLong> {
+    value.coerce_to_long().ok()
+}
+
+/// `skel_swap(array $pair): array`, swapping the elements of a `[string, int]` pair.
+#[php_function]
+pub fn skel_swap(pair: (String, ZendLong)) -> (ZendLong, String) {
+    (pair.1, pair.0)
+}
+
+/// `skel_split(array $parts): array`, returning the elements of a `[string, float, list]` triple
+/// in reverse order.
+#[php_function]
+pub fn skel_split(parts: (String, f64, Vec<bool>)) -> (Vec<bool>, f64, String) {
+    (parts.2, parts.1, parts.0)
+}
+
+/// `skel_unsigned(int $value): int`, returning the value as an unsigned integer. Negative values
+/// wrap around past `PHP_INT_MAX`, so returning them throws a `ValueError`.
+#[php_function]
+pub fn skel_unsigned(value: ZendLong) -> u64 {
+    value as u64
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Order {
+    id: u32,
+    items: Vec<Item>,
+    tags: BTreeMap<String, String>,
+    status: Status,
+    note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Item {
+    sku: String,
+    quantity: u8,
+    price: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Status {
+    Pending,
+    Shipped(String),
+    Cancelled { reason: String, refunded: bool },
+}
+
+/// `skel_order(array|object $order): ?array`, converting an order into a Rust struct and back
+/// through `serde`. Returns `null` if the order is invalid.
+#[php_function]
+pub fn skel_order(order: &Zval) -> Option<Zval> {
+    let order: Order = from_zval(order).ok()?;
+    to_zval(&order).ok()
+}
+
+/// `skel_convert(mixed $value, string $target): mixed`, converting a copy of the value in
+/// place into an `int`, `float`, `bool`, `string` or `array`.
+#[php_function]
+pub fn skel_convert(value: &Zval, target: String) -> Option<Zval> {
+    let mut copy = value.shallow_clone();
+    let result = match target.as_str() {
+        "int" => copy.convert_to_long(),
+        "float" => copy.convert_to_double(),
+        "bool" => copy.convert_to_bool(),
+        "string" => copy.convert_to_string(),
+        "array" => copy.convert_to_array(),
+        _ => Err(Error::ZvalConversion),
+    };
+
+    match result {
+        Ok(()) => Some(copy),
+        Err(_) => {
+            copy.release();
+            None
+        }
+    }
+}
+
+/// `skel_compare_pending(mixed $a, mixed $b): ?int`, comparing the values while an exception
+/// is pending, which is cleared before returning.
+#[php_function]
+pub fn skel_compare_pending(a: &Zval, b: &Zval) -> Option<ZendLong> {
+    errors::throw(ClassEntry::exception()?, "pending", 0);
+    let result = a.compare(b);
+    ExecutorGlobals::clear_exception();
+    result.ok().map(|ord| ord as ZendLong)
+}
+
+/// `skel_new_counter(): ?Skel\Util\Counter`, creating a counter from Rust.
+#[php_function]
+pub fn skel_new_counter() -> Option<Zval> {
+    ClassEntry::try_find("skel\\util\\counter")?
+        .new_object()
+        .ok()
+}
+
+/// `skel_is_counter(mixed $value): bool`
+#[php_function]
+pub fn skel_is_counter(value: &Zval) -> bool {
+    match (value.object(), ClassEntry::try_find("Skel\\Util\\Counter")) {
+        (Some(obj), Some(counter)) => obj.instance_of(counter),
+        _ => false,
+    }
+}
+
+/// `skel_requests(): int`
+#[php_function(name = "skel_requests")]
+pub fn requests() -> ZendLong {
+    GLOBALS.get().requests
+}
+
+/// `skel_globals_initialized(): bool`
+#[php_function]
+pub fn skel_globals_initialized() -> bool {
+    GLOBALS.get().initialized
+}
+
+/// `skel_cache_size(): int`
+#[php_function]
+pub fn skel_cache_size() -> ZendLong {
+    ini_get_long("skel.cache_size").unwrap_or(0)
+}
+
+/// `skel_enabled(): bool`
+#[php_function]
+pub fn skel_enabled() -> bool {
+    ini_get_bool("skel.enabled").unwrap_or(false)
+}
+
+/// `skel_known_strings(): array`, the contents of some of the strings known to the engine,
+/// which are only included if they are interned.
+#[php_function]
+pub fn skel_known_strings() -> Vec<String> {
+    [KnownString::File, KnownString::Name, KnownString::Mixed]
+        .iter()
+        .map(|id| ZendString::known(*id))
+        .filter(|known| known.is_interned())
+        .map(String::from)
+        .collect()
+}
+
+/// `skel_append_known(string $suffix): ?string`, appending to the known string `name`. The
+/// result is only returned if it was copied into a request string.
+#[php_function]
+pub fn skel_append_known(suffix: String) -> Option<String> {
+    let known = ZendString::known(KnownString::Name) as *const _ as *mut _;
+    let appended = unsafe { ZendString::from_raw(known) }?
+        .append(suffix.as_bytes())
+        .ok()?;
+
+    if appended.is_interned() || appended.is_persistent() {
+        return None;
+    }
+
+    Some(String::from(&appended))
+}
+
+/// `Skel\Util\reverse(string $string): string`
+#[php_function(name = "Skel\\Util\\reverse")]
+pub fn util_reverse(string: String) -> String {
+    string.chars().rev().collect()
+}
+
+/// `Skel\Util\upper(string $string): string`
+pub extern "C" fn util_upper(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if let Ok((string,)) = execute_data.parse_args().arg::<String>("string").finish() {
+        let _ = retval.set_string(string.to_uppercase());
+    }
+}
+
+#[php_module]
+pub fn get_module() -> ModuleBuilder {
     let funct = FunctionBuilder::new("skeleton_version", skeleton_version)
         .arg(Arg::new("a", DataType::Long))
         .arg(Arg::new("b", DataType::Double))
@@ -118,62 +704,1162 @@ pub extern "C" fn get_module() -> *mut ext_php_rs::php::module::ModuleEntry {
         .returns(DataType::String, false, false)
         .build();
 
+    let legacy_upper = FunctionBuilder::new("skel_legacy_upper", util_upper)
+        .arg(Arg::new("string", DataType::String))
+        .returns(DataType::String, false, false)
+        .deprecated()
+        .build();
+
+    let repeat = FunctionBuilder::new("skel_repeat", skeleton_repeat)
+        .arg(Arg::new("string", DataType::String))
+        .arg(Arg::new("times", DataType::Long))
+        .returns(DataType::String, false, false)
+        .build();
+
+    let truncate = FunctionBuilder::new("skel_truncate", skeleton_truncate)
+        .arg(Arg::new("string", DataType::String))
+        .not_required()
+        .arg(Arg::new("limit", DataType::Long).default_value(10))
+        .arg(Arg::new("suffix", DataType::String).default_value("..."))
+        .returns(DataType::String, false, false)
+        .build();
+
+    let format = FunctionBuilder::new("skel_format", skeleton_format)
+        .arg(Arg::new("format", DataType::String))
+        .arg(Arg::new("values", DataType::Mixed).variadic())
+        .returns(DataType::String, false, false)
+        .build();
+
+    let sum_ints = FunctionBuilder::new("skel_sum_ints", skeleton_sum_ints)
+        .arg(Arg::new("nums", DataType::Long).variadic())
+        .returns(DataType::Long, false, false)
+        .build();
+
+    let fill = FunctionBuilder::new("skel_fill", skeleton_fill)
+        .arg(Arg::new("out", DataType::Mixed).as_ref())
+        .build();
+
+    let arg_types = FunctionBuilder::new("skel_arg_types", skeleton_arg_types)
+        .arg(Arg::new("args", DataType::Mixed).variadic())
+        .build();
+
+    let describe = FunctionBuilder::new("skel_describe", skeleton_describe)
+        .returns(DataType::String, false, false)
+        .build();
+
+    let named_args = FunctionBuilder::new("skel_named_args", skeleton_named_args)
+        .arg(Arg::new("args", DataType::Long).variadic())
+        .returns(DataType::String, false, false)
+        .build();
+
+    let greet = FunctionBuilder::new("skel_greet", skeleton_greet)
+        .not_required()
+        .arg(
+            Arg::new("name", DataType::String)
+                .allow_null()
+                .default_value("World"),
+        )
+        .returns(DataType::String, false, false)
+        .build();
+
+    let clamp = FunctionBuilder::new("skel_clamp", skeleton_clamp)
+        .arg(Arg::new("value", DataType::Long))
+        .not_required()
+        .arg(Arg::new("min", DataType::Long).default_value(ZendLong::MIN))
+        .arg(Arg::new("max", DataType::Long).default_value(ZendLong::MAX))
+        .returns(DataType::Long, false, false)
+        .build();
+
+    let squares = FunctionBuilder::new("skel_squares", skeleton_squares)
+        .arg(Arg::new("n", DataType::Long))
+        .returns(DataType::Array, false, true)
+        .build();
+
     let array = FunctionBuilder::new("skel_array", skeleton_array)
         .arg(Arg::new("arr", DataType::Array))
         .build();
 
-    ModuleBuilder::new("ext-skel", "0.1.0")
-        .info_function(php_module_info)
-        .startup_function(module_init)
-        .function(funct)
-        .function(array)
-        .build()
-        .into_raw()
-}
+    let array_push = FunctionBuilder::new("skel_array_push", skeleton_array_push)
+        .arg(Arg::new("arr", DataType::Array))
+        .build();
 
-#[no_mangle]
-pub extern "C" fn skeleton_version(execute_data: &mut ExecutionData, _retval: &mut Zval) {
-    let mut x = Arg::new("x", DataType::Long);
-    let mut y = Arg::new("y", DataType::Double);
-    let mut z = Arg::new("z", DataType::Double);
+    let by_ref = FunctionBuilder::new("skel_by_ref", skeleton_by_ref)
+        .arg(Arg::new("out", DataType::String).as_ref())
+        .build();
 
-    let result = ArgParser::new(execute_data)
-        .arg(&mut x)
-        .arg(&mut y)
-        .not_required()
-        .arg(&mut z)
-        .parse();
+    let serialize = FunctionBuilder::new("skel_serialize_roundtrip", skeleton_serialize_roundtrip)
+        .arg(Arg::new("arr", DataType::Array))
+        .build();
 
-    if result.is_err() {
-        return;
-    }
+    let array_remove = FunctionBuilder::new("skel_array_remove", skeleton_array_remove)
+        .arg(Arg::new("arr", DataType::Array))
+        .arg(Arg::new("key", DataType::String))
+        .build();
 
-    let result = format!(
-        "x: {}, y: {}, z: {}",
-        x.val::<ZendLong>().unwrap_or_default(),
-        y.val::<f64>().unwrap_or_default(),
-        z.val::<f64>().unwrap_or_default()
-    );
+    let binary_key = FunctionBuilder::new("skel_binary_key", skeleton_binary_key)
+        .arg(Arg::new("key", DataType::String))
+        .build();
 
-    _retval.set_string(result);
-}
+    let strings = FunctionBuilder::new("skel_strings", skeleton_strings)
+        .arg(Arg::new("n", DataType::Long))
+        .arg(Arg::new("mode", DataType::String))
+        .build();
 
-#[no_mangle]
-pub extern "C" fn skeleton_array(execute_data: &mut ExecutionData, _retval: &mut Zval) {
-    let mut arr = Arg::new("arr", DataType::Array);
+    let call_interned = FunctionBuilder::new("skel_call_interned", skeleton_call_interned)
+        .arg(Arg::new("fn", DataType::Callable))
+        .arg(Arg::new("value", DataType::String))
+        .build();
 
-    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
-    if result.is_err() {
-        return;
-    }
+    let latin1 = FunctionBuilder::new("skel_latin1", skeleton_latin1)
+        .arg(Arg::new("mode", DataType::String))
+        .build();
+
+    let compare = FunctionBuilder::new("skel_compare", skeleton_compare)
+        .arg(Arg::new("a", DataType::String))
+        .arg(Arg::new("b", DataType::String))
+        .build();
 
-    let ht: ZendHashTable = arr.val().unwrap();
+    let build_string = FunctionBuilder::new("skel_build_string", skeleton_build_string)
+        .arg(Arg::new("chunk", DataType::String))
+        .arg(Arg::new("n", DataType::Long))
+        .arg(Arg::new("mode", DataType::String))
+        .build();
 
-    for (k, x, y) in ht {
-        println!("{:?} {:?} {:?}", k, x, y.string());
-    }
+    let concat = FunctionBuilder::new("skel_concat", skeleton_concat)
+        .arg(Arg::new("parts", DataType::Array))
+        .build();
 
-    let mut new = ZendHashTable::new();
-    new.insert("Hello", "WOrld");
-    let _ = _retval.set_array(new);
+    let implode = FunctionBuilder::new("skel_implode", skeleton_implode)
+        .arg(Arg::new("glue", DataType::String))
+        .arg(Arg::new("parts", DataType::Array))
+        .build();
+
+    let build_array = FunctionBuilder::new("skel_build_array", skeleton_build_array).build();
+
+    let sort = FunctionBuilder::new("skel_sort", skeleton_sort)
+        .arg(Arg::new("arr", DataType::Array))
+        .arg(Arg::new("mode", DataType::String))
+        .arg(Arg::new("flags", DataType::Long))
+        .build();
+
+    let merge = FunctionBuilder::new("skel_merge", skeleton_merge)
+        .arg(Arg::new("arr", DataType::Array))
+        .arg(Arg::new("other", DataType::Array))
+        .arg(Arg::new("mode", DataType::String))
+        .build();
+
+    let filter = FunctionBuilder::new("skel_filter", skeleton_filter)
+        .arg(Arg::new("arr", DataType::Array))
+        .arg(Arg::new("mode", DataType::String))
+        .build();
+
+    let count_values = FunctionBuilder::new("skel_count_values", skeleton_count_values)
+        .arg(Arg::new("arr", DataType::Array))
+        .build();
+
+    let uppercase = FunctionBuilder::new("skel_uppercase_values", skeleton_uppercase_values)
+        .arg(Arg::new("arr", DataType::Array))
+        .build();
+
+    let build_list = FunctionBuilder::new("skel_build_list", skeleton_build_list)
+        .arg(Arg::new("n", DataType::Long))
+        .arg(Arg::new("capacity", DataType::Long))
+        .build();
+
+    let collect = FunctionBuilder::new("skel_collect", skeleton_collect)
+        .arg(Arg::new("n", DataType::Long))
+        .build();
+
+    let sum_packed = FunctionBuilder::new("skel_sum_packed", skeleton_sum_packed)
+        .arg(Arg::new("values", DataType::Array))
+        .build();
+
+    let sum_generic = FunctionBuilder::new("skel_sum_generic", skeleton_sum_generic)
+        .arg(Arg::new("values", DataType::Array))
+        .build();
+
+    let lookup = FunctionBuilder::new("skel_persistent_lookup", skeleton_persistent_lookup)
+        .arg(Arg::new("key", DataType::String))
+        .build();
+
+    let module = ModuleBuilder::new("ext-skel", "0.1.0")
+        .requires("json")
+        .optional("standard")
+        .conflicts("ext-skel-legacy");
+
+    // Allows the tests to load the extension with a dependency which is missing.
+    let module = match std::env::var("SKEL_REQUIRE") {
+        Ok(name) => module.requires(name),
+        Err(_) => module,
+    };
+
+    // Allows the tests to load the extension with a class missing a method of its interface.
+    let module = match std::env::var("SKEL_INCOMPLETE_CLASS") {
+        Ok(_) => module.class(register_incomplete),
+        Err(_) => module,
+    };
+
+    module
+        .on_info(module_info)
+        .startup_function(module_init)
+        .on_shutdown(module_shutdown)
+        .on_request_startup(request_startup)
+        .globals_with_hooks(&GLOBALS, Some(globals_init), None)
+        .constant("SKEL_VERSION", "0.1.0")
+        .constant("SKEL_ANSWER", 42)
+        .constant("SKEL_MODE_FAST", 1)
+        .constant("SKEL_MODE_SAFE", 2)
+        .constant("SKEL_RATIO", 0.5)
+        .constant("SKEL_DEBUG", false)
+        .constant("SKEL_NOTHING", ())
+        .ini_entry(IniBuilder::new("skel.greeting").default("Hello").build())
+        .ini_entry(IniBuilder::new("skel.cache_size").default("64").build())
+        .ini_entry(IniBuilder::new("skel.enabled").default("On").build())
+        .class(register_skel_limits)
+        .class(register_counter)
+        .class(register_settings)
+        .class(register_exception)
+        .class(register_shape)
+        .namespace("Skel\\Util", |module| {
+            let upper = FunctionBuilder::new("upper", util_upper)
+                .arg(Arg::new("string", DataType::String))
+                .returns(DataType::String, false, false)
+                .build();
+
+            module
+                .function(upper)
+                .constant("MODE", 3)
+                .constant("\\SKEL_UTIL_LOADED", true)
+        })
+        .function(legacy_upper)
+        .alias("skel_upper", "Skel\\Util\\upper")
+        .deprecated_alias("skel_reverse", "Skel\\Util\\reverse")
+        .function(funct)
+        .function(array)
+        .function(repeat)
+        .function(truncate)
+        .function(format)
+        .function(sum_ints)
+        .function(fill)
+        .function(arg_types)
+        .function(describe)
+        .function(named_args)
+        .function(greet)
+        .function(clamp)
+        .function(squares)
+        .function(array_push)
+        .function(array_remove)
+        .function(binary_key)
+        .function(strings)
+        .function(call_interned)
+        .function(latin1)
+        .function(compare)
+        .function(build_string)
+        .function(concat)
+        .function(implode)
+        .function(build_array)
+        .function(by_ref)
+        .function(serialize)
+        .function(lookup)
+        .function(sort)
+        .function(merge)
+        .function(filter)
+        .function(count_values)
+        .function(uppercase)
+        .function(build_list)
+        .function(collect)
+        .function(sum_packed)
+        .function(sum_generic)
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_version(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (x, y, z) = match execute_data
+        .parse_args()
+        .arg::<ZendLong>("a")
+        .arg::<f64>("b")
+        .optional::<f64>("c")
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let result = format!("x: {}, y: {}, z: {}", x, y, z);
+    let _ = retval.set_string(result);
+}
+
+/// Repeats a string, declared in the same way as `str_repeat` to compare the errors thrown
+/// for invalid arguments.
+#[no_mangle]
+pub extern "C" fn skeleton_repeat(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (string, times) = match execute_data
+        .parse_args()
+        .arg::<&str>("string")
+        .arg::<usize>("times")
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let _ = retval.set_string(string.repeat(times));
+}
+
+/// Truncates a string to `limit` characters, appending `suffix` if it was truncated.
+#[no_mangle]
+pub extern "C" fn skeleton_truncate(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (string, limit, suffix) = match execute_data
+        .parse_args()
+        .arg::<&str>("string")
+        .optional_declared::<usize>("limit")
+        .optional_declared::<String>("suffix")
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let result = match string.char_indices().nth(limit) {
+        Some((i, _)) => format!("{}{}", &string[..i], suffix),
+        None => string.to_string(),
+    };
+    let _ = retval.set_string(result);
+}
+
+/// Replaces each `{}` in the format with the next value, converted to a string.
+#[no_mangle]
+pub extern "C" fn skeleton_format(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (format, values) = match execute_data
+        .parse_args()
+        .arg::<&str>("format")
+        .variadic::<&Zval>()
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let mut values = values.into_iter();
+    let mut parts = format.split("{}");
+    let mut result = parts.next().unwrap_or_default().to_string();
+
+    for part in parts {
+        match values.next().map(Zval::coerce_to_string) {
+            Some(Ok(value)) => result.push_str(&value),
+            Some(Err(_)) => return,
+            None => result.push_str("{}"),
+        }
+        result.push_str(part);
+    }
+
+    let _ = retval.set_string(result);
+}
+
+/// Sums any number of integers.
+#[no_mangle]
+pub extern "C" fn skeleton_sum_ints(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if let Ok((nums,)) = execute_data.parse_args().variadic::<ZendLong>().finish() {
+        retval.set_long(nums.iter().sum::<ZendLong>());
+    }
+}
+
+/// Writes `filled` into the variable passed by reference, returning its previous value.
+#[no_mangle]
+pub extern "C" fn skeleton_fill(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    if execute_data
+        .parse_args()
+        .arg::<&Zval>("out")
+        .finish()
+        .is_err()
+    {
+        return;
+    }
+
+    let mut out = execute_data.get_arg_by_ref(0).unwrap();
+    let _ = retval.set_string(out.get().coerce_to_string().unwrap_or_default());
+    let _ = out.set("filled");
+}
+
+/// Returns the number of arguments passed and their types.
+#[no_mangle]
+pub extern "C" fn skeleton_arg_types(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let types: Vec<String> = execute_data
+        .args()
+        .map(|arg| format!("{:?}", arg.get_type()))
+        .collect();
+
+    let _ = retval.set_string(format!(
+        "{}: {}",
+        execute_data.arg_count(),
+        types.join(", ")
+    ));
+}
+
+/// Describes the function or method being executed and the function calling it. Registered
+/// as both the function `skel_describe` and the static method `TestClass::describe`.
+#[no_mangle]
+pub extern "C" fn skeleton_describe(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut name = execute_data.function_name().unwrap_or_default();
+    if let Some(class) = execute_data.scope_class_name() {
+        name = format!("{}::{}", class, name);
+    }
+    if execute_data.is_static_call() {
+        name.push_str(" (static)");
+    }
+
+    let caller = execute_data
+        .caller()
+        .and_then(|caller| caller.function_name())
+        .unwrap_or_else(|| "main".into());
+
+    let _ = retval.set_string(format!("{} called from {}", name, caller));
+}
+
+/// Lists the positional and named arguments passed to the function, with the named arguments
+/// sorted by name.
+#[no_mangle]
+pub extern "C" fn skeleton_named_args(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (positional, named) = match execute_data
+        .parse_args()
+        .variadic::<ZendLong>()
+        .named_variadic::<ZendLong>()
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let mut named: Vec<_> = named.into_iter().collect();
+    named.sort();
+
+    let _ = retval.set_string(format!("{:?} {:?}", positional, named));
+}
+
+/// Greets `name`, which defaults to `World` when it is not passed, or nobody when it is null.
+#[no_mangle]
+pub extern "C" fn skeleton_greet(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (name,) = match execute_data
+        .parse_args()
+        .optional_declared::<Option<String>>("name")
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    let _ = retval.set_string(match name {
+        Some(name) => format!("Hello, {}!", name),
+        None => "Hello!".into(),
+    });
+}
+
+/// Clamps an integer between `min` and `max`, throwing a `ValueError` if the range is empty.
+/// The bounds default to `PHP_INT_MIN` and `PHP_INT_MAX`.
+#[no_mangle]
+pub extern "C" fn skeleton_clamp(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let (value, min, max) = match execute_data
+        .parse_args()
+        .arg::<ZendLong>("value")
+        .optional_declared::<ZendLong>("min")
+        .optional_declared::<ZendLong>("max")
+        .finish()
+    {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+
+    if min > max {
+        ArgError::Value {
+            num: 2,
+            message: "must be less than or equal to argument #3 ($max)".into(),
+        }
+        .throw();
+        return;
+    }
+
+    retval.set_long(value.clamp(min, max));
+}
+
+/// Returns the squares of the integers from 1 to `n`, or nothing if `n` is negative. The
+/// array is built in the return value slot by a function only given the execution data.
+#[no_mangle]
+pub extern "C" fn skeleton_squares(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    execute_data.record_return_value(retval);
+
+    if let Ok((n,)) = execute_data.parse_args().arg::<ZendLong>("n").finish() {
+        return_squares(execute_data, n);
+    }
+}
+
+fn return_squares(execute_data: &mut ExecutionData, n: ZendLong) {
+    if n < 0 {
+        return;
+    }
+
+    let squares: Vec<ZendLong> = (1..=n).map(|i| i * i).collect();
+    if let Err(e) = squares.set_zval(execute_data.return_value()) {
+        println!("Returning squares failed: {}", e);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_array(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let ht = arr.zval().unwrap().array().unwrap();
+
+    for (key, val) in ht.iter() {
+        println!("{} {:?}", key, val.string());
+    }
+
+    let mut new = ZendHashTable::new();
+    new.insert("Hello", "WOrld").unwrap();
+    let _ = _retval.set_array(new);
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_by_ref(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    let mut out = Arg::new("out", DataType::String);
+
+    let result = ArgParser::new(execute_data).arg(&mut out).parse();
+    if result.is_err() {
+        return;
+    }
+
+    if let Ok(val) = Zval::try_from("Hello from Rust") {
+        let _ = out.zval().unwrap().set_value_of_reference(val);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_array_push(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
+    if result.is_err() {
+        return;
+    }
+
+    // The array is shared with the caller, so it is separated before being modified.
+    *retval = arr.zval().unwrap().shallow_clone();
+    if let Some(mut ht) = retval.array_mut() {
+        let _ = ht.push("Pushed from Rust");
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_array_remove(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+    let mut key = Arg::new("key", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut arr)
+        .arg(&mut key)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let key: String = key.val().unwrap();
+
+    // See `skeleton_array_push`.
+    *retval = arr.zval().unwrap().shallow_clone();
+    if let Some(mut ht) = retval.array_mut() {
+        if let Some(removed) = ht.remove(&key) {
+            println!("Removed {:?}", removed.string());
+            removed.release();
+        }
+    }
+}
+
+/// Inserts a value with a key which may contain arbitrary bytes, and copies it into a new
+/// array using the keys read back from the first one.
+#[no_mangle]
+pub extern "C" fn skeleton_binary_key(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut key = Arg::new("key", DataType::String);
+
+    let result = ArgParser::new(execute_data).arg(&mut key).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let key = key.zval().unwrap().binary().unwrap();
+    let mut ht = ZendHashTable::new();
+    ht.insert_bytes(&key, "inserted").unwrap();
+    println!("Found: {:?}", ht.get_bytes(&key).and_then(Zval::string));
+
+    let copy: ZendHashTable = ht
+        .iter()
+        .map(|(key, val)| (key, val.shallow_clone()))
+        .collect();
+    let _ = retval.set_array(copy);
+}
+
+/// Creates `n` Zend strings, which are either dropped (`drop`) or handed over to a zval in
+/// the returned array (`transfer`). Used by `test.php` to check that neither leaks.
+#[no_mangle]
+pub extern "C" fn skeleton_strings(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut n = Arg::new("n", DataType::Long);
+    let mut mode = Arg::new("mode", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut n)
+        .arg(&mut mode)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let n: ZendLong = n.val().unwrap();
+    let mode: String = mode.val().unwrap();
+    let mut ht = ZendHashTable::new();
+
+    for i in 0..n {
+        let zs = ZendString::new(format!("string {}", i), false).unwrap();
+
+        match mode.as_str() {
+            "drop" => assert_eq!(zs.as_str(), Some(format!("string {}", i).as_str())),
+            "transfer" => ht.push(zs).unwrap(),
+            _ => {
+                println!("Unknown string mode {}", mode);
+                return;
+            }
+        }
+    }
+
+    let _ = retval.set_array(ht);
+}
+
+/// Calls the callable with the given string, a string interned in Rust and one of the
+/// strings known to the engine, several times. None of them may be released by the calls.
+#[no_mangle]
+pub extern "C" fn skeleton_call_interned(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut callable = Arg::new("fn", DataType::Callable);
+    let mut value = Arg::new("value", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut callable)
+        .arg(&mut value)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let value = value.zval().unwrap();
+    let interned = ZendString::new_interned("interned")
+        .unwrap()
+        .into_zval()
+        .unwrap();
+    let known = ZendString::known(KnownString::Name) as *const _ as *mut _;
+    let known = unsafe { ZendString::from_raw(known) }
+        .unwrap()
+        .into_zval()
+        .unwrap();
+
+    let mut results = ZendHashTable::new();
+    for _ in 0..3 {
+        match callable.try_call(&[value, &interned, &known]) {
+            Ok(result) => results.push(result).unwrap(),
+            Err(e) => println!("Call failed: {}", e),
+        }
+    }
+
+    let _ = retval.set_array(results);
+}
+
+/// Returns `café` encoded as Latin-1, which is not valid UTF-8, in a string which is either
+/// `interned` or not (`copied`).
+#[no_mangle]
+pub extern "C" fn skeleton_latin1(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut mode = Arg::new("mode", DataType::String);
+
+    let result = ArgParser::new(execute_data).arg(&mut mode).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let bytes = b"caf\xe9";
+    let mode: String = mode.val().unwrap();
+    let zs = match mode.as_str() {
+        "copied" => ZendString::from_bytes(bytes, false),
+        "interned" => ZendString::from_bytes_interned(bytes),
+        _ => {
+            println!("Unknown string mode {}", mode);
+            return;
+        }
+    };
+
+    retval.set_zend_string(zs.unwrap());
+}
+
+/// Compares an interned copy of `a` with a copy of `b` allocated on the heap.
+#[no_mangle]
+pub extern "C" fn skeleton_compare(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut a = Arg::new("a", DataType::String);
+    let mut b = Arg::new("b", DataType::String);
+
+    let result = ArgParser::new(execute_data).arg(&mut a).arg(&mut b).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let a = ZendString::from_bytes_interned(&a.zval().unwrap().binary().unwrap()).unwrap();
+    let b = ZendString::from_bytes(&b.zval().unwrap().binary().unwrap(), false).unwrap();
+    let hash = a.hash();
+
+    let result = ArrayBuilder::new()
+        .insert("eq", a == b)
+        .insert("eq_ci", a.eq_ci(&b))
+        .insert("same_hash", hash == b.hash())
+        .insert("stable_hash", hash == a.hash())
+        .build();
+
+    match result {
+        Ok(arr) => {
+            let _ = retval.set_array(arr);
+        }
+        Err(e) => println!("Building array failed: {}", e),
+    }
+}
+
+/// Builds a string by appending `chunk` to it `n` times, either by appending to the string
+/// in place (`append`) or by copying the whole string for each append (`naive`). See
+/// `bench.php`.
+#[no_mangle]
+pub extern "C" fn skeleton_build_string(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut chunk = Arg::new("chunk", DataType::String);
+    let mut n = Arg::new("n", DataType::Long);
+    let mut mode = Arg::new("mode", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut chunk)
+        .arg(&mut n)
+        .arg(&mut mode)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let chunk: String = chunk.val().unwrap();
+    let n: ZendLong = n.val().unwrap();
+    let mode: String = mode.val().unwrap();
+
+    retval.set_string("").unwrap();
+    for _ in 0..n {
+        match mode.as_str() {
+            "append" => retval.append_string(&chunk).unwrap(),
+            "naive" => {
+                let mut s = retval.string().unwrap();
+                s.push_str(&chunk);
+                let old = *retval;
+                retval.set_string(s).unwrap();
+                old.release();
+            }
+            _ => {
+                println!("Unknown build mode {}", mode);
+                return;
+            }
+        }
+    }
+}
+
+/// Concatenates a list of strings into a new string, allocating it once.
+#[no_mangle]
+pub extern "C" fn skeleton_concat(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut parts = Arg::new("parts", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut parts).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let parts = parts.zval().unwrap().array().unwrap();
+    let parts: Vec<Vec<u8>> = parts
+        .values()
+        .into_iter()
+        .filter_map(Zval::binary)
+        .collect();
+    let parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+
+    match ZendString::concat_all(&parts) {
+        Ok(zs) => retval.set_zend_string(zs),
+        Err(e) => println!("Concatenation failed: {}", e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_implode(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut glue = Arg::new("glue", DataType::String);
+    let mut parts = Arg::new("parts", DataType::Array);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut glue)
+        .arg(&mut parts)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let glue: String = glue.val().unwrap();
+    let parts = parts.zval().unwrap().array().unwrap();
+    let mut buf = SmartStr::new();
+
+    for (i, part) in parts.values().into_iter().enumerate() {
+        let result = if i > 0 { buf.append_str(&glue) } else { Ok(()) }.and_then(|_| {
+            match (part.long(), part.double()) {
+                (Some(val), _) => buf.append_long(val),
+                (_, Some(val)) => buf.append_double(val),
+                _ => buf.append_zval(part),
+            }
+        });
+
+        if result.is_err() {
+            return;
+        }
+    }
+
+    write!(buf, " ({} parts)", parts.len()).unwrap();
+    *retval = buf.finish_zval();
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_build_array(_execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let items = vec!["first", "second"];
+    let result = ArrayBuilder::new()
+        .insert(
+            "meta",
+            ArrayBuilder::new().insert("page", 1).insert("last", true),
+        )
+        .insert("items", items)
+        .insert(10, ArrayBuilder::new().push(1.5).push(ArrayBuilder::new()))
+        .build();
+
+    match result {
+        Ok(arr) => {
+            let _ = retval.set_array(arr);
+        }
+        Err(e) => println!("Building array failed: {}", e),
+    }
+}
+
+/// Sorts a copy of the array, in the same way as the PHP function named by `mode`.
+#[no_mangle]
+pub extern "C" fn skeleton_sort(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+    let mut mode = Arg::new("mode", DataType::String);
+    let mut flags = Arg::new("flags", DataType::Long);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut arr)
+        .arg(&mut mode)
+        .arg(&mut flags)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let mode: String = mode.val().unwrap();
+    let flags = SortFlags::from_bits_truncate(flags.val().unwrap());
+
+    // See `skeleton_array_push`.
+    *retval = arr.zval().unwrap().shallow_clone();
+    if let Some(mut ht) = retval.array_mut() {
+        match mode.as_str() {
+            "sort" => ht.sort_values(flags, false),
+            "asort" => ht.sort_values(flags, true),
+            "ksort" => ht.sort_keys(),
+            // Sorts integers in descending order, ignoring the flags.
+            "usort" => ht.sort_by(|a, b| b.long().cmp(&a.long())),
+            // The panic is resumed once the engine has finished sorting, and caught here so
+            // that it does not unwind out of the handler.
+            "usort_panic" => {
+                let sorted = panic::catch_unwind(AssertUnwindSafe(|| {
+                    ht.sort_by(|_, _| panic!("comparison failed"))
+                }));
+                if sorted.is_ok() {
+                    println!("The panic of the comparison function was lost");
+                }
+            }
+            _ => println!("Unknown sort mode {}", mode),
+        }
+    }
+}
+
+/// Combines two arrays, in the same way as the PHP operator or function named by `mode`.
+#[no_mangle]
+pub extern "C" fn skeleton_merge(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+    let mut other = Arg::new("other", DataType::Array);
+    let mut mode = Arg::new("mode", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut arr)
+        .arg(&mut other)
+        .arg(&mut mode)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let mode: String = mode.val().unwrap();
+    let other = other.zval().unwrap().array().unwrap();
+
+    // See `skeleton_array_push`.
+    *retval = arr.zval().unwrap().shallow_clone();
+    if let Some(mut ht) = retval.array_mut() {
+        let result = match mode.as_str() {
+            "plus" => ht.merge(&other, false),
+            "array_replace" => ht.merge(&other, true),
+            "array_merge" => ht.extend(&other),
+            _ => {
+                println!("Unknown merge mode {}", mode);
+                return;
+            }
+        };
+
+        if let Err(e) = result {
+            println!("Merge failed: {}", e);
+        }
+    }
+}
+
+/// Keeps the elements of the array with an odd integer value or a string key starting with
+/// `k`, either by removing the other elements in place (`retain`) or by copying the matching
+/// elements into a new array (`filtered`).
+#[no_mangle]
+pub extern "C" fn skeleton_filter(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+    let mut mode = Arg::new("mode", DataType::String);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut arr)
+        .arg(&mut mode)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let keep = |key: &ArrayKey, val: &Zval| match key {
+        ArrayKey::Str(key) if key.starts_with(b"k") => true,
+        _ => val.long().map_or(false, |n| n % 2 != 0),
+    };
+
+    let mode: String = mode.val().unwrap();
+    match mode.as_str() {
+        "retain" => {
+            // See `skeleton_array_push`.
+            *retval = arr.zval().unwrap().shallow_clone();
+            if let Some(mut ht) = retval.array_mut() {
+                ht.retain(keep);
+            }
+        }
+        "filtered" => {
+            let ht = arr.zval().unwrap().array().unwrap().filtered(keep);
+            let _ = retval.set_array(ht);
+        }
+        _ => println!("Unknown filter mode {}", mode),
+    }
+}
+
+/// Counts how often each integer or string occurs in the array, like `array_count_values`.
+#[no_mangle]
+pub extern "C" fn skeleton_count_values(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let values = arr.zval().unwrap().array().unwrap();
+    let mut counts = ZendHashTable::new();
+
+    for (_, val) in values.iter() {
+        let count = if let Some(idx) = val.long() {
+            counts.get_or_insert_index_with(idx as u64, || Zval::from(0))
+        } else if let Some(key) = val.string() {
+            counts.get_or_insert_with(&key, || Zval::from(0))
+        } else {
+            continue;
+        };
+
+        match count {
+            Ok(count) => {
+                let n = count.long().unwrap_or(0);
+                count.set_long(n + 1);
+            }
+            Err(e) => println!("Counting failed: {}", e),
+        }
+    }
+
+    let _ = retval.set_array(counts);
+}
+
+/// Returns the array with its string values converted to uppercase. The array shares its
+/// values with the argument until it is separated by `iter_mut`.
+#[no_mangle]
+pub extern "C" fn skeleton_uppercase_values(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut arr = Arg::new("arr", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let mut ht: ZendHashTable = arr.val().unwrap();
+
+    for (_, val) in ht.iter_mut() {
+        if let Some(s) = val.string() {
+            let old = *val;
+            if val.set_string(s.to_uppercase()).is_ok() {
+                old.release();
+            }
+        }
+    }
+
+    let _ = retval.set_array(ht);
+}
+
+/// Builds the list `[0, 1, ..., n - 1]` in an array created with the given capacity. See
+/// `bench.php`.
+#[no_mangle]
+pub extern "C" fn skeleton_build_list(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut n = Arg::new("n", DataType::Long);
+    let mut capacity = Arg::new("capacity", DataType::Long);
+
+    let result = ArgParser::new(execute_data)
+        .arg(&mut n)
+        .arg(&mut capacity)
+        .parse();
+    if result.is_err() {
+        return;
+    }
+
+    let n: ZendLong = n.val().unwrap();
+    let mut ht = ZendHashTable::with_capacity_packed(capacity.val().unwrap());
+
+    for i in 0..n {
+        if let Err(e) = ht.push(i) {
+            println!("Push failed: {}", e);
+            return;
+        }
+    }
+
+    let _ = retval.set_array(ht);
+}
+
+/// Returns a list of the squares of `0..n` and a map from their string form to the squares,
+/// both built with `collect`.
+#[no_mangle]
+pub extern "C" fn skeleton_collect(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut n = Arg::new("n", DataType::Long);
+
+    let result = ArgParser::new(execute_data).arg(&mut n).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let n: ZendLong = n.val().unwrap();
+    let squares: Zval = (0..n).map(|i| i * i).collect();
+    let named: Zval = (0..n).map(|i| (format!("n{}", i), i * i)).collect();
+
+    *retval = vec![squares, named].into_iter().collect();
+}
+
+/// Sums a list of floats using the packed array fast path. See `bench.php`.
+#[no_mangle]
+pub extern "C" fn skeleton_sum_packed(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut values = Arg::new("values", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut values).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let ht = values.zval().unwrap().array().unwrap();
+    match ht.to_vec_double() {
+        Ok(values) => retval.set_double(values.iter().sum::<f64>()),
+        Err(e) => println!("Conversion failed: {}", e),
+    }
+}
+
+/// Sums a list of floats, converting each element through `FromZval`.
+#[no_mangle]
+pub extern "C" fn skeleton_sum_generic(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut values = Arg::new("values", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut values).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let ht = values.zval().unwrap().array().unwrap();
+    match ht.values_owned::<f64>() {
+        Ok(values) => retval.set_double(values.iter().sum::<f64>()),
+        Err(e) => println!("Conversion failed: {}", e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_serialize_roundtrip(
+    execute_data: &mut ExecutionData,
+    retval: &mut Zval,
+) {
+    let mut arr = Arg::new("arr", DataType::Array);
+
+    let result = ArgParser::new(execute_data).arg(&mut arr).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let data = match arr.zval().unwrap().serialize() {
+        Ok(data) => data,
+        Err(e) => {
+            println!("Serialize failed: {}", e);
+            return;
+        }
+    };
+    println!("{}", String::from_utf8_lossy(&data));
+
+    let options = UnserializeOptions {
+        allowed_classes: AllowedClasses::Only(vec!["stdClass".into()]),
+    };
+
+    match Zval::unserialize(&data, &options) {
+        Ok(val) => *retval = val,
+        Err(e) => println!("Unserialize failed: {}", e),
+    }
+
+    if let Err(e) = Zval::unserialize(&data[..data.len() - 2], &options) {
+        println!("Truncated payload: {}", e);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn skeleton_persistent_lookup(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    let mut key = Arg::new("key", DataType::String);
+
+    let result = ArgParser::new(execute_data).arg(&mut key).parse();
+    if result.is_err() {
+        return;
+    }
+
+    let key: String = key.val().unwrap();
+    let lookup = unsafe { (*std::ptr::addr_of!(LOOKUP)).as_ref() }.unwrap();
+
+    // The value belongs to the persistent table, so it is copied into a request string
+    // rather than being handed to PHP directly.
+    match lookup.get(&key) {
+        Some(val) if val.is_string() => {
+            let _ = retval.set_string(val.string().unwrap());
+        }
+        Some(val) => retval.set_long(val.long().unwrap_or_default()),
+        None => retval.set_null(),
+    }
 }
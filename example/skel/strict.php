@@ -0,0 +1,12 @@
+<?php
+
+declare(strict_types=1);
+
+// Functions called from this file use strict types, so scalar arguments are not coerced.
+function strict_call(callable $f, ...$args) {
+    try {
+        return $f(...$args);
+    } catch (TypeError $e) {
+        return get_class($e) . ': ' . $e->getMessage();
+    }
+}
@@ -7,4 +7,905 @@ var_dump($x->call(function ($v1, $v2) {
     // echo "Hello, world! I'm a callable.".PHP_EOL;
     // return "Ok rust";
     return 0;
-}));
\ No newline at end of file
+}));
+
+$out = null;
+skel_by_ref($out);
+var_dump($out);
+
+var_dump($x->call(function ($v1, $v2) {
+    throw new InvalidArgumentException('Thrown from PHP', 5);
+}));
+
+
+$a = [1, 2];
+$b = $a;
+$c = skel_array_push($b);
+var_dump($a, $b, $c);
+
+$obj = new stdClass();
+$obj->name = 'nested';
+$obj->list = [1, 2.5, true, null];
+$value = ['a' => [1, [2, 3]], 'b' => 'str', 'obj' => $obj];
+$copy = skel_serialize_roundtrip($value);
+var_dump($copy == $value, $copy);
+
+// The lookup table is built once when the module starts, and is read by every request.
+var_dump(skel_persistent_lookup('rust'), skel_persistent_lookup('answer'), skel_persistent_lookup('missing'));
+$output = run_skel_requests(
+    'echo skel_requests(), ": ", skel_persistent_lookup("rust"), ", ", skel_persistent_lookup("answer"), "\n";',
+    2
+);
+var_dump($output === null || $output === "1: Persistent string, 42\n2: Persistent string, 42\n");
+
+$arr = ['a' => 1, 'b' => 'two', 'c' => 3];
+$removed = skel_array_remove($arr, 'b');
+unset($arr['b']);
+var_dump($removed === $arr, array_keys($removed));
+
+$built = skel_build_array();
+var_dump(json_encode($built) === '{"meta":{"page":1,"last":true},"items":["first","second"],"10":[1.5,[]]}');
+
+$unsorted = ['b' => 10, 'a' => '9', 'c' => 'apple', 'd' => 1.5, 'e' => true, 'f' => null, 'g' => '10'];
+foreach ([SORT_REGULAR, SORT_NUMERIC, SORT_STRING, SORT_STRING | SORT_FLAG_CASE, SORT_NATURAL] as $flags) {
+    $expected = $unsorted;
+    sort($expected, $flags);
+    $preserved = $unsorted;
+    asort($preserved, $flags);
+    var_dump(
+        skel_sort($unsorted, 'sort', $flags) === $expected,
+        skel_sort($unsorted, 'asort', $flags) === $preserved
+    );
+}
+
+$keys = [10 => 'a', '9' => 'b', 'x' => 'c', '-1' => 'd', 'X' => 'e'];
+$expected = $keys;
+ksort($expected);
+var_dump(skel_sort($keys, 'ksort', 0) === $expected);
+
+$ints = [3, 1, 2, 1, 5];
+$expected = $ints;
+usort($expected, fn ($a, $b) => $b <=> $a);
+var_dump(skel_sort($ints, 'usort', 0) === $expected, $ints);
+
+$defaults = ['host' => 'localhost', 'port' => 80, 5 => 'first', 'tags' => ['a']];
+$options = ['port' => 8080, 5 => 'second', 'debug' => true, 'tags' => ['b']];
+var_dump(
+    skel_merge($defaults, $options, 'plus') === $defaults + $options,
+    skel_merge($defaults, $options, 'array_replace') === array_replace($defaults, $options),
+    skel_merge($defaults, $options, 'array_merge') === array_merge($defaults, $options),
+    skel_merge([1, 2], [3, 4], 'array_merge') === [1, 2, 3, 4]
+);
+
+// Enough distinct values to grow the hash table many times while counting.
+$words = [];
+for ($i = 0; $i < 20000; $i++) {
+    $words[] = 'word' . ($i % 7919);
+    $words[] = $i % 13;
+}
+var_dump(skel_count_values($words) === array_count_values($words));
+
+// Numeric string keys are normalised the same way as in PHP.
+$numeric = [5 => 'five', '05' => 'zero five', '-0' => 'minus zero'];
+var_dump(skel_array_remove($numeric, '5') === ['05' => 'zero five', '-0' => 'minus zero']);
+
+// Numeric string keys of maps converted into arrays are normalised in the same way.
+$map = skel_index_keys(['5', '-5', '05', '-0', '9223372036854775808', 'x']);
+$ints = array_values(array_filter(array_keys($map), 'is_int'));
+sort($ints);
+var_dump(
+    $map == [5 => 0, '-5' => 1, '05' => 2, '-0' => 3, '9223372036854775808' => 4, 'x' => 5],
+    $ints === [-5, 5]
+);
+
+$lower = ['a', 'b' => 'bee', 3];
+$shared = $lower;
+$upper = skel_uppercase_values($shared);
+var_dump($upper === ['A', 'b' => 'BEE', 3], $shared === $lower, $lower === ['a', 'b' => 'bee', 3]);
+
+// Lists are converted into vectors and back without changing their elements.
+$lists = [['a', '', "\u{1F980}"], [0, -1, PHP_INT_MAX, PHP_INT_MIN], [0.0, -1.5, INF], [[1, 2], [], [3]]];
+var_dump(skel_lists(...$lists) === $lists, skel_lists([], [], [], []) === [[], [], [], []]);
+var_dump(
+    arg_error(function () { skel_lists(['a' => 'b'], [], [], []); }) === 'TypeError: skel_lists(): Argument #1 ($strings) must be of type array, array given',
+    arg_error(function () { skel_lists([], [1, 'x'], [], []); }) === 'TypeError: skel_lists(): Argument #2 ($longs) must be of type array, array given',
+    arg_error(function () { skel_lists([], [], [], [[1], 2]); }) === 'TypeError: skel_lists(): Argument #4 ($nested) must be of type array, array given'
+);
+
+var_dump(skel_collect(4) === [[0, 1, 4, 9], ['n0' => 0, 'n1' => 1, 'n2' => 4, 'n3' => 9]]);
+
+// Filtering keeps the keys of the remaining elements, as `array_filter` does.
+$mixed = [1, 2, 'k1' => 'kept', 'x' => 3, 'y' => 4, 7, 'key' => 8, 10 => 9, '11' => 'eleven'];
+$expected = array_filter(
+    $mixed,
+    fn ($val, $key) => (is_string($key) && $key[0] === 'k') || (is_int($val) && $val % 2 !== 0),
+    ARRAY_FILTER_USE_BOTH
+);
+$original = $mixed;
+var_dump(
+    skel_filter($mixed, 'retain') === $expected,
+    skel_filter($mixed, 'filtered') === $expected,
+    $mixed === $original
+);
+
+// Keys are byte strings, which need not be valid UTF-8.
+foreach (["\x00\x01", pack('N', 0xff00ff00), '12'] as $key) {
+    var_dump(skel_binary_key($key) === [$key => 'inserted']);
+}
+
+// Zend strings owned by Rust are released when dropped, or by PHP once handed over.
+skel_strings(10, 'drop');
+skel_strings(10, 'transfer');
+$before = memory_get_usage();
+skel_strings(10000, 'drop');
+$strings = skel_strings(10000, 'transfer');
+var_dump(count($strings) === 10000, $strings[42] === 'string 42');
+unset($strings);
+var_dump(memory_get_usage() === $before);
+
+// Interned strings, such as class names and the strings known to the engine, are never
+// released by calls. Run on a debug build of PHP to detect strings being freed twice.
+class Interned
+{
+    const NAME = 'constant value';
+}
+$join = fn (...$args) => implode(',', $args);
+var_dump(
+    skel_call_interned($join, Interned::class) === array_fill(0, 3, 'Interned,interned,name'),
+    skel_call_interned($join, Interned::NAME) === array_fill(0, 3, 'constant value,interned,name'),
+    Interned::NAME === 'constant value'
+);
+
+// Strings may be created from bytes which are not valid UTF-8.
+var_dump(
+    skel_latin1('copied') === "caf\xe9",
+    skel_latin1('interned') === "caf\xe9",
+    utf8_encode(skel_latin1('copied')) === 'café'
+);
+
+// Interned strings compare equal to heap strings with the same contents.
+var_dump(
+    skel_compare('Name', 'Name') === ['eq' => true, 'eq_ci' => true, 'same_hash' => true, 'stable_hash' => true],
+    skel_compare('Name', 'nAME') === ['eq' => false, 'eq_ci' => true, 'same_hash' => false, 'stable_hash' => true],
+    skel_compare("\xc9", "\xe9")['eq_ci'] === false,
+    skel_compare('Name', 'Names')['eq_ci'] === false
+);
+
+// Strings can be built by appending in place or concatenating several parts at once.
+var_dump(
+    skel_build_string('ab', 3, 'append') === 'ababab',
+    skel_build_string('ab', 3, 'naive') === 'ababab',
+    skel_concat([]) === '',
+    skel_concat(['a', "\0"]) === "a\0",
+    skel_concat(['a', 'b', 'c']) === 'abc',
+    skel_concat(['a', 'b', 'c', 'd', 'e']) === 'abcde'
+);
+
+// Strings can be built in a growable buffer, formatting values as PHP would.
+$parts = [1, -20, 1.5, 1.0, 0.1 + 0.2, 1e100, -INF, NAN, true, false, null, "a\0b"];
+var_dump(
+    skel_implode(', ', $parts) === implode(', ', $parts) . ' (12 parts)',
+    skel_implode('', []) === ' (0 parts)'
+);
+
+// Invalid arguments throw the same errors as functions built into PHP.
+function arg_error(callable $f, ...$args) {
+    try {
+        $f(...$args);
+    } catch (Throwable $e) {
+        return get_class($e) . ': ' . str_replace('str_repeat', 'skel_repeat', $e->getMessage());
+    }
+    return null;
+}
+
+var_dump(
+    skel_repeat('ab', 2) === 'abab',
+    skeleton_version(1, 2.5) === 'x: 1, y: 2.5, z: 0',
+    arg_error('skel_repeat', 'a', 'x') === arg_error('str_repeat', 'a', 'x'),
+    arg_error('skel_repeat', [], 1) === arg_error('str_repeat', [], 1),
+    arg_error('skel_repeat', 'a') === arg_error('str_repeat', 'a'),
+    arg_error('skel_repeat', 'a', 1, 2) === arg_error('str_repeat', 'a', 1, 2),
+    arg_error('skel_repeat', 'a', -1) === arg_error('str_repeat', 'a', -1),
+    arg_error('skeleton_version', 1) === 'ArgumentCountError: skeleton_version() expects at least 2 arguments, 1 given',
+    arg_error('skeleton_version', 1, 2.5, 'c') === 'TypeError: skeleton_version(): Argument #3 ($c) must be of type float, string given'
+);
+
+// Optional arguments take their default values when they are not passed, which are also
+// reported through reflection.
+$params = (new ReflectionFunction('skel_truncate'))->getParameters();
+var_dump(
+    skel_truncate('abcdefghijklmnop') === 'abcdefghij...',
+    skel_truncate('abcdefghijklmnop', 3) === 'abc...',
+    skel_truncate('abcdefghijklmnop', 3, '!') === 'abc!',
+    skel_truncate('abc', 3, '!') === 'abc',
+    skel_truncate('abcdef', suffix: '?', limit: 2) === 'ab?',
+    $params[0]->isOptional() === false,
+    $params[1]->getDefaultValue() === 10,
+    $params[2]->getDefaultValue() === '...'
+);
+
+// Variadic arguments collect the remaining arguments.
+$values = [1, 2.5, 'three', true, null, 6, '7', 8.0, 'nine', 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20];
+$fn = new ReflectionFunction('skel_format');
+var_dump(
+    skel_format('no values') === 'no values',
+    skel_format('{}, {}') === '{}, {}',
+    skel_format(str_repeat('{} ', 20), ...$values) === implode(' ', $values) . ' ',
+    skel_format(...['{}-{}', 'a', 'b']) === 'a-b',
+    skel_sum_ints() === 0,
+    skel_sum_ints(...range(1, 20)) === 210,
+    arg_error('skel_sum_ints', 1, 2, 'x') === 'TypeError: skel_sum_ints(): Argument #3 must be of type int, string given',
+    arg_error('skel_format') === 'ArgumentCountError: skel_format() expects at least 1 argument, 0 given',
+    $fn->isVariadic(),
+    $fn->getNumberOfParameters() === 2,
+    $fn->getNumberOfRequiredParameters() === 1,
+    $fn->getParameters()[1]->isVariadic()
+);
+
+// Values can be written back into arguments passed by reference.
+class Typed {
+    public int $value = 0;
+}
+
+$out = 'old';
+$arr = [];
+$typed = new Typed;
+var_dump(
+    skel_fill($out) === 'old',
+    $out === 'filled',
+    skel_fill($new) === '',
+    $new === 'filled',
+    skel_fill($arr['key']) === '',
+    $arr === ['key' => 'filled'],
+    (new ReflectionFunction('skel_fill'))->getParameters()[0]->isPassedByReference(),
+    arg_error(function () use ($typed) { skel_fill($typed->value); }) === 'TypeError: Cannot assign string to reference held by property Typed::$value of type int',
+    $typed->value === 0
+);
+
+// The number of arguments passed can be read without reading past the last argument.
+var_dump(
+    skel_arg_types() === '0: ',
+    skel_arg_types(1, 'a', null) === '3: Some(Long), Some(String), Some(Null)'
+);
+
+// Methods can read and write the properties of `$this`, including on child classes.
+class ChildTestClass extends TestClass {
+    public $declared = 'public';
+    private $hidden = 'private';
+}
+
+$parent = new TestClass();
+$child = new ChildTestClass();
+var_dump(
+    $parent->touch() === 'TestClass',
+    $parent->touch() === 'TestClass',
+    $parent->touched === 2,
+    $child->touch() === 'ChildTestClass',
+    $child->touched === 1
+);
+
+// Properties of `$this` can be read from inside a method, as `$this->name` would.
+$child = new ChildTestClass();
+$child->dynamic = [1, 2];
+var_dump(
+    $child->prop('declared') === 'public',
+    $child->prop('dynamic') === [1, 2],
+    $child->prop('missing') === '<missing>',
+    $child->prop('hidden') === '<missing>'
+);
+
+// The return value can be built in place by code which is only given the execution data, and
+// is null if it is left untouched.
+var_dump(skel_squares(3) === [1, 4, 9], skel_squares(0) === [], skel_squares(-1) === null);
+
+// Integer defaults at the edges of the range are declared so that PHP reads them as integers.
+$params = (new ReflectionFunction('skel_clamp'))->getParameters();
+var_dump($params[1]->getDefaultValue() === PHP_INT_MIN, $params[2]->getDefaultValue() === PHP_INT_MAX);
+
+// Errors found after parsing the arguments are thrown in the same way as by native functions.
+var_dump(
+    skel_clamp(5, 1, 3) === 3,
+    skel_clamp(-5, 1, 3) === 1,
+    skel_clamp(PHP_INT_MIN) === PHP_INT_MIN,
+    skel_clamp(PHP_INT_MAX, max: 0) === 0,
+    skel_clamp(5, 6) === 6,
+    arg_error('skel_clamp', 1, 3, 1) === 'ValueError: skel_clamp(): Argument #2 ($min) must be less than or equal to argument #3 ($max)',
+    arg_error('skel_clamp', 1, 3, 'x') === 'TypeError: skel_clamp(): Argument #3 ($max) must be of type int, string given'
+);
+
+// Nullable arguments are read as an `Option`, which is `None` when null is passed rather than
+// taking the default value.
+$param = (new ReflectionFunction('skel_greet'))->getParameters()[0];
+var_dump(
+    skel_greet() === 'Hello, World!',
+    skel_greet(null) === 'Hello!',
+    skel_greet('x') === 'Hello, x!',
+    arg_error('skel_greet', []) === 'TypeError: skel_greet(): Argument #1 ($name) must be of type ?string, array given',
+    $param->allowsNull(),
+    (string) $param->getType() === '?string',
+    $param->getDefaultValue() === 'World'
+);
+
+// Arguments can be passed by name in any order, with skipped optional arguments taking their
+// default values. Variadic functions collect the remaining named arguments.
+var_dump(
+    skel_truncate(suffix: '~', limit: 2, string: 'abc') === 'ab~',
+    skel_truncate('abcdefghijklmnop', suffix: '!') === 'abcdefghij!',
+    skel_truncate(string: 'abcdef', limit: 3) === 'abc...',
+    arg_error(function () { skel_truncate(limit: 2); }) === 'ArgumentCountError: skel_truncate(): Argument #1 ($string) not passed',
+    skel_named_args(1, 2, b: 4, a: 3) === '[1, 2] [("a", 3), ("b", 4)]',
+    skel_named_args(...['x' => 1]) === '[] [("x", 1)]',
+    arg_error(function () { skel_named_args(1, a: 'x'); }) === 'TypeError: skel_named_args(): Argument #2 must be of type int, string given'
+);
+
+// Functions can tell what is being executed and what called them.
+function describe_caller() {
+    return skel_describe();
+}
+
+var_dump(
+    skel_describe() === 'skel_describe called from main',
+    describe_caller() === 'skel_describe called from describe_caller',
+    TestClass::describe() === 'TestClass::describe (static) called from main',
+    (function () { return TestClass::describe(); })() === 'TestClass::describe (static) called from {closure}'
+);
+
+// Scalar arguments are coerced unless the caller uses strict types, as for native functions.
+require __DIR__ . '/strict.php';
+
+var_dump(
+    skel_repeat('ab', '2') === str_repeat('ab', '2'),
+    skel_repeat(5, 2) === '55',
+    skel_repeat(null, 2) === str_repeat(null, 2),
+    skel_truncate('abcdef', 3.0) === 'abc...',
+    skel_clamp('10', 1, 3) === 3,
+    skel_greet(null) === 'Hello!',
+    skel_greet(5) === 'Hello, 5!',
+    strict_call('skel_repeat', 'ab', 2) === 'abab',
+    strict_call('skel_repeat', 'ab', '2') === 'TypeError: skel_repeat(): Argument #2 ($times) must be of type int, string given',
+    strict_call('str_repeat', 'ab', '2') === 'TypeError: str_repeat(): Argument #2 ($times) must be of type int, string given',
+    strict_call('skel_greet', 5) === 'TypeError: skel_greet(): Argument #1 ($name) must be of type ?string, int given',
+    strict_call('skel_sum_ints', 1, '2') === 'TypeError: skel_sum_ints(): Argument #2 must be of type int, string given'
+);
+
+// Functions are registered with the signature they are built with.
+$fn = new ReflectionFunction('skel_repeat');
+var_dump(
+    $fn->isInternal(),
+    $fn->getExtensionName() === 'ext-skel',
+    (string) $fn->getReturnType() === 'string',
+    array_map(fn ($p) => $p->getType() . ' $' . $p->getName(), $fn->getParameters()) === ['string $string', 'int $times'],
+    $fn->getNumberOfRequiredParameters() === 2
+);
+
+// Functions exported with `#[php_function]` parse their arguments and declare their signature.
+$value = 41;
+skel_increment($value);
+$counter = 5;
+skel_increment($counter, 10);
+
+try {
+    skel_add('one');
+} catch (TypeError $e) {
+    $error = $e->getMessage();
+}
+
+$fn = new ReflectionFunction('skel_add');
+var_dump(
+    skel_add(1, 2) === 3,
+    skel_add(1) === 2,
+    skel_add(1, null) === 2,
+    skel_add('3', b: 4) === 7,
+    $error === 'skel_add(): Argument #1 ($a) must be of type int, string given',
+    $value === 42,
+    $counter === 15,
+    (string) $fn->getReturnType() === 'int',
+    array_map(fn ($p) => $p->getType() . ' $' . $p->getName(), $fn->getParameters()) === ['int $a', '?int $b'],
+    $fn->getNumberOfRequiredParameters() === 1,
+    (new ReflectionFunction('skel_increment'))->getParameters()[0]->isPassedByReference()
+);
+
+// The module registers its functions, constants, INI entries and classes when it starts up,
+// and calls its hooks for every request.
+var_dump(
+    extension_loaded('ext-skel'),
+    in_array('skel_add', get_extension_funcs('ext-skel'), true),
+    SKEL_VERSION === '0.1.0',
+    SKEL_ANSWER === 42,
+    ini_get('skel.greeting') === 'Hello',
+    ini_set('skel.greeting', 'Hi') === 'Hello',
+    ini_get('skel.greeting') === 'Hi',
+    SkelLimits::MAX === PHP_INT_MAX,
+    SkelLimits::NAME === 'limits',
+    skel_requests() === 1,
+    skel_globals_initialized()
+);
+
+// Constants may be strings, integers, floats, booleans or null, and are case-sensitive.
+var_dump(
+    SKEL_MODE_FAST === 1,
+    SKEL_MODE_SAFE === 2,
+    SKEL_RATIO === 0.5,
+    SKEL_DEBUG === false,
+    defined('SKEL_NOTHING') && SKEL_NOTHING === null,
+    defined('SKEL_VERSION'),
+    !defined('skel_version'),
+    (new ReflectionExtension('ext-skel'))->getConstants()['SKEL_MODE_FAST'] === 1
+);
+
+// Class constants may be public, protected or private.
+$limits = new ReflectionClass(SkelLimits::class);
+
+try {
+    SkelLimits::SECRET;
+    $private = '';
+} catch (Error $e) {
+    $private = $e->getMessage();
+}
+
+var_dump(
+    SkelLimits::MODE_FAST === 1,
+    SkelLimits::RATIO === 0.5,
+    SkelLimits::STRICT === true,
+    SkelLimits::NOTHING === null,
+    array_keys($limits->getConstants())
+        === ['MAX', 'NAME', 'MODE_FAST', 'RATIO', 'STRICT', 'NOTHING', 'STEP', 'SECRET'],
+    $limits->getReflectionConstant('MODE_FAST')->isPublic(),
+    $limits->getReflectionConstant('STEP')->isProtected(),
+    $limits->getReflectionConstant('SECRET')->isPrivate(),
+    $limits->getConstant('SECRET') === 'hidden',
+    $private === 'Cannot access private constant SkelLimits::SECRET'
+);
+
+// The extension prints its information in `phpinfo()`, as plain text on the command line.
+ob_start();
+phpinfo(INFO_MODULES);
+$info = ob_get_clean();
+
+var_dump(
+    str_contains($info, 'skeleton extension => enabled'),
+    str_contains($info, 'version => 0.1.0'),
+    str_contains($info, 'requests => 1'),
+    str_contains($info, 'motto => <Rust> & "PHP"'),
+    str_contains($info, 'Built with ext-php-rs')
+);
+
+// Dependencies are declared to the engine, which refuses to start the extension if a required
+// extension is missing.
+$ext = new ReflectionExtension('ext-skel');
+var_dump($ext->getDependencies() === ['json' => 'Required', 'standard' => 'Optional', 'ext-skel-legacy' => 'Conflicts']);
+
+// Runs code in a new PHP process with only the extension loaded, returning its output.
+function run_skel(string $code, array $ini = [], array $env = []): string
+{
+    $lib = __DIR__ . '/target/debug/' . (PHP_OS_FAMILY === 'Darwin' ? 'libskel.dylib' : 'libskel.so');
+    $cmd = [PHP_BINARY, '-n', '-d', 'display_startup_errors=1', '-d', 'extension=' . $lib];
+    foreach ($ini as $name => $value) {
+        array_push($cmd, '-d', $name . '=' . $value);
+    }
+    array_push($cmd, '-r', $code);
+
+    $proc = proc_open($cmd, [1 => ['pipe', 'w'], 2 => ['redirect', 1]], $pipes, null, $env);
+    $output = stream_get_contents($pipes[1]);
+    proc_close($proc);
+
+    return $output;
+}
+
+// Runs code with the extension loaded in a new `php-cgi` process, repeating the request the
+// given number of times in the same process. Returns `null` if `php-cgi` is not installed.
+function run_skel_requests(string $code, int $requests): ?string
+{
+    $cgi = PHP_BINDIR . '/php-cgi';
+    if (!is_executable($cgi)) {
+        return null;
+    }
+
+    $lib = __DIR__ . '/target/debug/' . (PHP_OS_FAMILY === 'Darwin' ? 'libskel.dylib' : 'libskel.so');
+    $script = tempnam(sys_get_temp_dir(), 'skel');
+    file_put_contents($script, '<?php ' . $code);
+
+    $cmd = [$cgi, '-n', '-q', '-d', 'extension=' . $lib, '-T', (string) $requests, $script];
+    $proc = proc_open($cmd, [1 => ['pipe', 'w'], 2 => ['redirect', 1]], $pipes);
+    $output = stream_get_contents($pipes[1]);
+    proc_close($proc);
+    unlink($script);
+
+    return $output;
+}
+
+$output = run_skel('var_dump(function_exists("skel_add"));', [], ['SKEL_REQUIRE' => 'missing_ext']);
+
+var_dump(
+    str_contains($output, 'Cannot load module "ext-skel" because required module "missing_ext" is not loaded'),
+    str_contains($output, 'bool(false)')
+);
+
+// A class which does not implement every method of its interfaces stops the engine from
+// starting, the same way as a class declared in PHP.
+$output = run_skel('echo "started";', [], ['SKEL_INCOMPLETE_CLASS' => '1']);
+
+var_dump(
+    str_contains(
+        $output,
+        'Class Skel\Util\Incomplete contains 1 abstract method and must therefore be declared abstract or implement the remaining methods (Countable::count)'
+    ),
+    !str_contains($output, 'started')
+);
+
+// INI entries are read with typed getters, which understand quantities such as `128M` and
+// booleans such as `off`.
+var_dump(
+    skel_cache_size() === 64,
+    skel_enabled() === true,
+    ini_set('skel.cache_size', '1k') === '64',
+    skel_cache_size() === 1024
+);
+
+$output = run_skel('var_dump(skel_cache_size(), skel_enabled());', ['skel.cache_size' => '128M', 'skel.enabled' => 'off']);
+var_dump($output === "int(134217728)\nbool(false)\n");
+
+// Functions and constants may be registered in a namespace, which is case-insensitive, and
+// imported with `use function` and `use const`.
+use function Skel\Util\reverse;
+use const Skel\Util\MODE;
+
+var_dump(
+    Skel\Util\reverse('abc') === 'cba',
+    \SKEL\UTIL\REVERSE('abc') === 'cba',
+    reverse('abc') === 'cba',
+    Skel\Util\upper('abc') === 'ABC',
+    function_exists('skel\util\upper'),
+    !function_exists('upper'),
+    (new ReflectionFunction('Skel\Util\upper'))->getNamespaceName() === 'Skel\Util',
+    (new ReflectionFunction('Skel\Util\reverse'))->getShortName() === 'reverse',
+    Skel\Util\MODE === 3,
+    \skel\util\MODE === 3,
+    MODE === 3,
+    defined('Skel\Util\MODE'),
+    !defined('Skel\Util\mode'),
+    SKEL_UTIL_LOADED === true
+);
+
+// Deprecated functions and aliases emit `E_DEPRECATED` when they are called, and aliases call
+// the same function with the same arguments.
+$deprecations = [];
+set_error_handler(function (int $errno, string $errstr) use (&$deprecations) {
+    $deprecations[] = [$errno, $errstr];
+    return true;
+}, E_DEPRECATED);
+$results = [skel_legacy_upper('abc'), skel_reverse('abc'), skel_upper('abc')];
+restore_error_handler();
+
+var_dump(
+    $results === ['ABC', 'cba', 'ABC'],
+    $deprecations === [
+        [E_DEPRECATED, 'Function skel_legacy_upper() is deprecated'],
+        [E_DEPRECATED, 'Function skel_reverse() is deprecated'],
+    ],
+    (new ReflectionFunction('skel_legacy_upper'))->isDeprecated(),
+    (new ReflectionFunction('skel_reverse'))->isDeprecated(),
+    !(new ReflectionFunction('Skel\Util\reverse'))->isDeprecated(),
+    !(new ReflectionFunction('skel_upper'))->isDeprecated(),
+    (new ReflectionFunction('skel_reverse'))->getNumberOfParameters() === 1
+);
+
+// Classes can be declared in a namespace, with properties and interfaces, and created from PHP
+// or from Rust.
+$counter = new Skel\Util\Counter();
+$counter->step = 5;
+$counter->increment();
+
+$fromRust = skel_new_counter();
+$fromRust->increment();
+
+var_dump(
+    $counter->increment() === 10,
+    count($counter) === 10,
+    $counter instanceof Countable,
+    $counter instanceof Stringable,
+    $counter instanceof JsonSerializable,
+    (string) $counter === 'counter: 10',
+    json_encode(['counter' => $counter]) === '{"counter":10}',
+    count(class_implements($counter)) === 3,
+    $counter->label === 'counter',
+    (new ReflectionProperty(Skel\Util\Counter::class, 'total'))->isProtected(),
+    (new ReflectionClass('skel\util\counter'))->getNamespaceName() === 'Skel\Util',
+    $fromRust instanceof Skel\Util\Counter,
+    count($fromRust) === 1,
+    skel_is_counter($counter),
+    !skel_is_counter(new ArrayObject()),
+    !skel_is_counter('Skel\Util\Counter')
+);
+
+// Properties can be typed, static, uninitialized or have arrays as their defaults.
+$settings = new Skel\Util\Settings();
+$settings->tags[] = 'php';
+$settings->tags['nested'][] = 4;
+Skel\Util\Settings::$instances++;
+Skel\Util\Settings::$defaults[] = 3;
+
+function type_error(callable $f): string
+{
+    try {
+        $f();
+    } catch (Error $e) {
+        return get_class($e) . ': ' . $e->getMessage();
+    }
+
+    return '';
+}
+
+$ratio = new ReflectionProperty(Skel\Util\Settings::class, 'ratio');
+$owner = new ReflectionProperty(Skel\Util\Settings::class, 'owner');
+$instances = new ReflectionProperty(Skel\Util\Settings::class, 'instances');
+$class = new ReflectionClass(Skel\Util\Settings::class);
+
+var_dump(
+    (string) $ratio->getType() === 'float',
+    $ratio->getDefaultValue() === 1.0,
+    (string) $owner->getType() === '?string',
+    $owner->getDefaultValue() === null,
+    $instances->isStatic(),
+    (string) $instances->getType() === 'int',
+    (new ReflectionProperty(Skel\Util\Settings::class, 'extra'))->isProtected(),
+    !(new ReflectionProperty(Skel\Util\Settings::class, 'id'))->hasDefaultValue(),
+    $class->getDefaultProperties()['tags'] === ['rust', 'nested' => [1, 2, 3]],
+    $settings->tags === ['rust', 'nested' => [1, 2, 3, 4], 'php'],
+    (new Skel\Util\Settings())->tags === ['rust', 'nested' => [1, 2, 3]],
+    Skel\Util\Settings::$instances === 1,
+    Skel\Util\Settings::$defaults === [1, 2, 3],
+    type_error(function () use ($settings) {
+        $settings->ratio = 'fast';
+    }) === 'TypeError: Cannot assign string to property Skel\Util\Settings::$ratio of type float',
+    type_error(function () {
+        Skel\Util\Settings::$instances = [];
+    }) === 'TypeError: Cannot assign array to property Skel\Util\Settings::$instances of type int',
+    type_error(function () use ($settings) {
+        return $settings->id;
+    }) === 'Error: Typed property Skel\Util\Settings::$id must not be accessed before initialization'
+);
+
+var_dump(new Skel\Util\Settings());
+
+// Exceptions declared by the extension extend `Exception`, and can be thrown from Rust and
+// caught as either class.
+try {
+    $line = __LINE__ + 1;
+    skel_fail('not found', Skel\Util\SkelException::NOT_FOUND);
+} catch (Skel\Util\SkelException $e) {
+    $caught = $e;
+}
+
+try {
+    skel_fail('also not found', 1);
+    $parent = null;
+} catch (Exception $e) {
+    $parent = $e;
+}
+
+$constructed = new Skel\Util\SkelException('built in PHP', 7, $caught);
+
+var_dump(
+    $caught instanceof Exception,
+    $caught->getMessage() === 'not found',
+    $caught->getCode() === 404,
+    $caught->getLine() === $line,
+    $caught->getFile() === __FILE__,
+    $parent instanceof Skel\Util\SkelException,
+    $parent->getMessage() === 'also not found',
+    $constructed->getMessage() === 'built in PHP',
+    $constructed->getCode() === 7,
+    $constructed->getPrevious() === $caught,
+    get_parent_class($constructed) === 'Exception'
+);
+
+// Only classes implementing `Throwable` are thrown.
+var_dump(
+    arg_error(fn () => skel_throw('RuntimeException', 'thrown')) === 'RuntimeException: thrown',
+    skel_throw('stdClass', 'not thrown') === 'Class `stdClass` cannot be thrown as it does not implement Throwable.'
+);
+
+// Methods can be abstract, static, final, protected or private, which the engine enforces.
+class Square extends Skel\Util\Shape
+{
+    public function area(): float
+    {
+        return 4.0;
+    }
+
+    public function callHelper(): string
+    {
+        return $this->helper();
+    }
+}
+
+function method_modifiers(string $method): int
+{
+    return (new ReflectionMethod(Skel\Util\Shape::class, $method))->getModifiers();
+}
+
+$square = new Square();
+$privateCall = null;
+try {
+    $square->secret();
+} catch (Error $e) {
+    $privateCall = $e->getMessage();
+}
+
+var_dump(
+    (new ReflectionClass(Skel\Util\Shape::class))->isAbstract(),
+    method_modifiers('area') === (ReflectionMethod::IS_PUBLIC | ReflectionMethod::IS_ABSTRACT),
+    method_modifiers('create') === (ReflectionMethod::IS_PUBLIC | ReflectionMethod::IS_STATIC),
+    method_modifiers('describe') === (ReflectionMethod::IS_PUBLIC | ReflectionMethod::IS_FINAL),
+    method_modifiers('helper') === ReflectionMethod::IS_PROTECTED,
+    method_modifiers('secret') === ReflectionMethod::IS_PRIVATE,
+    Square::create() === 'Square',
+    Skel\Util\Shape::create() === 'Skel\Util\Shape',
+    $square->describe() === 'Square shape',
+    $square->area() === 4.0,
+    $square->callHelper() === 'helper',
+    $privateCall === 'Call to private method Skel\Util\Shape::secret() from global scope'
+);
+
+// The strings known to the engine are looked up by their identifiers.
+var_dump(skel_known_strings() === ['file', 'name', 'mixed']);
+
+// Appending to a persistent interned string copies it into a request string.
+var_dump(skel_append_known('s') === 'names', skel_append_known('') === 'name');
+
+// An exception which was already pending is not mistaken for one thrown by a comparison.
+var_dump(skel_compare_pending(1, 2) === -1, skel_compare_pending('b', 'a') === 1);
+
+// Returns the result of the function, or the exception it threw, along with the errors it
+// emitted.
+function with_errors(callable $f): array
+{
+    $errors = [];
+    set_error_handler(function (int $errno, string $errstr) use (&$errors) {
+        $errors[] = [$errno, $errstr];
+        return true;
+    });
+
+    try {
+        $result = $f();
+    } catch (Throwable $e) {
+        $result = get_class($e) . ': ' . $e->getMessage();
+    } finally {
+        restore_error_handler();
+    }
+
+    return [$result, $errors];
+}
+
+// Values are coerced into strings as a `(string)` cast would, including the warning for arrays
+// and the error for objects which cannot be converted.
+var_dump(
+    skel_coerce_string('abc') === 'abc',
+    skel_coerce_string(42) === '42',
+    skel_coerce_string(-1.5) === '-1.5',
+    skel_coerce_string(1e100) === '1.0E+100',
+    skel_coerce_string(true) === '1',
+    skel_coerce_string(false) === '',
+    skel_coerce_string(null) === '',
+    skel_coerce_string("\xff") === null,
+    skel_coerce_string(new class {
+        public function __toString(): string
+        {
+            return 'stringable';
+        }
+    }) === 'stringable',
+    with_errors(fn () => skel_coerce_string([1])) === ['Array', [[E_WARNING, 'Array to string conversion']]],
+    with_errors(fn () => skel_coerce_string(new stdClass())) === ['Error: Object of class stdClass could not be converted to string', []]
+);
+
+// Values are coerced into integers as an `(int)` cast would, without emitting warnings for
+// strings with trailing data.
+$values = ['42abc', '1e3', ' 12', 'abc', '', true, false, null, 9.9, -9.9, 1e19, INF, NAN, [], [0]];
+var_dump(
+    skel_coerce_long('42abc') === 42,
+    skel_coerce_long('1e3') === 1000,
+    skel_coerce_long(true) === 1,
+    skel_coerce_long(9.9) === 9,
+    with_errors(fn () => array_map('skel_coerce_long', $values)) === [
+        array_map(fn ($value) => (int) $value, $values),
+        [],
+    ]
+);
+
+// Tuples are converted from and into arrays holding their elements at the keys `0` to `n - 1`.
+$pairError = 'TypeError: skel_swap(): Argument #1 ($pair) must be of type array, array given';
+var_dump(
+    skel_swap(['a', 1]) === [1, 'a'],
+    skel_swap([1 => 2, 0 => 'b']) === [2, 'b'],
+    skel_split(['x', 1.5, [true, false]]) === [[true, false], 1.5, 'x'],
+    arg_error(fn () => skel_swap(['a'])) === $pairError,
+    arg_error(fn () => skel_swap(['a', 1, 2])) === $pairError,
+    arg_error(fn () => skel_swap(['a', 'b'])) === $pairError,
+    arg_error(fn () => skel_swap(['x' => 'a', 'y' => 1])) === $pairError,
+    arg_error(fn () => skel_split(['x', 1.5])) === 'TypeError: skel_split(): Argument #1 ($parts) must be of type array, array given'
+);
+
+// Return values which cannot be converted throw instead of returning null.
+var_dump(
+    skel_unsigned(5) === 5,
+    arg_error(fn () => skel_unsigned(-1)) === 'ValueError: skel_unsigned(): Return value could not be converted: Integer `18446744073709551615` is out of range for type `ZendLong`.'
+);
+
+// Structs, maps, sequences and enums are converted through `serde`. Objects are read through
+// their public properties.
+$order = [
+    'id' => 7,
+    'items' => [
+        ['sku' => 'A-1', 'quantity' => 2, 'price' => 9.5],
+        ['sku' => 'B-2', 'quantity' => 1, 'price' => 0.0],
+    ],
+    'tags' => ['10' => 'ten', 'gift' => 'yes'],
+    'status' => 'Pending',
+    'note' => null,
+];
+$shipped = ['status' => ['Shipped' => 'tracking-1'], 'note' => 'fragile'] + $order;
+$cancelled = ['status' => ['Cancelled' => ['reason' => 'late', 'refunded' => true]]] + $order;
+$object = new class {
+    public $id = 7;
+    public $items = [];
+    public $tags = [];
+    public $status = 'Pending';
+    private $secret = 'hidden';
+};
+var_dump(
+    skel_order($order) === $order,
+    skel_order($shipped) == $shipped,
+    skel_order($cancelled) == $cancelled,
+    skel_order((object) $order) === $order,
+    skel_order($object) === ['id' => 7, 'items' => [], 'tags' => [], 'status' => 'Pending', 'note' => null],
+    skel_order(['id' => 7] + $order) === $order,
+    skel_order(['items' => [['sku' => 'A-1', 'quantity' => 300, 'price' => 1.0]]] + $order) === null,
+    skel_order(['status' => 'Lost'] + $order) === null,
+    skel_order(['status' => ['Shipped' => 'a', 'Pending' => null]] + $order) === null,
+    skel_order(array_diff_key($order, ['id' => true])) === null,
+    skel_order('order') === null
+);
+
+// Values are converted in place as casts would, emitting the same warnings and errors.
+$values = ['12abc', '1.5', '', '0', 'abc', 0, -1.9, 1e100, true, false, null, [1, 2], []];
+$casts = [
+    'int' => fn ($value) => (int) $value,
+    'float' => fn ($value) => (float) $value,
+    'bool' => fn ($value) => (bool) $value,
+    'array' => fn ($value) => (array) $value,
+];
+foreach ($casts as $target => $cast) {
+    var_dump(with_errors(fn () => array_map(fn ($value) => skel_convert($value, $target), $values)) === [
+        array_map($cast, $values),
+        [],
+    ]);
+}
+
+$scalars = array_filter($values, 'is_scalar') + [null];
+$stringable = new class {
+    public function __toString(): string
+    {
+        return 'stringable';
+    }
+};
+var_dump(
+    array_map(fn ($value) => skel_convert($value, 'string'), $scalars) === array_map('strval', $scalars),
+    skel_convert($stringable, 'string') === 'stringable',
+    skel_convert((object) ['a' => 1], 'array') === ['a' => 1],
+    with_errors(fn () => skel_convert([1], 'string')) === ['Array', [[E_WARNING, 'Array to string conversion']]],
+    with_errors(fn () => skel_convert(new stdClass(), 'string')) === ['Error: Object of class stdClass could not be converted to string', []],
+    with_errors(fn () => skel_convert(new stdClass(), 'int')) === [1, [[E_WARNING, 'Object of class stdClass could not be converted to int']]]
+);
+
+// Warnings turned into exceptions by an error handler fail the conversion.
+set_error_handler(function (int $errno, string $errstr) {
+    throw new ErrorException($errstr, 0, $errno);
+});
+try {
+    $converted = skel_convert([1], 'string');
+} catch (ErrorException $e) {
+    $converted = $e->getMessage();
+}
+restore_error_handler();
+var_dump($converted === 'Array to string conversion');
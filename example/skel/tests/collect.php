@@ -0,0 +1,36 @@
+<?php
+
+// Checks that collecting Rust iterators into arrays gives the same arrays as building them in
+// PHP. Exits with a non-zero status if a check fails:
+//
+//     php -d extension=target/debug/libskel.so tests/collect.php
+
+$failed = 0;
+
+function check(string $name, bool $passed): void
+{
+    global $failed;
+
+    if (!$passed) {
+        echo "FAILED: $name\n";
+        $failed++;
+    }
+}
+
+$text = "the quick  brown\tfox jumps over the lazy dog";
+$words = preg_split('/\s+/', $text);
+
+check('strings', skel_words($text) === $words);
+check('no strings', skel_words('  ') === []);
+
+$lengths = [];
+foreach ($words as $word) {
+    $lengths[$word] = strlen($word);
+}
+check('string keys', skel_word_lengths($text) === $lengths);
+check('numeric string keys', skel_word_lengths('10 -1 01') === [10 => 2, -1 => 2, '01' => 2]);
+
+check('characters', skel_chars('añ€😀') === ['a', 'ñ', '€', '😀']);
+
+echo $failed === 0 ? "All collect checks passed\n" : "$failed collect checks failed\n";
+exit($failed === 0 ? 0 : 1);
@@ -0,0 +1,69 @@
+<?php
+
+// Checks that walking arrays built by the engine from Rust yields the same keys and values, in
+// the same order, as `foreach`. Exits with a non-zero status if a check fails:
+//
+//     php -d extension=target/debug/libskel.so tests/iter.php
+
+$failed = 0;
+
+function check(string $name, bool $passed): void
+{
+    global $failed;
+
+    if (!$passed) {
+        echo "FAILED: $name\n";
+        $failed++;
+    }
+}
+
+function entries(array $array): array
+{
+    $entries = [];
+    foreach ($array as $key => $value) {
+        $entries[] = [$key, $value];
+    }
+
+    return $entries;
+}
+
+$packed = [1, 'two', 3.0, null, [4]];
+check('packed array', skel_entries($packed) === entries($packed));
+check('empty array', skel_entries([]) === []);
+
+$mixed = ['a' => 1, 5 => 'b', '10' => 'c', 'd' => [], -3 => false, "\0bin\xff" => 'e'];
+check('mixed keys', skel_entries($mixed) === entries($mixed));
+check('numeric string keys are integers', skel_entries($mixed)[2][0] === 10);
+
+// Unsetting elements leaves holes in the buckets, which are skipped.
+$holes = range(0, 9);
+unset($holes[0], $holes[4], $holes[9]);
+check('packed array with holes', skel_entries($holes) === entries($holes));
+
+$holes = $mixed;
+unset($holes['a'], $holes[-3]);
+$holes['f'] = 'appended';
+check('hash with holes', skel_entries($holes) === entries($holes));
+
+$holes = ['x' => 1, 'y' => 2];
+unset($holes['x'], $holes['y']);
+check('array of holes', skel_entries($holes) === []);
+
+// The internal pointer of the array is neither used nor moved.
+$pointer = ['a' => 1, 'b' => 2, 'c' => 3];
+next($pointer);
+check('internal pointer', skel_entries($pointer) === entries($pointer) && key($pointer) === 'b');
+
+end($pointer);
+next($pointer);
+check('internal pointer past the end', count(skel_entries($pointer)) === 3 && key($pointer) === null);
+
+$seen = [];
+foreach ($pointer as $key => &$value) {
+    $seen[] = count(skel_entries($pointer));
+}
+unset($value);
+check('while iterated by reference', $seen === [3, 3, 3]);
+
+echo $failed === 0 ? "All iteration checks passed\n" : "$failed iteration checks failed\n";
+exit($failed === 0 ? 0 : 1);
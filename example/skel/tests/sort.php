@@ -0,0 +1,55 @@
+<?php
+
+// Checks that the sorting functions of the extension order arrays as PHP does. Exits with a
+// non-zero status if a check fails:
+//
+//     php -d extension=target/debug/libskel.so tests/sort.php
+
+$failed = 0;
+
+function check(string $name, bool $passed): void
+{
+    global $failed;
+
+    if (!$passed) {
+        echo "FAILED: $name\n";
+        $failed++;
+    }
+}
+
+$unsorted = ['b' => 10, 'a' => '9', 'c' => 'apple', 'd' => 1.5, 'e' => true, 'f' => null, 'g' => '10'];
+$flagNames = [
+    'SORT_REGULAR' => SORT_REGULAR,
+    'SORT_NUMERIC' => SORT_NUMERIC,
+    'SORT_STRING' => SORT_STRING,
+    'SORT_STRING | SORT_FLAG_CASE' => SORT_STRING | SORT_FLAG_CASE,
+    'SORT_NATURAL' => SORT_NATURAL,
+];
+foreach ($flagNames as $name => $flags) {
+    $expected = $unsorted;
+    sort($expected, $flags);
+    check("sort with $name", skel_sort($unsorted, 'sort', $flags) === $expected);
+
+    $expected = $unsorted;
+    asort($expected, $flags);
+    check("asort with $name", skel_sort($unsorted, 'asort', $flags) === $expected);
+}
+
+$keys = [10 => 'a', '9' => 'b', 'x' => 'c', '-1' => 'd', 'X' => 'e'];
+$expected = $keys;
+ksort($expected);
+check('ksort', skel_sort($keys, 'ksort', 0) === $expected);
+
+$ints = [3, 1, 2, 1, 5];
+$expected = $ints;
+usort($expected, fn ($a, $b) => $b <=> $a);
+check('usort', skel_sort($ints, 'usort', 0) === $expected);
+check('usort leaves the argument untouched', $ints === [3, 1, 2, 1, 5]);
+
+// A panicking comparison function does not unwind through the engine, and the array is left
+// holding all of its elements.
+$sorted = skel_sort($ints, 'usort_panic', 0);
+check('panicking usort', is_array($sorted) && count($sorted) === count($ints));
+
+echo $failed === 0 ? "All sort checks passed\n" : "$failed sort checks failed\n";
+exit($failed === 0 ? 0 : 1);
@@ -0,0 +1,445 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::{
+    AttributeArgs, Error, FnArg, GenericArgument, ItemFn, Lit, Meta, NestedMeta, Pat,
+    PathArguments, Result, ReturnType, Type,
+};
+
+use crate::State;
+
+/// The options given to `#[php_function]`.
+#[derive(Default)]
+struct Attrs {
+    /// The name of the function in PHP, if different to the Rust name.
+    name: Option<String>,
+    /// The parameters passed by reference.
+    by_ref: Vec<Ident>,
+}
+
+/// A parameter of the exported function.
+struct Param {
+    ident: Ident,
+    ty: Type,
+    by_ref: bool,
+    optional: bool,
+}
+
+/// Generates the handler and the function entry for a Rust function, keeping the function as
+/// it is. The name of the function entry is recorded for `#[php_module]`.
+pub fn parser(args: AttributeArgs, input: ItemFn, state: &mut State) -> Result<TokenStream> {
+    let attrs = parse_attrs(args)?;
+    let sig = &input.sig;
+
+    if state.module {
+        return Err(Error::new_spanned(
+            &sig.ident,
+            "`#[php_function]` must be used before `#[php_module]`, unless the function is \
+             listed in `#[php_module(functions(...))]`",
+        ));
+    }
+
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "async functions cannot be exported to PHP",
+        ));
+    }
+
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "generic functions cannot be exported to PHP",
+        ));
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            "variadic functions cannot be exported to PHP",
+        ));
+    }
+
+    let ident = &sig.ident;
+    let name = attrs.name.clone().unwrap_or_else(|| ident.to_string());
+    let params = parse_params(&input, &attrs)?;
+    let output = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::ImplTrait(_) => {
+                return Err(Error::new_spanned(
+                    ty,
+                    "functions returning `impl Trait` cannot be exported to PHP",
+                ))
+            }
+            ty => quote! { #ty },
+        },
+    };
+
+    let handler = handler_ident(ident);
+    let entry = entry_ident(ident);
+    let parse = params.iter().map(parse_param);
+    let arg_info = params.iter().map(arg_info);
+
+    // Locals are hygienic so that they cannot clash with the names of the parameters, and the
+    // arguments cannot shadow the function, e.g. in `fn name(name: String)`.
+    let idents = params
+        .iter()
+        .map(|param| Ident::new(&param.ident.to_string(), Span::mixed_site()))
+        .collect::<Vec<_>>();
+    let execute_data = Ident::new("execute_data", Span::mixed_site());
+    let retval = Ident::new("retval", Span::mixed_site());
+    let result = Ident::new("result", Span::mixed_site());
+
+    state.functions.push(entry.to_string());
+
+    Ok(quote! {
+        #input
+
+        #[doc(hidden)]
+        pub extern "C" fn #handler(
+            #execute_data: &mut ::ext_php_rs::php::execution_data::ExecutionData,
+            #retval: &mut ::ext_php_rs::php::types::zval::Zval,
+        ) {
+            #execute_data.record_return_value(#retval);
+
+            let (#(#idents,)*) = match #execute_data
+                .parse_args()
+                #(#parse)*
+                .finish()
+            {
+                Ok(args) => args,
+                Err(_) => return,
+            };
+
+            let #result = #ident(#(#idents),*);
+            ::ext_php_rs::php::function::set_return_value(#retval, #result);
+        }
+
+        #[doc(hidden)]
+        pub fn #entry() -> ::ext_php_rs::php::function::FunctionEntry {
+            ::ext_php_rs::php::function::FunctionBuilder::new(#name, #handler)
+                #(#arg_info)*
+                .returns_type::<#output>()
+                .build()
+        }
+    })
+}
+
+/// Returns the name of the handler generated for a function.
+fn handler_ident(ident: &Ident) -> Ident {
+    Ident::new(&format!("_internal_php_{}", ident), ident.span())
+}
+
+/// Returns the name of the function returning the function entry generated for a function.
+pub fn entry_ident(ident: &Ident) -> Ident {
+    Ident::new(&format!("_internal_php_{}_function", ident), ident.span())
+}
+
+/// Parses the options given to the attribute, `name = "..."` and `by_ref(...)`.
+fn parse_attrs(args: AttributeArgs) -> Result<Attrs> {
+    let mut attrs = Attrs::default();
+
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("name") => {
+                match meta.lit {
+                    Lit::Str(name) => attrs.name = Some(name.value()),
+                    lit => return Err(Error::new_spanned(lit, "expected a string")),
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("by_ref") => {
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                            attrs.by_ref.push(path.get_ident().unwrap().clone())
+                        }
+                        nested => {
+                            return Err(Error::new_spanned(nested, "expected a parameter name"))
+                        }
+                    }
+                }
+            }
+            arg => {
+                return Err(Error::new_spanned(
+                    arg,
+                    "unknown option, expected `name = \"...\"` or `by_ref(...)`",
+                ))
+            }
+        }
+    }
+
+    Ok(attrs)
+}
+
+/// Parses the parameters of the function. Trailing parameters of type `Option` are optional,
+/// so that `None` is passed when they are omitted.
+fn parse_params(input: &ItemFn, attrs: &Attrs) -> Result<Vec<Param>> {
+    let mut params = Vec::with_capacity(input.sig.inputs.len());
+
+    for input in input.sig.inputs.iter() {
+        let typed = match input {
+            FnArg::Typed(typed) => typed,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver,
+                    "methods cannot be exported as PHP functions",
+                ))
+            }
+        };
+
+        let ident = match &*typed.pat {
+            Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => pat.ident.clone(),
+            pat => {
+                return Err(Error::new_spanned(
+                    pat,
+                    "parameters must be named to be exported to PHP",
+                ))
+            }
+        };
+
+        params.push(Param {
+            by_ref: attrs.by_ref.contains(&ident),
+            ident,
+            ty: (*typed.ty).clone(),
+            optional: false,
+        });
+    }
+
+    if let Some(ident) = attrs
+        .by_ref
+        .iter()
+        .find(|ident| !params.iter().any(|param| &param.ident == *ident))
+    {
+        return Err(Error::new_spanned(ident, "no parameter with this name"));
+    }
+
+    for param in params.iter_mut().rev() {
+        if param.by_ref || !is_option(&param.ty) {
+            break;
+        }
+
+        param.optional = true;
+    }
+
+    Ok(params)
+}
+
+/// Returns whether a type is an `Option`.
+fn is_option(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => &ty.path,
+        _ => return false,
+    };
+
+    match path.segments.last() {
+        Some(segment) if segment.ident == "Option" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => {
+                matches!(args.args.first(), Some(GenericArgument::Type(_)))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Generates the call reading a parameter from the arguments.
+fn parse_param(param: &Param) -> TokenStream {
+    let name = param.ident.to_string();
+    let ty = &param.ty;
+
+    if param.by_ref {
+        quote! { .arg_by_ref(#name) }
+    } else if param.optional {
+        quote! { .optional::<#ty>(#name) }
+    } else {
+        quote! { .arg::<#ty>(#name) }
+    }
+}
+
+/// Generates the calls declaring a parameter when building the function entry.
+fn arg_info(param: &Param) -> TokenStream {
+    let name = param.ident.to_string();
+    let ty = &param.ty;
+
+    if param.by_ref {
+        quote! {
+            .arg(
+                ::ext_php_rs::php::args::Arg::new(
+                    #name,
+                    ::ext_php_rs::php::enums::DataType::Mixed,
+                )
+                .as_ref()
+            )
+        }
+    } else if param.optional {
+        quote! {
+            .not_required()
+            .arg(::ext_php_rs::php::args::Arg::of::<#ty>(#name).default("null"))
+        }
+    } else {
+        quote! { .arg(::ext_php_rs::php::args::Arg::of::<#ty>(#name)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::{parse::Parser, parse_quote, punctuated::Punctuated, Token};
+
+    fn args(tokens: TokenStream) -> AttributeArgs {
+        Punctuated::<NestedMeta, Token![,]>::parse_terminated
+            .parse2(tokens)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    /// Removes the whitespace from tokens, which depends on how they were parsed.
+    fn compact(tokens: TokenStream) -> String {
+        tokens.to_string().replace(' ', "")
+    }
+
+    fn expand(args: AttributeArgs, input: ItemFn) -> String {
+        compact(parser(args, input, &mut State::default()).unwrap())
+    }
+
+    fn error(args: AttributeArgs, input: ItemFn) -> String {
+        parser(args, input, &mut State::default())
+            .err()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut state = State::default();
+        let out = parser(
+            vec![],
+            parse_quote! {
+                fn add(a: i64, b: Option<i64>) -> i64 {
+                    a + b.unwrap_or(0)
+                }
+            },
+            &mut state,
+        )
+        .unwrap();
+        let out = compact(out);
+
+        assert!(out.contains(&compact(quote!(fn add(a: i64, b: Option<i64>) -> i64))));
+        assert!(out.contains(&compact(quote!(pub extern "C" fn _internal_php_add))));
+        assert!(out.contains(&compact(quote!(.arg::<i64>("a")))));
+        assert!(out.contains(&compact(quote!(.optional::<Option<i64>>("b")))));
+        assert!(out.contains(&compact(quote!(FunctionBuilder::new(
+            "add",
+            _internal_php_add
+        )))));
+        assert!(out.contains(&compact(quote!(.returns_type::<i64>()))));
+        assert_eq!(state.functions, vec!["_internal_php_add_function"]);
+    }
+
+    #[test]
+    fn test_after_module() {
+        let mut state = State {
+            module: true,
+            ..State::default()
+        };
+        let err = parser(
+            vec![],
+            parse_quote!(
+                fn f() {}
+            ),
+            &mut state,
+        )
+        .err()
+        .unwrap();
+
+        assert!(err
+            .to_string()
+            .contains("must be used before `#[php_module]`"));
+        assert!(state.functions.is_empty());
+    }
+
+    #[test]
+    fn test_optional() {
+        let out = expand(
+            vec![],
+            parse_quote! {
+                fn pad(a: Option<i64>, b: String, c: Option<String>, d: Option<bool>) {}
+            },
+        );
+
+        // Only the trailing options are optional.
+        assert!(out.contains(&compact(quote!(.arg::<Option<i64>>("a")))));
+        assert!(out.contains(&compact(quote!(.arg::<String>("b")))));
+        assert!(out.contains(&compact(quote!(.optional::<Option<String>>("c")))));
+        assert!(out.contains(&compact(quote!(.optional::<Option<bool>>("d")))));
+        assert!(out.contains(&compact(quote!(.returns_type::<()>()))));
+    }
+
+    #[test]
+    fn test_attrs() {
+        let out = expand(
+            args(quote!(name = "skel_inc", by_ref(value))),
+            parse_quote! {
+                fn inc(mut value: RefArg, by: Option<i64>) {}
+            },
+        );
+
+        assert!(out.contains(&compact(quote!(FunctionBuilder::new(
+            "skel_inc",
+            _internal_php_inc
+        )))));
+        assert!(out.contains(&compact(quote!(.arg_by_ref("value")))));
+        assert!(out.contains(&compact(quote!(.as_ref()))));
+        assert!(out.contains(&compact(quote!(.optional::<Option<i64>>("by")))));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error(
+                vec![],
+                parse_quote!(
+                    fn f<T>(a: T) {}
+                )
+            ),
+            "generic functions cannot be exported to PHP"
+        );
+        assert_eq!(
+            error(
+                vec![],
+                parse_quote!(
+                    fn f(&self) {}
+                )
+            ),
+            "methods cannot be exported as PHP functions"
+        );
+        assert_eq!(
+            error(
+                vec![],
+                parse_quote!(
+                    fn f((a, b): (i64, i64)) {}
+                )
+            ),
+            "parameters must be named to be exported to PHP"
+        );
+        assert_eq!(
+            error(
+                args(quote!(by_ref(b))),
+                parse_quote!(
+                    fn f(a: RefArg) {}
+                )
+            ),
+            "no parameter with this name"
+        );
+        assert_eq!(
+            error(
+                args(quote!(name = 1)),
+                parse_quote!(
+                    fn f() {}
+                )
+            ),
+            "expected a string"
+        );
+    }
+}
@@ -1,10 +1,32 @@
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, AttributeArgs, DeriveInput, ItemFn};
 
 extern crate proc_macro;
 
+mod function;
+mod module;
+
+/// The state shared by the macros while a crate is expanded. Attributes are expanded in the
+/// order they appear in the module, so functions must be exported before the module is built.
+#[derive(Default)]
+pub(crate) struct State {
+    /// The function entries generated by `#[php_function]`, registered by the next
+    /// `#[php_module]`.
+    pub functions: Vec<String>,
+    /// Whether a `#[php_module]` without a list of functions has registered the functions
+    /// above, after which no more functions can be registered.
+    pub module: bool,
+}
+
+lazy_static! {
+    pub(crate) static ref STATE: Mutex<State> = Mutex::new(State::default());
+}
+
 /// Derives the implementation of `ZendObjectOverride` for the given structure.
 #[proc_macro_derive(ZendObjectHandler)]
 pub fn object_handler_derive(input: TokenStream) -> TokenStream {
@@ -43,3 +65,29 @@ pub fn object_handler_derive(input: TokenStream) -> TokenStream {
 
     TokenStream::from(output)
 }
+
+/// Exports a Rust function to PHP. See the documentation of `ext_php_rs::php_function`.
+#[proc_macro_attribute]
+pub fn php_function(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let input = parse_macro_input!(input as ItemFn);
+
+    match function::parser(args, input, &mut STATE.lock().unwrap()) {
+        Ok(output) => output,
+        Err(e) => e.to_compile_error(),
+    }
+    .into()
+}
+
+/// Builds the module loaded by PHP. See the documentation of `ext_php_rs::php_module`.
+#[proc_macro_attribute]
+pub fn php_module(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let input = parse_macro_input!(input as ItemFn);
+
+    match module::parser(args, input, &mut STATE.lock().unwrap()) {
+        Ok(output) => output,
+        Err(e) => e.to_compile_error(),
+    }
+    .into()
+}
@@ -0,0 +1,206 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::{AttributeArgs, Error, ItemFn, Meta, NestedMeta, Path, Result, ReturnType};
+
+use crate::{function::entry_ident, State};
+
+/// Generates the `get_module` function loaded by PHP from a function returning the module
+/// builder, registering the functions listed in `functions(...)`, or otherwise the functions
+/// exported with `#[php_function]` so far.
+pub fn parser(args: AttributeArgs, input: ItemFn, state: &mut State) -> Result<TokenStream> {
+    let listed = parse_attrs(args)?;
+    let sig = &input.sig;
+
+    if sig.ident != "get_module" {
+        return Err(Error::new_spanned(
+            &sig.ident,
+            "the module function must be named `get_module`",
+        ));
+    }
+
+    if !sig.inputs.is_empty() || !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.inputs,
+            "the module function cannot take any parameters",
+        ));
+    }
+
+    if let ReturnType::Default = sig.output {
+        return Err(Error::new_spanned(
+            sig,
+            "the module function must return a `ModuleBuilder`",
+        ));
+    }
+
+    let functions = match listed {
+        Some(paths) => paths.iter().map(entry_path).collect::<Vec<_>>(),
+        None => {
+            state.module = true;
+            state
+                .functions
+                .drain(..)
+                .map(|entry| Ident::new(&entry, Span::call_site()).into())
+                .collect()
+        }
+    };
+
+    let attrs = &input.attrs;
+    let output = &sig.output;
+    let block = &input.block;
+
+    Ok(quote! {
+        #(#attrs)*
+        #[no_mangle]
+        pub extern "C" fn get_module() -> *mut ::ext_php_rs::php::module::ModuleEntry {
+            fn build() #output #block
+
+            build()
+                #(.function(#functions()))*
+                .build()
+                .into_raw()
+        }
+    })
+}
+
+/// Parses the options given to the attribute, returning the functions listed in
+/// `functions(...)`.
+fn parse_attrs(args: AttributeArgs) -> Result<Option<Vec<Path>>> {
+    let mut functions = None;
+
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("functions") => {
+                let paths = functions.get_or_insert_with(Vec::new);
+
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(Meta::Path(path)) => paths.push(path),
+                        nested => {
+                            return Err(Error::new_spanned(nested, "expected a function path"))
+                        }
+                    }
+                }
+            }
+            arg => {
+                return Err(Error::new_spanned(
+                    arg,
+                    "unknown option, expected `functions(...)`",
+                ))
+            }
+        }
+    }
+
+    Ok(functions)
+}
+
+/// Returns the path of the function entry generated for the function at the given path.
+fn entry_path(path: &Path) -> Path {
+    let mut path = path.clone();
+    if let Some(last) = path.segments.last_mut() {
+        last.ident = entry_ident(&last.ident);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::{parse::Parser, parse_quote, punctuated::Punctuated, Token};
+
+    fn args(tokens: TokenStream) -> AttributeArgs {
+        Punctuated::<NestedMeta, Token![,]>::parse_terminated
+            .parse2(tokens)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    /// Removes the whitespace from tokens, which depends on how they were parsed.
+    fn compact(tokens: TokenStream) -> String {
+        tokens.to_string().replace(' ', "")
+    }
+
+    #[test]
+    fn test_registered_functions() {
+        let mut state = State {
+            functions: vec!["_internal_php_add_function".to_string()],
+            ..State::default()
+        };
+        let out = parser(
+            vec![],
+            parse_quote!(
+                fn get_module() -> ModuleBuilder {}
+            ),
+            &mut state,
+        )
+        .unwrap();
+
+        // Functions can no longer be registered once the module is built.
+        assert!(compact(out).contains(".function(_internal_php_add_function())"));
+        assert!(state.functions.is_empty());
+        assert!(state.module);
+    }
+
+    #[test]
+    fn test_listed_functions() {
+        let mut state = State {
+            functions: vec!["_internal_php_add_function".to_string()],
+            ..State::default()
+        };
+        let out = parser(
+            args(quote!(functions(sub, math::mul))),
+            parse_quote!(
+                fn get_module() -> ModuleBuilder {}
+            ),
+            &mut state,
+        )
+        .unwrap();
+        let out = compact(out);
+
+        // Only the listed functions are registered, wherever they are declared.
+        assert!(out.contains(".function(_internal_php_sub_function())"));
+        assert!(out.contains(".function(math::_internal_php_mul_function())"));
+        assert!(!out.contains("_internal_php_add_function"));
+        assert!(!state.module);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |input: ItemFn| {
+            parser(vec![], input, &mut State::default())
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            err(parse_quote!(
+                fn module() -> ModuleBuilder {}
+            )),
+            "the module function must be named `get_module`"
+        );
+        assert_eq!(
+            err(parse_quote!(
+                fn get_module(name: &str) -> ModuleBuilder {}
+            )),
+            "the module function cannot take any parameters"
+        );
+        assert_eq!(
+            err(parse_quote!(
+                fn get_module() {}
+            )),
+            "the module function must return a `ModuleBuilder`"
+        );
+
+        let err = parser(
+            args(quote!(functions("add"))),
+            parse_quote!(
+                fn get_module() -> ModuleBuilder {}
+            ),
+            &mut State::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "expected a function path");
+    }
+}
@@ -0,0 +1,154 @@
+//! Error and result types returned by the library.
+
+use std::{convert::Infallible, error::Error as ErrorTrait, fmt::Display};
+
+use crate::php::{enums::DataType, errors::ThrownException, json};
+
+/// The result type returned by the library.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type returned by the library inside the [`Result`] type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The zval was not of the type required by the conversion.
+    ZvalConversion,
+    /// The element of an array with the given key could not be converted
+    /// into the requested type.
+    ArrayElementConversion(String),
+    /// A string of the given length exceeds the maximum length of a Zend string.
+    StringTooLong(usize),
+    /// The integer value (first field) does not fit into the integer type named by the
+    /// second field.
+    IntegerOutOfRange(String, &'static str),
+    /// The value could not be called as it was not callable.
+    Callable,
+    /// The object does not have a callable method with the given name.
+    UndefinedMethod(String),
+    /// The call to a callable failed without throwing an exception.
+    CallFailed,
+    /// An exception was thrown, e.g. by a callable. The exception is left pending in the
+    /// engine, so it is rethrown when control returns to PHP unless it is cleared.
+    Exception(ThrownException),
+    /// A constant expression could not be evaluated.
+    ConstantEvaluation,
+    /// The engine returned a null pointer, for example when an allocation failed.
+    InvalidPointer,
+    /// Serialized data could not be unserialized due to an error at the given offset.
+    Unserialize(usize),
+    /// A value could not be encoded or decoded as JSON. Contains the error code, as returned
+    /// by `json_last_error`.
+    Json(u32),
+    /// A value could not be pushed onto an array, as an element already exists at the
+    /// largest possible index.
+    NextElementOccupied,
+    /// A value which is freed at the end of the request was inserted into a persistent
+    /// array.
+    NotPersistent,
+    /// The constant with the given name could not be registered, as constants may only be
+    /// strings, integers, floats, booleans or `null`. Contains the type of the value, if known.
+    InvalidConstant(String, Option<DataType>),
+    /// The class constant could not be declared, as the given name is not a valid constant
+    /// name.
+    InvalidConstantName(String),
+    /// The class constant with the given name could not be declared, as the class already has
+    /// a constant with the same name.
+    DuplicateConstant(String),
+    /// The class with the given name could not be thrown, as it does not implement
+    /// `Throwable`.
+    NotThrowable(String),
+    /// The property with the given name could not be declared, as its default value is not
+    /// of the type of the property, or is an object or a resource, which may not be the
+    /// default values of properties declared by extensions.
+    InvalidProperty(String),
+    /// There is no interface with the given name which a class can implement, see
+    /// [`ClassBuilder::implements_named`].
+    ///
+    /// [`ClassBuilder::implements_named`]: crate::php::class::ClassBuilder::implements_named
+    InvalidInterface(String),
+    /// A value could not be converted by the `serde` data format.
+    #[cfg(feature = "serde")]
+    Serde(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ZvalConversion => write!(f, "Zval was not of the required type."),
+            Error::ArrayElementConversion(key) => write!(
+                f,
+                "Array element with key `{}` could not be converted.",
+                key
+            ),
+            Error::StringTooLong(len) => write!(
+                f,
+                "String of length {} exceeds the maximum length of a Zend string.",
+                len
+            ),
+            Error::IntegerOutOfRange(val, ty) => {
+                write!(f, "Integer `{}` is out of range for type `{}`.", val, ty)
+            }
+            Error::Callable => write!(f, "Value was not callable."),
+            Error::UndefinedMethod(name) => write!(f, "Call to undefined method `{}`.", name),
+            Error::CallFailed => write!(f, "The call to the callable failed."),
+            Error::Exception(e) => write!(
+                f,
+                "Exception `{}` was thrown with message `{}` and code {}.",
+                e.class, e.message, e.code
+            ),
+            Error::ConstantEvaluation => write!(f, "Constant expression could not be evaluated."),
+            Error::InvalidPointer => write!(f, "Invalid pointer."),
+            Error::Unserialize(offset) => {
+                write!(f, "Could not unserialize data, error at offset {}.", offset)
+            }
+            Error::Json(code) => write!(f, "{}", json::error_message(*code)),
+            Error::NextElementOccupied => write!(
+                f,
+                "Cannot add element to the array as the next element is already occupied."
+            ),
+            Error::NotPersistent => write!(
+                f,
+                "Value is not persistent and cannot be stored in a persistent array."
+            ),
+            Error::InvalidConstant(name, ty) => write!(
+                f,
+                "Constant `{}` must be a string, integer, float, boolean or null, not {}.",
+                name,
+                match ty {
+                    Some(ty) => format!("{:?}", ty).to_lowercase(),
+                    None => "an unknown type".to_string(),
+                }
+            ),
+            Error::InvalidConstantName(name) => {
+                write!(f, "`{}` is not a valid class constant name.", name)
+            }
+            Error::DuplicateConstant(name) => {
+                write!(f, "Class constant `{}` is declared more than once.", name)
+            }
+            Error::NotThrowable(name) => write!(
+                f,
+                "Class `{}` cannot be thrown as it does not implement Throwable.",
+                name
+            ),
+            Error::InvalidProperty(name) => write!(
+                f,
+                "Default value of property `{}` must be a string, integer, float, boolean, null or array of the type of the property.",
+                name
+            ),
+            Error::InvalidInterface(name) => write!(
+                f,
+                "Interface `{}` does not exist, or was not declared while extensions were starting up.",
+                name
+            ),
+            #[cfg(feature = "serde")]
+            Error::Serde(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl ErrorTrait for Error {}
+
+impl From<Infallible> for Error {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
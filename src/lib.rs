@@ -5,7 +5,103 @@
 #[macro_use]
 pub mod macros;
 pub mod bindings;
+pub mod errors;
 pub mod functions;
 pub mod php;
 
 pub use ext_php_rs_derive::ZendObjectHandler;
+
+/// Exports a Rust function to PHP, generating the handler which parses the arguments and
+/// converts the return value, and the function entry describing its arguments to PHP. The
+/// function is registered by [`macro@php_module`], which must follow all of the exported
+/// functions in the crate, as attributes are expanded in the order they appear. Exporting a
+/// function after the module is a compile error, unless the module lists its functions.
+///
+/// Arguments are converted with [`FromZval`](php::types::zval::FromZval), throwing the same
+/// `TypeError` as the functions built into PHP if an argument has the wrong type. Trailing
+/// arguments of type [`Option`] are optional, and are `None` when they are omitted. The return
+/// value is converted with [`IntoZval`](php::types::zval::IntoZval).
+///
+/// The attribute accepts the following options:
+///
+/// * `name = "..."` - The name of the function in PHP, which defaults to the name of the Rust
+///   function. The name may include a namespace, e.g. `name = "Acme\\Crypto\\hash"`.
+/// * `by_ref(...)` - The arguments passed by reference, which must be of type
+///   [`RefArg`](php::args::RefArg).
+///
+/// ```no_run
+/// use ext_php_rs::{php::{args::RefArg, module::ModuleBuilder}, php_function, php_module};
+///
+/// /// `add(int $a, ?int $b = null): int`
+/// #[php_function]
+/// pub fn add(a: i64, b: Option<i64>) -> i64 {
+///     a + b.unwrap_or(1)
+/// }
+///
+/// /// `ext_increment(&$value): void`
+/// #[php_function(name = "ext_increment", by_ref(value))]
+/// pub fn increment(mut value: RefArg) {
+///     let next = value.val::<i64>().unwrap_or(0) + 1;
+///     let _ = value.set(next);
+/// }
+///
+/// #[php_module]
+/// pub fn get_module() -> ModuleBuilder {
+///     ModuleBuilder::new("ext-name", "0.1.0")
+/// }
+/// ```
+///
+/// Methods and generic functions cannot be exported:
+///
+/// ```compile_fail
+/// use ext_php_rs::php_function;
+///
+/// #[php_function]
+/// pub fn first<T: Clone>(values: Vec<T>) -> Option<T> {
+///     values.first().cloned()
+/// }
+/// ```
+///
+/// Arguments must be of a type which can be converted from a zval:
+///
+/// ```compile_fail
+/// use ext_php_rs::php_function;
+///
+/// #[php_function]
+/// pub fn len(values: std::collections::VecDeque<i64>) -> i64 {
+///     values.len() as i64
+/// }
+/// ```
+pub use ext_php_rs_derive::php_function;
+
+/// Generates the `get_module` function loaded by PHP from a function returning the
+/// [`ModuleBuilder`](php::module::ModuleBuilder), adding the functions exported with
+/// [`macro@php_function`] before building the module. See [`macro@php_function`] for an example.
+///
+/// By default, the functions exported before the module in the crate are added. The
+/// `functions(...)` option lists the functions to add instead, which may be declared anywhere,
+/// including in other modules:
+///
+/// ```no_run
+/// use ext_php_rs::{php::module::ModuleBuilder, php_function, php_module};
+///
+/// #[php_module(functions(hello, util::add))]
+/// pub fn get_module() -> ModuleBuilder {
+///     ModuleBuilder::new("ext-name", "0.1.0")
+/// }
+///
+/// #[php_function]
+/// pub fn hello() -> &'static str {
+///     "Hello"
+/// }
+///
+/// mod util {
+///     use ext_php_rs::php_function;
+///
+///     #[php_function]
+///     pub fn add(a: i64, b: i64) -> i64 {
+///         a + b
+///     }
+/// }
+/// ```
+pub use ext_php_rs_derive::php_module;
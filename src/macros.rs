@@ -52,10 +52,13 @@ macro_rules! _info_table_row {
 /// # Parameters
 ///
 /// * `$fn` - The 'function' to call. Can be an [`Arg`] or a [`Zval`].
-/// * ...`$param` - The parameters to pass to the function. Must be able to be converted into a [`Zval`].
+/// * ...`$param` - The parameters to pass to the function. Must implement [`IntoZvalDyn`].
+///
+/// Returns a [`Result`] containing the return value of the function, or an error if the
+/// function was not callable, threw an exception or a parameter could not be converted.
 #[macro_export]
 macro_rules! call_user_func {
     ($fn: expr, $($param: expr),*) => {
-        $fn.try_call(vec![$($param.into()),*])
+        $fn.try_call(&[$(&$param),*])
     };
 }
@@ -1,15 +1,58 @@
 //! Builder and objects relating to function and method arguments.
+//!
+//! The arguments passed to a function are read with [`ExecutionData::parse_args`], which
+//! checks the number of arguments and converts each of them into a Rust value. If the
+//! arguments are invalid, the same `ArgumentCountError` or `TypeError` is thrown as by the
+//! functions built into PHP, and the function should return straight away:
+//!
+//! ```no_run
+//! use ext_php_rs::php::{execution_data::ExecutionData, types::{array::ZendHashTable, zval::Zval}};
+//!
+//! pub extern "C" fn repeat(execute_data: &mut ExecutionData, retval: &mut Zval) {
+//!     let (name, count, options) = match execute_data
+//!         .parse_args()
+//!         .arg::<String>("name")
+//!         .arg::<i64>("count")
+//!         .optional::<Option<ZendHashTable>>("options")
+//!         .finish()
+//!     {
+//!         Ok(args) => args,
+//!         Err(_) => return,
+//!     };
+//!
+//!     // ...
+//! }
+//! ```
+//!
+//! Errors found after the arguments have been parsed, such as an argument being out of range,
+//! are thrown in the same way with [`ArgError::throw`].
 
-use std::convert::{TryFrom, TryInto};
+use std::{
+    collections::HashMap, convert::TryFrom, ffi::CString, marker::PhantomData, os::raw::c_char,
+};
 
-use super::{enums::DataType, execution_data::ExecutionData, types::zval::Zval};
+use super::{
+    enums::DataType,
+    errors::ThrownException,
+    execution_data::ExecutionData,
+    types::{
+        array::ArrayKey,
+        long::ZendLong,
+        zval::{FromZval, IntoZval, IntoZvalDyn, Zval},
+    },
+};
 
-use crate::bindings::{
-    _zend_expected_type, _zend_expected_type_Z_EXPECTED_ARRAY, _zend_expected_type_Z_EXPECTED_BOOL,
-    _zend_expected_type_Z_EXPECTED_DOUBLE, _zend_expected_type_Z_EXPECTED_LONG,
-    _zend_expected_type_Z_EXPECTED_OBJECT, _zend_expected_type_Z_EXPECTED_RESOURCE,
-    _zend_expected_type_Z_EXPECTED_STRING, zend_internal_arg_info,
-    zend_wrong_parameters_count_error,
+use crate::{
+    bindings::{
+        _zend_expected_type, _zend_expected_type_Z_EXPECTED_ARRAY,
+        _zend_expected_type_Z_EXPECTED_BOOL, _zend_expected_type_Z_EXPECTED_DOUBLE,
+        _zend_expected_type_Z_EXPECTED_LONG, _zend_expected_type_Z_EXPECTED_OBJECT,
+        _zend_expected_type_Z_EXPECTED_RESOURCE, _zend_expected_type_Z_EXPECTED_STRING,
+        zend_argument_error, zend_argument_type_error, zend_argument_value_error,
+        zend_ce_argument_count_error, zend_internal_arg_info, zend_reference,
+        zend_wrong_parameters_count_error, zend_zval_type_name,
+    },
+    errors::{Error, Result},
 };
 
 /// Represents an argument to a function.
@@ -18,6 +61,7 @@ pub struct Arg<'a> {
     pub(crate) _type: DataType,
     pub(crate) as_ref: bool,
     pub(crate) allow_null: bool,
+    pub(crate) variadic: bool,
     pub(crate) default_value: Option<String>,
     pub(crate) zval: Option<&'a Zval>,
 }
@@ -38,11 +82,32 @@ impl<'a> Arg<'a> {
             _type,
             as_ref: false,
             allow_null: false,
+            variadic: false,
             default_value: None,
             zval: None,
         }
     }
 
+    /// Creates a new argument declared with the type accepted by a Rust type, see
+    /// [`FromZval::DATA_TYPE`]. An [`Option`] declares a nullable argument.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the parameter.
+    pub fn of<T>(name: &str) -> Self
+    where
+        T: FromZval<'a>,
+    {
+        let arg = Self::new(name, T::DATA_TYPE);
+
+        // `mixed` already includes `null`, and cannot be declared nullable.
+        match T::DATA_TYPE {
+            DataType::Mixed => arg,
+            _ if T::NULLABLE => arg.allow_null(),
+            _ => arg,
+        }
+    }
+
     /// Sets the argument as a reference.
     #[allow(clippy::wrong_self_convention)]
     pub fn as_ref(mut self) -> Self {
@@ -50,30 +115,69 @@ impl<'a> Arg<'a> {
         self
     }
 
-    /// Sets the argument as nullable.
+    /// Sets the argument as nullable, as in `?string $name`, adding `null` to the type of the
+    /// argument. The argument should be read as an [`Option`] so that `null` is converted into
+    /// `None`.
     pub fn allow_null(mut self) -> Self {
         self.allow_null = true;
         self
     }
 
-    /// Sets the default value for the argument.
+    /// Sets the argument as variadic, collecting all of the remaining arguments passed to the
+    /// function, as in `...$args`. It must be the last argument of the function, and is read
+    /// with [`Args::variadic`].
+    pub fn variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+
+    /// Sets the default value for the argument as a PHP expression, e.g. `10` or
+    /// `PHP_INT_MAX`. Strings must be quoted, see [`Arg::default_value`].
+    ///
+    /// As in PHP, an argument with a default of `null` is implicitly nullable, see
+    /// [`Arg::allow_null`].
     pub fn default<S>(mut self, default: S) -> Self
     where
         S: ToString,
     {
-        self.default_value = Some(default.to_string());
+        let default = default.to_string();
+        if default.eq_ignore_ascii_case("null") {
+            self.allow_null = true;
+        }
+
+        self.default_value = Some(default);
         self
     }
 
+    /// Sets the default value for the argument from a Rust value, which is written as a PHP
+    /// literal. The default is reported by `ReflectionParameter::getDefaultValue`, and is
+    /// read back when parsing the arguments with [`Args::optional_declared`], so that it is
+    /// only declared once.
+    ///
+    /// # Parameters
+    ///
+    /// * `default` - The default value of the argument.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is an integer outside of the range of PHP integers, which cannot
+    /// be the default of an `int` argument.
+    pub fn default_value<T>(self, default: T) -> Self
+    where
+        T: ArgDefault,
+    {
+        self.default(default.to_php_literal())
+    }
+
     /// Attempts to retrieve the value of the argument.
     /// This will be None until the ArgParser is used to parse
     /// the arguments.
     pub fn val<T>(&self) -> Option<T>
     where
-        T: TryFrom<&'a Zval>,
+        T: FromZval<'a>,
     {
         match self.zval {
-            Some(zval) => match zval.try_into() {
+            Some(zval) => match T::from_zval(zval) {
                 Ok(val) => Some(val),
                 Err(_) => None,
             },
@@ -91,22 +195,23 @@ impl<'a> Arg<'a> {
         self.zval
     }
 
-    /// Attempts to call the argument as a callable with a list of arguments to pass to the function.
-    /// Note that a thrown exception inside the callable is not detectable, therefore you should
-    /// check if the return value is valid rather than unwrapping.
+    /// Attempts to call the argument as a callable with a list of arguments to pass to the
+    /// function. See [`Zval::try_call`].
     ///
     /// You should not call this function directly, rather through the [`call_user_func`] macro.
     ///
     /// # Parameters
     ///
-    /// * `params` - A list of parameters to call the function with.
+    /// * `params` - A list of parameters to call the function with, e.g. `&[&5, &"hello"]`.
     ///
     /// # Returns
     ///
-    /// * `Some(Zval)` - The result of the function call.
-    /// * `None` - The argument was empty, the argument was not callable or the call failed.
-    pub fn try_call(&self, params: Vec<Zval>) -> Option<Zval> {
-        self.zval()?.try_call(params)
+    /// * `Ok(Zval)` - The result of the function call.
+    /// * `Err(Error::Callable)` - The argument was empty or was not callable.
+    /// * `Err(Error)` - The call failed or threw an exception, or a parameter could not be
+    ///   converted into a zval.
+    pub fn try_call(&self, params: &[&dyn IntoZvalDyn]) -> Result<Zval> {
+        self.zval().ok_or(Error::Callable)?.try_call(params)
     }
 }
 
@@ -131,19 +236,74 @@ impl From<Arg<'_>> for _zend_expected_type {
     }
 }
 
+/// An argument passed by reference, such as `$out` in `preg_match($pattern, $subject, $out)`.
+/// Assigning to the argument writes the value into the caller's variable. Returned by
+/// [`ExecutionData::get_arg_by_ref`].
+pub struct RefArg<'a> {
+    zval: &'a mut Zval,
+}
+
+impl<'a> RefArg<'a> {
+    /// Wraps an argument which must be a reference.
+    pub(crate) fn new(zval: &'a mut Zval) -> Self {
+        debug_assert!(zval.is_reference(), "argument is not a reference");
+        Self { zval }
+    }
+
+    /// Returns the current value of the referenced variable.
+    pub fn get(&self) -> &Zval {
+        self.zval.dereference()
+    }
+
+    /// Attempts to convert the current value of the referenced variable.
+    pub fn val<'b, T>(&'b self) -> Option<T>
+    where
+        T: FromZval<'b>,
+    {
+        T::from_zval(self.get()).ok()
+    }
+
+    /// Returns the underlying reference, which is shared with the caller's variable.
+    pub fn reference(&mut self) -> &mut zend_reference {
+        unsafe { &mut *self.zval.value.ref_ }
+    }
+
+    /// Writes a value into the caller's variable, releasing its previous value. See
+    /// [`Zval::set_value_of_reference`].
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The value was written.
+    /// * `Err(Error)` - The value could not be converted into a zval, or the variable is a
+    ///   typed property which does not accept the value. In the latter case, a `TypeError` is
+    ///   left pending.
+    pub fn set<T>(&mut self, val: T) -> Result<()>
+    where
+        T: IntoZval,
+    {
+        self.zval.set_value_of_reference(val.into_zval()?)
+    }
+}
+
 /// Internal argument information used by Zend.
 pub type ArgInfo = zend_internal_arg_info;
 
-/// Parses the arguments of a function.
+/// Parses the arguments of a function into a list of [`Arg`]s. [`ExecutionData::parse_args`]
+/// should be preferred, as it also checks the types of the arguments.
 pub struct ArgParser<'a, 'b> {
     args: Vec<&'a mut Arg<'b>>,
     min_num_args: Option<u32>,
-    execute_data: *mut ExecutionData,
+    execute_data: &'b ExecutionData,
 }
 
 impl<'a, 'b> ArgParser<'a, 'b> {
-    /// Builds a new function argument parser.
-    pub fn new(execute_data: *mut ExecutionData) -> Self {
+    /// Builds a new function argument parser. The values of the arguments borrow from the
+    /// execution data, so they cannot be used once the function returns.
+    pub fn new(execute_data: &'b ExecutionData) -> Self {
         ArgParser {
             args: vec![],
             min_num_args: None,
@@ -181,7 +341,7 @@ impl<'a, 'b> ArgParser<'a, 'b> {
     /// passed to the function. The user has already been notified so you
     /// can discard and return from the function if an `Err` is received.
     pub fn parse(mut self) -> Result<(), String> {
-        let execute_data = unsafe { self.execute_data.as_ref() }.unwrap();
+        let execute_data = self.execute_data;
         let num_args = unsafe { execute_data.This.u2.num_args };
         let max_num_args = self.args.len() as u32;
         let min_num_args = match self.min_num_args {
@@ -225,3 +385,1032 @@ impl<'a, 'b> ArgParser<'a, 'b> {
         Ok(())
     }
 }
+
+/// Parses the arguments passed to a function into a tuple of Rust values, checking the number
+/// of arguments and converting each of them with [`FromZval`]. Created by
+/// [`ExecutionData::parse_args`], see the [module documentation](self) for an example.
+///
+/// Arguments are declared in order with [`Args::arg`] for required arguments, followed by
+/// [`Args::optional`] for optional arguments and [`Args::variadic`] for the remaining
+/// arguments. [`Args::finish`] returns the converted values,
+/// or throws the same errors as the functions built into PHP:
+///
+/// * An `ArgumentCountError` if too few or too many arguments were passed, e.g.
+///   `foo() expects exactly 2 arguments, 1 given`.
+/// * A `TypeError` if an argument could not be converted, e.g.
+///   `foo(): Argument #2 ($count) must be of type int, string given`. The type is taken from
+///   [`FromZval::TYPE`].
+/// * A `ValueError` if an integer argument is out of range for the Rust integer type.
+///
+/// Scalar arguments are coerced in the same way as for the functions built into PHP, unless
+/// the caller uses strict types, see [`ExecutionData::uses_strict_types`]. For example, the
+/// numeric string `"5"` is accepted as an `int`, and an integer is accepted as a `string`.
+///
+/// Borrowed values such as `&str` borrow from the execution data, so they cannot be kept once
+/// the function returns:
+///
+/// ```compile_fail,E0521
+/// use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+///
+/// static mut NAMES: Vec<&str> = Vec::new();
+///
+/// pub extern "C" fn remember(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+///     if let Ok((name,)) = execute_data.parse_args().arg::<&str>("name").finish() {
+///         unsafe { NAMES.push(name) };
+///     }
+/// }
+/// ```
+///
+/// The arguments are coerced in place, so arguments read before parsing cannot be used once
+/// the arguments have been parsed, as they could have been converted:
+///
+/// ```compile_fail,E0502
+/// use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+///
+/// pub extern "C" fn describe(execute_data: &mut ExecutionData, retval: &mut Zval) {
+///     let raw = execute_data.get_arg::<&str>(0);
+///     if let Ok((count,)) = execute_data.parse_args().arg::<i64>("count").finish() {
+///         let _ = retval.set_string(format!("{:?} {}", raw, count));
+///     }
+/// }
+/// ```
+pub struct Args<'a, T = ()> {
+    // The arguments are converted in place, and the frame is borrowed mutably for as long as
+    // the converted values are used, so no other references to the arguments exist.
+    execute_data: *mut ExecutionData,
+    _marker: PhantomData<&'a mut ExecutionData>,
+    values: Option<T>,
+    num_args: u32,
+    min_num_args: Option<u32>,
+    variadic: bool,
+    strict: bool,
+    error: Option<ArgError<'a>>,
+}
+
+/// An error in the arguments passed to a function, thrown with [`ArgError::throw`] as the same
+/// exception the functions built into PHP would throw. Messages are prefixed with the name of
+/// the function and the number and name of the argument, e.g. `foo(): Argument #2 ($count)`,
+/// where the name is taken from the arguments the function was registered with.
+pub enum ArgError<'a> {
+    /// Too few or too many arguments were passed, thrown as an `ArgumentCountError`, e.g.
+    /// `foo() expects exactly 2 arguments, 1 given`. Use `u32::MAX` as the maximum for a
+    /// function with a variadic argument.
+    Count { min: u32, max: u32 },
+    /// A required argument was skipped by a call with named arguments, thrown as an
+    /// `ArgumentCountError`, e.g. `foo(): Argument #1 ($name) not passed`.
+    NotPassed { num: u32 },
+    /// An argument was not of the expected type, thrown as a `TypeError`, e.g.
+    /// `foo(): Argument #2 ($count) must be of type int, string given`. Arguments are numbered
+    /// from 1.
+    Type {
+        num: u32,
+        expected: String,
+        given: &'a Zval,
+    },
+    /// An argument had the correct type but an invalid value, thrown as a `ValueError`. The
+    /// message follows the argument, e.g. `must be greater than 0`.
+    Value { num: u32, message: String },
+}
+
+impl ArgError<'_> {
+    /// Throws the error as an exception. The function should then return straight away
+    /// without setting a return value, so the exception is thrown in the caller.
+    ///
+    /// # Returns
+    ///
+    /// The error for the pending exception, which can be returned from a function returning
+    /// a [`Result`].
+    pub fn throw(self) -> Error {
+        match self {
+            Self::Count { min, max } => unsafe { zend_wrong_parameters_count_error(min, max) },
+            Self::NotPassed { num } => unsafe {
+                zend_argument_error(
+                    zend_ce_argument_count_error,
+                    num,
+                    b"not passed\0".as_ptr() as *const c_char,
+                )
+            },
+            Self::Type {
+                num,
+                expected,
+                given,
+            } => {
+                let expected = CString::new(expected).unwrap_or_default();
+
+                unsafe {
+                    zend_argument_type_error(
+                        num,
+                        b"must be of type %s, %s given\0".as_ptr() as *const c_char,
+                        expected.as_ptr(),
+                        zend_zval_type_name(given),
+                    )
+                };
+            }
+            Self::Value { num, message } => {
+                let message = CString::new(message).unwrap_or_default();
+
+                unsafe {
+                    zend_argument_value_error(
+                        num,
+                        b"%s\0".as_ptr() as *const c_char,
+                        message.as_ptr(),
+                    )
+                };
+            }
+        }
+
+        pending_error()
+    }
+}
+
+impl<'a> Args<'a> {
+    /// Creates a parser for the arguments passed in the given execution data.
+    pub(crate) fn new(execute_data: &'a mut ExecutionData) -> Self {
+        Self {
+            strict: execute_data.uses_strict_types(),
+            execute_data,
+            _marker: PhantomData,
+            values: Some(()),
+            num_args: 0,
+            min_num_args: None,
+            variadic: false,
+            error: None,
+        }
+    }
+}
+
+impl<'a, T> Args<'a, T> {
+    /// Returns the execution data the arguments are read from.
+    fn execute_data(&self) -> &'a ExecutionData {
+        unsafe { &*self.execute_data }
+    }
+
+    /// Returns a pointer to the argument at the given offset, or `None` if it was not passed.
+    /// Each argument is only read once by the parser.
+    fn arg_ptr(&self, num: u32) -> Option<*mut Zval> {
+        unsafe { self.execute_data().zend_call_arg(num as usize) }
+            .map(|zval| zval as *const Zval as *mut Zval)
+    }
+
+    /// Declares the next argument as required.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the argument, which should be the same as the name it was
+    ///   registered with, see [`Arg::new`]. Error messages show the registered name.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if an optional argument has already been declared.
+    pub fn arg<U>(self, name: &str) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+        U: FromZval<'a>,
+    {
+        debug_assert!(
+            self.min_num_args.is_none(),
+            "required argument `{}` declared after an optional argument",
+            name
+        );
+
+        self.next(name, || None)
+    }
+
+    /// Declares the next argument as required and passed by reference, such as `&$out`, so
+    /// that a value can be written back to the caller's variable. The argument should also be
+    /// declared as a reference when registering the function, see [`Arg::as_ref`]. As with
+    /// [`ExecutionData::get_arg_by_ref`], an argument which is not a reference is wrapped in
+    /// a new reference in place.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the argument, shown in error messages. See [`Args::arg`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if an optional or variadic argument has already been declared.
+    pub fn arg_by_ref(mut self, name: &str) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<RefArg<'a>>,
+    {
+        debug_assert!(
+            self.min_num_args.is_none() && !self.variadic,
+            "argument `{}` passed by reference declared after an optional argument",
+            name
+        );
+
+        let num = self.num_args;
+        self.num_args += 1;
+
+        let value = match self.arg_ptr(num) {
+            Some(ptr) => {
+                // The frame is borrowed mutably for `'a` by the parser, and each argument is
+                // only read once, so the argument is not borrowed anywhere else.
+                unsafe {
+                    if !(*ptr).is_reference() {
+                        let val = *ptr;
+                        (*ptr).set_reference(val);
+                    }
+
+                    Some(RefArg::new(&mut *ptr))
+                }
+            }
+            None => {
+                self.not_passed(num);
+                None
+            }
+        };
+
+        self.push(value)
+    }
+
+    /// Declares the next argument as optional. If the argument was not passed, the default
+    /// value of the type is used.
+    ///
+    /// An [`Option`] argument is `None` both when it was not passed and when `null` was
+    /// passed, as for `?string $name = null`. To tell the two apart, use
+    /// [`Args::optional_with_default`] with a default of `Some`, as for
+    /// `?string $name = 'default'`.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the argument, shown in error messages. See [`Args::arg`].
+    pub fn optional<U>(self, name: &str) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+        U: FromZval<'a> + Default,
+    {
+        self.optional_with_default(name, U::default())
+    }
+
+    /// Declares the next argument as optional, using the given value if the argument was not
+    /// passed. The same default should be declared when registering the function with
+    /// [`Arg::default_value`], so that it is reported through reflection. To declare the
+    /// default only once, use [`Args::optional_declared`] instead.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the argument, shown in error messages. See [`Args::arg`].
+    /// * `default` - The value of the argument if it was not passed.
+    pub fn optional_with_default<U>(mut self, name: &str, default: U) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+        U: FromZval<'a>,
+    {
+        if self.min_num_args.is_none() {
+            self.min_num_args = Some(self.num_args);
+        }
+
+        self.next(name, || Some(default))
+    }
+
+    /// Declares the next argument as optional, using the default value it was registered with
+    /// through [`Arg::default_value`] or [`Arg::default`] if the argument was not passed. The
+    /// default is evaluated as PHP would, so `PHP_INT_MAX` becomes an integer, and is then
+    /// converted like a passed argument.
+    ///
+    /// The value must be owned, e.g. a [`String`] rather than a `&str`, as the evaluated
+    /// default is released once it has been converted. The arguments are invalid if the
+    /// default cannot be evaluated or converted.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the argument, shown in error messages. See [`Args::arg`].
+    pub fn optional_declared<U>(mut self, name: &str) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+        U: for<'b> FromZval<'b>,
+    {
+        if self.min_num_args.is_none() {
+            self.min_num_args = Some(self.num_args);
+        }
+
+        let execute_data = self.execute_data();
+        let num = self.num_args as usize;
+        self.next(name, || {
+            let default = execute_data.declared_default(num);
+            debug_assert!(
+                default.is_some() || ThrownException::pending().is_some(),
+                "argument `{}` was registered without a default",
+                name
+            );
+
+            let default = default?;
+            let value = U::from_zval(&default).ok();
+            default.release();
+            value
+        })
+    }
+
+    /// Converts the next argument, or uses the value returned by `missing` if it was not
+    /// passed or was skipped by a call with named arguments. The values are discarded once
+    /// any argument is invalid or missing.
+    fn next<U>(mut self, name: &str, missing: impl FnOnce() -> Option<U>) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+        U: FromZval<'a>,
+    {
+        debug_assert!(
+            !self.variadic,
+            "argument `{}` declared after a variadic argument",
+            name
+        );
+
+        let num = self.num_args;
+        self.num_args += 1;
+
+        let value = match self.arg_ptr(num) {
+            Some(zval) => self.convert(num + 1, zval),
+            None => {
+                let value = missing();
+                if value.is_none() {
+                    self.not_passed(num);
+                }
+                value
+            }
+        };
+
+        self.push(value)
+    }
+
+    /// Records the error for a required argument at the given offset which was not passed, if
+    /// it was skipped by a call with named arguments. Too few arguments are reported by
+    /// [`Args::finish`] instead.
+    fn not_passed(&mut self, num: u32) {
+        if (num as usize) < self.execute_data().arg_count() && self.error.is_none() {
+            self.error = Some(ArgError::NotPassed { num: num + 1 });
+        }
+    }
+
+    /// Declares a variadic argument, collecting all of the remaining arguments passed to the
+    /// function into a vector, as in `...$args`. No arguments may be declared after it. Use
+    /// `&Zval` to collect the arguments without converting them.
+    ///
+    /// The argument should also be declared as variadic when registering the function, see
+    /// [`Arg::variadic`].
+    pub fn variadic<U>(mut self) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<Vec<U>>,
+        U: FromZval<'a>,
+    {
+        debug_assert!(!self.variadic, "more than one variadic argument declared");
+
+        let start = self.num_args;
+        let num_args = (self.execute_data().arg_count() as u32).max(start);
+        let mut values = Some(Vec::with_capacity((num_args - start) as usize));
+
+        for num in start..num_args {
+            let value = match self.arg_ptr(num) {
+                Some(zval) => self.convert(num + 1, zval),
+                None => {
+                    self.undefined::<U>(num);
+                    None
+                }
+            };
+
+            values = values.zip(value).map(|(mut values, value)| {
+                values.push(value);
+                values
+            });
+        }
+
+        self.variadic = true;
+        self.push(values)
+    }
+
+    /// Records an error for a variadic argument at the given offset which is undefined.
+    /// Variadic arguments cannot be skipped, so this is reported as an argument of the wrong
+    /// type rather than discarding the arguments without an error.
+    fn undefined<U>(&mut self, num: u32)
+    where
+        U: FromZval<'a>,
+    {
+        if self.error.is_none() {
+            let zval = unsafe { &*self.execute_data().zend_call_var_num(num as isize) };
+            self.error = Some(Self::arg_error::<U>(num + 1, zval, Error::ZvalConversion));
+        }
+    }
+
+    /// Declares the named arguments which do not match any declared argument, e.g. `b` in
+    /// `f(1, b: 2)`, collecting them into a map keyed by name. This must follow
+    /// [`Args::variadic`], as only variadic functions accept these arguments. Use `&Zval` to
+    /// collect the arguments without converting them.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if no variadic argument has been declared.
+    pub fn named_variadic<U>(mut self) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<HashMap<String, U>>,
+        U: FromZval<'a>,
+    {
+        debug_assert!(
+            self.variadic,
+            "named variadic arguments declared before the variadic argument"
+        );
+
+        let named = match self.execute_data().extra_named_args() {
+            Some(ht) => ht,
+            None => return self.push(Some(HashMap::new())),
+        };
+
+        // Named arguments follow all of the positional arguments.
+        let start = self.execute_data().arg_count() as u32 + 1;
+        let mut values = Some(HashMap::with_capacity(named.len()));
+
+        for (num, (key, zval)) in (start..).zip(named.iter()) {
+            // The array is owned by the call frame, and its elements are only read once.
+            let zval = zval as *const Zval as *mut Zval;
+            let name = match key {
+                ArrayKey::Str(name) => String::from_utf8_lossy(&name).into_owned(),
+                ArrayKey::Long(idx) => idx.to_string(),
+            };
+            let value = self.convert(num, zval);
+
+            values = values.zip(value).map(|(mut values, value)| {
+                values.insert(name, value);
+                values
+            });
+        }
+
+        self.push(values)
+    }
+
+    /// Converts an argument, recording the error if it is the first invalid argument. Scalar
+    /// arguments are first coerced in place unless the caller uses strict types, see
+    /// [`FromZval::DATA_TYPE`].
+    fn convert<U>(&mut self, num: u32, ptr: *mut Zval) -> Option<U>
+    where
+        U: FromZval<'a>,
+    {
+        // The engine also converts arguments in place. The frame is borrowed mutably by the
+        // parser, so no references to the argument are left to be invalidated.
+        let zval: &'a mut Zval = unsafe { &mut *ptr };
+        if !(self.strict || U::NULLABLE && zval.is_null()) {
+            zval.coerce_arg_weak(U::DATA_TYPE);
+        }
+
+        let zval: &'a Zval = zval;
+
+        match U::from_zval(zval) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                if self.error.is_none() {
+                    self.error = Some(Self::arg_error::<U>(num, zval, e));
+                }
+                None
+            }
+        }
+    }
+
+    /// Appends the value of the next argument, or discards the values if it was invalid.
+    fn push<U>(self, value: Option<U>) -> Args<'a, T::Output>
+    where
+        T: TupleAppend<U>,
+    {
+        Args {
+            execute_data: self.execute_data,
+            _marker: PhantomData,
+            values: self
+                .values
+                .zip(value)
+                .map(|(values, value)| values.append(value)),
+            num_args: self.num_args,
+            min_num_args: self.min_num_args,
+            variadic: self.variadic,
+            strict: self.strict,
+            error: self.error,
+        }
+    }
+
+    /// Builds the error for an argument which could not be converted.
+    fn arg_error<U>(num: u32, zval: &'a Zval, e: Error) -> ArgError<'a>
+    where
+        U: FromZval<'a>,
+    {
+        match e {
+            Error::IntegerOutOfRange(_, ty) => ArgError::Value {
+                num,
+                message: range_message(ty),
+            },
+            _ => ArgError::Type {
+                num,
+                expected: format!("{}{}", if U::NULLABLE { "?" } else { "" }, U::TYPE),
+                given: zval,
+            },
+        }
+    }
+
+    /// Checks the number of arguments passed to the function and returns the converted
+    /// arguments.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - A tuple of the converted arguments, in the order they were declared.
+    /// * `Err(Error::Exception)` - The arguments were invalid. The `ArgumentCountError`,
+    ///   `TypeError` or `ValueError` is left pending, so the function should return without
+    ///   setting a return value.
+    pub fn finish(self) -> Result<T> {
+        let min_num_args = self.min_num_args.unwrap_or(self.num_args);
+        let max_num_args = if self.variadic {
+            u32::MAX
+        } else {
+            self.num_args
+        };
+        let num_args = self.execute_data().arg_count() as u32;
+
+        if num_args < min_num_args || num_args > max_num_args {
+            return Err(ArgError::Count {
+                min: min_num_args,
+                max: max_num_args,
+            }
+            .throw());
+        }
+
+        if let Some(error) = self.error {
+            return Err(error.throw());
+        }
+
+        self.values.ok_or_else(pending_error)
+    }
+}
+
+/// Returns the error for the exception thrown for invalid arguments.
+fn pending_error() -> Error {
+    ThrownException::pending().map_or(Error::ZvalConversion, Error::Exception)
+}
+
+/// A value which can be used as the default value of an argument. See
+/// [`Arg::default_value`].
+pub trait ArgDefault {
+    /// Returns the value as a PHP literal, e.g. `10`, `'abc'` or `null`.
+    fn to_php_literal(&self) -> String;
+}
+
+/// Implements [`ArgDefault`] for integer types. The smallest integer is written as
+/// `PHP_INT_MIN`, as PHP reads `-9223372036854775808` as the negation of a float.
+macro_rules! arg_default_int {
+    ($($t: ty),*) => {
+        $(
+            impl ArgDefault for $t {
+                fn to_php_literal(&self) -> String {
+                    match ZendLong::try_from(*self) {
+                        Ok(ZendLong::MIN) => "PHP_INT_MIN".to_string(),
+                        Ok(val) => val.to_string(),
+                        Err(_) => panic!("default value {} is out of range for a PHP integer", self),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+arg_default_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl ArgDefault for bool {
+    fn to_php_literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Floats are written so that they are parsed back into the same value, e.g. `1.0` rather
+/// than `1`, which PHP would read as an integer.
+impl ArgDefault for f64 {
+    fn to_php_literal(&self) -> String {
+        if self.is_nan() {
+            "NAN".to_string()
+        } else if self.is_infinite() {
+            if *self > 0.0 { "INF" } else { "-INF" }.to_string()
+        } else {
+            format!("{:?}", self)
+        }
+    }
+}
+
+/// Strings are written as single quoted literals.
+impl ArgDefault for &str {
+    fn to_php_literal(&self) -> String {
+        format!("'{}'", self.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+impl ArgDefault for String {
+    fn to_php_literal(&self) -> String {
+        self.as_str().to_php_literal()
+    }
+}
+
+/// `None` is written as `null`.
+impl<T> ArgDefault for Option<T>
+where
+    T: ArgDefault,
+{
+    fn to_php_literal(&self) -> String {
+        match self {
+            Some(val) => val.to_php_literal(),
+            None => "null".to_string(),
+        }
+    }
+}
+
+/// Appends a value to the end of a tuple, used by [`Args`] to build the tuple of arguments.
+#[doc(hidden)]
+pub trait TupleAppend<U> {
+    /// The tuple with the value appended.
+    type Output;
+
+    /// Appends the value to the tuple.
+    fn append(self, value: U) -> Self::Output;
+}
+
+macro_rules! tuple_append {
+    ($($t: ident),*) => {
+        impl<$($t,)* U> TupleAppend<U> for ($($t,)*) {
+            type Output = ($($t,)* U,);
+
+            #[allow(clippy::unused_unit)]
+            fn append(self, value: U) -> Self::Output {
+                let ($($t,)*) = self;
+                ($($t,)* value,)
+            }
+        }
+    };
+}
+
+tuple_append!();
+tuple_append!(A);
+tuple_append!(A, B);
+tuple_append!(A, B, C);
+tuple_append!(A, B, C, D);
+tuple_append!(A, B, C, D, E);
+tuple_append!(A, B, C, D, E, F);
+tuple_append!(A, B, C, D, E, F, G);
+tuple_append!(A, B, C, D, E, F, G, H);
+tuple_append!(A, B, C, D, E, F, G, H, I);
+tuple_append!(A, B, C, D, E, F, G, H, I, J);
+tuple_append!(A, B, C, D, E, F, G, H, I, J, K);
+
+/// Returns the message of the `ValueError` thrown for an integer argument which does not fit
+/// into the Rust integer type with the given name, worded as the engine words range errors.
+/// Unsigned types which can hold every non-negative PHP integer only have a lower bound.
+fn range_message(ty: &str) -> String {
+    let (min, max): (i128, i128) = match ty {
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "u8" => (0, u8::MAX.into()),
+        "u16" => (0, u16::MAX.into()),
+        "u32" => (0, u32::MAX.into()),
+        "u64" | "usize" => (0, u64::MAX.into()),
+        _ => (ZendLong::MIN.into(), ZendLong::MAX.into()),
+    };
+
+    if max >= ZendLong::MAX.into() {
+        format!("must be greater than or equal to {}", min)
+    } else {
+        format!("must be between {} and {}", min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{range_message, Arg, ArgDefault, ArgError, ExecutionData};
+    use crate::{
+        bindings::{zend_reference, IS_REFERENCE_EX, IS_UNDEF, MAY_BE_NULL},
+        php::{
+            enums::DataType,
+            types::{
+                zval::{FromZval, Zval},
+                ZendType,
+            },
+        },
+    };
+
+    /// Builds a call frame holding the given arguments after the execution data, as laid out
+    /// by the engine.
+    fn frame(args: &[Zval]) -> Vec<Zval> {
+        let slot = ExecutionData::zend_call_frame_slot() as usize;
+        let mut buf = vec![Zval::new(); slot + args.len()];
+        buf[slot..].copy_from_slice(args);
+
+        let ex = buf.as_mut_ptr() as *mut ExecutionData;
+        unsafe { (*ex).This.u2.num_args = args.len() as u32 };
+        buf
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let mut a = Zval::new();
+        a.set_long(1);
+        let mut b = Zval::new();
+        b.set_double(2.5);
+
+        let mut buf = frame(&[a, b]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+        let args = ex
+            .parse_args()
+            .arg::<i64>("a")
+            .arg::<f64>("b")
+            .optional::<Option<i64>>("c")
+            .finish();
+        assert_eq!(args, Ok((1, 2.5, None)));
+
+        let args = ex
+            .parse_args()
+            .arg::<i64>("a")
+            .optional::<f64>("b")
+            .finish();
+        assert_eq!(args, Ok((1, 2.5)));
+    }
+
+    #[test]
+    fn test_optional_with_default() {
+        let mut a = Zval::new();
+        a.set_long(1);
+        let mut b = Zval::new();
+        b.set_long(2);
+
+        for (num, expected) in &[(1, (1, 10, 20)), (2, (1, 2, 20))] {
+            let mut buf = frame(&[a, b][..*num]);
+            let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+            let args = ex
+                .parse_args()
+                .arg::<i64>("a")
+                .optional_with_default("b", 10i64)
+                .optional_with_default("c", 20i64)
+                .finish();
+            assert_eq!(args, Ok(*expected));
+        }
+    }
+
+    #[test]
+    fn test_nullable() {
+        let mut x = Zval::new();
+        x.set_long(1);
+        let null = Zval::new();
+
+        // Calls to `f()`, `f(null)` and `f(1)`.
+        let calls: &[&[Zval]] = &[&[], &[null], &[x]];
+        let expected = [
+            (None, None, Some(10)),
+            (Some(None), None, None),
+            (Some(Some(1)), Some(1), Some(1)),
+        ];
+
+        for (args, expected) in calls.iter().zip(expected.iter()) {
+            let mut buf = frame(args);
+            let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+            let (optional,) = ex
+                .parse_args()
+                .optional::<Option<i64>>("x")
+                .finish()
+                .unwrap();
+            let (with_default,) = ex
+                .parse_args()
+                .optional_with_default("x", Some(10i64))
+                .finish()
+                .unwrap();
+
+            assert_eq!(
+                (ex.get_arg::<Option<i64>>(0), optional, with_default),
+                *expected
+            );
+        }
+
+        // `null` is not accepted for an argument which is not nullable.
+        let mut buf = frame(&[null]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+        assert_eq!(ex.get_arg::<i64>(0), None);
+    }
+
+    #[test]
+    fn test_nullable_type() {
+        let type_mask = |arg: Arg| {
+            ZendType::empty_from_type(arg._type, arg.as_ref, arg.variadic, arg.allow_null).type_mask
+        };
+
+        assert_eq!(type_mask(Arg::new("a", DataType::String)) & MAY_BE_NULL, 0);
+        assert_ne!(
+            type_mask(Arg::new("a", DataType::String).allow_null()) & MAY_BE_NULL,
+            0
+        );
+        assert_ne!(
+            type_mask(Arg::new("a", DataType::Long).default_value(None::<i64>)) & MAY_BE_NULL,
+            0
+        );
+        assert_eq!(
+            type_mask(Arg::new("a", DataType::Long).default_value(0)) & MAY_BE_NULL,
+            0
+        );
+    }
+
+    #[test]
+    fn test_skipped_args() {
+        let mut undef = Zval::new();
+        undef.u1.type_info = IS_UNDEF;
+        let mut c = Zval::new();
+        c.set_long(3);
+
+        // A call to `f(c: 3)`, where the engine leaves the skipped arguments undefined.
+        let mut buf = frame(&[undef, undef, c]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+        assert_eq!(ex.arg_count(), 3);
+        assert_eq!(ex.args().count(), 1);
+        assert_eq!(ex.get_arg::<Option<i64>>(0), None);
+        assert!(ex.extra_named_args().is_none());
+
+        let args = ex
+            .parse_args()
+            .optional_with_default("a", 1i64)
+            .optional::<Option<i64>>("b")
+            .optional_with_default("c", 0i64)
+            .finish();
+        assert_eq!(args, Ok((1, None, 3)));
+
+        let args = ex
+            .parse_args()
+            .optional::<i64>("a")
+            .optional::<i64>("b")
+            .variadic::<i64>()
+            .named_variadic::<i64>()
+            .finish()
+            .unwrap();
+        assert_eq!((args.0, args.1, args.2), (0, 0, vec![3]));
+        assert!(args.3.is_empty());
+    }
+
+    #[test]
+    fn test_variadic() {
+        let mut first = Zval::new();
+        first.set_long(-1);
+
+        let mut args = vec![first];
+        for i in 0..20 {
+            let mut zv = Zval::new();
+            match i % 4 {
+                0 => zv.set_long(i),
+                1 => zv.set_double(i as f64),
+                2 => zv.set_bool(true),
+                _ => {}
+            }
+            args.push(zv);
+        }
+
+        for num in &[1, 21] {
+            let mut buf = frame(&args[..*num]);
+            let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+            let (first, rest) = ex
+                .parse_args()
+                .arg::<i64>("first")
+                .variadic::<&Zval>()
+                .finish()
+                .unwrap();
+            assert_eq!(first, -1);
+            assert_eq!(rest.len(), num - 1);
+
+            for (i, zv) in rest.iter().enumerate() {
+                match i % 4 {
+                    0 => assert_eq!(zv.long(), Some(i as i64)),
+                    1 => assert_eq!(zv.double(), Some(i as f64)),
+                    2 => assert_eq!(zv.bool(), Some(true)),
+                    _ => assert!(zv.is_null()),
+                }
+            }
+        }
+
+        // Each of the arguments is converted into the element type.
+        let mut buf = frame(&[first, args[1]]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+        assert_eq!(
+            ex.parse_args().variadic::<i64>().finish(),
+            Ok((vec![-1, 0],))
+        );
+
+        // An undefined argument is reported rather than discarding the arguments.
+        let mut undef = Zval::new();
+        undef.u1.type_info = IS_UNDEF;
+        let mut buf = frame(&[first, undef]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+        let args = ex.parse_args().variadic::<i64>();
+        assert!(args.values.is_none());
+        assert!(matches!(
+            args.error,
+            Some(ArgError::Type { num: 2, ref expected, given })
+                if expected == "int" && given.is_undef()
+        ));
+    }
+
+    #[test]
+    fn test_get_arg_by_ref() {
+        let mut reference: zend_reference = unsafe { mem::zeroed() };
+        reference.gc.refcount = 2;
+        reference.val.set_long(1);
+
+        let mut arg = Zval::new();
+        arg.value.ref_ = &mut reference;
+        arg.u1.type_info = IS_REFERENCE_EX;
+
+        let mut buf = frame(&[arg]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+        assert!(ex.get_arg_by_ref(1).is_none());
+
+        let mut out = ex.get_arg_by_ref(0).unwrap();
+        assert_eq!(out.val::<i64>(), Some(1));
+        out.set(2.5).unwrap();
+        assert_eq!(out.get().double(), Some(2.5));
+        assert_eq!(out.reference().gc.refcount, 2);
+
+        // The value is written into the reference shared with the caller.
+        assert_eq!(reference.val.double(), Some(2.5));
+    }
+
+    #[test]
+    fn test_arg_by_ref() {
+        let mut reference: zend_reference = unsafe { mem::zeroed() };
+        reference.gc.refcount = 2;
+        reference.val.set_long(1);
+
+        let mut out = Zval::new();
+        out.value.ref_ = &mut reference;
+        out.u1.type_info = IS_REFERENCE_EX;
+        let mut by = Zval::new();
+        by.set_long(3);
+
+        let mut buf = frame(&[out, by]);
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+
+        let (mut out, by) = ex
+            .parse_args()
+            .arg_by_ref("out")
+            .arg::<i64>("by")
+            .finish()
+            .unwrap();
+        let next = out.val::<i64>().unwrap() + by;
+        out.set(next).unwrap();
+
+        assert_eq!(reference.val.long(), Some(4));
+    }
+
+    #[test]
+    fn test_range_message() {
+        assert_eq!(range_message("i8"), "must be between -128 and 127");
+        assert_eq!(range_message("u32"), "must be between 0 and 4294967295");
+        assert_eq!(range_message("u64"), "must be greater than or equal to 0");
+        assert_eq!(range_message("usize"), "must be greater than or equal to 0");
+    }
+
+    #[test]
+    fn test_php_literal() {
+        assert_eq!((-10i64).to_php_literal(), "-10");
+        assert_eq!(true.to_php_literal(), "true");
+        assert_eq!(1.0.to_php_literal(), "1.0");
+        assert_eq!(0.1.to_php_literal(), "0.1");
+        assert_eq!(1e100.to_php_literal(), "1e100");
+        assert_eq!(f64::NEG_INFINITY.to_php_literal(), "-INF");
+        assert_eq!("it's \\".to_php_literal(), "'it\\'s \\\\'");
+        assert_eq!(None::<i64>.to_php_literal(), "null");
+        assert_eq!(Some("a").to_php_literal(), "'a'");
+    }
+
+    #[test]
+    fn test_php_literal_int_range() {
+        assert_eq!(i64::MIN.to_php_literal(), "PHP_INT_MIN");
+        assert_eq!((i64::MIN + 1).to_php_literal(), "-9223372036854775807");
+        assert_eq!(i64::MAX.to_php_literal(), "9223372036854775807");
+        assert_eq!((i64::MAX as u64).to_php_literal(), "9223372036854775807");
+        assert_eq!(u8::MAX.to_php_literal(), "255");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_php_literal_too_large() {
+        (i64::MAX as u64 + 1).to_php_literal();
+    }
+
+    #[test]
+    fn test_type() {
+        assert_eq!(<i64 as FromZval>::TYPE, "int");
+        assert_eq!(
+            (
+                <Option<String> as FromZval>::TYPE,
+                <Option<String> as FromZval>::NULLABLE
+            ),
+            ("string", true)
+        );
+        assert_eq!(
+            (
+                <Vec<bool> as FromZval>::TYPE,
+                <Vec<bool> as FromZval>::NULLABLE
+            ),
+            ("array", false)
+        );
+        assert_eq!(<&Zval as FromZval>::TYPE, "mixed");
+    }
+}
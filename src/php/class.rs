@@ -1,36 +1,145 @@
 //! Builder and objects for creating classes in the PHP world.
 
-use std::{mem, ptr};
+use std::{mem, os::raw::c_int, ptr};
 
 use crate::{
     bindings::{
-        ext_php_rs_zend_string_release, zend_class_entry, zend_declare_class_constant,
-        zend_register_internal_class_ex,
+        ext_php_rs_property_default, instanceof_function_slow, object_init_ex,
+        php_json_serializable_ce, zend_ce_aggregate, zend_ce_arrayaccess, zend_ce_countable,
+        zend_ce_stringable, zend_class_entry, zend_class_implements,
+        zend_declare_class_constant_ex, zend_declare_typed_property, zend_lookup_class_ex,
+        zend_register_internal_class_ex, zend_verify_abstract_class, GC_IMMUTABLE, IS_ARRAY,
+        ZEND_FETCH_CLASS_NO_AUTOLOAD, ZEND_INTERNAL_CLASS, ZEND_MODULE_API_NO,
     },
-    functions::c_str,
+    errors::{Error, Result},
 };
 
 use super::{
-    flags::{ClassFlags, MethodFlags, PropertyFlags},
+    enums::DataType,
+    errors::ThrownException,
+    flags::{ClassFlags, ConstantFlags, MethodFlags, PropertyFlags},
     function::FunctionEntry,
+    module::is_valid_name,
     types::{
+        array::ZendHashTable,
         object::{ZendObject, ZendObjectOverride},
         string::ZendString,
-        zval::Zval,
+        zval::{IntoZval, Zval},
+        ZendType,
     },
 };
 
 /// A Zend class entry. Alias.
 pub type ClassEntry = zend_class_entry;
 
-/// Builds a class to be exported as a PHP class.
+impl ClassEntry {
+    /// Finds an internal class by its name, e.g. a class registered by the extension or built
+    /// into PHP, without autoloading it. As in PHP, names are case-insensitive, and may include
+    /// a namespace.
+    ///
+    /// Classes declared in PHP code are not returned, as they are freed at the end of the
+    /// request, while internal classes live until PHP shuts down.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the class, e.g. `Acme\Crypto\Hasher`.
+    pub fn try_find(name: &str) -> Option<&'static Self> {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        let name = ZendString::new(name, false).ok()?;
+
+        let class = unsafe {
+            zend_lookup_class_ex(name.as_ptr(), ptr::null_mut(), ZEND_FETCH_CLASS_NO_AUTOLOAD)
+                .as_ref()?
+        };
+
+        if class.type_ as u32 == ZEND_INTERNAL_CLASS {
+            Some(class)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `Countable` interface, which allows objects to be counted with `count()`
+    /// by implementing `count(): int`.
+    pub fn countable<'a>() -> Option<&'a Self> {
+        unsafe { zend_ce_countable.as_ref() }
+    }
+
+    /// Returns the `Stringable` interface, implemented by classes with a `__toString()`
+    /// method.
+    pub fn stringable<'a>() -> Option<&'a Self> {
+        unsafe { zend_ce_stringable.as_ref() }
+    }
+
+    /// Returns the `JsonSerializable` interface, which allows objects to choose how they are
+    /// encoded by `json_encode()` by implementing `jsonSerialize(): mixed`.
+    pub fn json_serializable<'a>() -> Option<&'a Self> {
+        unsafe { php_json_serializable_ce.as_ref() }
+    }
+
+    /// Returns the `ArrayAccess` interface, which allows objects to be accessed as arrays.
+    pub fn array_access<'a>() -> Option<&'a Self> {
+        unsafe { zend_ce_arrayaccess.as_ref() }
+    }
+
+    /// Returns the `IteratorAggregate` interface, which allows objects to be iterated over
+    /// with `foreach` by implementing `getIterator(): Traversable`.
+    pub fn iterator_aggregate<'a>() -> Option<&'a Self> {
+        unsafe { zend_ce_aggregate.as_ref() }
+    }
+
+    /// Returns whether the class entry is an interface.
+    pub fn is_interface(&self) -> bool {
+        self.ce_flags & ClassFlags::Interface.bits() != 0
+    }
+
+    /// Returns whether the class is the given class, extends it or implements it, equivalent to
+    /// `instanceof` in PHP.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The class, or interface, to check against.
+    pub fn instance_of(&self, other: &ClassEntry) -> bool {
+        ptr::eq(self, other) || unsafe { instanceof_function_slow(self, other) }
+    }
+
+    /// Creates an instance of the class, equivalent to `new` in PHP without calling the
+    /// constructor. Properties are initialized with their default values, and objects of
+    /// classes built with [`ClassBuilder::object_override`] are created with their Rust data.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The new object.
+    /// * `Err(Error::Exception)` - The class cannot be instantiated, e.g. it is abstract or an
+    ///   interface. The exception is left pending.
+    pub fn new_object(&self) -> Result<Zval> {
+        let mut zv = Zval::new();
+        let ptr = self as *const Self as *mut Self;
+
+        if unsafe { object_init_ex(&mut zv, ptr) } == 0 {
+            return Ok(zv);
+        }
+
+        Err(match ThrownException::pending() {
+            Some(e) => Error::Exception(e),
+            None => Error::InvalidPointer,
+        })
+    }
+}
+
+/// Builds a class to be exported as a PHP class, which must be built while the extension is
+/// starting up, see [`ModuleBuilder::class`]. The class can be found again afterwards with
+/// [`ClassEntry::try_find`].
+///
+/// [`ModuleBuilder::class`]: crate::php::module::ModuleBuilder::class
 pub struct ClassBuilder<'a> {
     ptr: &'a mut ClassEntry,
     extends: *mut ClassEntry,
+    interfaces: Vec<*mut ClassEntry>,
     methods: Vec<FunctionEntry>,
     object_override: Option<unsafe extern "C" fn(class_type: *mut ClassEntry) -> *mut ZendObject>,
-    // properties: Vec<(&'a str, Zval, PropertyFlags)>,
-    constants: Vec<(&'a str, Zval)>,
+    properties: Vec<Property<'a>>,
+    constants: Vec<(&'a str, Zval, ConstantFlags)>,
 }
 
 impl<'a> ClassBuilder<'a> {
@@ -39,93 +148,443 @@ impl<'a> ClassBuilder<'a> {
     ///
     /// # Parameters
     ///
-    /// * `name` - The name of the class.
+    /// * `name` - The name of the class, which may include a namespace, e.g.
+    ///   `Acme\Crypto\Hasher`.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the name is not a valid class name.
     pub fn new<N>(name: N) -> Self
     where
         N: AsRef<str>,
     {
+        let name = name.as_ref();
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        debug_assert!(is_valid_name(name), "`{}` is not a valid class name", name);
+
         let ptr = unsafe { libc::calloc(1, mem::size_of::<ClassEntry>()) } as *mut ClassEntry;
         let self_ = Self {
             ptr: unsafe { ptr.as_mut() }.unwrap(),
             extends: ptr::null_mut(),
+            interfaces: vec![],
             methods: vec![],
             object_override: None,
-            // properties: vec![],
+            properties: vec![],
             constants: vec![],
         };
-        self_.ptr.name = ZendString::new_interned(name);
+        self_.ptr.name = ZendString::new_interned(name).unwrap().into_raw();
         self_
     }
 
-    /// Sets the class builder to extend another class.
+    /// Sets the class builder to extend another class, which may be a class of PHP itself,
+    /// e.g. `Exception` to declare an exception thrown by the extension:
+    ///
+    /// ```no_run
+    /// use ext_php_rs::php::class::{ClassBuilder, ClassEntry};
+    ///
+    /// let parent = ClassEntry::exception().unwrap();
+    /// let class = ClassBuilder::new("Acme\\CryptoException").extends(parent).build();
+    /// ```
+    ///
+    /// The class inherits the methods, properties and constants of the parent, including
+    /// its constructor, as well as the way its objects are created, unless it is overridden
+    /// with [`ClassBuilder::object_override`]. Classes extending `Exception` must not
+    /// override it, as the engine then no longer records where the exception was created.
     ///
     /// # Parameters
     ///
-    /// * `parent` - The parent class to extend.
-    pub fn extends(mut self, parent: *mut ClassEntry) -> Self {
-        self.extends = parent;
+    /// * `parent` - The parent class to extend, e.g. [`ClassEntry::exception`] or a class
+    ///   returned by [`ClassEntry::try_find`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the parent is an interface or a final class.
+    pub fn extends(mut self, parent: &ClassEntry) -> Self {
+        debug_assert!(
+            parent.ce_flags & (ClassFlags::Interface | ClassFlags::Final).bits() == 0,
+            "classes cannot extend interfaces or final classes"
+        );
+
+        self.extends = parent as *const ClassEntry as *mut ClassEntry;
         self
     }
 
+    /// Adds an interface implemented by the class, e.g. `Countable`. The methods of the
+    /// interface must be added to the class, unless the class is abstract: when the class is
+    /// built, the engine stops with its usual fatal error, `Class X contains 1 abstract
+    /// method and must therefore be declared abstract or implement the remaining methods`,
+    /// if any are missing.
+    ///
+    /// # Parameters
+    ///
+    /// * `interface` - The interface to implement, e.g. [`ClassEntry::countable`] or an
+    ///   interface returned by [`ClassEntry::try_find`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the class entry is not an interface.
+    pub fn implements(mut self, interface: &ClassEntry) -> Self {
+        debug_assert!(
+            interface.is_interface(),
+            "classes can only implement interfaces"
+        );
+
+        self.interfaces
+            .push(interface as *const ClassEntry as *mut ClassEntry);
+        self
+    }
+
+    /// Adds an interface implemented by the class, found by its name. See
+    /// [`ClassBuilder::implements`].
+    ///
+    /// Classes are built while the extension is starting up, before any script has run, so
+    /// only the interfaces of PHP itself and of extensions which were started up before this
+    /// one can be found. Interfaces declared in PHP code cannot be implemented. Use
+    /// [`ModuleBuilder::requires`] to make sure that an extension declaring the interface is
+    /// started up first.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the interface, e.g. `JsonSerializable`. As in PHP, names are
+    ///   case-insensitive, and may include a namespace.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`Error::InvalidInterface`] if there is no interface with the given name.
+    ///
+    /// [`ModuleBuilder::requires`]: crate::php::module::ModuleBuilder::requires
+    pub fn implements_named(self, name: &str) -> Result<Self> {
+        match ClassEntry::try_find(name) {
+            Some(interface) if interface.is_interface() => Ok(self.implements(interface)),
+            _ => Err(Error::InvalidInterface(name.to_string())),
+        }
+    }
+
     /// Adds a method to the class.
     ///
+    /// The method is public unless it is declared [`MethodFlags::Protected`] or
+    /// [`MethodFlags::Private`], and may also be [`MethodFlags::Static`], in which case it is
+    /// called without `$this` and [`ExecutionData::get_called_scope`] returns the class it was
+    /// called on. An [`MethodFlags::Abstract`] method must be built with
+    /// [`FunctionBuilder::new_abstract`], and makes the class abstract.
+    ///
     /// # Parameters
     ///
     /// * `func` - The function entry to add to the class.
     /// * `flags` - Flags relating to the function. See [`MethodFlags`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if more than one visibility is given, if the method is both
+    /// abstract and either static, final or private, or if it has no handler and is not
+    /// abstract.
+    ///
+    /// [`ExecutionData::get_called_scope`]: crate::php::execution_data::ExecutionData::get_called_scope
+    /// [`FunctionBuilder::new_abstract`]: crate::php::function::FunctionBuilder::new_abstract
     pub fn method(mut self, mut func: FunctionEntry, flags: MethodFlags) -> Self {
-        func.flags = flags.bits();
+        let visibility =
+            flags & (MethodFlags::Public | MethodFlags::Protected | MethodFlags::Private);
+        debug_assert!(
+            visibility.bits().count_ones() <= 1,
+            "methods must have exactly one visibility"
+        );
+
+        if flags.contains(MethodFlags::Abstract) {
+            debug_assert!(
+                !flags.intersects(MethodFlags::Static | MethodFlags::Final | MethodFlags::Private),
+                "abstract methods cannot be static, final or private"
+            );
+        } else {
+            debug_assert!(
+                func.handler.is_some(),
+                "methods without a handler must be abstract"
+            );
+        }
+
+        // The engine refuses methods with flags but without a visibility.
+        func.flags = if visibility.is_empty() {
+            (flags | MethodFlags::Public).bits()
+        } else {
+            flags.bits()
+        };
+
         self.methods.push(func);
         self
     }
 
-    /// Adds a property to the class.
-    /// The type of the property is defined by the type of the given default.
+    /// Adds a property to the class, which is untyped. Each instance of the class starts with
+    /// the default value of the property. Defaults are shared by every request, so strings
+    /// are interned and arrays are copied into immutable arrays, which are never freed.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property to add to the class.
+    /// * `default` - The default value of the property.
+    /// * `flags` - Flags relating to the property. See [`PropertyFlags`].
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the value could not be converted into a zval, or is an object or a
+    /// resource, which cannot be the default value of a property of a class built by an
+    /// extension.
+    pub fn property<T>(self, name: &'a str, default: T, flags: PropertyFlags) -> Result<Self>
+    where
+        T: IntoZval,
+    {
+        self.add_property(name, default.into_zval()?, flags, None)
+    }
+
+    /// Adds a static property to the class, which is untyped. The value of the property is
+    /// shared by every instance of the class, and starts with the default value at the start
+    /// of each request. See [`ClassBuilder::property`].
     ///
     /// # Parameters
     ///
     /// * `name` - The name of the property to add to the class.
     /// * `default` - The default value of the property.
+    /// * `flags` - Flags relating to the property, which are combined with
+    ///   [`PropertyFlags::Static`].
+    pub fn static_property<T>(self, name: &'a str, default: T, flags: PropertyFlags) -> Result<Self>
+    where
+        T: IntoZval,
+    {
+        self.property(name, default, flags | PropertyFlags::Static)
+    }
+
+    /// Adds a typed property to the class, such as `public ?int $count = 0`. The engine
+    /// enforces the type when the property is assigned to, throwing a `TypeError` if a value
+    /// of another type is assigned. The property is static if the flags contain
+    /// [`PropertyFlags::Static`].
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property to add to the class.
+    /// * `type_` - The type of the property, which is one of the scalar types,
+    ///   [`DataType::Array`], [`DataType::Object`] or [`DataType::Mixed`].
+    /// * `nullable` - Whether the property may be null.
+    /// * `default` - The default value of the property, which must be of the type of the
+    ///   property. Integers are converted into floats for properties of type `float`.
     /// * `flags` - Flags relating to the property. See [`PropertyFlags`].
-    #[allow(unused_mut, unused_variables)]
-    pub fn property<T>(mut self, name: &'a str, default: T, flags: PropertyFlags) -> Self
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the value could not be converted into a zval, is not of the type
+    /// of the property, or cannot be the default value of a property, see
+    /// [`ClassBuilder::property`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the type cannot be the type of a property.
+    pub fn typed_property<T>(
+        self,
+        name: &'a str,
+        type_: DataType,
+        nullable: bool,
+        default: T,
+        flags: PropertyFlags,
+    ) -> Result<Self>
     where
-        T: Into<Zval>,
+        T: IntoZval,
     {
-        panic!("Properties are currently not supported. See #16");
-        // let mut default = default.into();
+        let mut default = default.into_zval()?;
+
+        if !accepts(type_, nullable, &default) {
+            default.release();
+            return Err(Error::InvalidProperty(name.to_string()));
+        }
 
-        // if default.is_string() {
-        //     let val = default.string().unwrap();
-        //     unsafe { ext_php_rs_zend_string_release(default.value.str) };
-        //     default.set_persistent_string(val);
-        // }
+        if type_ == DataType::Double {
+            if let Some(value) = default.long() {
+                default.set_double(value as f64);
+            }
+        }
 
-        // self.properties.push((name, default, flags));
-        // self
+        self.add_property(name, default, flags, Some((type_, nullable)))
     }
 
-    /// Adds a constant to the class.
+    /// Adds a typed property to the class without a default value, such as
+    /// `public string $name`. The property is uninitialized until it is assigned to, and
+    /// reading it before then throws an `Error`. See [`ClassBuilder::typed_property`].
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property to add to the class.
+    /// * `type_` - The type of the property.
+    /// * `nullable` - Whether the property may be null.
+    /// * `flags` - Flags relating to the property. See [`PropertyFlags`].
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the property could not be declared. Undefined values are not
+    /// reference counted, so this is not expected to fail.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the type cannot be the type of a property.
+    pub fn uninitialized_property(
+        self,
+        name: &'a str,
+        type_: DataType,
+        nullable: bool,
+        flags: PropertyFlags,
+    ) -> Result<Self> {
+        let mut default = Zval::new();
+        default.u1.type_info = DataType::Undef as u32;
+
+        self.add_property(name, default, flags, Some((type_, nullable)))
+    }
+
+    /// Adds a property with a default value which was converted into a zval, which is shared
+    /// by every request. Strings are interned and arrays are copied into immutable arrays,
+    /// while other values which are reference counted are rejected.
+    fn add_property(
+        mut self,
+        name: &'a str,
+        default: Zval,
+        flags: PropertyFlags,
+        type_: Option<(DataType, bool)>,
+    ) -> Result<Self> {
+        if let Some((type_, _)) = type_ {
+            debug_assert!(
+                matches!(
+                    type_,
+                    DataType::Bool
+                        | DataType::Long
+                        | DataType::Double
+                        | DataType::String
+                        | DataType::Array
+                        | DataType::Object
+                        | DataType::Mixed
+                ),
+                "`{:?}` cannot be the type of property `{}`",
+                type_,
+                name
+            );
+        }
+
+        let shared = share_value(&default);
+        default.release();
+
+        let default = shared.ok_or_else(|| Error::InvalidProperty(name.to_string()))?;
+        self.properties.push(Property {
+            name,
+            default,
+            flags,
+            type_,
+        });
+        Ok(self)
+    }
+
+    /// Adds a public constant to the class, read as `ClassName::NAME` in PHP.
     /// The type of the constant is defined by the type of the given default.
+    /// See [`ClassBuilder::constant_with_flags`].
     ///
     /// # Parameters
     ///
     /// * `name` - The name of the constant to add to the class.
     /// * `value` - The value of the constant.
-    pub fn constant<T>(mut self, name: &'a str, value: T) -> Self
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the name is not a valid constant name, the class already has a
+    /// constant with the same name, or the value could not be converted into a zval, or is
+    /// not a string, integer, float, boolean or `null`.
+    pub fn constant<T>(self, name: &'a str, value: T) -> Result<Self>
+    where
+        T: IntoZval,
+    {
+        self.constant_with_flags(name, value, ConstantFlags::Public)
+    }
+
+    /// Adds a constant to the class with the given visibility, e.g. a private constant
+    /// which can only be read by the methods of the class.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the constant to add to the class. Constant names are
+    ///   case-sensitive, and may not be `class`.
+    /// * `value` - The value of the constant.
+    /// * `flags` - The visibility of the constant, which is public if none is given, and
+    ///   whether it is final. See [`ConstantFlags`].
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the name is not a valid constant name, the class already has a
+    /// constant with the same name, or the value could not be converted into a zval, or is
+    /// not a string, integer, float, boolean or `null`.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if more than one visibility is given, or the constant is final
+    /// and private, or final before PHP 8.1.
+    pub fn constant_with_flags<T>(
+        mut self,
+        name: &'a str,
+        value: T,
+        flags: ConstantFlags,
+    ) -> Result<Self>
     where
-        T: Into<Zval>,
+        T: IntoZval,
     {
-        let mut value = value.into();
+        if !is_valid_constant_name(name) {
+            return Err(Error::InvalidConstantName(name.to_string()));
+        }
 
+        if self.constants.iter().any(|(other, _, _)| *other == name) {
+            return Err(Error::DuplicateConstant(name.to_string()));
+        }
+
+        let visibility =
+            flags & (ConstantFlags::Public | ConstantFlags::Protected | ConstantFlags::Private);
+        debug_assert!(
+            visibility.bits().count_ones() <= 1,
+            "class constant `{}` has more than one visibility",
+            name
+        );
+        debug_assert!(
+            !flags.contains(ConstantFlags::Final)
+                || (ZEND_MODULE_API_NO >= 20210902 && !flags.contains(ConstantFlags::Private)),
+            "class constant `{}` cannot be final",
+            name
+        );
+
+        let mut value = value.into_zval()?;
+
+        match value.get_type() {
+            Some(
+                DataType::Null
+                | DataType::False
+                | DataType::True
+                | DataType::Long
+                | DataType::Double
+                | DataType::String,
+            ) => {}
+            ty => {
+                value.release();
+                return Err(Error::InvalidConstant(name.to_string(), ty));
+            }
+        }
+
+        // Constants outlive the request, so strings are interned as the engine does for the
+        // constants of internal classes, unless they are interned already.
         if value.is_string() {
-            let val = value.string().unwrap();
-            unsafe { ext_php_rs_zend_string_release(value.value.str) };
-            value.set_persistent_string(val);
+            let zs = unsafe { &*value.value.str };
+
+            if !zs.is_interned() {
+                let old = value;
+                value.set_zend_string(ZendString::from_bytes_interned(zs.as_bytes())?);
+                old.release();
+            }
         }
 
-        self.constants.push((name, value));
-        self
+        let flags = if visibility.is_empty() {
+            flags | ConstantFlags::Public
+        } else {
+            flags
+        };
+
+        self.constants.push((name, value, flags));
+        Ok(self)
     }
 
     /// Sets the flags for the class.
@@ -152,6 +611,16 @@ impl<'a> ClassBuilder<'a> {
 
     /// Builds the class, returning a pointer to the class entry.
     pub fn build(mut self) -> *mut ClassEntry {
+        // A class with abstract methods must be declared abstract, unless it is an interface.
+        let is_abstract = self
+            .methods
+            .iter()
+            .any(|func| func.flags & MethodFlags::Abstract.bits() != 0);
+
+        if is_abstract && self.ptr.ce_flags & ClassFlags::Interface.bits() == 0 {
+            self.ptr.ce_flags |= ClassFlags::Abstract.bits();
+        }
+
         self.methods.push(FunctionEntry::end());
         let func = Box::into_raw(self.methods.into_boxed_slice()) as *const FunctionEntry;
         self.ptr.info.internal.builtin_functions = func;
@@ -164,22 +633,35 @@ impl<'a> ClassBuilder<'a> {
 
         unsafe { libc::free((self.ptr as *mut ClassEntry) as *mut libc::c_void) };
 
-        // DC: Commented out for now. Bug with properties.
-        // for (name, mut default, flags) in self.properties {
-        //     unsafe {
-        //         zend_declare_property(
-        //             class,
-        //             c_str(name),
-        //             name.len() as u64,
-        //             &mut default,
-        //             flags.bits() as i32,
-        //         );
-        //     }
-        // }
+        for interface in self.interfaces {
+            unsafe { zend_class_implements(class, 1 as c_int, interface) };
+        }
+
+        // Methods of the interfaces which the class does not implement are inherited as
+        // abstract methods. The engine raises a fatal error for them, as it does for classes
+        // declared in PHP.
+        let abstract_flags = ClassFlags::Abstract | ClassFlags::Interface | ClassFlags::Trait;
+        if class.ce_flags & ClassFlags::ImplicitAbstractClass.bits() != 0
+            && class.ce_flags & abstract_flags.bits() == 0
+        {
+            unsafe { zend_verify_abstract_class(class) };
+        }
 
-        for (name, value) in self.constants {
-            let value = Box::into_raw(Box::new(value));
-            unsafe { zend_declare_class_constant(class, c_str(name), name.len() as u64, value) };
+        for property in self.properties {
+            property.declare(class);
+        }
+
+        // The values are copied into the class.
+        for (name, mut value, flags) in self.constants {
+            unsafe {
+                zend_declare_class_constant_ex(
+                    class,
+                    ZendString::new_interned(name).unwrap().into_raw(),
+                    &mut value,
+                    flags.bits() as c_int,
+                    ptr::null_mut(),
+                )
+            };
         }
 
         if let Some(object_override) = self.object_override {
@@ -189,3 +671,188 @@ impl<'a> ClassBuilder<'a> {
         class
     }
 }
+
+/// A property added to a class by [`ClassBuilder`].
+struct Property<'a> {
+    name: &'a str,
+    default: Zval,
+    flags: PropertyFlags,
+    type_: Option<(DataType, bool)>,
+}
+
+impl Property<'_> {
+    /// Declares the property on the class, which copies the default value into the class.
+    fn declare(self, class: &mut ClassEntry) {
+        let type_ = match self.type_ {
+            Some((type_, nullable)) => ZendType::empty_from_type(type_, false, false, nullable),
+            None => ZendType::empty(false, false),
+        };
+
+        // The engine does not accept arrays as the defaults of properties of internal
+        // classes, so the default is declared as null and replaced afterwards.
+        let mut default = if self.default.is_array() {
+            Zval::new()
+        } else {
+            self.default
+        };
+
+        unsafe {
+            let info = zend_declare_typed_property(
+                class,
+                ZendString::new_interned(self.name).unwrap().into_raw(),
+                &mut default,
+                self.flags.bits() as c_int,
+                ptr::null_mut(),
+                type_,
+            );
+
+            if self.default.is_array() {
+                *ext_php_rs_property_default(class, info) = self.default;
+            }
+        }
+    }
+}
+
+/// Returns whether a name is valid as the name of a class constant, which is an identifier
+/// other than `class`, which is reserved for `ClassName::class`.
+fn is_valid_constant_name(name: &str) -> bool {
+    !name.contains('\\') && is_valid_name(name) && !name.eq_ignore_ascii_case("class")
+}
+
+/// Returns whether a value can be assigned to a property of the given type without being
+/// coerced, other than integers being converted into floats.
+fn accepts(type_: DataType, nullable: bool, value: &Zval) -> bool {
+    let actual = match value.get_type() {
+        Some(actual) => actual,
+        None => return false,
+    };
+
+    match (type_, actual) {
+        (DataType::Mixed, _) => true,
+        (_, DataType::Null) => nullable,
+        (DataType::Bool, DataType::False | DataType::True) => true,
+        (DataType::Double, DataType::Long) => true,
+        (type_, actual) => type_ == actual,
+    }
+}
+
+/// Copies a value into memory which is shared by every request, as the default value of a
+/// property is. Strings are interned, and arrays are copied into persistent arrays which are
+/// immutable, so they are separated when they are modified, and are never freed. Returns
+/// `None` for other values which are reference counted, such as objects.
+fn share_value(value: &Zval) -> Option<Zval> {
+    let value = value.dereference();
+    let mut shared = *value;
+
+    if value.is_string() {
+        let zs = unsafe { &*value.value.str };
+
+        if !zs.is_interned() {
+            shared.set_zend_string(ZendString::from_bytes_interned(zs.as_bytes()).ok()?);
+        }
+    } else if let Some(arr) = value.array() {
+        let mut ht = ZendHashTable::with_capacity_persistent(arr.len() as u32);
+
+        for (key, val) in arr.iter() {
+            let inserted = share_value(val).and_then(|val| match ht.insert(key, val) {
+                Ok(_) => Some(()),
+                Err(_) => {
+                    release_shared(val);
+                    None
+                }
+            });
+
+            // The elements which were already copied are freed along with the array.
+            if inserted.is_none() {
+                release_shared_elements(&ht);
+                return None;
+            }
+        }
+
+        // Immutable arrays are given two references, so they are always separated before
+        // being modified.
+        let ptr = ht.into_ptr();
+        unsafe {
+            (*ptr).gc.refcount = 2;
+            (*ptr).gc.u.type_info |= GC_IMMUTABLE;
+        }
+
+        shared.u1.type_info = IS_ARRAY;
+        shared.value.arr = ptr;
+    } else if value.is_refcounted() {
+        return None;
+    }
+
+    Some(shared)
+}
+
+/// Frees a value returned by [`share_value`] which is not used after all. Interned strings are
+/// left to the engine.
+fn release_shared(value: Zval) {
+    if !value.is_array() {
+        return;
+    }
+
+    // The array was made immutable by `share_value`, so it is made mutable again to be freed.
+    let ptr = unsafe { value.value.arr };
+    unsafe {
+        (*ptr).gc.refcount = 1;
+        (*ptr).gc.u.type_info &= !GC_IMMUTABLE;
+    }
+
+    let ht = unsafe { ZendHashTable::from_raw(ptr) };
+    release_shared_elements(&ht);
+}
+
+/// Frees the arrays copied by [`share_value`] into a persistent array, before the array itself
+/// is dropped. The persistent destructor does not free them, as they are immutable.
+fn release_shared_elements(ht: &ZendHashTable) {
+    for (_, val) in ht.iter() {
+        release_shared(*val);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::php::types::long::ZendLong;
+
+    #[test]
+    fn test_is_interface() {
+        let mut ce: ClassEntry = unsafe { mem::zeroed() };
+        assert!(!ce.is_interface());
+
+        ce.ce_flags = (ClassFlags::Interface | ClassFlags::Abstract).bits();
+        assert!(ce.is_interface());
+    }
+
+    #[test]
+    fn test_accepts() {
+        let long = Zval::from(5 as ZendLong);
+        let null = Zval::from(());
+
+        assert!(accepts(DataType::Long, false, &long));
+        assert!(accepts(DataType::Double, false, &long));
+        assert!(accepts(DataType::Mixed, false, &long));
+        assert!(!accepts(DataType::String, false, &long));
+        assert!(!accepts(DataType::Bool, false, &long));
+
+        assert!(accepts(DataType::Bool, false, &Zval::from(false)));
+        assert!(accepts(DataType::Double, false, &Zval::from(1.5)));
+        assert!(!accepts(DataType::Long, false, &Zval::from(1.5)));
+
+        assert!(accepts(DataType::Array, true, &null));
+        assert!(accepts(DataType::Mixed, false, &null));
+        assert!(!accepts(DataType::Array, false, &null));
+    }
+
+    #[test]
+    fn test_is_valid_constant_name() {
+        assert!(is_valid_constant_name("MODE_FAST"));
+        assert!(is_valid_constant_name("_private2"));
+        assert!(!is_valid_constant_name("2FAST"));
+        assert!(!is_valid_constant_name("Acme\\MODE"));
+        assert!(!is_valid_constant_name("CLASS"));
+        assert!(!is_valid_constant_name(""));
+    }
+}
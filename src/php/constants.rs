@@ -0,0 +1,195 @@
+//! Constants registered by an extension, such as `PHP_VERSION` in the core of PHP. See
+//! [`ModuleBuilder::constant`].
+//!
+//! [`ModuleBuilder::constant`]: crate::php::module::ModuleBuilder::constant
+
+use std::os::raw::c_char;
+
+use crate::{
+    bindings::{
+        zend_register_bool_constant, zend_register_double_constant, zend_register_long_constant,
+        zend_register_null_constant, zend_register_stringl_constant, CONST_CS, CONST_PERSISTENT,
+    },
+    errors::{Error, Result},
+};
+
+use super::{
+    enums::DataType,
+    types::{long::ZendLong, zval::Zval},
+};
+
+/// The flags of constants registered by extensions, which live until the engine shuts down.
+const FLAGS: i32 = (CONST_CS | CONST_PERSISTENT) as i32;
+
+/// A value which can be registered as a constant of an extension. The value is copied into
+/// persistent memory by the engine when it is registered.
+pub trait IntoConst {
+    /// Registers the value as a constant. The constant is removed when the extension shuts
+    /// down. If a constant with the same name already exists, a warning is emitted and the
+    /// existing constant is kept.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the constant.
+    /// * `module_number` - The module number of the extension registering the constant, as
+    ///   passed to its startup function.
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the value cannot be the value of a constant.
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()>;
+}
+
+macro_rules! into_const_long {
+    ($($t: ty),*) => {
+        $(
+            impl IntoConst for $t {
+                fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+                    unsafe {
+                        zend_register_long_constant(
+                            name.as_ptr() as *const c_char,
+                            name.len() as _,
+                            *self as ZendLong,
+                            FLAGS,
+                            module_number,
+                        )
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+into_const_long!(i8, i16, i32, i64, u8, u16, u32);
+
+impl IntoConst for f64 {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        unsafe {
+            zend_register_double_constant(
+                name.as_ptr() as *const c_char,
+                name.len() as _,
+                *self,
+                FLAGS,
+                module_number,
+            )
+        };
+        Ok(())
+    }
+}
+
+impl IntoConst for bool {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        unsafe {
+            zend_register_bool_constant(
+                name.as_ptr() as *const c_char,
+                name.len() as _,
+                *self,
+                FLAGS,
+                module_number,
+            )
+        };
+        Ok(())
+    }
+}
+
+/// Registers a `null` constant.
+impl IntoConst for () {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        unsafe {
+            zend_register_null_constant(
+                name.as_ptr() as *const c_char,
+                name.len() as _,
+                FLAGS,
+                module_number,
+            )
+        };
+        Ok(())
+    }
+}
+
+impl IntoConst for str {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        register_bytes(name, self.as_bytes(), module_number);
+        Ok(())
+    }
+}
+
+impl IntoConst for &str {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        (*self).register_constant(name, module_number)
+    }
+}
+
+impl IntoConst for String {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        self.as_str().register_constant(name, module_number)
+    }
+}
+
+/// Registers the value of a zval, following references. Only strings, integers, floats,
+/// booleans and `null` may be registered, other values such as arrays and objects return
+/// [`Error::InvalidConstant`].
+impl IntoConst for Zval {
+    fn register_constant(&self, name: &str, module_number: i32) -> Result<()> {
+        let zv = self.dereference();
+
+        match zv.get_type() {
+            Some(DataType::Null) => ().register_constant(name, module_number),
+            Some(DataType::False) => false.register_constant(name, module_number),
+            Some(DataType::True) => true.register_constant(name, module_number),
+            Some(DataType::Long) => match zv.long() {
+                Some(value) => value.register_constant(name, module_number),
+                None => Err(Error::ZvalConversion),
+            },
+            Some(DataType::Double) => match zv.double() {
+                Some(value) => value.register_constant(name, module_number),
+                None => Err(Error::ZvalConversion),
+            },
+            Some(DataType::String) => match zv.str_bytes() {
+                Some(bytes) => {
+                    register_bytes(name, bytes, module_number);
+                    Ok(())
+                }
+                None => Err(Error::ZvalConversion),
+            },
+            ty => Err(Error::InvalidConstant(name.to_string(), ty)),
+        }
+    }
+}
+
+/// Registers a string constant, which may contain any bytes.
+fn register_bytes(name: &str, value: &[u8], module_number: i32) {
+    unsafe {
+        zend_register_stringl_constant(
+            name.as_ptr() as *const c_char,
+            name.len() as _,
+            value.as_ptr() as *const c_char,
+            value.len() as _,
+            FLAGS,
+            module_number,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_constant() {
+        // The array is never read, so it does not need to be allocated.
+        let mut zv = Zval::new();
+        zv.u1.type_info = crate::bindings::IS_ARRAY;
+
+        let err = zv.register_constant("EXT_NAME_X", 0).unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidConstant("EXT_NAME_X".to_string(), Some(DataType::Array))
+        );
+        assert_eq!(
+            err.to_string(),
+            "Constant `EXT_NAME_X` must be a string, integer, float, boolean or null, not array."
+        );
+    }
+}
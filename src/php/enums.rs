@@ -1,14 +1,43 @@
 //! Wrapper for enums introduced in C.
 
 use crate::bindings::{
-    IS_ARRAY, IS_CALLABLE, IS_CONSTANT_AST, IS_DOUBLE, IS_FALSE, IS_LONG, IS_NULL, IS_OBJECT,
-    IS_REFERENCE, IS_RESOURCE, IS_STRING, IS_TRUE, IS_UNDEF, IS_VOID,
+    _zend_known_string_id_ZEND_STR_ARGC, _zend_known_string_id_ZEND_STR_ARGS,
+    _zend_known_string_id_ZEND_STR_ARGV, _zend_known_string_id_ZEND_STR_ARRAY,
+    _zend_known_string_id_ZEND_STR_ARRAY_CAPITALIZED, _zend_known_string_id_ZEND_STR_BOOL,
+    _zend_known_string_id_ZEND_STR_BOOLEAN, _zend_known_string_id_ZEND_STR_CALLABLE,
+    _zend_known_string_id_ZEND_STR_CLASS, _zend_known_string_id_ZEND_STR_CLOSED_RESOURCE,
+    _zend_known_string_id_ZEND_STR_CODE, _zend_known_string_id_ZEND_STR_DOUBLE,
+    _zend_known_string_id_ZEND_STR_ERROR_REPORTING, _zend_known_string_id_ZEND_STR_EVAL,
+    _zend_known_string_id_ZEND_STR_FALSE, _zend_known_string_id_ZEND_STR_FILE,
+    _zend_known_string_id_ZEND_STR_FLOAT, _zend_known_string_id_ZEND_STR_FRAGMENT,
+    _zend_known_string_id_ZEND_STR_FUNCTION, _zend_known_string_id_ZEND_STR_HOST,
+    _zend_known_string_id_ZEND_STR_INCLUDE, _zend_known_string_id_ZEND_STR_INCLUDE_ONCE,
+    _zend_known_string_id_ZEND_STR_INT, _zend_known_string_id_ZEND_STR_INTEGER,
+    _zend_known_string_id_ZEND_STR_ITERABLE, _zend_known_string_id_ZEND_STR_KEY,
+    _zend_known_string_id_ZEND_STR_LINE, _zend_known_string_id_ZEND_STR_MAGIC_INVOKE,
+    _zend_known_string_id_ZEND_STR_MESSAGE, _zend_known_string_id_ZEND_STR_MIXED,
+    _zend_known_string_id_ZEND_STR_NAME, _zend_known_string_id_ZEND_STR_NULL,
+    _zend_known_string_id_ZEND_STR_NULL_LOWERCASE, _zend_known_string_id_ZEND_STR_OBJECT,
+    _zend_known_string_id_ZEND_STR_OBJECT_OPERATOR,
+    _zend_known_string_id_ZEND_STR_PAAMAYIM_NEKUDOTAYIM, _zend_known_string_id_ZEND_STR_PASS,
+    _zend_known_string_id_ZEND_STR_PATH, _zend_known_string_id_ZEND_STR_PORT,
+    _zend_known_string_id_ZEND_STR_PREVIOUS, _zend_known_string_id_ZEND_STR_QUERY,
+    _zend_known_string_id_ZEND_STR_REQUIRE, _zend_known_string_id_ZEND_STR_REQUIRE_ONCE,
+    _zend_known_string_id_ZEND_STR_RESOURCE, _zend_known_string_id_ZEND_STR_SCALAR,
+    _zend_known_string_id_ZEND_STR_SCHEME, _zend_known_string_id_ZEND_STR_SEVERITY,
+    _zend_known_string_id_ZEND_STR_STATIC, _zend_known_string_id_ZEND_STR_STRING,
+    _zend_known_string_id_ZEND_STR_THIS, _zend_known_string_id_ZEND_STR_TRACE,
+    _zend_known_string_id_ZEND_STR_TYPE, _zend_known_string_id_ZEND_STR_UNKNOWN,
+    _zend_known_string_id_ZEND_STR_USER, _zend_known_string_id_ZEND_STR_VALUE,
+    _zend_known_string_id_ZEND_STR_VOID, _IS_BOOL, IS_ARRAY, IS_CALLABLE, IS_CONSTANT_AST,
+    IS_DOUBLE, IS_FALSE, IS_LONG, IS_MIXED, IS_NULL, IS_OBJECT, IS_REFERENCE, IS_RESOURCE,
+    IS_STRING, IS_TRUE, IS_UNDEF, IS_VOID,
 };
 
 use super::types::long::ZendLong;
 
 /// Valid data types for PHP.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum DataType {
     Undef = IS_UNDEF,
@@ -24,11 +53,134 @@ pub enum DataType {
     Resource = IS_RESOURCE,
     Reference = IS_REFERENCE,
     Callable = IS_CALLABLE,
+    Mixed = IS_MIXED,
+    Bool = _IS_BOOL,
 
     ConstantExpression = IS_CONSTANT_AST,
     Void = IS_VOID,
 }
 
+/// The strings interned by the engine at startup, equivalent to the `zend_known_string_id`
+/// enum. See [`ZendString::known`].
+///
+/// [`ZendString::known`]: crate::php::types::string::ZendString::known
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum KnownString {
+    /// `file`
+    File = _zend_known_string_id_ZEND_STR_FILE,
+    /// `line`
+    Line = _zend_known_string_id_ZEND_STR_LINE,
+    /// `function`
+    Function = _zend_known_string_id_ZEND_STR_FUNCTION,
+    /// `class`
+    Class = _zend_known_string_id_ZEND_STR_CLASS,
+    /// `object`
+    Object = _zend_known_string_id_ZEND_STR_OBJECT,
+    /// `type`
+    Type = _zend_known_string_id_ZEND_STR_TYPE,
+    /// `->`
+    ObjectOperator = _zend_known_string_id_ZEND_STR_OBJECT_OPERATOR,
+    /// `::`
+    PaamayimNekudotayim = _zend_known_string_id_ZEND_STR_PAAMAYIM_NEKUDOTAYIM,
+    /// `args`
+    Args = _zend_known_string_id_ZEND_STR_ARGS,
+    /// `unknown`
+    Unknown = _zend_known_string_id_ZEND_STR_UNKNOWN,
+    /// `eval`
+    Eval = _zend_known_string_id_ZEND_STR_EVAL,
+    /// `include`
+    Include = _zend_known_string_id_ZEND_STR_INCLUDE,
+    /// `require`
+    Require = _zend_known_string_id_ZEND_STR_REQUIRE,
+    /// `include_once`
+    IncludeOnce = _zend_known_string_id_ZEND_STR_INCLUDE_ONCE,
+    /// `require_once`
+    RequireOnce = _zend_known_string_id_ZEND_STR_REQUIRE_ONCE,
+    /// `scalar`
+    Scalar = _zend_known_string_id_ZEND_STR_SCALAR,
+    /// `error_reporting`
+    ErrorReporting = _zend_known_string_id_ZEND_STR_ERROR_REPORTING,
+    /// `static`
+    Static = _zend_known_string_id_ZEND_STR_STATIC,
+    /// `this`
+    This = _zend_known_string_id_ZEND_STR_THIS,
+    /// `value`
+    Value = _zend_known_string_id_ZEND_STR_VALUE,
+    /// `key`
+    Key = _zend_known_string_id_ZEND_STR_KEY,
+    /// `__invoke`
+    MagicInvoke = _zend_known_string_id_ZEND_STR_MAGIC_INVOKE,
+    /// `previous`
+    Previous = _zend_known_string_id_ZEND_STR_PREVIOUS,
+    /// `code`
+    Code = _zend_known_string_id_ZEND_STR_CODE,
+    /// `message`
+    Message = _zend_known_string_id_ZEND_STR_MESSAGE,
+    /// `severity`
+    Severity = _zend_known_string_id_ZEND_STR_SEVERITY,
+    /// `string`
+    String = _zend_known_string_id_ZEND_STR_STRING,
+    /// `trace`
+    Trace = _zend_known_string_id_ZEND_STR_TRACE,
+    /// `scheme`
+    Scheme = _zend_known_string_id_ZEND_STR_SCHEME,
+    /// `host`
+    Host = _zend_known_string_id_ZEND_STR_HOST,
+    /// `port`
+    Port = _zend_known_string_id_ZEND_STR_PORT,
+    /// `user`
+    User = _zend_known_string_id_ZEND_STR_USER,
+    /// `pass`
+    Pass = _zend_known_string_id_ZEND_STR_PASS,
+    /// `path`
+    Path = _zend_known_string_id_ZEND_STR_PATH,
+    /// `query`
+    Query = _zend_known_string_id_ZEND_STR_QUERY,
+    /// `fragment`
+    Fragment = _zend_known_string_id_ZEND_STR_FRAGMENT,
+    /// `NULL`
+    Null = _zend_known_string_id_ZEND_STR_NULL,
+    /// `boolean`
+    Boolean = _zend_known_string_id_ZEND_STR_BOOLEAN,
+    /// `integer`
+    Integer = _zend_known_string_id_ZEND_STR_INTEGER,
+    /// `double`
+    Double = _zend_known_string_id_ZEND_STR_DOUBLE,
+    /// `array`
+    Array = _zend_known_string_id_ZEND_STR_ARRAY,
+    /// `resource`
+    Resource = _zend_known_string_id_ZEND_STR_RESOURCE,
+    /// `resource (closed)`
+    ClosedResource = _zend_known_string_id_ZEND_STR_CLOSED_RESOURCE,
+    /// `name`
+    Name = _zend_known_string_id_ZEND_STR_NAME,
+    /// `argv`
+    Argv = _zend_known_string_id_ZEND_STR_ARGV,
+    /// `argc`
+    Argc = _zend_known_string_id_ZEND_STR_ARGC,
+    /// `Array`
+    ArrayCapitalized = _zend_known_string_id_ZEND_STR_ARRAY_CAPITALIZED,
+    /// `bool`
+    Bool = _zend_known_string_id_ZEND_STR_BOOL,
+    /// `int`
+    Int = _zend_known_string_id_ZEND_STR_INT,
+    /// `float`
+    Float = _zend_known_string_id_ZEND_STR_FLOAT,
+    /// `callable`
+    Callable = _zend_known_string_id_ZEND_STR_CALLABLE,
+    /// `iterable`
+    Iterable = _zend_known_string_id_ZEND_STR_ITERABLE,
+    /// `void`
+    Void = _zend_known_string_id_ZEND_STR_VOID,
+    /// `false`
+    False = _zend_known_string_id_ZEND_STR_FALSE,
+    /// `null`
+    NullLowercase = _zend_known_string_id_ZEND_STR_NULL_LOWERCASE,
+    /// `mixed`
+    Mixed = _zend_known_string_id_ZEND_STR_MIXED,
+}
+
 impl From<ZendLong> for DataType {
     fn from(_: ZendLong) -> Self {
         Self::Long
@@ -56,3 +208,12 @@ impl From<String> for DataType {
         Self::String
     }
 }
+
+/// The value of a numeric string, as parsed by [`Zval::parse_numeric`].
+///
+/// [`Zval::parse_numeric`]: crate::php::types::zval::Zval::parse_numeric
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumericValue {
+    Long(ZendLong),
+    Double(f64),
+}
@@ -1,13 +1,136 @@
 //! Contains all the base PHP throwables, including `Throwable` and `Exception`.
 
-use super::class::ClassEntry;
-use crate::bindings::{
-    zend_ce_argument_count_error, zend_ce_arithmetic_error, zend_ce_compile_error,
-    zend_ce_division_by_zero_error, zend_ce_error_exception, zend_ce_exception,
-    zend_ce_parse_error, zend_ce_throwable, zend_ce_type_error, zend_ce_unhandled_match_error,
-    zend_ce_value_error,
+use std::{ffi::CString, ptr};
+
+use super::{
+    class::ClassEntry,
+    globals::ExecutorGlobals,
+    types::{long::ZendLong, object::ZendObject},
+};
+use crate::{
+    bindings::{
+        zend_ce_argument_count_error, zend_ce_arithmetic_error, zend_ce_compile_error,
+        zend_ce_division_by_zero_error, zend_ce_error_exception, zend_ce_exception,
+        zend_ce_parse_error, zend_ce_throwable, zend_ce_type_error, zend_ce_unhandled_match_error,
+        zend_ce_value_error, zend_get_exception_base, zend_throw_exception,
+    },
+    errors::{Error, Result},
 };
 
+/// Throws an exception, equivalent to `throw new $class($message, $code)` in PHP for
+/// exceptions which do not override their constructor. The exception is left pending, and is
+/// thrown in PHP once the function returns.
+///
+/// # Parameters
+///
+/// * `class` - The class of the exception, which must implement `Throwable`, e.g.
+///   [`ClassEntry::exception`] or a class built by the extension which extends it.
+/// * `message` - The message of the exception. It is cut off at the first NUL byte.
+/// * `code` - The code of the exception.
+///
+/// # Returns
+///
+/// The error for the pending exception, which can be returned from a function returning a
+/// [`Result`]. If the class does not implement `Throwable`, nothing is thrown and
+/// [`Error::NotThrowable`] is returned instead.
+///
+/// [`Result`]: crate::errors::Result
+pub fn throw(class: &ClassEntry, message: &str, code: ZendLong) -> Error {
+    match ClassEntry::throwable() {
+        Some(throwable) if class.instance_of(throwable) => {}
+        _ => {
+            let name = unsafe { class.name.as_ref() }
+                .map(String::from)
+                .unwrap_or_default();
+            return Error::NotThrowable(name);
+        }
+    }
+
+    let end = message.find('\0').unwrap_or(message.len());
+    let message = CString::new(&message[..end]).unwrap_or_default();
+    let class = class as *const ClassEntry as *mut ClassEntry;
+
+    unsafe { zend_throw_exception(class, message.as_ptr(), code) };
+
+    ThrownException::pending().map_or(Error::InvalidPointer, Error::Exception)
+}
+
+/// The details of a throwable which was thrown in PHP, read from the throwable object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrownException {
+    /// The name of the class of the throwable, e.g. `InvalidArgumentException`.
+    pub class: String,
+    /// The message of the throwable.
+    pub message: String,
+    /// The code of the throwable.
+    pub code: ZendLong,
+}
+
+impl ThrownException {
+    /// Returns the details of the exception which has been thrown and not yet handled, if any.
+    /// The exception is left pending.
+    pub fn pending() -> Option<Self> {
+        ExecutorGlobals::get().exception().map(Self::from_object)
+    }
+
+    /// Reads the details of a throwable object.
+    ///
+    /// # Parameters
+    ///
+    /// * `obj` - The throwable object, which must be an instance of `Exception` or `Error`.
+    pub(crate) fn from_object(obj: &ZendObject) -> Self {
+        // The properties are declared on the `Exception` and `Error` base classes, which must
+        // be used as the scope to be able to read them.
+        let ptr = obj as *const ZendObject as *mut ZendObject;
+        let scope = unsafe { zend_get_exception_base(ptr).as_ref() };
+
+        Self {
+            class: obj.class_name(),
+            message: scope
+                .and_then(|scope| obj.get_property_in_scope(scope, "message"))
+                .unwrap_or_default(),
+            code: scope
+                .and_then(|scope| obj.get_property_in_scope(scope, "code"))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The exception pending before an operation which can throw, used to tell whether the
+/// operation threw an exception of its own. An exception which was already pending when the
+/// operation started is not reported as thrown by it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ExceptionMark(*const ZendObject);
+
+impl ExceptionMark {
+    /// Records the exception which is currently pending, if any.
+    pub(crate) fn new() -> Self {
+        Self(
+            ExecutorGlobals::get()
+                .exception()
+                .map_or(ptr::null(), |obj| obj as *const ZendObject),
+        )
+    }
+
+    /// Returns the details of the pending exception if it was thrown after the mark was
+    /// recorded.
+    pub(crate) fn thrown(self) -> Option<ThrownException> {
+        ExecutorGlobals::get()
+            .exception()
+            .filter(|obj| !ptr::eq(*obj, self.0))
+            .map(ThrownException::from_object)
+    }
+
+    /// Returns the error for the exception thrown after the mark was recorded, or `Ok(val)`
+    /// if none was thrown.
+    pub(crate) fn check<T>(self, val: T) -> Result<T> {
+        match self.thrown() {
+            Some(e) => Err(Error::Exception(e)),
+            None => Ok(val),
+        }
+    }
+}
+
 impl ClassEntry {
     /// Returns the base `Throwable` class.
     pub fn throwable<'a>() -> Option<&'a Self> {
@@ -1,44 +1,271 @@
 //! Functions for interacting with the execution data passed to PHP functions\
 //! introduced in Rust.
 
-use std::{convert::TryFrom, mem};
+use std::mem;
 
-use crate::{
-    bindings::{zend_execute_data, zend_read_property, ZEND_MM_ALIGNMENT, ZEND_MM_ALIGNMENT_MASK},
-    functions::c_str,
+use crate::bindings::{
+    zend_execute_data, zend_get_default_from_internal_arg_info, ZEND_ACC_STRICT_TYPES,
+    ZEND_CALL_HAS_EXTRA_NAMED_PARAMS, ZEND_INTERNAL_FUNCTION, ZEND_MM_ALIGNMENT,
+    ZEND_MM_ALIGNMENT_MASK,
 };
 
-use super::types::zval::Zval;
+use super::{
+    args::{Args, RefArg},
+    class::ClassEntry,
+    types::{
+        array::HashTableRef,
+        object::ZendObject,
+        zval::{FromZval, Zval},
+    },
+};
 
 /// Execution data passed when a function is called from Zend.
 pub type ExecutionData = zend_execute_data;
 
 impl ExecutionData {
-    pub fn get_parameter(&mut self, name: &str) -> Option<&'static mut Zval> {
-        let ce = unsafe { (*self.func).common.scope.as_mut() }?;
-        let mut rv = Zval::new();
-
-        let x = unsafe {
-            zend_read_property(
-                ce,
-                self.This.value.obj,
-                c_str(name),
-                (name.len() + 1) as _,
-                false,
-                &mut rv,
-            )
-            .as_mut()
-        };
-
-        println!("done");
-        x
+    /// Reads a property of the object the method was called on, i.e. `$this`. The property
+    /// is read as `$this->name` would from inside the method, so protected and private properties of
+    /// the class declaring the method are visible.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Zval)` - A copy of the value of the property, holding its own reference. It
+    ///   must be handed over to PHP or released with [`Zval::release`].
+    /// * `None` - The function is not a method called on an object, or the property does not
+    ///   exist or is not visible from the method.
+    pub fn get_self_property(&self, name: &str) -> Option<Zval> {
+        self.get_this()?.read_property(self.get_scope()?, name)
     }
 
-    /// Retrieves an argument from the execution data at a given offset.
-    /// Offsets start at zero. Make sure to never attempt to retrieve an
-    /// argument that may not exist (greater offset than arg_len - 1).
+    /// Returns the object the method was called on, i.e. `$this`. When a method is called on
+    /// an instance of a child class, this is the instance of the child class.
     ///
-    /// Marked as unsafe.
+    /// # Returns
+    ///
+    /// * `Some(&ZendObject)` - The object.
+    /// * `None` - The function is not a method, or the method was called statically.
+    pub fn get_this(&self) -> Option<&ZendObject> {
+        self.get_this_zval()?.object()
+    }
+
+    /// Returns a mutable reference to the object the method was called on, e.g. to write its
+    /// properties with [`ZendObject::set_property`]. See [`ExecutionData::get_this`].
+    ///
+    /// The arguments borrow from the execution data, so they must no longer be used once the
+    /// object is borrowed mutably, as it could be one of the arguments:
+    ///
+    /// ```compile_fail,E0502
+    /// use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+    ///
+    /// pub extern "C" fn set_name(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    ///     let name = execute_data.get_arg::<&str>(0).unwrap_or_default();
+    ///     if let Some(this) = execute_data.get_this_mut() {
+    ///         let _ = this.set_property("name", name);
+    ///     }
+    /// }
+    /// ```
+    pub fn get_this_mut(&mut self) -> Option<&mut ZendObject> {
+        if self.This.is_object() {
+            self.This.object_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the zval holding the object the method was called on. See
+    /// [`ExecutionData::get_this`].
+    pub fn get_this_zval(&self) -> Option<&Zval> {
+        // For static calls, the zval holds the called class instead of an object, and is
+        // marked as undefined.
+        if self.This.is_object() {
+            Some(&self.This)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the class which declares the method being called, as used for the visibility
+    /// of properties, i.e. `self::class`. Returns `None` for functions.
+    pub fn get_scope(&self) -> Option<&ClassEntry> {
+        unsafe { self.func.as_ref()?.common.scope.as_ref() }
+    }
+
+    /// Returns the class the method was called on, i.e. `static::class`. This is the class of
+    /// `$this`, or the class named in a static call, which may be a child class of
+    /// [`ExecutionData::get_scope`]. Returns `None` for functions.
+    pub fn get_called_scope(&self) -> Option<&ClassEntry> {
+        match self.get_this() {
+            Some(obj) => unsafe { obj.ce.as_ref() },
+            None => unsafe { self.This.value.ce.as_ref() },
+        }
+    }
+
+    /// Starts parsing the arguments passed to the function. This is the recommended way to
+    /// read arguments, as the number and types of the arguments are checked and the same
+    /// errors are thrown as by the functions built into PHP. See [`Args`].
+    ///
+    /// Scalar arguments are coerced in place, as the engine does, so the execution data is
+    /// borrowed mutably for as long as the parsed arguments are used.
+    pub fn parse_args(&mut self) -> Args<'_> {
+        Args::new(self)
+    }
+
+    /// Returns an argument which was passed by reference, such as `&$out`, so that a value
+    /// can be written back to the caller's variable. The argument must be declared as a
+    /// reference when registering the function, see [`Arg::as_ref`].
+    ///
+    /// If the argument is not a reference, e.g. because it was not declared as one, the value
+    /// is wrapped in a new reference in place, as the engine would. Writing to it does not
+    /// change the variable of the caller in that case.
+    ///
+    /// # Parameters
+    ///
+    /// * `offset` - The offset of the argument, where the first argument is at offset 0.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(RefArg)` - The argument.
+    /// * `None` - The argument was not passed.
+    ///
+    /// [`Arg::as_ref`]: crate::php::args::Arg::as_ref
+    pub fn get_arg_by_ref(&mut self, offset: usize) -> Option<RefArg<'_>> {
+        if offset >= self.arg_count() {
+            return None;
+        }
+
+        let zval = unsafe { &mut *(self.zend_call_var_num(offset as isize) as *mut Zval) };
+        if !zval.is_reference() {
+            let val = *zval;
+            zval.set_reference(val);
+        }
+
+        Some(RefArg::new(zval))
+    }
+
+    /// Returns the number of arguments actually passed to the function, equivalent to the
+    /// `ZEND_CALL_NUM_ARGS` macro. This may be fewer than the number of arguments the
+    /// function declares if some are optional, or more if it is variadic.
+    pub fn arg_count(&self) -> usize {
+        unsafe { self.This.u2.num_args as usize }
+    }
+
+    /// Returns an iterator over the arguments passed to the function, in order. Arguments
+    /// skipped by a call with named arguments are left out.
+    pub fn args(&self) -> impl Iterator<Item = &Zval> {
+        (0..self.arg_count()).filter_map(move |n| unsafe { self.zend_call_arg(n) })
+    }
+
+    /// Returns the named arguments which do not match any of the arguments the function
+    /// declares, e.g. `b` in `f(1, b: 2)`. These are only accepted by variadic functions, and
+    /// are collected by [`Args::named_variadic`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(HashTableRef)` - An array of the extra named arguments, keyed by name.
+    /// * `None` - No extra named arguments were passed.
+    pub fn extra_named_args(&self) -> Option<HashTableRef<'_>> {
+        let call_info = unsafe { self.This.u1.type_info };
+
+        if call_info & ZEND_CALL_HAS_EXTRA_NAMED_PARAMS != 0 && !self.extra_named_params.is_null() {
+            Some(HashTableRef::from_ptr(self.extra_named_params))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the name of the function being executed, e.g. `str_repeat`, or `bar` for the
+    /// method `Foo::bar`. Closures are named `{closure}`.
+    ///
+    /// Returns `None` if the frame is not executing a function, such as the frame of the main
+    /// script.
+    pub fn function_name(&self) -> Option<String> {
+        // The name is shared by user and internal functions.
+        let name = unsafe { self.func.as_ref()?.common.function_name.as_ref()? };
+        Some(String::from(name))
+    }
+
+    /// Returns the name of the class which declares the method being executed, e.g. `Foo` for
+    /// the method `Foo::bar`. See [`ExecutionData::get_scope`].
+    ///
+    /// Returns `None` if the frame is not executing a method.
+    pub fn scope_class_name(&self) -> Option<String> {
+        let name = unsafe { self.get_scope()?.name.as_ref()? };
+        Some(String::from(name))
+    }
+
+    /// Returns whether a method is being executed without an object, i.e. it was called
+    /// statically as in `Foo::bar()`. Returns false for functions.
+    pub fn is_static_call(&self) -> bool {
+        self.get_scope().is_some() && self.get_this().is_none()
+    }
+
+    /// Returns whether the function was called from a file which declares
+    /// `strict_types=1`, equivalent to the `ZEND_ARG_USES_STRICT_TYPES` macro. Scalar
+    /// arguments are then not coerced, so `"5"` is not accepted as an `int`. Functions called
+    /// through another internal function, such as `call_user_func`, never use strict types.
+    pub fn uses_strict_types(&self) -> bool {
+        match self
+            .caller()
+            .and_then(|caller| unsafe { caller.func.as_ref() })
+        {
+            Some(func) => unsafe { func.common.fn_flags & ZEND_ACC_STRICT_TYPES != 0 },
+            None => false,
+        }
+    }
+
+    /// Returns the execution data of the frame which called the function, e.g. the user
+    /// function calling an extension function.
+    ///
+    /// Returns `None` if there is no previous frame, which is only the case for the main
+    /// script.
+    pub fn caller(&self) -> Option<&ExecutionData> {
+        unsafe { self.prev_execute_data.as_ref() }
+    }
+
+    /// Records the return value slot passed to the handler in the execution data, so that
+    /// code which is only given the execution data can construct the value returned by the
+    /// function in place, see [`ExecutionData::return_value`]. The engine does not record the
+    /// slot for calls to internal functions, so handlers call this first. Handlers generated
+    /// by `#[php_function]` already do.
+    ///
+    /// # Parameters
+    ///
+    /// * `retval` - The return value slot passed to the handler.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::php::{execution_data::ExecutionData, types::zval::{IntoZval, Zval}};
+    ///
+    /// pub extern "C" fn range(execute_data: &mut ExecutionData, retval: &mut Zval) {
+    ///     execute_data.record_return_value(retval);
+    ///
+    ///     if let Ok((n,)) = execute_data.parse_args().arg::<i64>("n").finish() {
+    ///         let _ = (0..n).collect::<Vec<_>>().set_zval(execute_data.return_value());
+    ///     }
+    /// }
+    /// ```
+    pub fn record_return_value(&mut self, retval: &mut Zval) {
+        self.return_value = retval;
+    }
+
+    /// Returns the slot which the value returned by the function is written to, i.e. the
+    /// `retval` passed to the handler and recorded with
+    /// [`ExecutionData::record_return_value`]. Values are constructed in place, e.g. with
+    /// [`IntoZval::set_zval`], and the function returns null if the slot is left untouched.
+    ///
+    /// [`IntoZval::set_zval`]: super::types::zval::IntoZval::set_zval
+    pub fn return_value(&mut self) -> &mut Zval {
+        unsafe { &mut *self.return_value }
+    }
+
+    /// Retrieves an argument from the execution data at a given offset.
+    /// Offsets start at zero. See [`ExecutionData::parse_args`] to read all of the
+    /// arguments with error reporting.
     ///
     /// # Parameters
     ///
@@ -51,16 +278,31 @@ impl ExecutionData {
     ///
     /// # Returns
     ///
-    /// * `Some()` - The argument was successfully read and parsed.
-    /// * `None` - The argument was not present or the type of the
-    /// argument was wrong.
-    #[allow(dead_code)]
-    pub(crate) unsafe fn get_arg<T>(&self, offset: usize) -> Option<T>
+    /// * `Some()` - The argument was successfully read and parsed. When reading an [`Option`],
+    ///   `Some(None)` is returned if `null` was passed.
+    /// * `None` - The argument was not passed, i.e. the offset is not less than
+    /// [`ExecutionData::arg_count`], or the type of the argument was wrong.
+    ///
+    /// The argument borrows from the execution data, as it only lives as long as the call, so
+    /// it cannot be kept once the function returns:
+    ///
+    /// ```compile_fail,E0521
+    /// use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+    ///
+    /// static mut ARGS: Vec<&Zval> = Vec::new();
+    ///
+    /// pub extern "C" fn remember(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    ///     if let Some(arg) = execute_data.get_arg::<&Zval>(0) {
+    ///         unsafe { ARGS.push(arg) };
+    ///     }
+    /// }
+    /// ```
+    pub fn get_arg<'a, T>(&'a self, offset: usize) -> Option<T>
     where
-        T: TryFrom<&'static Zval>,
+        T: FromZval<'a>,
     {
-        match self.zend_call_arg(offset) {
-            Some(zval) => match T::try_from(zval) {
+        match unsafe { self.zend_call_arg(offset) } {
+            Some(zval) => match T::from_zval(zval) {
                 Ok(res) => Some(res),
                 Err(_) => None,
             },
@@ -70,24 +312,64 @@ impl ExecutionData {
 
     /// Translation of macro `ZEND_CALL_ARG(call, n)`
     /// zend_compile.h:578
+    ///
+    /// Returns `None` if the argument at the given offset was not passed to the function. This
+    /// includes optional arguments skipped by a call with named arguments, e.g. `$b` in
+    /// `f(c: 1)`, which are left undefined.
     #[doc(hidden)]
-    pub(crate) unsafe fn zend_call_arg(&self, n: usize) -> Option<&'static Zval> {
+    pub(crate) unsafe fn zend_call_arg(&self, n: usize) -> Option<&Zval> {
+        if n >= self.arg_count() {
+            return None;
+        }
+
         let ptr = self.zend_call_var_num(n as isize);
-        ptr.as_ref()
+        ptr.as_ref().filter(|zval| !zval.is_undef())
     }
 
     /// Translation of macro `ZEND_CALL_VAR_NUM(call, n)`
     /// zend_compile.h: 575
     #[doc(hidden)]
-    unsafe fn zend_call_var_num(&self, n: isize) -> *const Zval {
+    pub(crate) unsafe fn zend_call_var_num(&self, n: isize) -> *const Zval {
         let ptr = self as *const Self as *const Zval;
         ptr.offset(Self::zend_call_frame_slot() + n as isize)
     }
 
+    /// Evaluates the default value of the argument at the given offset, as declared when the
+    /// internal function was registered, e.g. with [`Arg::default_value`].
+    ///
+    /// Returns `None` if the function is not an internal function, the argument has no
+    /// default, or the default could not be evaluated, in which case an exception may have
+    /// been thrown. The returned zval must be released.
+    ///
+    /// [`Arg::default_value`]: super::args::Arg::default_value
+    pub(crate) fn declared_default(&self, n: usize) -> Option<Zval> {
+        let func = unsafe { self.func.as_ref()? };
+        if unsafe { func.type_ } as u32 != ZEND_INTERNAL_FUNCTION {
+            return None;
+        }
+
+        let internal = unsafe { &func.internal_function };
+        if n >= internal.num_args as usize || internal.arg_info.is_null() {
+            return None;
+        }
+
+        let mut val = Zval::new();
+        if unsafe { zend_get_default_from_internal_arg_info(&mut val, internal.arg_info.add(n)) }
+            < 0
+        {
+            return None;
+        }
+
+        // Defaults referencing constants, e.g. `PHP_INT_MAX`, are left as constant expressions.
+        let result = val.eval_constant(self.get_scope());
+        val.release();
+        result.ok()
+    }
+
     /// Translation of macro `ZEND_CALL_FRAME_SLOT`
     /// zend_compile:573
     #[doc(hidden)]
-    fn zend_call_frame_slot() -> isize {
+    pub(crate) fn zend_call_frame_slot() -> isize {
         (Self::zend_mm_aligned_size::<Self>() + Self::zend_mm_aligned_size::<Zval>() - 1)
             / Self::zend_mm_aligned_size::<Zval>()
     }
@@ -103,7 +385,13 @@ impl ExecutionData {
 
 #[cfg(test)]
 mod tests {
-    use super::ExecutionData;
+    use std::{mem, ptr};
+
+    use super::{ClassEntry, ExecutionData, ZendObject, Zval};
+    use crate::{
+        bindings::{zend_function, IS_OBJECT_EX, IS_STR_INTERNED, ZEND_ACC_STRICT_TYPES},
+        php::types::fixtures::fake_string,
+    };
 
     #[test]
     fn test_zend_call_frame_slot() {
@@ -112,4 +400,118 @@ mod tests {
         // Zend Engine v4.0.2, Copyright (c) Zend Technologies
         assert_eq!(ExecutionData::zend_call_frame_slot(), 5);
     }
+
+    #[test]
+    fn test_arg_count() {
+        let slot = ExecutionData::zend_call_frame_slot() as usize;
+        let mut buf = vec![Zval::new(); slot + 3];
+        for (i, zv) in buf[slot..].iter_mut().enumerate() {
+            zv.set_long(i as _);
+        }
+
+        // The slot after the arguments which were passed is not read.
+        let ex = unsafe { &mut *(buf.as_mut_ptr() as *mut ExecutionData) };
+        ex.This.u2.num_args = 2;
+
+        assert_eq!(ex.arg_count(), 2);
+        assert_eq!(ex.get_arg::<i64>(1), Some(1));
+        assert_eq!(ex.get_arg::<i64>(2), None);
+        assert_eq!(
+            ex.args().map(|zv| zv.long()).collect::<Vec<_>>(),
+            vec![Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_get_this() {
+        let mut ce: ClassEntry = unsafe { mem::zeroed() };
+        let mut obj: ZendObject = unsafe { mem::zeroed() };
+        obj.ce = &mut ce;
+
+        let mut ex: ExecutionData = unsafe { mem::zeroed() };
+        assert!(ex.get_this().is_none());
+        assert!(ex.get_called_scope().is_none());
+
+        // A static call holds the called class.
+        ex.This.value.ce = &mut ce;
+        assert!(ex.get_this_zval().is_none());
+        assert!(ex.get_this_mut().is_none());
+        assert!(ptr::eq(ex.get_called_scope().unwrap(), &ce));
+
+        ex.This.value.obj = &mut obj;
+        ex.This.u1.type_info = IS_OBJECT_EX;
+        assert!(ptr::eq(ex.get_this().unwrap(), &obj));
+        assert!(ex.get_this_zval().unwrap().is_object());
+        assert!(ptr::eq(ex.get_called_scope().unwrap(), &ce));
+    }
+
+    #[test]
+    fn test_function_info() {
+        let mut func_name = fake_string(b"bar", IS_STR_INTERNED, 0);
+        let mut class_name = fake_string(b"Foo", IS_STR_INTERNED, 0);
+
+        let mut ce: ClassEntry = unsafe { mem::zeroed() };
+        ce.name = class_name.as_mut_ptr() as _;
+        let mut obj: ZendObject = unsafe { mem::zeroed() };
+        obj.ce = &mut ce;
+        let mut func: zend_function = unsafe { mem::zeroed() };
+        func.common.function_name = func_name.as_mut_ptr() as _;
+
+        // The frame of the main script.
+        let mut caller: ExecutionData = unsafe { mem::zeroed() };
+        assert_eq!(caller.function_name(), None);
+        assert_eq!(caller.scope_class_name(), None);
+        assert!(!caller.is_static_call());
+        assert!(caller.caller().is_none());
+
+        // A function.
+        let mut ex: ExecutionData = unsafe { mem::zeroed() };
+        ex.func = &mut func;
+        ex.prev_execute_data = &mut caller;
+
+        assert_eq!(ex.function_name().as_deref(), Some("bar"));
+        assert_eq!(ex.scope_class_name(), None);
+        assert!(!ex.is_static_call());
+        assert!(ptr::eq(ex.caller().unwrap(), &caller));
+
+        // A static method.
+        unsafe { (*ex.func).common.scope = &mut ce };
+        ex.This.value.ce = &mut ce;
+        assert_eq!(ex.function_name().as_deref(), Some("bar"));
+        assert_eq!(ex.scope_class_name().as_deref(), Some("Foo"));
+        assert!(ex.is_static_call());
+
+        // A method called on an object.
+        ex.This.value.obj = &mut obj;
+        ex.This.u1.type_info = IS_OBJECT_EX;
+        assert_eq!(ex.scope_class_name().as_deref(), Some("Foo"));
+        assert!(!ex.is_static_call());
+    }
+
+    #[test]
+    fn test_return_value() {
+        let mut ex: ExecutionData = unsafe { mem::zeroed() };
+        let mut retval = Zval::new();
+
+        ex.record_return_value(&mut retval);
+        ex.return_value().set_long(5);
+        assert_eq!(ex.return_value().long(), Some(5));
+        assert_eq!(retval.long(), Some(5));
+    }
+
+    #[test]
+    fn test_uses_strict_types() {
+        let mut ex: ExecutionData = unsafe { mem::zeroed() };
+        assert!(!ex.uses_strict_types());
+
+        // The main script of a file without strict types.
+        let mut main: zend_function = unsafe { mem::zeroed() };
+        let mut caller: ExecutionData = unsafe { mem::zeroed() };
+        caller.func = &mut main;
+        ex.prev_execute_data = &mut caller;
+        assert!(!ex.uses_strict_types());
+
+        unsafe { (*caller.func).common.fn_flags |= ZEND_ACC_STRICT_TYPES };
+        assert!(ex.uses_strict_types());
+    }
 }
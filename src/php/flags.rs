@@ -3,18 +3,26 @@
 use bitflags::bitflags;
 
 use crate::bindings::{
-    ZEND_ACC_ABSTRACT, ZEND_ACC_ANON_CLASS, ZEND_ACC_CALL_VIA_TRAMPOLINE, ZEND_ACC_CHANGED,
-    ZEND_ACC_CLOSURE, ZEND_ACC_CONSTANTS_UPDATED, ZEND_ACC_CTOR, ZEND_ACC_DEPRECATED,
-    ZEND_ACC_DONE_PASS_TWO, ZEND_ACC_EARLY_BINDING, ZEND_ACC_FAKE_CLOSURE, ZEND_ACC_FINAL,
-    ZEND_ACC_GENERATOR, ZEND_ACC_HAS_FINALLY_BLOCK, ZEND_ACC_HAS_RETURN_TYPE,
-    ZEND_ACC_HAS_TYPE_HINTS, ZEND_ACC_HAS_UNLINKED_USES, ZEND_ACC_HEAP_RT_CACHE,
-    ZEND_ACC_IMMUTABLE, ZEND_ACC_IMPLICIT_ABSTRACT_CLASS, ZEND_ACC_INTERFACE, ZEND_ACC_LINKED,
-    ZEND_ACC_NEARLY_LINKED, ZEND_ACC_NEVER_CACHE, ZEND_ACC_NO_DYNAMIC_PROPERTIES,
-    ZEND_ACC_PRELOADED, ZEND_ACC_PRIVATE, ZEND_ACC_PROMOTED, ZEND_ACC_PROPERTY_TYPES_RESOLVED,
-    ZEND_ACC_PROTECTED, ZEND_ACC_PUBLIC, ZEND_ACC_RESOLVED_INTERFACES, ZEND_ACC_RESOLVED_PARENT,
-    ZEND_ACC_RETURN_REFERENCE, ZEND_ACC_REUSE_GET_ITERATOR, ZEND_ACC_STATIC, ZEND_ACC_STRICT_TYPES,
-    ZEND_ACC_TOP_LEVEL, ZEND_ACC_TRAIT, ZEND_ACC_TRAIT_CLONE, ZEND_ACC_UNRESOLVED_VARIANCE,
-    ZEND_ACC_USES_THIS, ZEND_ACC_USE_GUARDS, ZEND_ACC_VARIADIC, ZEND_HAS_STATIC_IN_METHODS,
+    PHP_JSON_BIGINT_AS_STRING, PHP_JSON_FORCE_OBJECT, PHP_JSON_HEX_AMP, PHP_JSON_HEX_APOS,
+    PHP_JSON_HEX_QUOT, PHP_JSON_HEX_TAG, PHP_JSON_INVALID_UTF8_IGNORE,
+    PHP_JSON_INVALID_UTF8_SUBSTITUTE, PHP_JSON_NUMERIC_CHECK, PHP_JSON_OBJECT_AS_ARRAY,
+    PHP_JSON_PARTIAL_OUTPUT_ON_ERROR, PHP_JSON_PRESERVE_ZERO_FRACTION, PHP_JSON_PRETTY_PRINT,
+    PHP_JSON_THROW_ON_ERROR, PHP_JSON_UNESCAPED_LINE_TERMINATORS, PHP_JSON_UNESCAPED_SLASHES,
+    PHP_JSON_UNESCAPED_UNICODE, PHP_SORT_FLAG_CASE, PHP_SORT_LOCALE_STRING, PHP_SORT_NATURAL,
+    PHP_SORT_NUMERIC, PHP_SORT_REGULAR, PHP_SORT_STRING, ZEND_ACC_ABSTRACT, ZEND_ACC_ANON_CLASS,
+    ZEND_ACC_CALL_VIA_TRAMPOLINE, ZEND_ACC_CHANGED, ZEND_ACC_CLOSURE, ZEND_ACC_CONSTANTS_UPDATED,
+    ZEND_ACC_CTOR, ZEND_ACC_DEPRECATED, ZEND_ACC_DONE_PASS_TWO, ZEND_ACC_EARLY_BINDING,
+    ZEND_ACC_FAKE_CLOSURE, ZEND_ACC_FINAL, ZEND_ACC_GENERATOR, ZEND_ACC_HAS_FINALLY_BLOCK,
+    ZEND_ACC_HAS_RETURN_TYPE, ZEND_ACC_HAS_TYPE_HINTS, ZEND_ACC_HAS_UNLINKED_USES,
+    ZEND_ACC_HEAP_RT_CACHE, ZEND_ACC_IMMUTABLE, ZEND_ACC_IMPLICIT_ABSTRACT_CLASS,
+    ZEND_ACC_INTERFACE, ZEND_ACC_LINKED, ZEND_ACC_NEARLY_LINKED, ZEND_ACC_NEVER_CACHE,
+    ZEND_ACC_NO_DYNAMIC_PROPERTIES, ZEND_ACC_PRELOADED, ZEND_ACC_PRIVATE, ZEND_ACC_PROMOTED,
+    ZEND_ACC_PROPERTY_TYPES_RESOLVED, ZEND_ACC_PROTECTED, ZEND_ACC_PUBLIC,
+    ZEND_ACC_RESOLVED_INTERFACES, ZEND_ACC_RESOLVED_PARENT, ZEND_ACC_RETURN_REFERENCE,
+    ZEND_ACC_REUSE_GET_ITERATOR, ZEND_ACC_STATIC, ZEND_ACC_STRICT_TYPES, ZEND_ACC_TOP_LEVEL,
+    ZEND_ACC_TRAIT, ZEND_ACC_TRAIT_CLONE, ZEND_ACC_UNRESOLVED_VARIANCE, ZEND_ACC_USES_THIS,
+    ZEND_ACC_USE_GUARDS, ZEND_ACC_VARIADIC, ZEND_HAS_STATIC_IN_METHODS, ZEND_INI_ALL,
+    ZEND_INI_PERDIR, ZEND_INI_SYSTEM, ZEND_INI_USER,
 };
 
 bitflags! {
@@ -100,5 +108,67 @@ bitflags! {
         const Protected = ZEND_ACC_PROTECTED;
         const Private = ZEND_ACC_PRIVATE;
         const Promoted = ZEND_ACC_PROMOTED;
+        /// Final constants cannot be overridden by child classes. Requires PHP 8.1.
+        const Final = ZEND_ACC_FINAL;
+    }
+}
+
+bitflags! {
+    /// Flags for encoding JSON, equivalent to the `JSON_*` constants accepted by
+    /// `json_encode`.
+    pub struct JsonEncodeFlags: u32 {
+        const HexTag = PHP_JSON_HEX_TAG;
+        const HexAmp = PHP_JSON_HEX_AMP;
+        const HexApos = PHP_JSON_HEX_APOS;
+        const HexQuot = PHP_JSON_HEX_QUOT;
+        const ForceObject = PHP_JSON_FORCE_OBJECT;
+        const NumericCheck = PHP_JSON_NUMERIC_CHECK;
+        const UnescapedSlashes = PHP_JSON_UNESCAPED_SLASHES;
+        const PrettyPrint = PHP_JSON_PRETTY_PRINT;
+        const UnescapedUnicode = PHP_JSON_UNESCAPED_UNICODE;
+        const PartialOutputOnError = PHP_JSON_PARTIAL_OUTPUT_ON_ERROR;
+        const PreserveZeroFraction = PHP_JSON_PRESERVE_ZERO_FRACTION;
+        const UnescapedLineTerminators = PHP_JSON_UNESCAPED_LINE_TERMINATORS;
+        const InvalidUtf8Ignore = PHP_JSON_INVALID_UTF8_IGNORE;
+        const InvalidUtf8Substitute = PHP_JSON_INVALID_UTF8_SUBSTITUTE;
+    }
+}
+
+bitflags! {
+    /// Flags for decoding JSON, equivalent to the `JSON_*` constants accepted by
+    /// `json_decode`.
+    pub struct JsonDecodeFlags: u32 {
+        const ObjectAsArray = PHP_JSON_OBJECT_AS_ARRAY;
+        const BigintAsString = PHP_JSON_BIGINT_AS_STRING;
+        const InvalidUtf8Ignore = PHP_JSON_INVALID_UTF8_IGNORE;
+        const InvalidUtf8Substitute = PHP_JSON_INVALID_UTF8_SUBSTITUTE;
+        const ThrowOnError = PHP_JSON_THROW_ON_ERROR;
+    }
+}
+
+bitflags! {
+    /// Flags for sorting arrays, equivalent to the `SORT_*` constants accepted by `sort`.
+    /// Only one of the sort types may be given, which may be combined with
+    /// [`SortFlags::FlagCase`] for [`SortFlags::String`] and [`SortFlags::Natural`].
+    pub struct SortFlags: u32 {
+        const Regular = PHP_SORT_REGULAR;
+        const Numeric = PHP_SORT_NUMERIC;
+        const String = PHP_SORT_STRING;
+        const LocaleString = PHP_SORT_LOCALE_STRING;
+        const Natural = PHP_SORT_NATURAL;
+        const FlagCase = PHP_SORT_FLAG_CASE;
+    }
+}
+
+bitflags! {
+    /// Flags declaring where the value of an INI entry may be changed, equivalent to the
+    /// `PHP_INI_*` constants. [`IniEntryPermission::User`] allows the value to be changed
+    /// with `ini_set`, [`IniEntryPermission::PerDir`] in `.htaccess` and `.user.ini` files,
+    /// and [`IniEntryPermission::System`] in `php.ini`.
+    pub struct IniEntryPermission: u32 {
+        const User = ZEND_INI_USER;
+        const PerDir = ZEND_INI_PERDIR;
+        const System = ZEND_INI_SYSTEM;
+        const All = ZEND_INI_ALL;
     }
 }
@@ -2,13 +2,17 @@
 
 use std::{mem, os::raw::c_char, ptr};
 
-use crate::{bindings::zend_function_entry, functions::c_str};
+use crate::{bindings::zend_function_entry, errors::Error, functions::c_str};
 
 use super::{
     args::{Arg, ArgInfo},
+    class::ClassEntry,
     enums::DataType,
+    errors,
     execution_data::ExecutionData,
-    types::zval::Zval,
+    flags::MethodFlags,
+    globals::ExecutorGlobals,
+    types::zval::{IntoZval, Zval},
     types::ZendType,
 };
 
@@ -34,12 +38,95 @@ impl FunctionEntry {
 }
 
 /// Function representation in Rust.
+///
+/// `retval` is the engine's return value slot itself, rather than a copy, so values such as
+/// large arrays and strings are built in place without being copied afterwards. The slot is
+/// set to null before the handler is called, so a function which does not write to it returns
+/// null. The engine does not record the slot in the [`ExecutionData`] of calls to internal
+/// functions, so it is recorded with [`ExecutionData::record_return_value`] for code which is
+/// only given the execution data.
 pub type FunctionHandler = extern "C" fn(execute_data: &mut ExecutionData, retval: &mut Zval);
 
+/// Sets the value returned by a function exported with `#[php_function]`. If the value cannot
+/// be converted into a zval, a `ValueError` is thrown for integers and strings which are out
+/// of range, and a `TypeError` otherwise, so that the function does not return null silently.
+///
+/// # Parameters
+///
+/// * `retval` - The return value slot passed to the handler.
+/// * `value` - The value returned by the function.
+#[doc(hidden)]
+pub fn set_return_value<T>(retval: &mut Zval, value: T)
+where
+    T: IntoZval,
+{
+    let e = match value.set_zval(retval) {
+        Ok(()) => return,
+        // The exception thrown by the conversion is already pending.
+        Err(Error::Exception(_)) => return,
+        Err(e) => e,
+    };
+
+    let class = match e {
+        Error::IntegerOutOfRange(..) | Error::StringTooLong(_) => ClassEntry::value_error(),
+        _ => ClassEntry::type_error(),
+    };
+    let name = unsafe { ExecutorGlobals::get().current_execute_data.as_ref() }
+        .and_then(ExecutionData::function_name)
+        .unwrap_or_default();
+
+    if let Some(class) = class {
+        errors::throw(
+            class,
+            &format!("{}(): Return value could not be converted: {}", name, e),
+            0,
+        );
+    }
+}
+
+/// The handler of abstract methods, which is replaced with no handler as the engine never calls
+/// abstract methods.
+extern "C" fn abstract_handler(_: &mut ExecutionData, _: &mut Zval) {}
+
 /// Function representation in Rust using pointers.
 type FunctionPointerHandler = extern "C" fn(execute_data: *mut ExecutionData, retval: *mut Zval);
 
-/// Builds a function to be exported as a PHP function.
+/// Builds a function to be exported as a PHP function. The function is registered by adding it
+/// to the extension with [`ModuleBuilder::function`], which builds the table of functions when
+/// the extension is loaded:
+///
+/// ```no_run
+/// use ext_php_rs::php::{
+///     args::Arg,
+///     enums::DataType,
+///     execution_data::ExecutionData,
+///     function::FunctionBuilder,
+///     module::{ModuleBuilder, ModuleEntry},
+///     types::zval::Zval,
+/// };
+///
+/// /// `str_reverse(string $string): string`
+/// pub extern "C" fn str_reverse(execute_data: &mut ExecutionData, retval: &mut Zval) {
+///     if let Ok((string,)) = execute_data.parse_args().arg::<&str>("string").finish() {
+///         let _ = retval.set_string(string.chars().rev().collect::<String>());
+///     }
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn get_module() -> *mut ModuleEntry {
+///     let reverse = FunctionBuilder::new("str_reverse", str_reverse)
+///         .arg(Arg::new("string", DataType::String))
+///         .returns(DataType::String, false, false)
+///         .build();
+///
+///     ModuleBuilder::new("ext-name", "0.1.0")
+///         .function(reverse)
+///         .build()
+///         .into_raw()
+/// }
+/// ```
+///
+/// [`ModuleBuilder::function`]: crate::php::module::ModuleBuilder::function
 pub struct FunctionBuilder<'a> {
     function: FunctionEntry,
     args: Vec<Arg<'a>>,
@@ -79,6 +166,22 @@ impl<'a> FunctionBuilder<'a> {
         }
     }
 
+    /// Creates a builder for an abstract method, which has no handler. The method must be added
+    /// to a class with [`MethodFlags::Abstract`], which makes the class abstract, and is
+    /// implemented by the classes extending it.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the method.
+    pub fn new_abstract<N>(name: N) -> Self
+    where
+        N: AsRef<str>,
+    {
+        let mut builder = Self::new(name, abstract_handler);
+        builder.function.handler = None;
+        builder
+    }
+
     /// Creates a constructor builder, used to build the constructor
     /// for classes.
     ///
@@ -119,6 +222,29 @@ impl<'a> FunctionBuilder<'a> {
         self
     }
 
+    /// Sets the return type of the function to the type a Rust value is converted into, see
+    /// [`IntoZval::DATA_TYPE`]. An [`Option`] declares a nullable return type.
+    pub fn returns_type<T>(self) -> Self
+    where
+        T: IntoZval,
+    {
+        // `mixed` already includes `null`, and `void` cannot be declared nullable.
+        let allow_null = match T::DATA_TYPE {
+            DataType::Mixed | DataType::Void => false,
+            _ => T::NULLABLE,
+        };
+
+        self.returns(T::DATA_TYPE, false, allow_null)
+    }
+
+    /// Marks the function as deprecated, as with `ZEND_ACC_DEPRECATED` in PHP. The engine emits
+    /// `E_DEPRECATED` with `Function name() is deprecated` each time the function is called,
+    /// and reflection reports the function as deprecated.
+    pub fn deprecated(mut self) -> Self {
+        self.function.flags |= MethodFlags::Deprecated.bits();
+        self
+    }
+
     /// Builds the function converting it into a Zend function entry.
     pub fn build(mut self) -> FunctionEntry {
         let mut args = Vec::with_capacity(self.args.len() + 1);
@@ -127,7 +253,7 @@ impl<'a> FunctionBuilder<'a> {
         args.push(ArgInfo {
             name: ((match self.n_req {
                 Some(req) => req,
-                None => self.args.len(),
+                None => self.args.iter().filter(|arg| !arg.variadic).count(),
             }) as libc::uintptr_t) as *const i8,
             type_: match self.retval {
                 Some(retval) => {
@@ -142,7 +268,12 @@ impl<'a> FunctionBuilder<'a> {
         for arg in self.args.iter() {
             args.push(ArgInfo {
                 name: c_str(arg.name.clone()),
-                type_: ZendType::empty_from_type(arg._type, arg.as_ref, false, arg.allow_null),
+                type_: ZendType::empty_from_type(
+                    arg._type,
+                    arg.as_ref,
+                    arg.variadic,
+                    arg.allow_null,
+                ),
                 default_value: match &arg.default_value {
                     Some(val) => c_str(val),
                     None => ptr::null(),
@@ -0,0 +1,257 @@
+//! Types related to the global state of the PHP executor and of extensions.
+
+use std::{
+    cell::UnsafeCell,
+    ffi::c_void,
+    mem::MaybeUninit,
+    os::raw::c_int,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::bindings::{
+    ext_php_rs_executor_globals, ext_php_rs_tsrm_globals, zend_clear_exception,
+    zend_executor_globals, USING_ZTS,
+};
+
+use super::types::object::ZendObject;
+
+/// Stores global variables used in the PHP executor.
+pub type ExecutorGlobals = zend_executor_globals;
+
+impl ExecutorGlobals {
+    /// Returns a reference to the executor globals of the current thread.
+    pub fn get() -> &'static Self {
+        // SAFETY: The executor globals are allocated for the lifetime of the thread, and are
+        // only accessed from the thread they belong to.
+        unsafe { ext_php_rs_executor_globals().as_ref() }
+            .expect("Executor globals were not initialized")
+    }
+
+    /// Returns the exception which has been thrown and not yet handled, if any.
+    pub fn exception(&self) -> Option<&ZendObject> {
+        unsafe { self.exception.as_ref() }
+    }
+
+    /// Clears the pending exception, if any, releasing the exception object.
+    pub fn clear_exception() {
+        unsafe { zend_clear_exception() };
+    }
+}
+
+/// A function called with the globals of an extension when they are initialized or shut down
+/// for a thread, see [`ModuleBuilder::globals_with_hooks`].
+///
+/// [`ModuleBuilder::globals_with_hooks`]: crate::php::module::ModuleBuilder::globals_with_hooks
+pub type GlobalsFunc<T> = fn(globals: &mut T);
+
+/// The globals of an extension, equivalent to `ZEND_DECLARE_MODULE_GLOBALS` in PHP. Declared as
+/// a static and added to the extension with [`ModuleBuilder::globals`]:
+///
+/// ```no_run
+/// use ext_php_rs::php::{globals::ModuleGlobals, module::ModuleBuilder};
+///
+/// #[derive(Default)]
+/// struct Globals {
+///     calls: i64,
+/// }
+///
+/// static GLOBALS: ModuleGlobals<Globals> = ModuleGlobals::new();
+///
+/// fn count_call() -> i64 {
+///     let globals = unsafe { GLOBALS.get_mut() };
+///     globals.calls += 1;
+///     globals.calls
+/// }
+///
+/// let module = ModuleBuilder::new("ext-name", "0.1.0").globals(&GLOBALS);
+/// ```
+///
+/// When PHP is built with thread safety (ZTS), as used by threaded web servers, each thread has
+/// its own copy of the globals, allocated by the engine and found through the thread-local
+/// storage of PHP. Otherwise, the globals are stored in the static itself. The globals are
+/// initialized with [`Default`] when the extension is loaded, or when a thread starts, and
+/// dropped when the extension is unloaded, or when a thread ends. The same code works with
+/// both builds of PHP. As the static is shared between threads, `T` must be [`Sync`].
+pub struct ModuleGlobals<T> {
+    /// The ID of the globals in the thread-local storage of PHP, set by the engine when PHP is
+    /// built with thread safety.
+    id: UnsafeCell<c_int>,
+    /// The globals, used when PHP is built without thread safety.
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// Whether `value` has been initialized by the engine.
+    initialized: AtomicBool,
+}
+
+// SAFETY: Each thread has its own globals when PHP is built with thread safety, otherwise PHP
+// only runs on a single thread. The globals are only shared between threads as `&T`.
+unsafe impl<T: Sync> Sync for ModuleGlobals<T> {}
+
+impl<T> ModuleGlobals<T> {
+    /// Creates the globals of an extension, which are initialized when the extension is loaded.
+    pub const fn new() -> Self {
+        Self {
+            id: UnsafeCell::new(0),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            initialized: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns a reference to the globals of the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the globals have not been initialized, as they have not been added to the
+    /// extension with [`ModuleBuilder::globals`], the extension has not been loaded or the
+    /// globals have already been shut down.
+    ///
+    /// [`ModuleBuilder::globals`]: crate::php::module::ModuleBuilder::globals
+    pub fn get(&self) -> &T {
+        unsafe { &*self.as_ptr() }
+    }
+
+    /// Returns a mutable reference to the globals of the current thread.
+    ///
+    /// # Safety
+    ///
+    /// No other reference to the globals of the current thread, returned by
+    /// [`ModuleGlobals::get`] or [`ModuleGlobals::get_mut`], may be in use while the returned
+    /// reference is in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the globals have not been initialized, as with [`ModuleGlobals::get`].
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn get_mut(&self) -> &mut T {
+        &mut *self.as_ptr()
+    }
+
+    /// Returns a pointer to the initialized globals of the current thread.
+    fn as_ptr(&self) -> *mut T {
+        // Checked with a constant rather than `cfg`, so that both paths are compiled whichever
+        // build of PHP the bindings were generated from.
+        if USING_ZTS != 0 {
+            // The ID is set when the globals are registered, at which point the engine
+            // initializes them for each thread.
+            let id = unsafe { *self.id.get() };
+            assert!(id != 0, "Module globals were not initialized");
+
+            let globals = unsafe { ext_php_rs_tsrm_globals(id) } as *mut T;
+            assert!(!globals.is_null(), "Module globals were not initialized");
+            globals
+        } else {
+            assert!(
+                self.initialized.load(Ordering::Acquire),
+                "Module globals were not initialized"
+            );
+            self.value.get() as *mut T
+        }
+    }
+
+    /// Returns the pointers given to the engine, to the ID of the globals when PHP is built
+    /// with thread safety and to the globals otherwise.
+    pub(crate) fn storage(&self) -> (*mut c_int, *mut c_void) {
+        (self.id.get(), self.value.get() as *mut c_void)
+    }
+
+    /// Initializes the globals allocated by the engine, then calls the given function with
+    /// them.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to uninitialized memory which is valid for `T`, either the
+    /// storage of these globals or the copy for a thread allocated by the engine.
+    pub(crate) unsafe fn init(&self, globals: *mut c_void, init: Option<GlobalsFunc<T>>)
+    where
+        T: Default,
+    {
+        let globals = globals as *mut T;
+        globals.write(T::default());
+
+        if let Some(init) = init {
+            init(&mut *globals);
+        }
+
+        if globals == self.value.get() as *mut T {
+            self.initialized.store(true, Ordering::Release);
+        }
+    }
+
+    /// Calls the given function with the globals, then drops them.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to globals initialized with [`ModuleGlobals::init`], which are
+    /// not used afterwards.
+    pub(crate) unsafe fn shutdown(&self, globals: *mut c_void, shutdown: Option<GlobalsFunc<T>>) {
+        let globals = globals as *mut T;
+
+        if globals == self.value.get() as *mut T {
+            self.initialized.store(false, Ordering::Release);
+        }
+
+        if let Some(shutdown) = shutdown {
+            shutdown(&mut *globals);
+        }
+
+        ptr::drop_in_place(globals);
+    }
+}
+
+impl<T> Default for ModuleGlobals<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    struct Counter {
+        calls: i64,
+        name: String,
+    }
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    static GLOBALS: ModuleGlobals<Counter> = ModuleGlobals::new();
+
+    fn init(globals: &mut Counter) {
+        globals.name.push_str("counter");
+    }
+
+    fn shutdown(globals: &mut Counter) {
+        assert_eq!(globals.calls, 2);
+    }
+
+    #[test]
+    fn test_module_globals() {
+        let (_, storage) = GLOBALS.storage();
+
+        unsafe { GLOBALS.init(storage, Some(init)) };
+        assert_eq!(GLOBALS.get().calls, 0);
+        assert_eq!(GLOBALS.get().name, "counter");
+
+        unsafe { GLOBALS.get_mut() }.calls += 2;
+        assert_eq!(GLOBALS.get().calls, 2);
+
+        unsafe { GLOBALS.shutdown(storage, Some(shutdown)) };
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "not initialized")]
+    fn test_module_globals_not_initialized() {
+        static GLOBALS: ModuleGlobals<Counter> = ModuleGlobals::new();
+        GLOBALS.get();
+    }
+}
@@ -0,0 +1,196 @@
+//! Prints the information about an extension shown by `phpinfo()` and `php -i`, from the
+//! function given to [`ModuleBuilder::on_info`].
+//!
+//! The information is printed as HTML by web SAPIs and as plain text on the command line.
+//! Values are escaped when printed as HTML, so they may contain any text.
+//!
+//! [`ModuleBuilder::on_info`]: crate::php::module::ModuleBuilder::on_info
+
+use std::{borrow::Cow, ffi::CString, fmt::Display, marker::PhantomData, os::raw::c_char};
+
+use crate::bindings::{
+    ext_php_rs_php_info_print, ext_php_rs_phpinfo_as_text, php_info_print_box_end,
+    php_info_print_box_start, php_info_print_table_end, php_info_print_table_header,
+    php_info_print_table_row, php_info_print_table_start,
+};
+
+use super::module::ModuleEntry;
+
+/// Prints the information about an extension. Passed to the function given to
+/// [`ModuleBuilder::on_info`]:
+///
+/// ```no_run
+/// use ext_php_rs::php::info::ModuleInfo;
+///
+/// fn info(info: &mut ModuleInfo) {
+///     info.table()
+///         .header(&["ext-name support", "enabled"])
+///         .row("ext-name version", "0.1.0")
+///         .row("feature X", "enabled")
+///         .end();
+/// }
+/// ```
+///
+/// [`ModuleBuilder::on_info`]: crate::php::module::ModuleBuilder::on_info
+pub struct ModuleInfo<'a> {
+    module: &'a ModuleEntry,
+    as_text: bool,
+}
+
+impl<'a> ModuleInfo<'a> {
+    /// Creates the printer for the information about the given extension.
+    pub(crate) fn new(module: &'a ModuleEntry) -> Self {
+        Self {
+            module,
+            as_text: unsafe { ext_php_rs_phpinfo_as_text() },
+        }
+    }
+
+    /// Returns the module entry of the extension.
+    pub fn module(&self) -> &ModuleEntry {
+        self.module
+    }
+
+    /// Returns whether the information is printed as plain text, as by `php -i`, rather than
+    /// as HTML.
+    pub fn as_text(&self) -> bool {
+        self.as_text
+    }
+
+    /// Starts a table, which is ended when [`InfoTable::end`] is called or the table is
+    /// dropped.
+    pub fn table(&mut self) -> InfoTable<'_> {
+        unsafe { php_info_print_table_start() };
+
+        InfoTable {
+            as_text: self.as_text,
+            _info: PhantomData,
+        }
+    }
+
+    /// Prints text in a box styled as a header, as used for the banner at the top of
+    /// `phpinfo()`.
+    ///
+    /// # Parameters
+    ///
+    /// * `text` - The text to print, which is escaped when printed as HTML.
+    pub fn print_box(&mut self, text: &str) {
+        let text = escape(text, self.as_text);
+
+        unsafe {
+            php_info_print_box_start(1);
+            ext_php_rs_php_info_print(text.as_ptr() as *const c_char, text.len() as _);
+            php_info_print_box_end();
+        }
+    }
+}
+
+/// A table of the information about an extension, started with [`ModuleInfo::table`].
+pub struct InfoTable<'a> {
+    as_text: bool,
+    _info: PhantomData<&'a mut ModuleInfo<'a>>,
+}
+
+impl InfoTable<'_> {
+    /// Prints a header row, with a cell for each of the given values.
+    ///
+    /// # Parameters
+    ///
+    /// * `cells` - The values of up to three cells, which are escaped when printed as HTML.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if more than three cells are given.
+    pub fn header(self, cells: &[&str]) -> Self {
+        debug_assert!(cells.len() <= 3, "table headers have up to three cells");
+
+        // Unlike other rows, headers are not escaped by PHP.
+        let cells = cells
+            .iter()
+            .map(|cell| to_c_string(&escape(cell, self.as_text)))
+            .collect::<Vec<_>>();
+
+        match cells.as_slice() {
+            [] => {}
+            [a] => unsafe { php_info_print_table_header(1, a.as_ptr()) },
+            [a, b] => unsafe { php_info_print_table_header(2, a.as_ptr(), b.as_ptr()) },
+            [a, b, c, ..] => unsafe {
+                php_info_print_table_header(3, a.as_ptr(), b.as_ptr(), c.as_ptr())
+            },
+        }
+
+        self
+    }
+
+    /// Prints a row with the name of a setting or feature and its value, e.g.
+    /// `row("feature X", "enabled")`, printed as `feature X => enabled` on the command line.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name shown in the first column.
+    /// * `value` - The value shown in the second column.
+    pub fn row<V>(self, name: &str, value: V) -> Self
+    where
+        V: Display,
+    {
+        let name = to_c_string(name);
+        let value = to_c_string(&value.to_string());
+
+        // Rows are escaped by PHP when printed as HTML.
+        unsafe { php_info_print_table_row(2, name.as_ptr(), value.as_ptr()) };
+        self
+    }
+
+    /// Ends the table.
+    pub fn end(self) {}
+}
+
+impl Drop for InfoTable<'_> {
+    fn drop(&mut self) {
+        unsafe { php_info_print_table_end() };
+    }
+}
+
+/// Converts text into a C string, cutting it off at the first NUL byte.
+fn to_c_string(text: &str) -> CString {
+    let end = text.find('\0').unwrap_or(text.len());
+    CString::new(&text[..end]).unwrap_or_default()
+}
+
+/// Escapes the HTML special characters in text, unless it is printed as plain text, in the
+/// same way as `htmlspecialchars` with `ENT_QUOTES`.
+fn escape(text: &str, as_text: bool) -> Cow<'_, str> {
+    if as_text || !text.contains(&['&', '<', '>', '"', '\''][..]) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#039;"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("enabled", false), "enabled");
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>", false),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#039;s&lt;/a&gt;"
+        );
+        assert_eq!(escape("<b>", true), "<b>");
+        assert_eq!(to_c_string("a\0b").as_bytes(), b"a");
+    }
+}
@@ -0,0 +1,296 @@
+//! INI entries, used to configure extensions in `php.ini` or with `-d` on the command line,
+//! e.g. `ext_name.cache_size = 128M`.
+//!
+//! Entries are declared with [`IniBuilder`] and added to the extension with
+//! [`ModuleBuilder::ini_entry`], which registers them when the extension starts up and
+//! unregisters them when it shuts down. Their values are read with the typed getters,
+//! [`ini_get_str`], [`ini_get_long`] and [`ini_get_bool`].
+//!
+//! [`ModuleBuilder::ini_entry`]: crate::php::module::ModuleBuilder::ini_entry
+
+use std::{ffi::CStr, os::raw::c_char, ptr};
+
+use crate::{
+    bindings::{zend_ini_entry_def, zend_ini_string_ex},
+    functions::c_str,
+};
+
+use super::{flags::IniEntryPermission, types::long::ZendLong};
+
+/// The definition of an INI entry, registered by the engine. Alias.
+pub type IniEntryDef = zend_ini_entry_def;
+
+impl IniEntryDef {
+    /// Returns an empty definition, signifying the end of a list of definitions.
+    pub fn end() -> Self {
+        Self {
+            name: ptr::null(),
+            on_modify: None,
+            mh_arg1: ptr::null_mut(),
+            mh_arg2: ptr::null_mut(),
+            mh_arg3: ptr::null_mut(),
+            value: ptr::null(),
+            displayer: None,
+            value_length: 0,
+            name_length: 0,
+            modifiable: 0,
+        }
+    }
+}
+
+/// Builds the definition of an INI entry:
+///
+/// ```no_run
+/// use ext_php_rs::php::{flags::IniEntryPermission, ini::IniBuilder, module::ModuleBuilder};
+///
+/// let cache_size = IniBuilder::new("ext_name.cache_size")
+///     .default("64M")
+///     .changeable(IniEntryPermission::System)
+///     .build();
+///
+/// let module = ModuleBuilder::new("ext-name", "0.1.0").ini_entry(cache_size);
+/// ```
+pub struct IniBuilder {
+    name: String,
+    default: String,
+    permission: IniEntryPermission,
+}
+
+impl IniBuilder {
+    /// Creates a new INI entry builder. The entry defaults to an empty string, and may be
+    /// changed anywhere.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the entry, which by convention is prefixed with the name of the
+    ///   extension, e.g. `ext_name.cache_size`.
+    pub fn new<N>(name: N) -> Self
+    where
+        N: AsRef<str>,
+    {
+        Self {
+            name: name.as_ref().to_string(),
+            default: String::new(),
+            permission: IniEntryPermission::All,
+        }
+    }
+
+    /// Sets the value of the entry when it is not set in `php.ini` or on the command line.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The default value, as it would be written in `php.ini`.
+    pub fn default<V>(mut self, value: V) -> Self
+    where
+        V: AsRef<str>,
+    {
+        self.default = value.as_ref().to_string();
+        self
+    }
+
+    /// Sets where the value of the entry may be changed. Entries which may not be changed
+    /// by [`IniEntryPermission::User`] cannot be changed with `ini_set`.
+    ///
+    /// # Parameters
+    ///
+    /// * `permission` - Where the value of the entry may be changed.
+    pub fn changeable(mut self, permission: IniEntryPermission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    /// Builds the definition of the entry.
+    pub fn build(self) -> IniEntryDef {
+        IniEntryDef {
+            name: c_str(&self.name),
+            on_modify: None,
+            mh_arg1: ptr::null_mut(),
+            mh_arg2: ptr::null_mut(),
+            mh_arg3: ptr::null_mut(),
+            value: c_str(&self.default),
+            displayer: None,
+            value_length: self.default.len() as u32,
+            name_length: self.name.len() as u16,
+            modifiable: self.permission.bits() as u8,
+        }
+    }
+}
+
+/// Returns the current value of an INI entry, equivalent to `ini_get`.
+///
+/// # Parameters
+///
+/// * `name` - The name of the entry.
+///
+/// # Returns
+///
+/// * `Some(String)` - The value of the entry. Bytes which are not valid UTF-8 are replaced.
+/// * `None` - There is no entry with the given name.
+pub fn ini_get_str(name: &str) -> Option<String> {
+    let mut exists = false;
+    let value = unsafe {
+        zend_ini_string_ex(
+            name.as_ptr() as *const c_char,
+            name.len() as _,
+            0,
+            &mut exists,
+        )
+    };
+
+    if !exists {
+        return None;
+    }
+
+    if value.is_null() {
+        return Some(String::new());
+    }
+
+    Some(
+        unsafe { CStr::from_ptr(value) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Returns the current value of an INI entry as an integer, which may be given as a quantity
+/// of bytes with the shorthand used in `php.ini`, e.g. `128M`. See [`parse_quantity`].
+///
+/// # Parameters
+///
+/// * `name` - The name of the entry.
+///
+/// # Returns
+///
+/// * `Some(ZendLong)` - The value of the entry.
+/// * `None` - There is no entry with the given name.
+pub fn ini_get_long(name: &str) -> Option<ZendLong> {
+    ini_get_str(name).map(|value| parse_quantity(&value))
+}
+
+/// Returns the current value of an INI entry as a boolean, following the same rules as the
+/// engine. See [`parse_bool`].
+///
+/// # Parameters
+///
+/// * `name` - The name of the entry.
+///
+/// # Returns
+///
+/// * `Some(bool)` - The value of the entry.
+/// * `None` - There is no entry with the given name.
+pub fn ini_get_bool(name: &str) -> Option<bool> {
+    ini_get_str(name).map(|value| parse_bool(&value))
+}
+
+/// Parses the value of an INI entry as an integer in the same way as the engine, which
+/// allows a quantity of bytes to be given with the suffix `K`, `M` or `G`, e.g. `128M`. The
+/// number may be given in hexadecimal with the prefix `0x`, or in octal with a leading `0`.
+/// Parsing stops at the first invalid character, so a value which is not a number is `0`.
+///
+/// # Parameters
+///
+/// * `value` - The value to parse.
+pub fn parse_quantity(value: &str) -> ZendLong {
+    let num = strtol(value, 0);
+
+    let multiplier = match value.bytes().last() {
+        Some(b'g') | Some(b'G') => 1 << 30,
+        Some(b'm') | Some(b'M') => 1 << 20,
+        Some(b'k') | Some(b'K') => 1 << 10,
+        _ => 1,
+    };
+
+    num.wrapping_mul(multiplier)
+}
+
+/// Parses the value of an INI entry as a boolean in the same way as the engine. The values
+/// `true`, `yes` and `on` are true, ignoring case, and any other value is true if it is a
+/// non-zero number.
+///
+/// # Parameters
+///
+/// * `value` - The value to parse.
+pub fn parse_bool(value: &str) -> bool {
+    ["true", "yes", "on"]
+        .iter()
+        .any(|word| value.eq_ignore_ascii_case(word))
+        || strtol(value, 10) != 0
+}
+
+/// Parses the number at the start of a string as `strtol` does, clamping numbers which are
+/// out of range. A base of 0 detects hexadecimal and octal numbers from their prefix.
+fn strtol(value: &str, base: u32) -> ZendLong {
+    let value = value.trim_start();
+    let (negative, value) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+
+    let (radix, digits) = if base != 0 {
+        (base, value)
+    } else if value.len() > 2
+        && (value.starts_with("0x") || value.starts_with("0X"))
+        && value.as_bytes()[2].is_ascii_hexdigit()
+    {
+        (16, &value[2..])
+    } else if value.starts_with('0') {
+        (8, value)
+    } else {
+        (10, value)
+    };
+
+    let mut num: ZendLong = 0;
+    for c in digits.chars() {
+        let digit = match c.to_digit(radix) {
+            Some(digit) => digit,
+            None => break,
+        };
+
+        num = match num
+            .checked_mul(radix as ZendLong)
+            .and_then(|num| num.checked_add(digit as ZendLong))
+        {
+            Some(num) => num,
+            None if negative => return ZendLong::MIN,
+            None => return ZendLong::MAX,
+        };
+    }
+
+    if negative {
+        -num
+    } else {
+        num
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("64"), 64);
+        assert_eq!(parse_quantity("128M"), 128 << 20);
+        assert_eq!(parse_quantity(" 2k"), 2048);
+        assert_eq!(parse_quantity("1G"), 1 << 30);
+        assert_eq!(parse_quantity("-1"), -1);
+        assert_eq!(parse_quantity("0x10"), 16);
+        assert_eq!(parse_quantity("010"), 8);
+        assert_eq!(parse_quantity("12abc"), 12);
+        assert_eq!(parse_quantity("abc"), 0);
+        assert_eq!(parse_quantity(""), 0);
+        assert_eq!(parse_quantity("99999999999999999999"), ZendLong::MAX);
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        for value in &["1", "On", "yes", "TRUE", "2"] {
+            assert!(parse_bool(value), "{}", value);
+        }
+
+        for value in &["0", "Off", "no", "false", "", "none", "0x1"] {
+            assert!(!parse_bool(value), "{}", value);
+        }
+    }
+}
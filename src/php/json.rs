@@ -0,0 +1,142 @@
+//! Encodes and decodes JSON through the `json` extension, with the same behaviour as the
+//! `json_encode` and `json_decode` functions. The extension is always enabled as of PHP 8.0.
+
+use std::os::raw::c_char;
+
+use crate::{
+    bindings::{ext_php_rs_json_decode, ext_php_rs_json_encode, PHP_JSON_PARSER_DEFAULT_DEPTH},
+    errors::{Error, Result},
+};
+
+use super::{
+    errors::ExceptionMark,
+    flags::{JsonDecodeFlags, JsonEncodeFlags},
+    types::{string::ZendString, zval::Zval},
+};
+
+/// The error code returned when the maximum depth is exceeded, `JSON_ERROR_DEPTH`.
+const ERROR_DEPTH: u32 = 1;
+
+/// Returns the message for a JSON error code, as returned by `json_last_error_msg`.
+pub(crate) fn error_message(code: u32) -> &'static str {
+    match code {
+        0 => "No error",
+        1 => "Maximum stack depth exceeded",
+        2 => "State mismatch (invalid or malformed JSON)",
+        3 => "Control character error, possibly incorrectly encoded",
+        4 => "Syntax error",
+        5 => "Malformed UTF-8 characters, possibly incorrectly encoded",
+        6 => "Recursion detected",
+        7 => "Inf and NaN cannot be JSON encoded",
+        8 => "Type is not supported",
+        9 => "The decoded property name is invalid",
+        10 => "Single unpaired UTF-16 surrogate in unicode escape",
+        _ => "Unknown error",
+    }
+}
+
+impl Zval {
+    /// Encodes the zval as JSON, equivalent to `json_encode` with the default depth of 512.
+    ///
+    /// # Parameters
+    ///
+    /// * `flags` - Flags changing how the value is encoded.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The encoded JSON. If [`JsonEncodeFlags::PartialOutputOnError`] is
+    ///   given, values which could not be encoded are replaced rather than returning an error.
+    /// * `Err(Error::Json)` - The value could not be encoded, e.g. it contained `NAN` or a
+    ///   resource.
+    /// * `Err(Error::Exception)` - An exception was thrown while encoding, e.g. by the
+    ///   `jsonSerialize` method of an object. The exception is left pending.
+    pub fn to_json(&self, flags: JsonEncodeFlags) -> Result<String> {
+        let ptr: *const Zval = self;
+        let mut code = 0;
+        let mark = ExceptionMark::new();
+
+        let zs = unsafe {
+            ZendString::from_raw(ext_php_rs_json_encode(
+                ptr as *mut Zval,
+                flags.bits() as _,
+                PHP_JSON_PARSER_DEFAULT_DEPTH as _,
+                &mut code,
+            ))
+        }
+        .ok_or(Error::InvalidPointer)?;
+        let json = zs
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or(Error::ZvalConversion);
+        drop(zs);
+
+        if let Some(e) = mark.thrown() {
+            return Err(Error::Exception(e));
+        }
+
+        if code != 0 && !flags.contains(JsonEncodeFlags::PartialOutputOnError) {
+            return Err(Error::Json(code as u32));
+        }
+
+        json
+    }
+
+    /// Decodes a JSON string into a zval, equivalent to `json_decode`. Objects are decoded
+    /// as instances of `stdClass`, unless [`JsonDecodeFlags::ObjectAsArray`] is given. The
+    /// returned zval must be handed over to PHP or released with [`Zval::release`].
+    ///
+    /// # Parameters
+    ///
+    /// * `json` - The JSON to decode.
+    /// * `flags` - Flags changing how the JSON is decoded.
+    /// * `depth` - The maximum nesting depth of the JSON, which must be greater than zero.
+    ///   `json_decode` uses a depth of 512 by default.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The decoded value.
+    /// * `Err(Error::Json)` - The JSON was invalid or nested deeper than `depth`.
+    /// * `Err(Error::Exception)` - The JSON was invalid and [`JsonDecodeFlags::ThrowOnError`]
+    ///   was given. The `JsonException` is left pending.
+    pub fn from_json(json: &str, flags: JsonDecodeFlags, depth: u32) -> Result<Zval> {
+        if depth == 0 {
+            return Err(Error::Json(ERROR_DEPTH));
+        }
+
+        let mut rv = Zval::new();
+        let mark = ExceptionMark::new();
+
+        let code = unsafe {
+            ext_php_rs_json_decode(
+                &mut rv,
+                json.as_ptr() as *const c_char,
+                json.len() as _,
+                flags.bits() as _,
+                depth as _,
+            )
+        };
+
+        if let Some(e) = mark.thrown() {
+            rv.release();
+            return Err(Error::Exception(e));
+        }
+
+        if code != 0 {
+            rv.release();
+            Err(Error::Json(code as u32))
+        } else {
+            Ok(rv)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(Error::Json(4).to_string(), "Syntax error");
+        assert_eq!(Error::Json(99).to_string(), "Unknown error");
+    }
+}
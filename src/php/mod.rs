@@ -2,10 +2,18 @@
 
 pub mod args;
 pub mod class;
+pub mod constants;
 pub mod enums;
 pub mod errors;
 pub mod execution_data;
 pub mod flags;
 pub mod function;
+pub mod globals;
+pub mod info;
+pub mod ini;
+pub mod json;
 pub mod module;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod serialize;
 pub mod types;
@@ -1,47 +1,131 @@
 //! Builder and objects for creating modules in PHP. A module is the base of a PHP extension.
 
-use std::{ffi::c_void, mem, ptr};
+use std::{
+    ffi::{c_void, CStr, CString},
+    mem,
+    os::raw::c_char,
+    ptr,
+};
 
 use crate::{
     bindings::{
-        ext_php_rs_php_build_id, zend_module_entry, USING_ZTS, ZEND_DEBUG, ZEND_MODULE_API_NO,
+        ext_php_rs_module_set_globals, ext_php_rs_php_build_id, zend_error, zend_module_dep,
+        zend_module_entry, zend_register_ini_entries, zend_unregister_ini_entries, E_CORE_WARNING,
+        MODULE_DEP_CONFLICTS, MODULE_DEP_OPTIONAL, MODULE_DEP_REQUIRED, USING_ZTS, ZEND_DEBUG,
+        ZEND_MODULE_API_NO,
     },
+    errors::Result,
     functions::c_str,
 };
 
-use super::function::FunctionEntry;
+use super::{
+    constants::IntoConst,
+    flags::MethodFlags,
+    function::FunctionEntry,
+    globals::{GlobalsFunc, ModuleGlobals},
+    info::ModuleInfo,
+    ini::IniEntryDef,
+};
 
 /// A Zend module entry. Alias.
 pub type ModuleEntry = zend_module_entry;
+/// A dependency of a Zend module on another module. Alias.
+pub type ModuleDep = zend_module_dep;
 /// A function to be called when the extension is starting up or shutting down.
 pub type StartupShutdownFunc = extern "C" fn(_type: i32, _module_number: i32) -> i32;
 /// A function to be called when `phpinfo();` is called.
 pub type InfoFunc = extern "C" fn(zend_module: *mut ModuleEntry);
+/// A Rust function to be called when the extension is starting up or shutting down, or when a
+/// request starts or ends. Receives the module number of the extension. Returning an error
+/// fails the startup or shutdown, see [`ModuleBuilder::on_startup`].
+pub type LifecycleFunc = fn(module_number: i32) -> Result<()>;
+/// A Rust function to be called when `phpinfo();` is called, see [`ModuleBuilder::on_info`].
+pub type InfoCallback = fn(info: &mut ModuleInfo);
+/// A Rust function registering a class when the extension starts up, see
+/// [`ModuleBuilder::class`].
+pub type ClassRegistration = fn() -> Result<()>;
+
+/// The value returned to the engine by a startup or shutdown function which succeeded.
+const SUCCESS: i32 = 0;
+/// The value returned to the engine by a startup or shutdown function which failed.
+const FAILURE: i32 = -1;
 
 /// Builds a Zend extension. Must be called from within an external function called `get_module`,
-/// returning a mutable pointer to a `ModuleEntry`.
+/// returning a mutable pointer to a `ModuleEntry`, which is generated by the [`php_module`]
+/// attribute.
 ///
-/// ```
-/// #[no_mangle]
-/// pub extern "C" fn php_module_info(_module: *mut ModuleEntry) {
-///     print_table_start();
-///     print_table_row("column 1", "column 2");
-///     print_table_end();
+/// Besides functions, the extension may register constants, INI entries and classes, which
+/// are registered when the extension starts up. Rust functions can be called when the
+/// extension starts up or shuts down and when each request starts or ends, failing the startup
+/// or shutdown if they return an error:
+///
+/// ```no_run
+/// use ext_php_rs::{
+///     errors::Result,
+///     php::{ini::IniBuilder, module::ModuleBuilder},
+///     php_module,
+/// };
+///
+/// fn startup(_module_number: i32) -> Result<()> {
+///     // Allocate the resources used by the extension...
+///     Ok(())
 /// }
 ///
-/// #[no_mangle]
-/// pub extern "C" fn get_module() -> *mut ModuleEntry {
-///     ModuleBuilder::new("ext-name", "ext-version")
-///         .info_function(php_module_info)
-///         .build()
-///         .into_raw()
+/// #[php_module]
+/// pub fn get_module() -> ModuleBuilder {
+///     ModuleBuilder::new("ext-name", "0.1.0")
+///         .constant("EXT_NAME_VERSION", "0.1.0")
+///         .ini_entry(IniBuilder::new("ext_name.enabled").default("1").build())
+///         .on_startup(startup)
 /// }
 /// ```
+///
+/// Functions and constants are registered in the global namespace, unless their names are
+/// qualified, e.g. `Acme\Crypto\hash`, or they are added within
+/// [`ModuleBuilder::namespace`].
+///
+/// [`php_module`]: macro@crate::php_module
 pub struct ModuleBuilder {
     module: ModuleEntry,
     functions: Vec<FunctionEntry>,
+    deps: Vec<ModuleDep>,
+    aliases: Vec<Alias>,
+    namespace: String,
+    hooks: Hooks,
 }
 
+/// An alias of a function, added when the module is built.
+struct Alias {
+    name: String,
+    function: String,
+    deprecated: bool,
+}
+
+/// The Rust functions called by the engine through the module entry, and the items registered
+/// when the extension starts up. An extension has a single module, so these are stored
+/// globally once the module is built.
+#[derive(Default)]
+struct Hooks {
+    name: String,
+    startup: Option<LifecycleFunc>,
+    shutdown: Option<LifecycleFunc>,
+    request_startup: Option<LifecycleFunc>,
+    request_shutdown: Option<LifecycleFunc>,
+    info: Option<InfoCallback>,
+    raw_startup: Option<RawStartupShutdownFunc>,
+    raw_shutdown: Option<RawStartupShutdownFunc>,
+    constants: Vec<(String, Box<dyn IntoConst>)>,
+    ini_entries: Vec<IniEntryDef>,
+    classes: Vec<ClassRegistration>,
+    globals_init: Option<Box<dyn Fn(*mut c_void)>>,
+    globals_shutdown: Option<Box<dyn Fn(*mut c_void)>>,
+}
+
+/// A startup or shutdown function as stored in the module entry.
+type RawStartupShutdownFunc = unsafe extern "C" fn(_type: i32, _module_number: i32) -> i32;
+
+static mut HOOKS: Option<Hooks> = None;
+
 impl ModuleBuilder {
     /// Creates a new module builder with a given name and version.
     ///
@@ -54,6 +138,11 @@ impl ModuleBuilder {
         N: AsRef<str>,
         V: AsRef<str>,
     {
+        let hooks = Hooks {
+            name: name.as_ref().to_string(),
+            ..Default::default()
+        };
+
         Self {
             module: ModuleEntry {
                 size: mem::size_of::<ModuleEntry>() as u16,
@@ -71,7 +160,6 @@ impl ModuleBuilder {
                 info_func: None,
                 version: c_str(version),
                 globals_size: 0,
-                globals_ptr: ptr::null::<c_void>() as *mut c_void,
                 globals_ctor: None,
                 globals_dtor: None,
                 post_deactivate_func: None,
@@ -80,8 +168,15 @@ impl ModuleBuilder {
                 handle: ptr::null::<c_void>() as *mut c_void,
                 module_number: 0,
                 build_id: unsafe { ext_php_rs_php_build_id() },
+                // The pointer to the globals is named differently when PHP is built with
+                // thread safety, see `ModuleBuilder::globals`.
+                ..unsafe { mem::zeroed() }
             },
             functions: vec![],
+            deps: vec![],
+            aliases: vec![],
+            namespace: String::new(),
+            hooks,
         }
     }
 
@@ -111,7 +206,7 @@ impl ModuleBuilder {
     ///
     /// * `func` - The function to be called when startup is requested.
     pub fn request_startup_function(mut self, func: StartupShutdownFunc) -> Self {
-        self.module.module_startup_func = Some(func);
+        self.module.request_startup_func = Some(func);
         self
     }
 
@@ -121,7 +216,7 @@ impl ModuleBuilder {
     ///
     /// * `func` - The function to be called when shutdown is requested.
     pub fn request_shutdown_function(mut self, func: StartupShutdownFunc) -> Self {
-        self.module.module_shutdown_func = Some(func);
+        self.module.request_shutdown_func = Some(func);
         self
     }
 
@@ -135,29 +230,728 @@ impl ModuleBuilder {
         self
     }
 
-    /// Adds a function to the extension.
+    /// Sets a Rust function to be called when the extension starts up, after its constants,
+    /// INI entries and classes have been registered. If the function returns an error, the
+    /// error is emitted as a warning and the engine fails to start with
+    /// `Unable to start <name> module`.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The function to be called on startup.
+    pub fn on_startup(mut self, func: LifecycleFunc) -> Self {
+        self.hooks.startup = Some(func);
+        self
+    }
+
+    /// Sets a Rust function to be called when the extension shuts down. If the function
+    /// returns an error, the error is emitted as a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The function to be called on shutdown.
+    pub fn on_shutdown(mut self, func: LifecycleFunc) -> Self {
+        self.hooks.shutdown = Some(func);
+        self
+    }
+
+    /// Sets a Rust function to be called when each request starts. If the function returns an
+    /// error, the error is emitted as a warning and the request is not executed. Replaces any
+    /// function set with [`ModuleBuilder::request_startup_function`].
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The function to be called when a request starts.
+    pub fn on_request_startup(mut self, func: LifecycleFunc) -> Self {
+        self.hooks.request_startup = Some(func);
+        self.module.request_startup_func = Some(request_startup);
+        self
+    }
+
+    /// Sets a Rust function to be called when each request ends. If the function returns an
+    /// error, the error is emitted as a warning. Replaces any function set with
+    /// [`ModuleBuilder::request_shutdown_function`].
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The function to be called when a request ends.
+    pub fn on_request_shutdown(mut self, func: LifecycleFunc) -> Self {
+        self.hooks.request_shutdown = Some(func);
+        self.module.request_shutdown_func = Some(request_shutdown);
+        self
+    }
+
+    /// Sets a Rust function to be called to print the information about the extension shown by
+    /// `phpinfo();`, see [`ModuleInfo`]. Replaces any function set with
+    /// [`ModuleBuilder::info_function`].
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The function to be called to print the information about the extension.
+    pub fn on_info(mut self, func: InfoCallback) -> Self {
+        self.hooks.info = Some(func);
+        self.module.info_func = Some(info);
+        self
+    }
+
+    /// Adds a constant to the extension, registered when the extension starts up, e.g.
+    /// `EXT_NAME_VERSION`. See [`IntoConst`] for the types of value which can be registered.
+    ///
+    /// If the value cannot be registered, such as a [`Zval`] containing an array, a warning is
+    /// emitted and the extension fails to start up.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the constant, which is case-sensitive except for its namespace,
+    ///   e.g. `Acme\Crypto\VERSION`. See [`ModuleBuilder::namespace`].
+    /// * `value` - The value of the constant.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the name is not a valid constant name, or a constant with the
+    /// same name has already been added.
+    ///
+    /// [`Zval`]: crate::php::types::zval::Zval
+    pub fn constant<N, T>(mut self, name: N, value: T) -> Self
+    where
+        N: AsRef<str>,
+        T: IntoConst + 'static,
+    {
+        let name = qualify(&self.namespace, name.as_ref());
+
+        debug_assert!(
+            is_valid_name(&name),
+            "`{}` is not a valid constant name",
+            name
+        );
+        debug_assert!(
+            !self
+                .hooks
+                .constants
+                .iter()
+                .any(|(other, _)| same_constant(other, &name)),
+            "constant `{}` is registered more than once",
+            name
+        );
+
+        self.hooks.constants.push((name, Box::new(value)));
+        self
+    }
+
+    /// Adds the functions and constants added by `scope` to a namespace, e.g. `Acme\Crypto`,
+    /// so that a function `hash` is called as `Acme\Crypto\hash()` in PHP:
+    ///
+    /// ```no_run
+    /// # use ext_php_rs::php::{function::FunctionBuilder, module::ModuleBuilder};
+    /// # use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+    /// # extern "C" fn hash(_: &mut ExecutionData, _: &mut Zval) {}
+    /// let module = ModuleBuilder::new("ext-name", "0.1.0").namespace("Acme\\Crypto", |module| {
+    ///     module
+    ///         .function(FunctionBuilder::new("hash", hash).build())
+    ///         .constant("VERSION", "0.1.0")
+    ///         .constant("\\ACME_CRYPTO_LOADED", true)
+    /// });
+    /// ```
+    ///
+    /// Namespaces may be nested, in which case the name of the inner namespace is relative to
+    /// the outer namespace. Names starting with a backslash are fully qualified, and are not
+    /// added to the namespace. As in PHP, namespaces are case-insensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The name of the namespace.
+    /// * `scope` - A function adding the functions and constants to the namespace.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the namespace is not a valid namespace name.
+    pub fn namespace<N, F>(mut self, namespace: N, scope: F) -> Self
+    where
+        N: AsRef<str>,
+        F: FnOnce(Self) -> Self,
+    {
+        let outer = mem::take(&mut self.namespace);
+        self.namespace = qualify(&outer, namespace.as_ref());
+
+        debug_assert!(
+            is_valid_name(&self.namespace),
+            "`{}` is not a valid namespace name",
+            self.namespace
+        );
+
+        let mut module = scope(self);
+        module.namespace = outer;
+        module
+    }
+
+    /// Adds an INI entry to the extension, registered when the extension starts up and
+    /// unregistered when it shuts down. See [`IniBuilder`].
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The definition of the entry.
+    ///
+    /// [`IniBuilder`]: crate::php::ini::IniBuilder
+    pub fn ini_entry(mut self, entry: IniEntryDef) -> Self {
+        self.hooks.ini_entries.push(entry);
+        self
+    }
+
+    /// Adds a function registering a class, called when the extension starts up before the
+    /// function given to [`ModuleBuilder::on_startup`]. Classes must be registered while the
+    /// extension starts up, e.g. with [`ClassBuilder`].
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The function registering the class.
+    ///
+    /// [`ClassBuilder`]: crate::php::class::ClassBuilder
+    pub fn class(mut self, register: ClassRegistration) -> Self {
+        self.hooks.classes.push(register);
+        self
+    }
+
+    /// Declares that the extension requires another extension, e.g. `json`. The other
+    /// extension is started up first, and the engine refuses to start the extension with
+    /// `Cannot load module "<name>" because required module "<other>" is not loaded` if it is
+    /// not loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the required extension, as shown by `php -m`.
+    pub fn requires<N>(self, name: N) -> Self
+    where
+        N: AsRef<str>,
+    {
+        self.dependency(name, MODULE_DEP_REQUIRED)
+    }
+
+    /// Declares that the extension conflicts with another extension. The engine refuses to load
+    /// the extension with `Cannot load module "<name>" because conflicting module "<other>" is
+    /// already loaded` if the other extension was loaded first.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the conflicting extension, as shown by `php -m`.
+    pub fn conflicts<N>(self, name: N) -> Self
+    where
+        N: AsRef<str>,
+    {
+        self.dependency(name, MODULE_DEP_CONFLICTS)
+    }
+
+    /// Declares that the extension uses another extension if it is loaded, which is then
+    /// started up first.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the optional extension, as shown by `php -m`.
+    pub fn optional<N>(self, name: N) -> Self
+    where
+        N: AsRef<str>,
+    {
+        self.dependency(name, MODULE_DEP_OPTIONAL)
+    }
+
+    /// Adds a dependency of the given type, one of the `MODULE_DEP_*` constants.
+    fn dependency<N>(mut self, name: N, type_: u32) -> Self
+    where
+        N: AsRef<str>,
+    {
+        self.deps.push(ModuleDep {
+            name: c_str(name),
+            rel: ptr::null(),
+            version: ptr::null(),
+            type_: type_ as u8,
+        });
+        self
+    }
+
+    /// Adds a function to the extension. See [`FunctionBuilder`]. The name of the function may
+    /// include a namespace, e.g. `Acme\Crypto\hash`, see [`ModuleBuilder::namespace`].
     ///
     /// # Arguments
     ///
     /// * `func` - The function to be added to the extension.
-    pub fn function(mut self, func: FunctionEntry) -> Self {
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the name is not a valid function name, or a function with the
+    /// same name has already been added. As in PHP, names are compared case-insensitively. The
+    /// engine refuses to load an extension which registers the same function twice.
+    ///
+    /// [`FunctionBuilder`]: crate::php::function::FunctionBuilder
+    pub fn function(mut self, mut func: FunctionEntry) -> Self {
+        let name = unsafe { CStr::from_ptr(func.fname) }
+            .to_string_lossy()
+            .into_owned();
+        let qualified = qualify(&self.namespace, &name);
+
+        if qualified != name {
+            func.fname = c_str(&qualified);
+        }
+
+        debug_assert!(
+            is_valid_name(&qualified),
+            "`{}` is not a valid function name",
+            qualified
+        );
+        debug_assert!(
+            !is_registered(&self.functions, &func),
+            "function `{}` is registered more than once",
+            unsafe { CStr::from_ptr(func.fname) }.to_string_lossy()
+        );
+
         self.functions.push(func);
         self
     }
 
+    /// Adds the globals of the extension, which are allocated for each thread when PHP is built
+    /// with thread safety. See [`ModuleGlobals`]. An extension has a single set of globals, so
+    /// this replaces any globals added before.
+    ///
+    /// # Arguments
+    ///
+    /// * `globals` - The globals of the extension, declared as a static.
+    pub fn globals<T>(self, globals: &'static ModuleGlobals<T>) -> Self
+    where
+        T: Default + 'static,
+    {
+        self.globals_with_hooks(globals, None, None)
+    }
+
+    /// Adds the globals of the extension, as with [`ModuleBuilder::globals`], with functions
+    /// called when the globals are initialized and shut down, equivalent to `GINIT` and
+    /// `GSHUTDOWN` in PHP. The functions are called once for each thread when PHP is built with
+    /// thread safety, and otherwise when the extension is loaded and unloaded. Unlike the
+    /// functions given to [`ModuleBuilder::on_request_startup`], they are not called for each
+    /// request.
+    ///
+    /// # Arguments
+    ///
+    /// * `globals` - The globals of the extension, declared as a static.
+    /// * `init` - Called after the globals are initialized with [`Default`].
+    /// * `shutdown` - Called before the globals are dropped.
+    pub fn globals_with_hooks<T>(
+        mut self,
+        globals: &'static ModuleGlobals<T>,
+        init: Option<GlobalsFunc<T>>,
+        shutdown: Option<GlobalsFunc<T>>,
+    ) -> Self
+    where
+        T: Default + 'static,
+    {
+        let (id, storage) = globals.storage();
+        unsafe { ext_php_rs_module_set_globals(&mut self.module, id, storage) };
+
+        self.module.globals_size = mem::size_of::<T>() as _;
+        self.module.globals_ctor = Some(globals_ctor);
+        self.module.globals_dtor = Some(globals_dtor);
+        self.hooks.globals_init = Some(Box::new(move |ptr| unsafe { globals.init(ptr, init) }));
+        self.hooks.globals_shutdown = Some(Box::new(move |ptr| unsafe {
+            globals.shutdown(ptr, shutdown)
+        }));
+        self
+    }
+
+    /// Adds an alias of a function, which calls the same handler and has the same arguments
+    /// as the function, as with `ZEND_FALIAS` in PHP. Aliases are added when the module is
+    /// built, so the function may be added after its alias.
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - The name of the alias, e.g. the name of a function which was renamed.
+    /// * `function` - The name of the function called by the alias.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the alias is not a valid function name, or if no function
+    /// with the given name has been added when the module is built.
+    pub fn alias<A, F>(self, alias: A, function: F) -> Self
+    where
+        A: AsRef<str>,
+        F: AsRef<str>,
+    {
+        self.add_alias(alias.as_ref(), function.as_ref(), false)
+    }
+
+    /// Adds a deprecated alias of a function, which emits `E_DEPRECATED` each time it is
+    /// called, while the function itself is not deprecated. See [`ModuleBuilder::alias`] and
+    /// [`FunctionBuilder::deprecated`].
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - The name of the alias, e.g. the name of a function which was renamed.
+    /// * `function` - The name of the function called by the alias.
+    ///
+    /// [`FunctionBuilder::deprecated`]: crate::php::function::FunctionBuilder::deprecated
+    pub fn deprecated_alias<A, F>(self, alias: A, function: F) -> Self
+    where
+        A: AsRef<str>,
+        F: AsRef<str>,
+    {
+        self.add_alias(alias.as_ref(), function.as_ref(), true)
+    }
+
+    /// Adds an alias, qualifying both names with the current namespace.
+    fn add_alias(mut self, alias: &str, function: &str, deprecated: bool) -> Self {
+        let name = qualify(&self.namespace, alias);
+        debug_assert!(
+            is_valid_name(&name),
+            "`{}` is not a valid function name",
+            name
+        );
+
+        self.aliases.push(Alias {
+            name,
+            function: qualify(&self.namespace, function),
+            deprecated,
+        });
+        self
+    }
+
     /// Builds the extension and returns a `ModuleEntry`.
     pub fn build(mut self) -> ModuleEntry {
+        for alias in mem::take(&mut self.aliases) {
+            let added = add_alias(&mut self.functions, &alias);
+            debug_assert!(
+                added,
+                "cannot alias `{}` to undefined function `{}`",
+                alias.name, alias.function
+            );
+        }
+
         // TODO: move to seperate function
         self.functions.push(FunctionEntry::end());
         self.module.functions =
             Box::into_raw(self.functions.into_boxed_slice()) as *const FunctionEntry;
+
+        if !self.deps.is_empty() {
+            self.deps.push(ModuleDep {
+                name: ptr::null(),
+                rel: ptr::null(),
+                version: ptr::null(),
+                type_: 0,
+            });
+            self.module.deps = Box::into_raw(self.deps.into_boxed_slice()) as *const ModuleDep;
+        }
+
+        let hooks = &mut self.hooks;
+
+        // The Rust functions are called in addition to any functions set directly.
+        if hooks.startup.is_some()
+            || !hooks.constants.is_empty()
+            || !hooks.ini_entries.is_empty()
+            || !hooks.classes.is_empty()
+        {
+            hooks.raw_startup = self.module.module_startup_func.replace(startup);
+        }
+
+        if hooks.shutdown.is_some() || !hooks.ini_entries.is_empty() {
+            hooks.raw_shutdown = self.module.module_shutdown_func.replace(shutdown);
+        }
+
+        unsafe { HOOKS = Some(self.hooks) };
         self.module
     }
 }
 
+/// Returns the hooks of the extension, which are set when the module is built.
+fn hooks() -> &'static Hooks {
+    unsafe { (*ptr::addr_of!(HOOKS)).as_ref() }.expect("module has not been built")
+}
+
+/// Emits an error returned by a hook of the extension as a warning, prefixed with the name of
+/// the extension.
+fn warn(hooks: &Hooks, error: &dyn std::fmt::Display) {
+    let message = CString::new(format!("{}: {}", hooks.name, error)).unwrap_or_default();
+
+    unsafe {
+        zend_error(
+            E_CORE_WARNING as _,
+            b"%s\0".as_ptr() as *const c_char,
+            message.as_ptr(),
+        )
+    };
+}
+
+/// Converts the result of a hook into the value returned to the engine, emitting the error as
+/// a warning.
+fn result_code(hooks: &Hooks, result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => SUCCESS,
+        Err(e) => {
+            warn(hooks, &e);
+            FAILURE
+        }
+    }
+}
+
+/// Registers the constants, INI entries and classes of the extension before calling its
+/// startup functions.
+extern "C" fn startup(type_: i32, module_number: i32) -> i32 {
+    let hooks = hooks();
+
+    for (name, value) in hooks.constants.iter() {
+        if let Err(e) = value.register_constant(name, module_number) {
+            return result_code(hooks, Err(e));
+        }
+    }
+
+    if !hooks.ini_entries.is_empty() {
+        let mut entries = hooks.ini_entries.clone();
+        entries.push(IniEntryDef::end());
+
+        // The definitions are copied into the entries by the engine.
+        if unsafe { zend_register_ini_entries(entries.as_ptr(), module_number) } != SUCCESS {
+            return FAILURE;
+        }
+    }
+
+    for register in hooks.classes.iter() {
+        if let Err(e) = register() {
+            return result_code(hooks, Err(e));
+        }
+    }
+
+    if let Some(func) = hooks.raw_startup {
+        if unsafe { func(type_, module_number) } != SUCCESS {
+            return FAILURE;
+        }
+    }
+
+    result_code(hooks, hooks.startup.map_or(Ok(()), |f| f(module_number)))
+}
+
+/// Calls the shutdown functions of the extension, then unregisters its INI entries.
+extern "C" fn shutdown(type_: i32, module_number: i32) -> i32 {
+    let hooks = hooks();
+    let mut code = result_code(hooks, hooks.shutdown.map_or(Ok(()), |f| f(module_number)));
+
+    if let Some(func) = hooks.raw_shutdown {
+        if unsafe { func(type_, module_number) } != SUCCESS {
+            code = FAILURE;
+        }
+    }
+
+    if !hooks.ini_entries.is_empty() {
+        unsafe { zend_unregister_ini_entries(module_number) };
+    }
+
+    code
+}
+
+/// Calls the request startup function of the extension.
+extern "C" fn request_startup(_type: i32, module_number: i32) -> i32 {
+    let hooks = hooks();
+    result_code(
+        hooks,
+        hooks.request_startup.map_or(Ok(()), |f| f(module_number)),
+    )
+}
+
+/// Calls the request shutdown function of the extension.
+extern "C" fn request_shutdown(_type: i32, module_number: i32) -> i32 {
+    let hooks = hooks();
+    result_code(
+        hooks,
+        hooks.request_shutdown.map_or(Ok(()), |f| f(module_number)),
+    )
+}
+
+/// Calls the info function of the extension.
+extern "C" fn info(module: *mut ModuleEntry) {
+    if let (Some(func), Some(module)) = (hooks().info, unsafe { module.as_ref() }) {
+        func(&mut ModuleInfo::new(module));
+    }
+}
+
+/// Returns whether a function with the same name as `func` is in the list of functions.
+fn is_registered(functions: &[FunctionEntry], func: &FunctionEntry) -> bool {
+    let name = unsafe { CStr::from_ptr(func.fname) }.to_bytes();
+
+    functions.iter().any(|f| {
+        unsafe { CStr::from_ptr(f.fname) }
+            .to_bytes()
+            .eq_ignore_ascii_case(name)
+    })
+}
+
+/// Initializes the globals of the extension, for each thread if PHP is built with thread
+/// safety.
+extern "C" fn globals_ctor(globals: *mut c_void) {
+    if let Some(init) = &hooks().globals_init {
+        init(globals);
+    }
+}
+
+/// Drops the globals of the extension.
+extern "C" fn globals_dtor(globals: *mut c_void) {
+    if let Some(shutdown) = &hooks().globals_shutdown {
+        shutdown(globals);
+    }
+}
+
+/// Adds an alias to a list of functions, copying the entry of the function it calls. Returns
+/// `false` if there is no function with the given name.
+fn add_alias(functions: &mut Vec<FunctionEntry>, alias: &Alias) -> bool {
+    let found = functions.iter().find(|f| {
+        unsafe { CStr::from_ptr(f.fname) }
+            .to_bytes()
+            .eq_ignore_ascii_case(alias.function.as_bytes())
+    });
+
+    let mut func = match found {
+        Some(func) => *func,
+        None => return false,
+    };
+
+    func.fname = c_str(&alias.name);
+    if alias.deprecated {
+        func.flags |= MethodFlags::Deprecated.bits();
+    }
+
+    debug_assert!(
+        !is_registered(functions, &func),
+        "function `{}` is registered more than once",
+        alias.name
+    );
+
+    functions.push(func);
+    true
+}
+
+/// Qualifies a name with a namespace, unless the name is fully qualified with a leading
+/// backslash, which is removed. An empty namespace is the global namespace.
+fn qualify(namespace: &str, name: &str) -> String {
+    match name.strip_prefix('\\') {
+        Some(name) => name.to_string(),
+        None if namespace.is_empty() => name.to_string(),
+        None => format!("{}\\{}", namespace, name),
+    }
+}
+
+/// Returns whether a name, which may include a namespace, is valid in PHP. Each part must be
+/// an identifier made of letters, digits, underscores and bytes above 127, and may not start
+/// with a digit.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    name.split('\\').all(|part| {
+        let mut bytes = part.bytes();
+
+        match bytes.next() {
+            Some(b) if b == b'_' || b.is_ascii_alphabetic() || b >= 0x80 => {
+                bytes.all(|b| b == b'_' || b.is_ascii_alphanumeric() || b >= 0x80)
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Returns whether two qualified names refer to the same constant. The namespaces of constants
+/// are case-insensitive, but their names are case-sensitive.
+fn same_constant(a: &str, b: &str) -> bool {
+    let a_split = a.rfind('\\').map_or(0, |i| i + 1);
+    let b_split = b.rfind('\\').map_or(0, |i| i + 1);
+
+    a[..a_split].eq_ignore_ascii_case(&b[..b_split]) && a[a_split..] == b[b_split..]
+}
+
 impl ModuleEntry {
     /// Converts the module entry into a raw pointer, releasing it to the C world.
     pub fn into_raw(self) -> *mut Self {
         Box::into_raw(Box::new(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{add_alias, is_registered, is_valid_name, qualify, same_constant, Alias};
+    use crate::php::flags::MethodFlags;
+    use crate::php::{execution_data::ExecutionData, function::FunctionBuilder, types::zval::Zval};
+    use std::ffi::CStr;
+
+    extern "C" fn handler(_: &mut ExecutionData, _: &mut Zval) {}
+
+    #[test]
+    fn test_is_registered() {
+        let functions = vec![
+            FunctionBuilder::new("foo", handler).build(),
+            FunctionBuilder::new("bar", handler).build(),
+        ];
+
+        assert!(is_registered(
+            &functions,
+            &FunctionBuilder::new("bar", handler).build()
+        ));
+        assert!(is_registered(
+            &functions,
+            &FunctionBuilder::new("FOO", handler).build()
+        ));
+        assert!(!is_registered(
+            &functions,
+            &FunctionBuilder::new("foobar", handler).build()
+        ));
+    }
+
+    #[test]
+    fn test_add_alias() {
+        let mut functions = vec![FunctionBuilder::new("new_name", handler).build()];
+        let alias = |name: &str, deprecated| Alias {
+            name: name.to_string(),
+            function: "NEW_NAME".to_string(),
+            deprecated,
+        };
+
+        assert!(add_alias(&mut functions, &alias("old_name", true)));
+        assert!(add_alias(&mut functions, &alias("other_name", false)));
+
+        let old = &functions[1];
+        assert_eq!(unsafe { CStr::from_ptr(old.fname) }.to_bytes(), b"old_name");
+        assert_eq!(
+            old.handler.map(|f| f as usize),
+            functions[0].handler.map(|f| f as usize)
+        );
+        assert_eq!(old.arg_info, functions[0].arg_info);
+        assert_eq!(old.flags, MethodFlags::Deprecated.bits());
+        assert_eq!(functions[2].flags, 0);
+
+        let mut missing = alias("old_name", false);
+        missing.function = "missing".to_string();
+        assert!(!add_alias(&mut functions, &missing));
+    }
+
+    #[test]
+    fn test_qualify() {
+        assert_eq!(qualify("", "hash"), "hash");
+        assert_eq!(qualify("Acme\\Crypto", "hash"), "Acme\\Crypto\\hash");
+        assert_eq!(qualify("Acme", "Crypto\\hash"), "Acme\\Crypto\\hash");
+        assert_eq!(qualify("Acme", "\\hash"), "hash");
+        assert_eq!(qualify("", "\\Acme\\hash"), "Acme\\hash");
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        for name in &["hash", "_hash2", "Acme\\Crypto\\hash", "caf\u{e9}"] {
+            assert!(is_valid_name(name), "{}", name);
+        }
+
+        for name in &[
+            "",
+            "2hash",
+            "Acme\\",
+            "\\hash",
+            "Acme\\\\hash",
+            "a-b",
+            "a b",
+        ] {
+            assert!(!is_valid_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_same_constant() {
+        assert!(same_constant("Acme\\VERSION", "acme\\VERSION"));
+        assert!(same_constant("VERSION", "VERSION"));
+        assert!(!same_constant("Acme\\VERSION", "Acme\\version"));
+        assert!(!same_constant("Acme\\VERSION", "VERSION"));
+    }
+}
@@ -0,0 +1,1329 @@
+//! A [`serde`] data format for zvals, allowing any Rust type implementing `Serialize` or
+//! `Deserialize` to be converted to and from PHP values. Requires the `serde` feature.
+//!
+//! Structs and maps are represented as associative arrays, sequences and tuples as packed
+//! arrays and `None` as `null`. Enums are externally tagged: unit variants are represented
+//! by the name of the variant, other variants by an array with the name of the variant as
+//! the only key.
+
+use core::slice;
+use std::{convert::TryInto, fmt::Display, str};
+
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    bindings::{zend_string, HashTable},
+    errors::{Error, Result},
+};
+
+use super::{
+    enums::DataType,
+    types::{
+        array::ZendHashTable,
+        long::ZendLong,
+        zval::{IntoZval, Zval},
+    },
+};
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
+
+/// Converts a Rust value into a zval. The zval must be handed over to PHP or released with
+/// [`Zval::release`].
+///
+/// # Parameters
+///
+/// * `value` - The value to convert.
+///
+/// # Returns
+///
+/// Returns an error if the value contains a map key which is not a string or an integer, an
+/// integer which does not fit into a [`ZendLong`], or if serializing the value fails.
+pub fn to_zval<T>(value: &T) -> Result<Zval>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer)
+}
+
+/// Converts a zval into a Rust value. Both arrays and objects can be converted into structs
+/// and maps. Only the public properties of objects are read.
+///
+/// # Parameters
+///
+/// * `zval` - The zval to convert. Strings are borrowed from the zval where possible.
+pub fn from_zval<'de, T>(zval: &'de Zval) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(Deserializer(zval))
+}
+
+/// The key of an array element, before it is inserted into a hash table.
+enum Key {
+    Str(String),
+    Index(u64),
+}
+
+/// Inserts an element into a hash table at the given key.
+fn insert(ht: &mut ZendHashTable, key: Key, val: Zval) -> Result<()> {
+    match key {
+        Key::Str(key) => ht.insert(key, val).map(|_| ()),
+        Key::Index(idx) => ht.insert_at_index(idx, val).map(|_| ()),
+    }
+}
+
+/// Converts an integer into a [`ZendLong`], failing if it is out of range.
+fn to_long<T>(val: T) -> Result<ZendLong>
+where
+    T: Copy + ToString + TryInto<ZendLong>,
+{
+    val.try_into()
+        .map_err(|_| Error::IntegerOutOfRange(val.to_string(), "ZendLong"))
+}
+
+/// Converts an integer into a zval, failing if it does not fit into a [`ZendLong`].
+fn long<T>(val: T) -> Result<Zval>
+where
+    T: Copy + ToString + TryInto<ZendLong>,
+{
+    to_long(val).map(Zval::from)
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Zval;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeArray;
+    type SerializeStruct = SerializeArray;
+    type SerializeStructVariant = SerializeVariant<SerializeArray>;
+
+    fn serialize_bool(self, v: bool) -> Result<Zval> {
+        Ok(Zval::from(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Zval> {
+        long(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Zval> {
+        Ok(Zval::from(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Zval> {
+        Ok(Zval::from(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Zval> {
+        v.into_zval()
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Zval> {
+        v.into_zval()
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Zval> {
+        let mut zv = Zval::new();
+        zv.set_binary(v)?;
+        Ok(zv)
+    }
+
+    fn serialize_none(self) -> Result<Zval> {
+        Ok(Zval::new())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Zval>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Zval> {
+        Ok(Zval::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Zval> {
+        Ok(Zval::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Zval> {
+        variant.into_zval()
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Zval>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Zval>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut ht = ZendHashTable::with_capacity(1);
+        ht.insert(variant, to_zval(value)?)?;
+        ht.into_zval()
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
+        Ok(SerializeArray::new(len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+        Ok(SerializeArray::new(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray> {
+        Ok(SerializeArray::new(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeArray>> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeArray::new(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeArray> {
+        Ok(SerializeArray::new(len.unwrap_or(0)))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray> {
+        Ok(SerializeArray::new(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeArray>> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeArray::new(len),
+        })
+    }
+}
+
+/// Builds a PHP array from a sequence, tuple, map or struct.
+struct SerializeArray {
+    ht: ZendHashTable,
+    next_key: Option<Key>,
+}
+
+impl SerializeArray {
+    fn new(len: usize) -> Self {
+        Self {
+            ht: ZendHashTable::with_capacity(len as u32),
+            next_key: None,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.ht.push(to_zval(value)?)
+    }
+
+    fn end(self) -> Result<Zval> {
+        self.ht.into_zval()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Zval> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Zval> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for SerializeArray {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error::Serde("value serialized before its key".into()))?;
+
+        insert(&mut self.ht, key, to_zval(value)?)
+    }
+
+    fn end(self) -> Result<Zval> {
+        self.ht.into_zval()
+    }
+}
+
+impl ser::SerializeStruct for SerializeArray {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.ht.insert(key, to_zval(value)?).map(|_| ())
+    }
+
+    fn end(self) -> Result<Zval> {
+        self.ht.into_zval()
+    }
+}
+
+/// Wraps the array built for a tuple or struct variant in an array keyed by the name of the
+/// variant.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl SerializeVariant<SerializeArray> {
+    fn end(self) -> Result<Zval> {
+        let mut ht = ZendHashTable::with_capacity(1);
+        ht.insert(self.variant, self.inner.ht.into_zval()?)?;
+        ht.into_zval()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Zval> {
+        SerializeVariant::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeArray> {
+    type Ok = Zval;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Zval> {
+        SerializeVariant::end(self)
+    }
+}
+
+/// Serializes the keys of a map, which must be strings or integers to be used as the keys of
+/// a PHP array.
+struct KeySerializer;
+
+fn key_error() -> Error {
+    Error::Serde("array key must be a string or an integer".into())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = Key;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<Key, Error>;
+    type SerializeTuple = Impossible<Key, Error>;
+    type SerializeTupleStruct = Impossible<Key, Error>;
+    type SerializeTupleVariant = Impossible<Key, Error>;
+    type SerializeMap = Impossible<Key, Error>;
+    type SerializeStruct = Impossible<Key, Error>;
+    type SerializeStructVariant = Impossible<Key, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Key> {
+        // Negative keys are stored by the engine as their unsigned representation.
+        Ok(Key::Index(to_long(v)? as u64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Key> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Key> {
+        Ok(Key::Index(to_long(v)? as u64))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Key> {
+        Ok(Key::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Key> {
+        Ok(Key::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Key>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Key> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Key> {
+        Ok(Key::Str(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Key>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Key>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_error())
+    }
+}
+
+/// The key of an element of a hash table, borrowed from the hash table.
+#[derive(Clone, Copy)]
+enum BorrowedKey<'de> {
+    Str(&'de zend_string),
+    Index(u64),
+}
+
+/// Returns the elements of a hash table, borrowed for as long as the zval containing the
+/// hash table is borrowed. Deleted elements and unset properties are skipped, and private
+/// and protected properties, whose names are mangled with a leading NUL byte, are skipped if
+/// `public_only` is set.
+fn elements(
+    ht: &HashTable,
+    public_only: bool,
+) -> impl Iterator<Item = (BorrowedKey<'_>, &Zval)> + '_ {
+    let buckets = unsafe { slice::from_raw_parts(ht.arData, ht.nNumUsed as usize) };
+
+    buckets.iter().filter_map(move |bucket| {
+        if let Some(DataType::Undef) = bucket.val.dereference().get_type() {
+            return None;
+        }
+
+        let key = match unsafe { bucket.key.as_ref() } {
+            Some(key) if public_only && key.len > 0 && key.val[0] == 0 => return None,
+            Some(key) => BorrowedKey::Str(key),
+            None => BorrowedKey::Index(bucket.h),
+        };
+
+        Some((key, &bucket.val))
+    })
+}
+
+/// Returns whether the elements of a hash table have sequential integer keys starting at
+/// zero, in which case it is deserialized as a sequence rather than a map.
+fn is_list(ht: &HashTable) -> bool {
+    elements(ht, false)
+        .enumerate()
+        .all(|(i, (key, _))| matches!(key, BorrowedKey::Index(idx) if idx == i as u64))
+}
+
+struct Deserializer<'de>(&'de Zval);
+
+impl<'de> Deserializer<'de> {
+    /// Returns the hash table of an array, or the public properties of an object.
+    fn table(&self) -> Option<(&'de HashTable, bool)> {
+        let zv = self.0.dereference();
+
+        match zv.get_type()? {
+            DataType::Array => unsafe { zv.value.arr.as_ref() }.map(|ht| (ht, false)),
+            DataType::Object => {
+                let obj = unsafe { zv.value.obj };
+                let props = unsafe { (*(*obj).handlers).get_properties?(obj).as_ref() }?;
+                Some((props, true))
+            }
+            _ => None,
+        }
+    }
+
+    fn invalid_type(&self, exp: &dyn de::Expected) -> Error {
+        let zv = self.0.dereference();
+        let unexp = match zv.get_type() {
+            Some(DataType::Null) => de::Unexpected::Unit,
+            Some(DataType::True) => de::Unexpected::Bool(true),
+            Some(DataType::False) => de::Unexpected::Bool(false),
+            Some(DataType::Long) => de::Unexpected::Signed(unsafe { zv.value.lval }),
+            Some(DataType::Double) => de::Unexpected::Float(unsafe { zv.value.dval }),
+            Some(DataType::String) => de::Unexpected::Other("string"),
+            Some(DataType::Array) => de::Unexpected::Other("array"),
+            Some(DataType::Object) => de::Unexpected::Other("object"),
+            _ => de::Unexpected::Other("unsupported type"),
+        };
+
+        de::Error::invalid_type(unexp, exp)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let zv = self.0.dereference();
+
+        match zv.get_type() {
+            Some(DataType::Null) => visitor.visit_unit(),
+            Some(DataType::True) => visitor.visit_bool(true),
+            Some(DataType::False) => visitor.visit_bool(false),
+            Some(DataType::Long) => visitor.visit_i64(unsafe { zv.value.lval }),
+            Some(DataType::Double) => visitor.visit_f64(unsafe { zv.value.dval }),
+            Some(DataType::String) => {
+                let bytes = zv.str_bytes().unwrap_or_default();
+
+                match str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            _ => match self.table() {
+                Some((ht, false)) if is_list(ht) => self.deserialize_seq(visitor),
+                Some(_) => self.deserialize_map(visitor),
+                None => Err(self.invalid_type(&visitor)),
+            },
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.dereference().is_null() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Any array can be deserialized as a sequence, in which case its keys are ignored.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.table() {
+            Some((ht, false)) => visitor.visit_seq(SeqAccess {
+                iter: Box::new(elements(ht, false).map(|(_, val)| val)),
+                len: ht.nNumOfElements as usize,
+            }),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.table() {
+            Some((ht, public_only)) => visitor.visit_map(MapAccess {
+                iter: Box::new(elements(ht, public_only)),
+                value: None,
+            }),
+            None => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let zv = self.0.dereference();
+
+        if zv.is_string() {
+            return visitor.visit_enum(Deserializer(zv));
+        }
+
+        let mut iter = match self.table() {
+            Some((ht, false)) => elements(ht, false),
+            _ => return Err(self.invalid_type(&visitor)),
+        };
+
+        match (iter.next(), iter.next()) {
+            (Some((key, val)), None) => visitor.visit_enum(EnumAccess { key, val }),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Map,
+                &"an array with a single element",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct identifier ignored_any
+    }
+}
+
+/// Unit variants are represented by their name.
+impl<'de> de::EnumAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+    type Variant = UnitVariant;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, UnitVariant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(self)?, UnitVariant))
+    }
+}
+
+struct UnitVariant;
+
+impl<'de> de::VariantAccess<'de> for UnitVariant {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Variants with data are represented by an array with the name of the variant as the only
+/// key.
+struct EnumAccess<'de> {
+    key: BorrowedKey<'de>,
+    val: &'de Zval,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Deserializer<'de>)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((
+            seed.deserialize(KeyDeserializer(self.key))?,
+            Deserializer(self.val),
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+struct SeqAccess<'de> {
+    iter: Box<dyn Iterator<Item = &'de Zval> + 'de>,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(val) => seed.deserialize(Deserializer(val)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+struct MapAccess<'de> {
+    iter: Box<dyn Iterator<Item = (BorrowedKey<'de>, &'de Zval)> + 'de>,
+    value: Option<&'de Zval>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, val)) => {
+                self.value = Some(val);
+                seed.deserialize(KeyDeserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let val = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Serde("value deserialized before its key".into()))?;
+
+        seed.deserialize(Deserializer(val))
+    }
+}
+
+/// Deserializes the key of an array element. Integer keys can be deserialized as integers
+/// or as strings, as PHP converts numeric string keys into integers.
+struct KeyDeserializer<'de>(BorrowedKey<'de>);
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            BorrowedKey::Str(key) => {
+                // SAFETY: See `Zval::str_bytes`.
+                let bytes =
+                    unsafe { slice::from_raw_parts(key.val.as_ptr() as *const u8, key.len as _) };
+
+                match str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            BorrowedKey::Index(idx) => visitor.visit_string((idx as ZendLong).to_string()),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            BorrowedKey::Str(key) => visitor.visit_enum(String::from(key).into_deserializer()),
+            BorrowedKey::Index(_) => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            BorrowedKey::Index(idx) => visitor.visit_i64(idx as ZendLong),
+            BorrowedKey::Str(_) => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, mem};
+
+    use serde::Deserialize;
+
+    use super::{from_zval, to_zval};
+    use crate::{
+        bindings::{
+            zend_object, zend_object_handlers, zend_string, HashTable, HASH_FLAG_PACKED,
+            IS_INTERNED_STRING_EX, IS_OBJECT, IS_STR_INTERNED,
+        },
+        errors::Error,
+        php::types::{
+            fixtures::{array_zval, bucket, fake_string, hash_table},
+            long::ZendLong,
+            zval::Zval,
+        },
+    };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: ZendLong,
+        y: ZendLong,
+        label: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(ZendLong),
+        Rect { w: ZendLong, h: ZendLong },
+    }
+
+    /// Builds a zval containing an interned fake string backed by `buf`.
+    fn string_zval(buf: &mut Vec<u64>) -> Zval {
+        let mut zv = Zval::new();
+        zv.u1.type_info = IS_INTERNED_STRING_EX;
+        zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+        zv
+    }
+
+    unsafe extern "C" fn get_properties(obj: *mut zend_object) -> *mut HashTable {
+        (*obj).properties
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(to_zval(&5u8).unwrap().long(), Some(5));
+        assert_eq!(to_zval(&2.5f32).unwrap().double(), Some(2.5));
+        assert_eq!(to_zval(&true).unwrap().bool(), Some(true));
+        assert!(to_zval(&Option::<i32>::None).unwrap().is_null());
+        assert!(matches!(
+            to_zval(&u64::MAX),
+            Err(Error::IntegerOutOfRange(_, "ZendLong"))
+        ));
+
+        assert_eq!(from_zval::<i32>(&Zval::from(5i32)), Ok(5));
+        assert_eq!(from_zval::<f64>(&Zval::from(5i32)), Ok(5.0));
+        assert_eq!(from_zval::<Option<bool>>(&Zval::new()), Ok(None));
+        assert_eq!(
+            from_zval::<Option<bool>>(&Zval::from(false)),
+            Ok(Some(false))
+        );
+        assert!(matches!(
+            from_zval::<u8>(&Zval::from(300i32)),
+            Err(Error::Serde(_))
+        ));
+        assert!(matches!(
+            from_zval::<String>(&Zval::from(true)),
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn test_struct() {
+        let mut x = fake_string(b"x", IS_STR_INTERNED, 0);
+        let mut y = fake_string(b"y", IS_STR_INTERNED, 0);
+        let mut z = fake_string(b"z", IS_STR_INTERNED, 0);
+        let mut buckets = [
+            bucket(0, Some(&mut y), Some(2)),
+            bucket(0, Some(&mut x), Some(1)),
+            bucket(0, Some(&mut z), Some(3)),
+        ];
+        let mut ht = hash_table(&mut buckets, 0);
+        let zv = array_zval(&mut ht);
+
+        // Missing optional fields are `None`, and unknown keys are ignored.
+        assert_eq!(
+            from_zval::<Point>(&zv),
+            Ok(Point {
+                x: 1,
+                y: 2,
+                label: None
+            })
+        );
+
+        let mut label = fake_string(b"label", IS_STR_INTERNED, 0);
+        let mut origin = fake_string(b"origin", IS_STR_INTERNED, 0);
+        buckets[2] = bucket(0, Some(&mut label), None);
+        buckets[2].val = string_zval(&mut origin);
+        let mut ht = hash_table(&mut buckets, 0);
+        let zv = array_zval(&mut ht);
+        assert_eq!(
+            from_zval::<Point>(&zv),
+            Ok(Point {
+                x: 1,
+                y: 2,
+                label: Some("origin".into())
+            })
+        );
+
+        let mut buckets = [bucket(0, Some(&mut x), Some(1))];
+        let mut ht = hash_table(&mut buckets, 0);
+        let zv = array_zval(&mut ht);
+        assert!(matches!(from_zval::<Point>(&zv), Err(Error::Serde(_))));
+        assert!(matches!(
+            from_zval::<Point>(&Zval::from(1)),
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn test_map() {
+        let mut a = fake_string(b"a", IS_STR_INTERNED, 0);
+        let mut buckets = [bucket(0, Some(&mut a), Some(1)), bucket(7, None, Some(2))];
+        let mut ht = hash_table(&mut buckets, 0);
+        let zv = array_zval(&mut ht);
+
+        // Integer keys are converted into strings for string keyed maps.
+        let map: BTreeMap<String, ZendLong> = from_zval(&zv).unwrap();
+        assert_eq!(
+            map,
+            vec![("7".to_string(), 2), ("a".to_string(), 1)]
+                .into_iter()
+                .collect()
+        );
+
+        let mut buckets = [bucket(5, None, Some(1)), bucket(3, None, Some(2))];
+        let mut ht = hash_table(&mut buckets, 0);
+        let zv = array_zval(&mut ht);
+        let map: BTreeMap<ZendLong, ZendLong> = from_zval(&zv).unwrap();
+        assert_eq!(map, vec![(3, 2), (5, 1)].into_iter().collect());
+    }
+
+    #[test]
+    fn test_seq() {
+        let mut buckets = [
+            bucket(0, None, Some(1)),
+            bucket(1, None, None),
+            bucket(2, None, Some(3)),
+        ];
+        let mut ht = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let zv = array_zval(&mut ht);
+
+        // Holes are skipped, and the keys of the remaining elements are ignored.
+        assert_eq!(from_zval::<Vec<ZendLong>>(&zv), Ok(vec![1, 3]));
+        assert_eq!(from_zval::<(ZendLong, ZendLong)>(&zv), Ok((1, 3)));
+
+        let mut inner = [bucket(0, None, Some(4))];
+        let mut inner = hash_table(&mut inner, HASH_FLAG_PACKED);
+        let mut empty = hash_table(&mut [], HASH_FLAG_PACKED);
+        let mut buckets = [bucket(0, None, None), bucket(1, None, None)];
+        buckets[0].val = array_zval(&mut inner);
+        buckets[1].val = array_zval(&mut empty);
+        let mut ht = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let zv = array_zval(&mut ht);
+        assert_eq!(
+            from_zval::<Vec<Vec<ZendLong>>>(&zv),
+            Ok(vec![vec![4], vec![]])
+        );
+        assert!(matches!(
+            from_zval::<Vec<ZendLong>>(&Zval::from(1)),
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn test_enum() {
+        let mut empty = fake_string(b"Empty", IS_STR_INTERNED, 0);
+        assert_eq!(from_zval(&string_zval(&mut empty)), Ok(Shape::Empty));
+
+        let mut circle = fake_string(b"Circle", IS_STR_INTERNED, 0);
+        let mut buckets = [bucket(0, Some(&mut circle), Some(5))];
+        let mut ht = hash_table(&mut buckets, 0);
+        assert_eq!(from_zval(&array_zval(&mut ht)), Ok(Shape::Circle(5)));
+
+        let mut w = fake_string(b"w", IS_STR_INTERNED, 0);
+        let mut h = fake_string(b"h", IS_STR_INTERNED, 0);
+        let mut fields = [
+            bucket(0, Some(&mut w), Some(1)),
+            bucket(0, Some(&mut h), Some(2)),
+        ];
+        let mut fields = hash_table(&mut fields, 0);
+        let mut rect = fake_string(b"Rect", IS_STR_INTERNED, 0);
+        let mut buckets = [bucket(0, Some(&mut rect), None)];
+        buckets[0].val = array_zval(&mut fields);
+        let mut ht = hash_table(&mut buckets, 0);
+        assert_eq!(
+            from_zval(&array_zval(&mut ht)),
+            Ok(Shape::Rect { w: 1, h: 2 })
+        );
+
+        // Variants other than unit variants must be the only element of the array.
+        let mut buckets = [
+            bucket(0, Some(&mut circle), Some(5)),
+            bucket(0, Some(&mut empty), Some(0)),
+        ];
+        let mut ht = hash_table(&mut buckets, 0);
+        assert!(matches!(
+            from_zval::<Shape>(&array_zval(&mut ht)),
+            Err(Error::Serde(_))
+        ));
+
+        let mut unknown = fake_string(b"Square", IS_STR_INTERNED, 0);
+        assert!(matches!(
+            from_zval::<Shape>(&string_zval(&mut unknown)),
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn test_object() {
+        let mut x = fake_string(b"x", IS_STR_INTERNED, 0);
+        let mut y = fake_string(b"y", IS_STR_INTERNED, 0);
+        let mut private = fake_string(b"\0Point\0secret", IS_STR_INTERNED, 0);
+        let mut buckets = [
+            bucket(0, Some(&mut x), Some(1)),
+            bucket(0, Some(&mut private), Some(3)),
+            bucket(0, Some(&mut y), Some(2)),
+        ];
+        let mut props = hash_table(&mut buckets, 0);
+
+        let mut handlers: zend_object_handlers = unsafe { mem::zeroed() };
+        handlers.get_properties = Some(get_properties);
+        let mut obj: zend_object = unsafe { mem::zeroed() };
+        obj.handlers = &handlers;
+        obj.properties = &mut props;
+
+        let mut zv = Zval::new();
+        zv.u1.type_info = IS_OBJECT;
+        zv.value.obj = &mut obj;
+
+        // Only public properties are read.
+        assert_eq!(
+            from_zval::<Point>(&zv),
+            Ok(Point {
+                x: 1,
+                y: 2,
+                label: None
+            })
+        );
+
+        let map: BTreeMap<String, ZendLong> = from_zval(&zv).unwrap();
+        assert_eq!(
+            map,
+            vec![("x".to_string(), 1), ("y".to_string(), 2)]
+                .into_iter()
+                .collect()
+        );
+
+        // Objects are not sequences.
+        assert!(matches!(
+            from_zval::<Vec<ZendLong>>(&zv),
+            Err(Error::Serde(_))
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+//! Converts zvals to and from the native serialization format of PHP, as used by the
+//! `serialize` and `unserialize` functions.
+
+use std::{os::raw::c_char, ptr};
+
+use crate::{
+    bindings::{ext_php_rs_var_serialize, ext_php_rs_var_unserialize},
+    errors::{Error, Result},
+};
+
+use super::{
+    errors::ExceptionMark,
+    types::{array::ZendHashTable, string::ZendString, zval::Zval},
+};
+
+/// The classes which may be instantiated when unserializing, equivalent to the
+/// `allowed_classes` option of `unserialize`. Objects of any other class are unserialized
+/// as instances of `__PHP_Incomplete_Class`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AllowedClasses {
+    /// Objects of any class may be instantiated.
+    #[default]
+    All,
+    /// No objects may be instantiated.
+    None,
+    /// Only objects of the classes with the given names may be instantiated. The names are
+    /// case insensitive.
+    Only(Vec<String>),
+}
+
+/// Options passed to [`Zval::unserialize`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnserializeOptions {
+    /// The classes which may be instantiated.
+    pub allowed_classes: AllowedClasses,
+}
+
+impl Zval {
+    /// Serializes the zval into the same format as the `serialize` function in PHP. Objects
+    /// are serialized through `__serialize`, `__sleep` or the `Serializable` interface.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The serialized representation of the zval.
+    /// * `Err(Error::Exception)` - The value could not be serialized, e.g. it contained a
+    ///   closure. The exception is left pending.
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let ptr: *const Zval = self;
+        let mark = ExceptionMark::new();
+        let zs = unsafe { ZendString::from_raw(ext_php_rs_var_serialize(ptr as *mut Zval)) }
+            .ok_or(Error::InvalidPointer)?;
+        let bytes = zs.to_vec();
+        drop(zs);
+
+        mark.check(bytes)
+    }
+
+    /// Unserializes a value from the format produced by the `serialize` function in PHP. The
+    /// returned zval must be handed over to PHP or released with [`Zval::release`].
+    ///
+    /// # Parameters
+    ///
+    /// * `data` - The serialized representation of the value.
+    /// * `options` - Options controlling which values may be unserialized.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The unserialized value.
+    /// * `Err(Error::Unserialize)` - The data was malformed or truncated, with the offset at
+    ///   which the error occurred.
+    /// * `Err(Error::Exception)` - An exception was thrown while unserializing, e.g. by the
+    ///   `__unserialize` method of an object. The exception is left pending.
+    pub fn unserialize(data: &[u8], options: &UnserializeOptions) -> Result<Zval> {
+        // The allowed classes are looked up by their lowercase names, which are stored as the
+        // keys of the hash table.
+        let allowed_classes = match &options.allowed_classes {
+            AllowedClasses::All => None,
+            AllowedClasses::None => Some(ZendHashTable::new()),
+            AllowedClasses::Only(classes) => {
+                let mut ht = ZendHashTable::with_capacity(classes.len() as u32);
+
+                for class in classes {
+                    ht.insert(class.to_ascii_lowercase(), ())?;
+                }
+
+                Some(ht)
+            }
+        };
+
+        let mut rv = Zval::new();
+        let mut offset = 0;
+        let mark = ExceptionMark::new();
+
+        let result = unsafe {
+            ext_php_rs_var_unserialize(
+                &mut rv,
+                data.as_ptr() as *const c_char,
+                data.len() as _,
+                allowed_classes
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |ht| ht.ptr),
+                &mut offset,
+            )
+        };
+
+        if let Some(e) = mark.thrown() {
+            rv.release();
+            return Err(Error::Exception(e));
+        }
+
+        if result {
+            Ok(rv)
+        } else {
+            Err(Error::Unserialize(offset as usize))
+        }
+    }
+}
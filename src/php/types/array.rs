@@ -1,24 +1,172 @@
 //! Represents an array in PHP. As all arrays in PHP are associative arrays, they are represented
 //! by hash tables.
 
-use std::{collections::HashMap, u64};
+use std::{
+    any::Any,
+    borrow::Cow,
+    cell::Cell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    iter::FromIterator,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::Deref,
+    os::raw::{c_char, c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice, u64,
+};
 
 use crate::{
     bindings::{
-        HashTable, _Bucket, _zend_new_array, zend_array_destroy, zend_hash_clean,
-        zend_hash_index_del, zend_hash_index_find, zend_hash_index_update,
-        zend_hash_next_index_insert, zend_hash_str_del, zend_hash_str_find, zend_hash_str_update,
-        HT_MIN_SIZE,
+        _Bucket, _zend_new_array, ext_php_rs_zend_hash_sort_keys, ext_php_rs_zend_hash_sort_values,
+        ext_php_rs_zend_new_persistent_array, ext_php_rs_zend_persistent_array_destroy,
+        php_array_merge, zend_array_destroy, zend_array_dup, zend_hash_clean, zend_hash_del_bucket,
+        zend_hash_extend, zend_hash_index_del, zend_hash_index_find, zend_hash_index_lookup,
+        zend_hash_index_update, zend_hash_lookup, zend_hash_merge, zend_hash_next_index_insert,
+        zend_hash_real_init_packed, zend_hash_sort_ex, zend_hash_str_del, zend_hash_str_find,
+        zend_hash_str_update, zend_sort, zval_add_ref, zval_ptr_dtor, Bucket, HashTable,
+        GC_IMMUTABLE, HASH_FLAG_HAS_EMPTY_IND, HASH_FLAG_PACKED, HT_MIN_SIZE, IS_ARRAY_IMMUTABLE,
+        IS_ARRAY_PERSISTENT, IS_STR_PERMANENT, IS_STR_PERSISTENT, IS_UNDEF,
     },
-    functions::c_str,
+    errors::{Error, Result},
+    php::{enums::DataType, flags::SortFlags},
+};
+
+use super::{
+    long::ZendLong,
+    string::ZendString,
+    zval::{FromZval, IntoZval, Zval},
 };
 
-use super::zval::Zval;
+/// The key of an element in a PHP array, which is either an integer or a string. String keys
+/// are byte strings, which are not required to be valid UTF-8.
+///
+/// Keys converted from strings or bytes are normalised the same way PHP normalises array
+/// keys: strings containing a decimal integer without leading zeros, such as `"5"` or `"-5"`,
+/// are converted into integer keys, while strings such as `"05"`, `"-0"` or `"5.0"` are kept.
+/// Keys constructed from the variants directly are used as they are.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArrayKey {
+    /// An integer key. Negative integers are stored the same way as the engine stores them,
+    /// as their two's complement representation.
+    Long(u64),
+    /// A string key, containing the raw bytes of the key.
+    Str(Vec<u8>),
+}
+
+impl ArrayKey {
+    /// Returns the string key if it is valid UTF-8. Returns `None` for integer keys.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Long(_) => None,
+            Self::Str(key) => std::str::from_utf8(key).ok(),
+        }
+    }
+
+    /// Converts the key into a string the same way PHP converts array keys into strings,
+    /// replacing invalid UTF-8 sequences in string keys with the Unicode replacement
+    /// character. This is the same as formatting the key with [`Display`].
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        match self {
+            Self::Long(idx) => Cow::Owned((*idx as ZendLong).to_string()),
+            Self::Str(key) => String::from_utf8_lossy(key),
+        }
+    }
+
+    /// Returns the integer a string key is normalised into, equivalent to the
+    /// `ZEND_HANDLE_NUMERIC_STR` macro.
+    fn numeric(key: &[u8]) -> Option<ZendLong> {
+        let digits = key.strip_prefix(b"-").unwrap_or(key);
+
+        match digits {
+            [] => return None,
+            // Leading zeros are not allowed, and `-0` is not the same key as `0`.
+            [b'0', ..] if digits.len() > 1 || digits.len() < key.len() => return None,
+            _ if !digits.iter().all(u8::is_ascii_digit) => return None,
+            _ => {}
+        }
+
+        // Integers which do not fit in a long are kept as strings. The key only contains
+        // ASCII characters at this point.
+        std::str::from_utf8(key).ok()?.parse().ok()
+    }
+}
+
+impl From<Vec<u8>> for ArrayKey {
+    fn from(key: Vec<u8>) -> Self {
+        match Self::numeric(&key) {
+            Some(idx) => Self::Long(idx as u64),
+            None => Self::Str(key),
+        }
+    }
+}
+
+impl From<&[u8]> for ArrayKey {
+    fn from(key: &[u8]) -> Self {
+        match Self::numeric(key) {
+            Some(idx) => Self::Long(idx as u64),
+            None => Self::Str(key.to_vec()),
+        }
+    }
+}
+
+impl From<String> for ArrayKey {
+    fn from(key: String) -> Self {
+        key.into_bytes().into()
+    }
+}
+
+impl From<&String> for ArrayKey {
+    fn from(key: &String) -> Self {
+        key.as_bytes().into()
+    }
+}
+
+impl From<&str> for ArrayKey {
+    fn from(key: &str) -> Self {
+        key.as_bytes().into()
+    }
+}
+
+/// Formats the key the same way PHP converts array keys into strings. Invalid UTF-8
+/// sequences in string keys are replaced, see [`ArrayKey::to_string_lossy`].
+impl Display for ArrayKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Long(idx) => write!(f, "{}", *idx as ZendLong),
+            Self::Str(key) => write!(f, "{}", String::from_utf8_lossy(key)),
+        }
+    }
+}
+
+/// Implements [`ArrayKey`] for integer types. Negative integers are stored in the same way
+/// as the engine stores them, as their two's complement representation.
+macro_rules! array_key_index {
+    ($($t: ty),*) => {
+        $(
+            impl From<$t> for ArrayKey {
+                fn from(key: $t) -> Self {
+                    Self::Long(key as u64)
+                }
+            }
+        )*
+    };
+}
+
+array_key_index!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
 
 /// A PHP array, which internally is a hash table.
+///
+/// A `ZendHashTable` owns one reference to the array. Arrays created in Rust are freed when
+/// they are dropped, unless they are handed over to PHP, e.g. with [`Zval::set_array`].
+/// Arrays which are shared with PHP, such as those returned by [`ZendHashTable::shallow_clone`],
+/// only have their reference count decremented when dropped. Arrays borrowed from a zval are
+/// represented by [`HashTableRef`] and [`HashTableMut`] instead, which never release the
+/// array.
 pub struct ZendHashTable {
-    ptr: *mut HashTable,
-    free: bool,
+    pub(crate) ptr: *mut HashTable,
 }
 
 impl ZendHashTable {
@@ -27,7 +175,9 @@ impl ZendHashTable {
         Self::with_capacity(HT_MIN_SIZE)
     }
 
-    /// Creates a new, empty, PHP associative array with an initial size.
+    /// Creates a new, empty, PHP associative array with an initial size. The memory for the
+    /// elements is allocated when the first element is inserted, with enough space for `size`
+    /// elements, so the hash table does not need to grow until it is full.
     ///
     /// # Parameters
     ///
@@ -36,22 +186,86 @@ impl ZendHashTable {
         // SAFETY: PHP allocater handles the creation of the
         // array.
         let ptr = unsafe { _zend_new_array(size) };
-        Self { ptr, free: true }
+        Self { ptr }
     }
 
-    /// Creates a new hash table wrapper.
-    /// This _will not_ be freed when it goes out of scope in Rust.
+    /// Creates a new, empty, packed PHP array with space for `size` elements, for building
+    /// lists with [`ZendHashTable::push`]. See [`ZendHashTable::is_packed`].
     ///
     /// # Parameters
     ///
-    /// * `ptr` - The pointer of the actual hash table.
-    pub(crate) fn from_ptr(ptr: *mut HashTable) -> Self {
-        Self { ptr, free: false }
+    /// * `size` - The size to initialize the array with.
+    pub fn with_capacity_packed(size: u32) -> Self {
+        let ht = Self::with_capacity(size);
+        unsafe { zend_hash_real_init_packed(ht.ptr) };
+        ht
+    }
+
+    /// Grows the hash table so that at least `additional` more elements can be inserted
+    /// without growing it again, equivalent to `zend_hash_extend`. Packed hash tables stay
+    /// packed. A hash table without any memory allocated for its elements is initialised as
+    /// an associative array, unless it was created with
+    /// [`ZendHashTable::with_capacity_packed`].
+    ///
+    /// # Parameters
+    ///
+    /// * `additional` - The number of elements to reserve space for.
+    pub fn reserve(&mut self, additional: u32) {
+        self.debug_assert_unshared();
+
+        // Removed elements still occupy their buckets until the hash table is rehashed.
+        let size = unsafe { (*self.ptr).nNumUsed }.saturating_add(additional);
+        unsafe { zend_hash_extend(self.ptr, size, self.is_packed()) };
+    }
+
+    /// Creates a new, empty, persistent PHP associative array. Persistent arrays are
+    /// allocated outside of the request allocator, so they survive the end of a request and
+    /// can be used for module-global caches, e.g. built in the module startup function.
+    ///
+    /// Only values which are themselves persistent can be inserted into a persistent array:
+    /// null, booleans, integers, doubles, persistent or permanent interned strings (see
+    /// [`Zval::set_persistent_string`]) and immutable arrays, such as the empty array. Arrays
+    /// cannot be nested otherwise, as the engine cannot release refcounted arrays held by a
+    /// persistent array. Inserting any other value returns [`Error::NotPersistent`]. Values read from the array are owned by the array,
+    /// and must be copied, e.g. with [`Zval::string`], rather than handed over to PHP.
+    ///
+    /// A persistent array must not be returned to PHP with [`Zval::set_array`], as the engine
+    /// would free it with the request allocator.
+    pub fn new_persistent() -> Self {
+        Self::with_capacity_persistent(HT_MIN_SIZE)
+    }
+
+    /// Creates a new, empty, persistent PHP associative array with an initial size. See
+    /// [`ZendHashTable::new_persistent`].
+    ///
+    /// # Parameters
+    ///
+    /// * `size` - The size to initialize the array with.
+    pub fn with_capacity_persistent(size: u32) -> Self {
+        let ptr = unsafe { ext_php_rs_zend_new_persistent_array(size) };
+        Self { ptr }
+    }
+
+    /// Returns a new reference to the hash table, incrementing its reference count, the same
+    /// way as [`Zval::shallow_clone`]. The hash table is shared with the original until one
+    /// of them is separated, see [`Zval::array_mut`].
+    pub fn shallow_clone(&self) -> ZendHashTable {
+        unsafe {
+            if (*self.ptr).gc.u.type_info & GC_IMMUTABLE == 0 {
+                (*self.ptr).gc.refcount += 1;
+            }
+        }
+
+        Self { ptr: self.ptr }
     }
 
-    /// Returns the current number of elements in the array.
+    /// Returns the current number of elements in the array, equivalent to the
+    /// `zend_hash_num_elements` macro. Removed elements are not counted.
     pub fn len(&self) -> usize {
-        unsafe { *self.ptr }.nNumOfElements as usize
+        match unsafe { self.ptr.as_ref() } {
+            Some(ht) => ht.nNumOfElements as usize,
+            None => 0,
+        }
     }
 
     /// Returns whether the hash table is empty.
@@ -59,16 +273,99 @@ impl ZendHashTable {
         self.len() == 0
     }
 
-    /// Clears the hash table, removing all values.
+    /// Returns the index which the next value pushed onto the array will be inserted at, e.g.
+    /// by [`ZendHashTable::push`] or `$array[] = $value` in PHP. This is one more than the
+    /// largest integer key which was ever inserted into the array, or zero if there were none.
+    /// The index can be negative if all integer keys are negative.
+    pub fn next_free_index(&self) -> ZendLong {
+        match unsafe { self.ptr.as_ref() } {
+            // The engine marks arrays which never contained an integer key with the minimum
+            // value, in which case the next element is inserted at zero.
+            Some(ht) if ht.nNextFreeElement != ZendLong::MIN => ht.nNextFreeElement,
+            _ => 0,
+        }
+    }
+
+    /// Returns whether the hash table is allocated persistently, see
+    /// [`ZendHashTable::new_persistent`].
+    pub fn is_persistent(&self) -> bool {
+        unsafe { (*self.ptr).gc.u.type_info & IS_ARRAY_PERSISTENT != 0 }
+    }
+
+    /// Checks that the value can be stored in the hash table. Persistent hash tables outlive
+    /// the request, so they may only contain values which are not freed at the end of it.
+    fn check_value(&self, val: &Zval) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
+
+        let flags = match val.get_type() {
+            Some(
+                DataType::Null
+                | DataType::False
+                | DataType::True
+                | DataType::Long
+                | DataType::Double,
+            ) => return Ok(()),
+            Some(DataType::String) => unsafe {
+                (*val.value.str).gc.u.type_info & (IS_STR_PERSISTENT | IS_STR_PERMANENT)
+            },
+            // Nested persistent arrays would be refcounted, which the persistent destructor
+            // rejects, so only immutable arrays are accepted.
+            Some(DataType::Array) => unsafe {
+                (*val.value.arr).gc.u.type_info & IS_ARRAY_IMMUTABLE
+            },
+            _ => 0,
+        };
+
+        if flags != 0 {
+            Ok(())
+        } else {
+            Err(Error::NotPersistent)
+        }
+    }
+
+    /// Checks that the elements of another hash table can be copied into the hash table, see
+    /// [`ZendHashTable::check_value`]. String keys are shared with the other hash table, so
+    /// they must be persistent as well.
+    fn check_elements(&self, other: &ZendHashTable) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
+
+        for bucket in unsafe { buckets(other.ptr) } {
+            if bucket.val.is_undef() {
+                continue;
+            }
+
+            if let Some(key) = unsafe { bucket.key.as_ref() } {
+                if unsafe { key.gc.u.type_info } & (IS_STR_PERSISTENT | IS_STR_PERMANENT) == 0 {
+                    return Err(Error::NotPersistent);
+                }
+            }
+
+            self.check_value(&bucket.val)?;
+        }
+
+        Ok(())
+    }
+
+    /// Clears the hash table, releasing all values. The next index used by
+    /// [`ZendHashTable::push`] is reset to zero.
     pub fn clear(&mut self) {
+        if self.ptr.is_null() {
+            return;
+        }
+
+        self.debug_assert_unshared();
         unsafe { zend_hash_clean(self.ptr) }
     }
 
-    /// Attempts to retrieve a value from the hash table with a string key.
+    /// Attempts to retrieve a value from the hash table.
     ///
     /// # Parameters
     ///
-    /// * `key` - The key to search for in the hash table.
+    /// * `key` - The key to search for in the hash table, see [`ArrayKey`].
     ///
     /// # Returns
     ///
@@ -76,11 +373,80 @@ impl ZendHashTable {
     /// * `None` - No value at the given position was found.
     pub fn get<K>(&self, key: K) -> Option<&Zval>
     where
-        K: Into<String>,
+        K: Into<ArrayKey>,
+    {
+        if self.ptr.is_null() {
+            return None;
+        }
+
+        match key.into() {
+            ArrayKey::Long(idx) => self.get_index(idx),
+            // The key is passed with its length, see `insert`.
+            ArrayKey::Str(key) => unsafe {
+                zend_hash_str_find(self.ptr, key.as_ptr() as *const c_char, key.len() as u64)
+                    .as_ref()
+            },
+        }
+    }
+
+    /// Attempts to retrieve a value from the hash table with a key containing arbitrary
+    /// bytes, such as a key created with `pack` in PHP. Numeric keys are normalised as in
+    /// [`ZendHashTable::get`], which accepts byte slices as well.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The key to search for in the hash table.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(&Zval)` - A reference to the zval at the position in the hash table.
+    /// * `None` - No value at the given position was found.
+    pub fn get_bytes(&self, key: &[u8]) -> Option<&Zval> {
+        self.get(key)
+    }
+
+    /// Attempts to retrieve a value from the hash table, converted into the given type.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The key to search for in the hash table.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(T)` - The converted value at the position in the hash table.
+    /// * `None` - No value at the given position was found, or it could not be converted.
+    pub fn get_as<'a, T>(&'a self, key: impl Into<ArrayKey>) -> Option<T>
+    where
+        T: FromZval<'a>,
+    {
+        self.get(key).and_then(|val| T::from_zval(val).ok())
+    }
+
+    /// Returns whether the hash table contains the given key, equivalent to
+    /// `array_key_exists` in PHP. Unlike `isset`, a key containing `null` exists.
+    ///
+    /// This differs from `get(key).is_some()` for symbol tables and property tables, whose
+    /// slots can be indirect zvals pointing to an undefined value, e.g. after a property has
+    /// been unset. Such keys are reported as not existing, the same way PHP reports them.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The key to search for in the hash table.
+    pub fn has_key<K>(&self, key: K) -> bool
+    where
+        K: Into<ArrayKey>,
     {
-        let _key = key.into();
-        let len = _key.len();
-        unsafe { zend_hash_str_find(self.ptr, c_str(_key), len as u64).as_ref() }
+        matches!(self.get(key), Some(slot) if slot_exists(slot))
+    }
+
+    /// Returns whether the hash table contains the given index, equivalent to
+    /// `array_key_exists` in PHP. See [`ZendHashTable::has_key`].
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The index to search for in the hash table.
+    pub fn has_index(&self, key: u64) -> bool {
+        matches!(self.get_index(key), Some(slot) if slot_exists(slot))
     }
 
     /// Attempts to retrieve a value from the hash table with an index.
@@ -94,10 +460,31 @@ impl ZendHashTable {
     /// * `Some(&Zval)` - A reference to the zval at the position in the hash table.
     /// * `None` - No value at the given position was found.
     pub fn get_index(&self, key: u64) -> Option<&Zval> {
+        if self.ptr.is_null() {
+            return None;
+        }
+
         unsafe { zend_hash_index_find(self.ptr, key).as_ref() }
     }
 
-    /// Attempts to remove a value from the hash table with a string key.
+    /// Attempts to retrieve a value from the hash table with an index, converted into the
+    /// given type. See [`ZendHashTable::get_as`].
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The key to search for in the hash table.
+    pub fn get_index_as<'a, T>(&'a self, key: u64) -> Option<T>
+    where
+        T: FromZval<'a>,
+    {
+        self.get_index(key).and_then(|val| T::from_zval(val).ok())
+    }
+
+    /// Removes a value from the hash table, equivalent to `unset()` in PHP. The order of the
+    /// remaining elements is preserved.
+    ///
+    /// Elements of symbol tables which point to a variable are removed as `unset()` removes a
+    /// variable, by leaving the variable undefined.
     ///
     /// # Parameters
     ///
@@ -105,236 +492,1968 @@ impl ZendHashTable {
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - Key was successfully removed.
-    /// * `Err(())` - No key was removed, did not exist.
-    pub fn remove<K>(&self, key: K) -> Option<()>
+    /// * `Some(Zval)` - The removed value. Ownership of the value is transferred to the
+    ///   caller, so it must be handed over to PHP or released with [`Zval::release`].
+    /// * `None` - The key did not exist, and the hash table was not modified.
+    pub fn remove<K>(&mut self, key: K) -> Option<Zval>
     where
-        K: Into<String>,
+        K: Into<ArrayKey>,
     {
-        let _key = key.into();
-        let len = _key.len();
-        let result = unsafe { zend_hash_str_del(self.ptr, c_str(_key), len as u64) };
+        if self.ptr.is_null() {
+            return None;
+        }
 
-        if result < 0 {
-            None
-        } else {
-            Some(())
+        // The value is the first field of a bucket, so the zval found is the bucket holding it.
+        let bucket = match key.into() {
+            ArrayKey::Long(idx) => unsafe { zend_hash_index_find(self.ptr, idx) },
+            ArrayKey::Str(key) => unsafe {
+                zend_hash_str_find(self.ptr, key.as_ptr() as *const c_char, key.len() as u64)
+            },
+        } as *mut Bucket;
+
+        self.remove_bucket(bucket)
+    }
+
+    /// Removes the element held by a bucket of the hash table. See [`ZendHashTable::remove`].
+    fn remove_bucket(&mut self, bucket: *mut Bucket) -> Option<Zval> {
+        let val = &mut unsafe { bucket.as_mut() }?.val;
+
+        if let Some(var) = val.indirect() {
+            let var = unsafe { &mut *var };
+            if var.is_undef() {
+                return None;
+            }
+
+            // The same as `zend_hash_del_ind`, except that the value is moved out of the
+            // variable rather than released.
+            self.debug_assert_unshared();
+            let val = *var;
+            var.u1.type_info = IS_UNDEF;
+            unsafe { (*self.ptr).u.flags |= HASH_FLAG_HAS_EMPTY_IND };
+
+            return Some(val);
         }
+
+        let mut val = *val;
+        self.debug_assert_unshared();
+
+        // The hash table releases the value when it is deleted, so a reference is taken for
+        // the caller first.
+        val.addref();
+        unsafe { zend_hash_del_bucket(self.ptr, bucket) };
+
+        Some(val)
     }
 
-    /// Attempts to remove a value from the hash table with a string key.
+    /// Removes a value from the hash table with an index. See [`ZendHashTable::remove`].
     ///
     /// # Parameters
     ///
-    /// * `key` - The key to remove from the hash table.
+    /// * `key` - The index to remove from the hash table.
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - Key was successfully removed.
-    /// * `Err(())` - No key was removed, did not exist.
-    pub fn remove_index(&self, key: u64) -> Option<()> {
-        let result = unsafe { zend_hash_index_del(self.ptr, key) };
+    /// * `Some(Zval)` - The removed value, owned by the caller.
+    /// * `None` - The index did not exist, and the hash table was not modified.
+    pub fn remove_index(&mut self, key: u64) -> Option<Zval> {
+        self.remove(ArrayKey::Long(key))
+    }
 
-        if result < 0 {
-            None
-        } else {
-            Some(())
+    /// Removes the elements for which the predicate returns false, releasing their values.
+    /// The remaining elements keep their keys and order, as with `array_filter`.
+    ///
+    /// The hash table must not be shared with other zvals, see [`ZendHashTable::insert`].
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - Called with the key and value of each element, in insertion order. Returns
+    ///   whether the element is kept.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&ArrayKey, &Zval) -> bool,
+    {
+        self.debug_assert_unshared();
+
+        // Deleting a bucket only marks it as removed, so the positions of the other buckets
+        // do not change. Removed buckets at the end are no longer counted as used, so the
+        // number of used buckets is read again for each bucket.
+        let mut pos = 0;
+        while pos < unsafe { (*self.ptr).nNumUsed } as usize {
+            let bucket = unsafe { (*self.ptr).arData.add(pos) };
+            pos += 1;
+
+            let bucket_ref = unsafe { &*bucket };
+            if bucket_ref.val.is_undef() {
+                continue;
+            }
+
+            if !f(&bucket_key(bucket_ref), &bucket_ref.val) {
+                unsafe { zend_hash_del_bucket(self.ptr, bucket) };
+            }
+        }
+    }
+
+    /// Creates a new hash table containing the elements for which the predicate returns
+    /// true, equivalent to `array_filter` with `ARRAY_FILTER_USE_BOTH`. The elements keep
+    /// their keys, so the new hash table may have gaps in its integer keys.
+    ///
+    /// Values are shared with this hash table as in [`ZendHashTable::duplicate`]. The new
+    /// hash table is not persistent, even if this hash table is.
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - Called with the key and value of each element, in insertion order. Returns
+    ///   whether the element is copied.
+    pub fn filtered<F>(&self, mut f: F) -> ZendHashTable
+    where
+        F: FnMut(&ArrayKey, &Zval) -> bool,
+    {
+        let mut ht = ZendHashTable::new();
+
+        for (key, val) in self.iter() {
+            if !f(&key, val) {
+                continue;
+            }
+
+            // `zval_add_ref` also unwraps references which are not shared, as PHP does when
+            // copying array elements.
+            let mut copy = *val;
+            unsafe { zval_add_ref(&mut copy) };
+            ht.insert(key, copy)
+                .expect("inserting a zval into a new hash table cannot fail");
         }
+
+        ht
     }
 
-    /// Attempts to insert an item into the hash table, or update if the key already exists.
+    /// Inserts an item into the hash table, or updates it if the key already exists, the same
+    /// way as assigning to an array element in PHP. The previous value is released.
+    ///
+    /// The hash table must not be shared with other zvals, otherwise the change is visible
+    /// through all of them. Use [`Zval::array_mut`] to separate an array before modifying it.
     ///
     /// # Parameters
     ///
-    /// * `key` - The key to insert the value at in the hash table.
+    /// * `key` - The key to insert the value at in the hash table, see [`ArrayKey`].
     /// * `value` - The value to insert into the hash table.
     ///
     /// # Returns
     ///
-    /// * `Some(Zval)` - The existing value in the hash table that was overriden.
-    /// * `None` - The element was inserted.
-    pub fn insert<K, V>(&mut self, key: K, val: V) -> Option<&Zval>
+    /// * `Ok(true)` - The key already existed, and its value was replaced.
+    /// * `Ok(false)` - The key did not exist, and the element was inserted.
+    /// * `Err(Error)` - The value could not be converted into a zval, or could not be
+    ///   stored in a persistent hash table.
+    pub fn insert<K, V>(&mut self, key: K, val: V) -> Result<bool>
     where
-        K: Into<String>,
-        V: Into<Zval>,
+        K: Into<ArrayKey>,
+        V: IntoZval,
     {
-        let key: String = key.into();
-        let len = key.len();
-        let val: Zval = val.into();
-
-        let existing_ptr = unsafe {
-            zend_hash_str_update(
-                self.ptr,
-                c_str(key),
-                len as u64,
-                Box::into_raw(Box::new(val)), // Do we really want to allocate the value on the heap?
-                                              // I read somewhere that zvals are't usually (or never) allocated on the heap.
-            )
+        let key = match key.into() {
+            ArrayKey::Long(idx) => return self.insert_at_index(idx, val),
+            ArrayKey::Str(key) => key,
         };
+        let mut val = self.convert_value(val)?;
+        self.debug_assert_unshared();
+
+        // The key is passed with its length rather than as a C string, which allows keys
+        // containing NUL bytes. The value is copied into the bucket by the hash table, and
+        // the previous value is released by the destructor of the hash table.
+        unsafe {
+            let key_ptr = key.as_ptr() as *const c_char;
+            let existed = !zend_hash_str_find(self.ptr, key_ptr, key.len() as u64).is_null();
+            zend_hash_str_update(self.ptr, key_ptr, key.len() as u64, &mut val);
 
-        // Should we be claiming this Zval into rust?
-        // I'm not sure if the PHP GC will collect this.
-        unsafe { existing_ptr.as_ref() }
+            Ok(existed)
+        }
     }
 
-    /// Inserts an item into the hash table at a specified index,
-    /// or updates if the key already exists.
+    /// Inserts an item into the hash table with a key containing arbitrary bytes, or updates
+    /// it if the key already exists. See [`ZendHashTable::insert`], which accepts byte slices
+    /// as well.
     ///
     /// # Parameters
     ///
-    /// * `key` - The index at which the value should be inserted.
+    /// * `key` - The key to insert the value at. Numeric keys are normalised, see
+    ///   [`ArrayKey`].
     /// * `val` - The value to insert into the hash table.
     ///
     /// # Returns
     ///
-    /// * `Some(Zval)` - The existing value in the hash table that was overriden.
-    /// * `None` - The element was inserted.
-    pub fn insert_at_index<V>(&mut self, key: u64, val: V) -> Option<&Zval>
+    /// * `Ok(true)` - The key already existed, and its value was replaced.
+    /// * `Ok(false)` - The key did not exist, and the element was inserted.
+    /// * `Err(Error)` - The value could not be converted into a zval, or could not be
+    ///   stored in a persistent hash table.
+    pub fn insert_bytes<V>(&mut self, key: &[u8], val: V) -> Result<bool>
     where
-        V: Into<Zval>,
+        V: IntoZval,
     {
-        let val: Zval = val.into();
-
-        let existing_ptr =
-            unsafe { zend_hash_index_update(self.ptr, key, Box::into_raw(Box::new(val))) };
-
-        // See `insert` function comment.
-        unsafe { existing_ptr.as_ref() }
+        self.insert(key, val)
     }
 
-    /// Pushes an item onto the end of the hash table.
+    /// Inserts an item into the hash table at a specified index, or updates it if the index
+    /// already exists. See [`ZendHashTable::insert`].
     ///
     /// # Parameters
     ///
+    /// * `key` - The index at which the value should be inserted.
     /// * `val` - The value to insert into the hash table.
-    pub fn push<V>(&mut self, val: V)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - The index already existed, and its value was replaced.
+    /// * `Ok(false)` - The index did not exist, and the element was inserted.
+    /// * `Err(Error)` - The value could not be converted into a zval, or could not be
+    ///   stored in a persistent hash table.
+    pub fn insert_at_index<V>(&mut self, key: u64, val: V) -> Result<bool>
     where
-        V: Into<Zval>,
+        V: IntoZval,
     {
-        let val: Zval = val.into();
+        let mut val = self.convert_value(val)?;
+        self.debug_assert_unshared();
 
-        unsafe { zend_hash_next_index_insert(self.ptr, Box::into_raw(Box::new(val))) };
-    }
+        // The value is copied into the bucket, see `insert`.
+        unsafe {
+            let existed = !zend_hash_index_find(self.ptr, key).is_null();
+            zend_hash_index_update(self.ptr, key, &mut val);
 
-    /// Converts the hash table into a raw pointer to be passed to Zend.
-    pub(crate) fn into_ptr(mut self) -> *mut HashTable {
-        self.free = false;
-        self.ptr
+            Ok(existed)
+        }
     }
-}
 
-impl Default for ZendHashTable {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    /// Pushes an item onto the end of the hash table at the index given by
+    /// [`ZendHashTable::next_free_index`], equivalent to `$array[] = $value` in PHP.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to insert into the hash table.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The element was pushed.
+    /// * `Err(Error::NextElementOccupied)` - The array already contains an element at the
+    ///   largest possible index, so there is no next index. The value was released.
+    /// * `Err(Error)` - The value could not be converted into a zval, or could not be
+    ///   stored in a persistent hash table.
+    pub fn push<V>(&mut self, val: V) -> Result<()>
+    where
+        V: IntoZval,
+    {
+        let mut val = self.convert_value(val)?;
+        self.debug_assert_unshared();
 
-impl Drop for ZendHashTable {
-    fn drop(&mut self) {
-        if self.free {
-            unsafe { zend_array_destroy(self.ptr) };
-        }
-    }
-}
+        // The hash table copies the value into its own bucket, so the zval does not need to be
+        // allocated on the heap.
+        let result = unsafe { zend_hash_next_index_insert(self.ptr, &mut val) };
 
-impl IntoIterator for ZendHashTable {
-    type Item = (u64, Option<String>, Zval);
-    type IntoIter = Iter;
+        // If the insertion failed the hash table did not take ownership of the value,
+        // therefore we must release it ourselves.
+        if result.is_null() {
+            unsafe { zval_ptr_dtor(&mut val) };
+            return Err(Error::NextElementOccupied);
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        Self::IntoIter::new(self)
+        Ok(())
     }
-}
 
-/// Iterator for a Zend hashtable/array.
-pub struct Iter {
-    ht: ZendHashTable,
-    pos: *mut _Bucket,
-    end: *mut _Bucket,
-}
+    /// Returns the value with the given key, inserting the value returned by `default` if the
+    /// key does not exist. The hash table is only searched once, which makes this suitable for
+    /// aggregating values, e.g. counting how often each value occurs.
+    ///
+    /// The returned reference points into the buckets of the hash table, which are moved when
+    /// the hash table grows. It borrows the hash table mutably, so no elements can be inserted
+    /// until it is dropped. `default` is called after the element was inserted, and cannot
+    /// access the hash table either.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The key of the value, see [`ArrayKey`].
+    /// * `default` - Returns the value to insert. Only called if the key does not exist.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(&mut Zval)` - The existing or inserted value.
+    /// * `Err(Error::NotPersistent)` - The hash table is persistent, and the value returned by
+    ///   `default` is not. The value was released and nothing was inserted.
+    /// * `Err(Error)` - The key could not be allocated.
+    pub fn get_or_insert_with<K, F>(&mut self, key: K, default: F) -> Result<&mut Zval>
+    where
+        K: Into<ArrayKey>,
+        F: FnOnce() -> Zval,
+    {
+        let key = match key.into() {
+            ArrayKey::Long(idx) => return self.get_or_insert_index_with(idx, default),
+            ArrayKey::Str(key) => key,
+        };
+        self.debug_assert_unshared();
 
-impl Iter {
-    pub fn new(ht: ZendHashTable) -> Self {
-        let ptr = unsafe { *ht.ptr };
-        let pos = ptr.arData;
-        let end = unsafe { ptr.arData.offset(ptr.nNumUsed as isize) };
-        Self { ht, pos, end }
-    }
-}
+        let zs = ZendString::from_bytes(&key, self.is_persistent())?;
+        let len = self.len();
 
-impl Iterator for Iter {
-    type Item = (u64, Option<String>, Zval);
+        // Inserts a null value if the key does not exist, taking a reference to the key.
+        let slot = unsafe { zend_hash_lookup(self.ptr, zs.as_ptr()) };
+        drop(zs);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // iterator complete
-        if self.pos == self.end {
-            return None;
-        }
+        self.fill_slot(slot, self.len() > len, default, |ht| unsafe {
+            zend_hash_str_del(ht, key.as_ptr() as *const c_char, key.len() as u64);
+        })
+    }
 
-        let result = if let Some(val) = unsafe { self.pos.as_ref() } {
-            // SAFETY: We can ensure safety further by checking if it is null before
-            // converting it to a reference (val.key.as_ref() returns None if ptr == null)
-            let str_key: Option<String> = unsafe { val.key.as_ref() }.map(|key| key.into());
+    /// Returns the value with the given index, inserting the value returned by `default` if
+    /// the index does not exist. See [`ZendHashTable::get_or_insert_with`].
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The index of the value.
+    /// * `default` - Returns the value to insert. Only called if the index does not exist.
+    pub fn get_or_insert_index_with<F>(&mut self, key: u64, default: F) -> Result<&mut Zval>
+    where
+        F: FnOnce() -> Zval,
+    {
+        self.debug_assert_unshared();
 
-            Some((val.h, str_key, val.val))
-        } else {
-            None
-        };
+        let len = self.len();
+        let slot = unsafe { zend_hash_index_lookup(self.ptr, key) };
 
-        self.pos = unsafe { self.pos.offset(1) };
-        result
+        self.fill_slot(slot, self.len() > len, default, |ht| unsafe {
+            zend_hash_index_del(ht, key);
+        })
     }
 
-    fn count(self) -> usize
+    /// Sets a slot returned by `zend_hash_lookup` or `zend_hash_index_lookup` to the default
+    /// value if the lookup inserted it, in which case it holds null. If the default value
+    /// cannot be stored in the hash table, the inserted element is removed with `delete`.
+    fn fill_slot<F, D>(
+        &mut self,
+        slot: *mut Zval,
+        inserted: bool,
+        default: F,
+        delete: D,
+    ) -> Result<&mut Zval>
     where
-        Self: Sized,
+        F: FnOnce() -> Zval,
+        D: FnOnce(*mut HashTable),
     {
-        unsafe { *self.ht.ptr }.nNumOfElements as usize
-    }
-}
+        let slot = unsafe { slot.as_mut() }.ok_or(Error::InvalidPointer)?;
 
-/// Implementation converting a ZendHashTable into a Rust HashTable.
-impl<'a> From<ZendHashTable> for HashMap<String, Zval> {
-    fn from(zht: ZendHashTable) -> Self {
-        let mut hm = HashMap::new();
+        if inserted {
+            let val = default();
+
+            if let Err(e) = self.check_value(&val) {
+                val.release();
+                delete(self.ptr);
+                return Err(e);
+            }
 
-        for (idx, key, val) in zht.into_iter() {
-            hm.insert(key.unwrap_or_else(|| idx.to_string()), val);
+            *slot = val;
         }
 
-        hm
+        Ok(slot)
     }
-}
-
-/// Implementation converting a Rust HashTable into a ZendHashTable.
-impl<'a, K, V> From<HashMap<K, V>> for ZendHashTable
-where
-    K: Into<String>,
-    V: Into<Zval>,
-{
-    fn from(hm: HashMap<K, V>) -> Self {
-        let mut ht = ZendHashTable::with_capacity(hm.len() as u32);
 
-        for (k, v) in hm {
-            ht.insert(k.into(), v.into());
+    /// Copies the elements of another hash table into the hash table, keeping their keys.
+    /// Values are shared with the other hash table by incrementing their reference count.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The hash table to copy the elements from.
+    /// * `overwrite` - Whether elements with keys which exist in both hash tables are
+    ///   replaced by those of `other`, as in `array_replace`. If false, the existing elements
+    ///   are kept, as with the `+` operator in PHP.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The elements were copied.
+    /// * `Err(Error::NotPersistent)` - The hash table is persistent, and `other` contains
+    ///   keys or values which are not. Nothing was copied.
+    pub fn merge(&mut self, other: &ZendHashTable, overwrite: bool) -> Result<()> {
+        self.check_elements(other)?;
+        self.debug_assert_unshared();
+
+        unsafe { zend_hash_merge(self.ptr, other.ptr, Some(zval_add_ref), overwrite) };
+        Ok(())
+    }
+
+    /// Appends the elements of another hash table to the hash table, equivalent to
+    /// `array_merge`. Elements with integer keys are pushed onto the end of the hash table,
+    /// see [`ZendHashTable::push`], while elements with string keys replace the existing
+    /// elements with the same key. Values are shared as in [`ZendHashTable::merge`].
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The hash table to append the elements of.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The elements were appended.
+    /// * `Err(Error::NextElementOccupied)` - There are not enough free indexes left for the
+    ///   elements with integer keys. Nothing was appended.
+    /// * `Err(Error::NotPersistent)` - See [`ZendHashTable::merge`].
+    pub fn extend(&mut self, other: &ZendHashTable) -> Result<()> {
+        self.check_elements(other)?;
+
+        let indexed = unsafe { buckets(other.ptr) }
+            .iter()
+            .filter(|bucket| !bucket.val.is_undef() && bucket.key.is_null())
+            .count();
+
+        // Indexes are assigned sequentially from the next free index. The largest index may
+        // already be in use, as the next free index does not move past it.
+        if indexed > 0 {
+            let last = ZendLong::try_from(indexed - 1)
+                .ok()
+                .and_then(|n| self.next_free_index().checked_add(n));
+
+            match last {
+                Some(ZendLong::MAX) if self.has_index(ZendLong::MAX as u64) => {
+                    return Err(Error::NextElementOccupied)
+                }
+                None => return Err(Error::NextElementOccupied),
+                _ => {}
+            }
+        }
+
+        self.debug_assert_unshared();
+        unsafe { php_array_merge(self.ptr, other.ptr) };
+        Ok(())
+    }
+
+    /// Sorts the values of the hash table, equivalent to `sort` or `asort`. The sort is
+    /// stable, and values are compared in the same way as in PHP, including values of mixed
+    /// types.
+    ///
+    /// # Parameters
+    ///
+    /// * `flags` - How the values are compared.
+    /// * `preserve_keys` - Whether the keys are kept with their values, as in `asort`. If
+    ///   false, the elements are renumbered from zero, as in `sort`.
+    pub fn sort_values(&mut self, flags: SortFlags, preserve_keys: bool) {
+        self.debug_assert_unshared();
+        unsafe { ext_php_rs_zend_hash_sort_values(self.ptr, flags.bits() as _, !preserve_keys) };
+    }
+
+    /// Sorts the hash table by its keys, equivalent to `ksort` with the default flags.
+    pub fn sort_keys(&mut self) {
+        self.debug_assert_unshared();
+        unsafe { ext_php_rs_zend_hash_sort_keys(self.ptr) };
+    }
+
+    /// Sorts the values of the hash table with a comparison function, equivalent to `usort`.
+    /// The sort is stable, and the elements are renumbered from zero.
+    ///
+    /// # Parameters
+    ///
+    /// * `cmp` - Compares two values of the hash table. Values which are references are
+    ///   passed as references, see [`Zval::dereference`].
+    ///
+    /// # Panics
+    ///
+    /// If the comparison function panics, the remaining elements are compared as equal, and
+    /// the panic is resumed once the engine has finished sorting.
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&Zval, &Zval) -> Ordering,
+    {
+        self.debug_assert_unshared();
+
+        let mut state = SortCompare {
+            cmp: &mut cmp,
+            panic: None,
+        };
+        let state_ptr = &mut state as *mut SortCompare as *mut c_void;
+
+        // The comparison function may sort another hash table, so the outer comparison
+        // function is restored afterwards.
+        let prev = SORT_COMPARE.with(|current| current.replace(state_ptr));
+        unsafe { zend_hash_sort_ex(self.ptr, Some(zend_sort), Some(sort_compare), true) };
+        SORT_COMPARE.with(|current| current.set(prev));
+
+        if let Some(payload) = state.panic {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Returns an iterator over the keys and values of the hash table, in insertion order.
+    /// Elements which were removed are skipped.
+    ///
+    /// The internal pointer of the hash table, used by functions such as `next` and `reset`
+    /// in PHP, is neither used nor modified.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            buckets: unsafe { buckets(self.ptr) }.iter(),
+        }
+    }
+
+    /// Returns an iterator over the keys and values of the hash table in insertion order,
+    /// which allows the values to be modified in place. Elements which were removed are
+    /// skipped.
+    ///
+    /// If the hash table is shared with other variables it is separated first, see
+    /// [`Zval::array_mut`], so the changes are only visible through this hash table. Elements
+    /// cannot be inserted or removed while iterating, as the iterator borrows the hash table.
+    ///
+    /// Assigning to a value does not release the previous value, which must be released with
+    /// [`Zval::release`]. The `convert_to_*` methods of [`Zval`] release it themselves.
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        self.separate();
+
+        IterMut {
+            buckets: unsafe { buckets_mut(self.ptr) }.iter_mut(),
+        }
+    }
+
+    /// Returns the keys of the hash table in insertion order, equivalent to `array_keys`.
+    pub fn keys(&self) -> Vec<ArrayKey> {
+        self.iter().map(|(key, _)| key).collect()
+    }
+
+    /// Returns the values of the hash table in insertion order, equivalent to
+    /// `array_values`.
+    pub fn values(&self) -> Vec<&Zval> {
+        self.iter().map(|(_, val)| val).collect()
+    }
+
+    /// Returns the values of the hash table in insertion order, converted into the given
+    /// type.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<T>)` - The converted values.
+    /// * `Err(Error::ArrayElementConversion)` - The value with the given key could not be
+    ///   converted.
+    pub fn values_owned<T>(&self) -> Result<Vec<T>>
+    where
+        T: for<'a> FromZval<'a>,
+    {
+        self.iter()
+            .map(|(key, val)| {
+                T::from_zval(val).map_err(|_| Error::ArrayElementConversion(key.to_string()))
+            })
+            .collect()
+    }
+
+    /// Creates a copy of the hash table, equivalent to `zend_array_dup`. Reference counted
+    /// values are shared with the original hash table by incrementing their reference count,
+    /// and nested arrays are separated lazily when they are modified through
+    /// [`Zval::array_mut`].
+    ///
+    /// The copy is owned by Rust and freed when it is dropped, unless it is converted into a
+    /// zval, e.g. with [`Zval::set_array`].
+    pub fn duplicate(&self) -> ZendHashTable {
+        let ptr = unsafe { zend_array_dup(self.ptr) };
+        Self { ptr }
+    }
+
+    /// Creates a copy of the hash table, recursively copying nested arrays, so the copy can
+    /// be modified at any depth without separating each nested array first. Other values,
+    /// including references, are shared as in [`ZendHashTable::duplicate`].
+    pub fn deep_duplicate(&self) -> ZendHashTable {
+        let ht = self.duplicate();
+
+        for bucket in unsafe { buckets_mut(ht.ptr) } {
+            if !bucket.val.is_array() {
+                continue;
+            }
+
+            let copy = HashTableRef::from_ptr(unsafe { bucket.val.value.arr }).deep_duplicate();
+
+            // Only the value of the zval is replaced, as the rest of the bucket links it into
+            // the hash table. The nested array is still held by the original hash table, so
+            // releasing it only decrements its reference count.
+            unsafe { zval_ptr_dtor(&mut bucket.val) };
+            bucket.val.set_request_array(copy);
+        }
+
+        ht
+    }
+
+    /// Copies the elements of the hash table into a hash map. Integer keys are converted into
+    /// strings, the same way PHP does when casting array keys to strings.
+    ///
+    /// The values are shallow cloned, see [`Zval::shallow_clone`], so the map remains valid
+    /// after the hash table is freed. Each value must be released with [`Zval::release`]
+    /// once it is no longer used.
+    pub fn to_hashmap(&self) -> HashMap<String, Zval> {
+        let mut map = HashMap::with_capacity(self.len());
+
+        for (key, val) in self.iter() {
+            // Keys can only collide if a numeric string key was inserted without being
+            // normalised, in which case the later element wins as it would in PHP.
+            if let Some(prev) = map.insert(key.to_string(), val.shallow_clone()) {
+                prev.release();
+            }
+        }
+
+        map
+    }
+
+    /// Copies the elements of the hash table into a vector, if the hash table is a list:
+    /// its keys are the sequential integers starting at zero, in order. See
+    /// [`ZendHashTable::to_hashmap`] for how the values are copied.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Zval>)` - The values of the hash table.
+    /// * `Err(Error::ArrayElementConversion)` - The hash table is not a list. Contains the
+    ///   first key which is not in sequence.
+    pub fn to_vec(&self) -> Result<Vec<Zval>> {
+        // Check the keys first, so values are not cloned unless the conversion succeeds.
+        self.check_list()?;
+
+        Ok(self.iter().map(|(_, val)| val.shallow_clone()).collect())
+    }
+
+    /// Returns whether the hash table is packed: its elements are stored in order of their
+    /// integer keys, without a hash index. Lists created in PHP, e.g. `[1, 2, 3]` or with
+    /// `range`, are packed.
+    pub fn is_packed(&self) -> bool {
+        unsafe { (*self.ptr).u.flags & HASH_FLAG_PACKED != 0 }
+    }
+
+    /// Converts the values of the hash table into integers, if the hash table is a list.
+    ///
+    /// Packed hash tables containing only integers are converted directly from their
+    /// buckets, which is much faster for large lists than converting each value through
+    /// [`FromZval`]. Other hash tables fall back to the generic conversion.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<ZendLong>)` - The values of the hash table.
+    /// * `Err(Error::ArrayElementConversion)` - The hash table is not a list, or a value is
+    ///   not an integer. Contains the key of the offending element.
+    pub fn to_vec_long(&self) -> Result<Vec<ZendLong>> {
+        self.to_vec_with(|val| {
+            if val.is_long() {
+                Some(unsafe { val.value.lval })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Converts the values of the hash table into floats, if the hash table is a list.
+    /// Integers are converted into floats, as in [`Zval::double`]. See
+    /// [`ZendHashTable::to_vec_long`].
+    pub fn to_vec_double(&self) -> Result<Vec<f64>> {
+        self.to_vec_with(|val| {
+            if val.is_double() {
+                Some(unsafe { val.value.dval })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Converts the values of the hash table into strings, if the hash table is a list.
+    /// Strings which are not valid UTF-8 cannot be converted. See
+    /// [`ZendHashTable::to_vec_long`].
+    pub fn to_vec_string(&self) -> Result<Vec<String>> {
+        self.to_vec_with(|val| if val.is_string() { val.string() } else { None })
+    }
+
+    /// Converts the values of a list into the given type.
+    ///
+    /// Packed hash tables without removed elements are always lists, so their values are
+    /// read directly from the buckets with `fast`, which returns `None` for values it cannot
+    /// convert. If any value cannot be converted this way, or the hash table is not packed,
+    /// the keys are checked and each value is converted through [`FromZval`] instead, which
+    /// also follows references.
+    fn to_vec_with<T, F>(&self, fast: F) -> Result<Vec<T>>
+    where
+        T: for<'a> FromZval<'a>,
+        F: Fn(&Zval) -> Option<T>,
+    {
+        let ht = unsafe { &*self.ptr };
+
+        if self.is_packed() && ht.nNumUsed == ht.nNumOfElements {
+            let vec: Option<Vec<T>> = unsafe { buckets(self.ptr) }
+                .iter()
+                .map(|bucket| fast(&bucket.val))
+                .collect();
+
+            if let Some(vec) = vec {
+                return Ok(vec);
+            }
+        }
+
+        self.check_list()?;
+        self.values_owned()
+    }
+
+    /// Creates a packed hash table containing the values of the iterator, with sequential
+    /// integer keys starting at zero. The hash table is allocated using the iterator's size
+    /// hint, see [`ZendHashTable::with_capacity_packed`].
+    ///
+    /// # Parameters
+    ///
+    /// * `iter` - The values to insert into the hash table.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendHashTable)` - The new hash table.
+    /// * `Err(Error)` - A value could not be converted into a zval. Values which were already
+    ///   inserted are released along with the hash table.
+    pub fn try_from_iter<I, V>(iter: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = V>,
+        V: IntoZval,
+    {
+        let iter = iter.into_iter();
+        let mut ht = Self::for_iter(&iter, true);
+
+        for val in iter {
+            ht.push(val)?;
+        }
+
+        Ok(ht)
+    }
+
+    /// Creates a hash table with enough capacity for the lower bound of the iterator's size
+    /// hint, which is packed if the elements are pushed onto it.
+    pub(crate) fn for_iter<I: Iterator>(iter: &I, packed: bool) -> Self {
+        let (lower, _) = iter.size_hint();
+        let size = u32::try_from(lower).unwrap_or(u32::MAX);
+
+        if packed {
+            Self::with_capacity_packed(size)
+        } else {
+            Self::with_capacity(size)
+        }
+    }
+
+    /// Separates the hash table from the other references to it if it is shared or immutable,
+    /// by replacing it with a copy, equivalent to the `SEPARATE_ARRAY` macro.
+    ///
+    /// # Returns
+    ///
+    /// Whether the hash table was copied.
+    pub(crate) fn separate(&mut self) -> bool {
+        unsafe {
+            let ht = &mut *self.ptr;
+
+            // Immutable arrays always have a reference count of two, so they are separated
+            // here as well. Their reference count is never modified.
+            if ht.gc.refcount <= 1 {
+                return false;
+            }
+
+            let copy = zend_array_dup(self.ptr);
+            if ht.gc.u.type_info & GC_IMMUTABLE == 0 {
+                ht.gc.refcount -= 1;
+            }
+            self.ptr = copy;
+
+            true
+        }
+    }
+
+    /// Checks that the hash table is not shared with other zvals before it is modified.
+    fn debug_assert_unshared(&self) {
+        debug_assert!(
+            unsafe { (*self.ptr).gc.refcount } <= 1,
+            "shared arrays must be separated before being modified"
+        );
+    }
+
+    /// Converts a value into a zval to be inserted into the hash table, releasing it if it
+    /// cannot be stored in the hash table.
+    fn convert_value<V: IntoZval>(&self, val: V) -> Result<Zval> {
+        let mut val = val.into_zval()?;
+
+        if let Err(e) = self.check_value(&val) {
+            unsafe { zval_ptr_dtor(&mut val) };
+            return Err(e);
+        }
+
+        Ok(val)
+    }
+
+    /// Checks that the keys of the hash table are the sequential integers starting at zero,
+    /// returning the first key which is not in sequence.
+    fn check_list(&self) -> Result<()> {
+        match self
+            .iter()
+            .enumerate()
+            .find(|(i, (key, _))| *key != ArrayKey::Long(*i as u64))
+        {
+            Some((_, (key, _))) => Err(Error::ArrayElementConversion(key.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Converts the hash table into a raw pointer to be passed to Zend, transferring the
+    /// reference owned by the hash table.
+    pub(crate) fn into_ptr(self) -> *mut HashTable {
+        let ptr = self.ptr;
+        mem::forget(self);
+        ptr
+    }
+
+    /// Takes over a reference to the hash table behind the pointer, the reverse of
+    /// [`ZendHashTable::into_ptr`].
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid hash table, and the caller must own the reference,
+    /// which is released when the returned hash table is dropped.
+    pub(crate) unsafe fn from_raw(ptr: *mut HashTable) -> Self {
+        Self { ptr }
+    }
+}
+
+/// A PHP array borrowed from a zval, see [`Zval::array`]. Dereferences to a
+/// [`ZendHashTable`] which can only be read from, and is not released when dropped.
+pub struct HashTableRef<'a> {
+    ht: ManuallyDrop<ZendHashTable>,
+    _marker: PhantomData<&'a ZendHashTable>,
+}
+
+impl HashTableRef<'_> {
+    /// Borrows the hash table behind the pointer.
+    ///
+    /// # Parameters
+    ///
+    /// * `ptr` - The pointer of the actual hash table.
+    pub(crate) fn from_ptr(ptr: *mut HashTable) -> Self {
+        Self {
+            ht: ManuallyDrop::new(ZendHashTable { ptr }),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Deref for HashTableRef<'_> {
+    type Target = ZendHashTable;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ht
+    }
+}
+
+impl<'a> IntoIterator for &'a HashTableRef<'_> {
+    type Item = (ArrayKey, &'a Zval);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A PHP array mutably borrowed from a zval, see [`Zval::array_mut`]. Dereferences to a
+/// [`ZendHashTable`] for reading, provides the methods which modify the hash table in place,
+/// and is not released when dropped.
+///
+/// The hash table itself is never exposed mutably, as it could then be swapped out of the
+/// zval and released while it is still borrowed.
+pub struct HashTableMut<'a> {
+    ht: ManuallyDrop<ZendHashTable>,
+    _marker: PhantomData<&'a mut ZendHashTable>,
+}
+
+impl HashTableMut<'_> {
+    /// Mutably borrows the hash table behind the pointer. The hash table must not be shared.
+    ///
+    /// # Parameters
+    ///
+    /// * `ptr` - The pointer of the actual hash table.
+    pub(crate) fn from_ptr(ptr: *mut HashTable) -> Self {
+        Self {
+            ht: ManuallyDrop::new(ZendHashTable { ptr }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Separates the hash table if it is shared, see [`ZendHashTable::separate`].
+    pub(crate) fn separate(&mut self) -> bool {
+        self.ht.separate()
+    }
+
+    /// See [`ZendHashTable::reserve`].
+    pub fn reserve(&mut self, additional: u32) {
+        self.ht.reserve(additional)
+    }
+
+    /// See [`ZendHashTable::clear`].
+    pub fn clear(&mut self) {
+        self.ht.clear()
+    }
+
+    /// See [`ZendHashTable::remove`].
+    pub fn remove<K>(&mut self, key: K) -> Option<Zval>
+    where
+        K: Into<ArrayKey>,
+    {
+        self.ht.remove(key)
+    }
+
+    /// See [`ZendHashTable::remove_index`].
+    pub fn remove_index(&mut self, key: u64) -> Option<Zval> {
+        self.ht.remove_index(key)
+    }
+
+    /// See [`ZendHashTable::retain`].
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&ArrayKey, &Zval) -> bool,
+    {
+        self.ht.retain(f)
+    }
+
+    /// See [`ZendHashTable::insert`].
+    pub fn insert<K, V>(&mut self, key: K, val: V) -> Result<bool>
+    where
+        K: Into<ArrayKey>,
+        V: IntoZval,
+    {
+        self.ht.insert(key, val)
+    }
+
+    /// See [`ZendHashTable::insert_bytes`].
+    pub fn insert_bytes<V>(&mut self, key: &[u8], val: V) -> Result<bool>
+    where
+        V: IntoZval,
+    {
+        self.ht.insert_bytes(key, val)
+    }
+
+    /// See [`ZendHashTable::insert_at_index`].
+    pub fn insert_at_index<V>(&mut self, key: u64, val: V) -> Result<bool>
+    where
+        V: IntoZval,
+    {
+        self.ht.insert_at_index(key, val)
+    }
+
+    /// See [`ZendHashTable::push`].
+    pub fn push<V>(&mut self, val: V) -> Result<()>
+    where
+        V: IntoZval,
+    {
+        self.ht.push(val)
+    }
+
+    /// See [`ZendHashTable::get_or_insert_with`].
+    pub fn get_or_insert_with<K, F>(&mut self, key: K, default: F) -> Result<&mut Zval>
+    where
+        K: Into<ArrayKey>,
+        F: FnOnce() -> Zval,
+    {
+        self.ht.get_or_insert_with(key, default)
+    }
+
+    /// See [`ZendHashTable::get_or_insert_index_with`].
+    pub fn get_or_insert_index_with<F>(&mut self, key: u64, default: F) -> Result<&mut Zval>
+    where
+        F: FnOnce() -> Zval,
+    {
+        self.ht.get_or_insert_index_with(key, default)
+    }
+
+    /// See [`ZendHashTable::merge`].
+    pub fn merge(&mut self, other: &ZendHashTable, overwrite: bool) -> Result<()> {
+        self.ht.merge(other, overwrite)
+    }
+
+    /// See [`ZendHashTable::extend`].
+    pub fn extend(&mut self, other: &ZendHashTable) -> Result<()> {
+        self.ht.extend(other)
+    }
+
+    /// See [`ZendHashTable::sort_values`].
+    pub fn sort_values(&mut self, flags: SortFlags, preserve_keys: bool) {
+        self.ht.sort_values(flags, preserve_keys)
+    }
+
+    /// See [`ZendHashTable::sort_keys`].
+    pub fn sort_keys(&mut self) {
+        self.ht.sort_keys()
+    }
+
+    /// See [`ZendHashTable::sort_by`].
+    pub fn sort_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&Zval, &Zval) -> Ordering,
+    {
+        self.ht.sort_by(cmp)
+    }
+
+    /// See [`ZendHashTable::iter_mut`].
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        self.ht.iter_mut()
+    }
+}
+
+impl Deref for HashTableMut<'_> {
+    type Target = ZendHashTable;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ht
+    }
+}
+
+impl<'a> IntoIterator for &'a HashTableMut<'_> {
+    type Item = (ArrayKey, &'a Zval);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut HashTableMut<'_> {
+    type Item = (ArrayKey, &'a mut Zval);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Builds a PHP array, which can be nested inside other builders to construct nested arrays.
+/// Errors are collected while building, and the first one is returned from
+/// [`ArrayBuilder::build`], so elements can be chained without handling each one.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::php::types::array::ArrayBuilder;
+///
+/// let items = vec![1, 2, 3];
+/// let arr = ArrayBuilder::new()
+///     .insert("meta", ArrayBuilder::new().insert("page", 1))
+///     .insert("items", items)
+///     .build();
+/// ```
+pub struct ArrayBuilder {
+    ht: ZendHashTable,
+    error: Option<Error>,
+}
+
+impl ArrayBuilder {
+    /// Creates a new builder for an empty array.
+    pub fn new() -> Self {
+        Self::with_capacity(HT_MIN_SIZE)
+    }
+
+    /// Creates a new builder for an empty array with an initial size.
+    ///
+    /// # Parameters
+    ///
+    /// * `size` - The size to initialize the array with.
+    pub fn with_capacity(size: u32) -> Self {
+        Self {
+            ht: ZendHashTable::with_capacity(size),
+            error: None,
+        }
+    }
+
+    /// Inserts a value into the array with the given key, or updates it if the key already
+    /// exists. See [`ZendHashTable::insert`].
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The string or integer key to insert the value at.
+    /// * `val` - The value to insert, which may be another builder.
+    pub fn insert<K, V>(mut self, key: K, val: V) -> Self
+    where
+        K: Into<ArrayKey>,
+        V: IntoZval,
+    {
+        let result = match self.error {
+            None => self.ht.insert(key, val).map(|_| ()),
+            Some(_) => val.into_zval().map(Zval::release),
+        };
+
+        self.record(result)
+    }
+
+    /// Pushes a value onto the end of the array. See [`ZendHashTable::push`].
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to push, which may be another builder.
+    pub fn push<V>(mut self, val: V) -> Self
+    where
+        V: IntoZval,
+    {
+        let result = match self.error {
+            None => self.ht.push(val),
+            Some(_) => val.into_zval().map(Zval::release),
+        };
+
+        self.record(result)
+    }
+
+    /// Records the first error that occurred. Values given after an error are released
+    /// rather than inserted.
+    fn record(mut self, result: Result<()>) -> Self {
+        if let (None, Err(e)) = (&self.error, result) {
+            self.error = Some(e);
+        }
+
+        self
+    }
+
+    /// Builds the array.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendHashTable)` - The built array.
+    /// * `Err(Error)` - The first error that occurred while inserting a value. The values
+    ///   which were already inserted are released.
+    pub fn build(self) -> Result<ZendHashTable> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.ht),
         }
+    }
+}
 
-        ht
+impl Default for ArrayBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the array and converts it into a zval, so builders can be nested.
+impl IntoZval for ArrayBuilder {
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_request_array(self.build()?);
+        Ok(())
+    }
+}
+
+impl Default for ZendHashTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Releases the reference owned by the hash table, freeing the hash table if it was the last
+/// reference.
+impl Drop for ZendHashTable {
+    fn drop(&mut self) {
+        if self.ptr.is_null() {
+            return;
+        }
+
+        let gc = unsafe { &mut (*self.ptr).gc };
+
+        if unsafe { gc.u.type_info } & GC_IMMUTABLE != 0 {
+            return;
+        }
+
+        if gc.refcount > 1 {
+            gc.refcount -= 1;
+        } else if self.is_persistent() {
+            unsafe { ext_php_rs_zend_persistent_array_destroy(self.ptr) };
+        } else {
+            unsafe { zend_array_destroy(self.ptr) };
+        }
+    }
+}
+
+impl IntoIterator for ZendHashTable {
+    type Item = (ArrayKey, Zval);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Self::IntoIter::new(self)
+    }
+}
+
+impl<'a> IntoIterator for &'a ZendHashTable {
+    type Item = (ArrayKey, &'a Zval);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ZendHashTable {
+    type Item = (ArrayKey, &'a mut Zval);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Returns the buckets of a hash table which are in use, including deleted buckets.
+///
+/// # Safety
+///
+/// The pointer must point to a valid hash table, which must not be modified while the
+/// returned slice is alive.
+unsafe fn buckets<'a>(ht: *const HashTable) -> &'a [_Bucket] {
+    let ht = &*ht;
+
+    if ht.arData.is_null() || ht.nNumUsed == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ht.arData, ht.nNumUsed as usize)
+    }
+}
+
+/// Returns the buckets of a hash table which are in use, for modifying their values in place.
+///
+/// # Safety
+///
+/// See [`buckets`]. The hash table must not be shared.
+unsafe fn buckets_mut<'a>(ht: *mut HashTable) -> &'a mut [_Bucket] {
+    let ht = &mut *ht;
+
+    if ht.arData.is_null() || ht.nNumUsed == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(ht.arData, ht.nNumUsed as usize)
+    }
+}
+
+thread_local! {
+    /// The state of the current call to [`ZendHashTable::sort_by`], as a pointer to a
+    /// [`SortCompare`]. The bucket comparison callback cannot be given any other data.
+    static SORT_COMPARE: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// The state of a call to [`ZendHashTable::sort_by`], read by [`sort_compare`].
+struct SortCompare<'a> {
+    /// The comparison function given to [`ZendHashTable::sort_by`].
+    cmp: &'a mut dyn FnMut(&Zval, &Zval) -> Ordering,
+    /// The panic raised by the comparison function, which cannot unwind through the engine,
+    /// so it is resumed once the engine has returned.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// Compares two buckets with the comparison function given to [`ZendHashTable::sort_by`].
+/// Elements which compare equal are ordered by their original position, which is stored in
+/// the buckets by `zend_hash_sort_ex`, as `usort` does. Once the comparison function has
+/// panicked, it is not called again and all elements compare equal.
+unsafe extern "C" fn sort_compare(a: *mut Bucket, b: *mut Bucket) -> c_int {
+    let state = &mut *(SORT_COMPARE.with(Cell::get) as *mut SortCompare);
+    let (a, b) = (&*a, &*b);
+
+    if state.panic.is_some() {
+        return 0;
+    }
+
+    let cmp = &mut state.cmp;
+    let ordering = match panic::catch_unwind(AssertUnwindSafe(|| cmp(&a.val, &b.val))) {
+        Ok(ordering) => ordering,
+        Err(payload) => {
+            state.panic = Some(payload);
+            return 0;
+        }
+    };
+
+    match ordering.then_with(|| a.val.u2.extra.cmp(&b.val.u2.extra)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+/// Returns the key of a bucket which is in use.
+fn bucket_key(bucket: &_Bucket) -> ArrayKey {
+    match unsafe { bucket.key.as_ref() } {
+        Some(key) => ArrayKey::Str(key.as_bytes().to_vec()),
+        None => ArrayKey::Long(bucket.h),
+    }
+}
+
+/// Returns whether a slot found in a hash table holds a value, following indirect slots.
+fn slot_exists(slot: &Zval) -> bool {
+    let val = if slot.is_indirect() {
+        unsafe { &*slot.value.zv }
+    } else {
+        slot
+    };
+
+    !val.is_undef()
+}
+
+/// Owning iterator for a Zend hashtable/array, see [`ZendHashTable::into_iter`]. The values
+/// are shallow cloned, see [`Zval::shallow_clone`], so they remain valid after the hash table
+/// is released, and must be released with [`Zval::release`] once they are no longer used.
+pub struct IntoIter {
+    ht: ZendHashTable,
+    pos: usize,
+}
+
+impl IntoIter {
+    pub fn new(ht: ZendHashTable) -> Self {
+        Self { ht, pos: 0 }
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = (ArrayKey, Zval);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buckets = unsafe { buckets(self.ht.ptr) };
+
+        for bucket in &buckets[self.pos..] {
+            self.pos += 1;
+
+            if bucket.val.is_undef() {
+                continue;
+            }
+
+            return Some((bucket_key(bucket), bucket.val.shallow_clone()));
+        }
+
+        None
+    }
+
+    fn count(self) -> usize
+    where
+        Self: Sized,
+    {
+        let buckets = unsafe { buckets(self.ht.ptr) };
+        buckets[self.pos..]
+            .iter()
+            .filter(|bucket| !bucket.val.is_undef())
+            .count()
+    }
+}
+
+/// Borrowing iterator for a Zend hashtable/array, see [`ZendHashTable::iter`].
+pub struct Iter<'a> {
+    buckets: slice::Iter<'a, _Bucket>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (ArrayKey, &'a Zval);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buckets
+            .find(|bucket| !bucket.val.is_undef())
+            .map(|bucket| (bucket_key(bucket), &bucket.val))
+    }
+}
+
+/// Mutable borrowing iterator for a Zend hashtable/array, see [`ZendHashTable::iter_mut`].
+pub struct IterMut<'a> {
+    buckets: slice::IterMut<'a, _Bucket>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (ArrayKey, &'a mut Zval);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buckets
+            .find(|bucket| !bucket.val.is_undef())
+            .map(|bucket| (bucket_key(bucket), &mut bucket.val))
+    }
+}
+
+/// Implementation converting a ZendHashTable into a Rust HashTable. See
+/// [`ZendHashTable::to_hashmap`].
+impl From<ZendHashTable> for HashMap<String, Zval> {
+    fn from(zht: ZendHashTable) -> Self {
+        zht.to_hashmap()
+    }
+}
+
+/// Converts a hash table into a hash map. See [`ZendHashTable::to_hashmap`].
+impl From<&ZendHashTable> for HashMap<String, Zval> {
+    fn from(zht: &ZendHashTable) -> Self {
+        zht.to_hashmap()
+    }
+}
+
+/// Converts a hash table into a vector. See [`ZendHashTable::to_vec`].
+impl TryFrom<&ZendHashTable> for Vec<Zval> {
+    type Error = Error;
+
+    fn try_from(zht: &ZendHashTable) -> Result<Self> {
+        zht.to_vec()
+    }
+}
+
+/// Implementation converting a Rust HashTable into a ZendHashTable.
+impl<K, V> TryFrom<HashMap<K, V>> for ZendHashTable
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    type Error = Error;
+    fn try_from(hm: HashMap<K, V>) -> Result<Self> {
+        let mut ht = ZendHashTable::with_capacity(hm.len() as u32);
+
+        for (k, v) in hm {
+            ht.insert(k, v)?;
+        }
+
+        Ok(ht)
+    }
+}
+
+/// Implementation converting a Rust BTreeMap into a ZendHashTable.
+impl<K, V> TryFrom<BTreeMap<K, V>> for ZendHashTable
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    type Error = Error;
+    fn try_from(map: BTreeMap<K, V>) -> Result<Self> {
+        let mut ht = ZendHashTable::with_capacity(map.len() as u32);
+
+        for (k, v) in map {
+            ht.insert(k, v)?;
+        }
+
+        Ok(ht)
+    }
+}
+
+/// Converts a vector into a packed hash table with sequential integer keys. Ownership of each
+/// element is transferred to the hash table. See [`ZendHashTable::try_from_iter`].
+impl<T> TryFrom<Vec<T>> for ZendHashTable
+where
+    T: IntoZval,
+{
+    type Error = Error;
+    fn try_from(vec: Vec<T>) -> Result<Self> {
+        Self::try_from_iter(vec)
+    }
+}
+
+/// Collects values into a packed hash table with sequential integer keys, see
+/// [`ZendHashTable::try_from_iter`]. If the iterator panics, the values which were already
+/// collected are released along with the hash table.
+impl<T> FromIterator<T> for ZendHashTable
+where
+    T: Into<Zval>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        // Zvals are moved into the new hash table as they are, which cannot fail.
+        Self::try_from_iter(iter.into_iter().map(Into::into))
+            .expect("pushing a zval onto a new hash table failed")
     }
 }
 
-/// Implementation for converting a Rust Vec into a ZendHashTable.
-impl<'a, V> From<Vec<V>> for ZendHashTable
+/// Collects key-value pairs into an associative hash table. Later pairs replace earlier
+/// pairs with the same key, see [`ZendHashTable::insert`]. If the iterator panics, the values
+/// which were already collected are released along with the hash table.
+impl<K, V> FromIterator<(K, V)> for ZendHashTable
 where
+    K: Into<ArrayKey>,
     V: Into<Zval>,
 {
-    fn from(vec: Vec<V>) -> Self {
-        let mut ht = ZendHashTable::with_capacity(vec.len() as u32);
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut ht = Self::for_iter(&iter, false);
 
-        for v in vec {
-            ht.push(v);
+        for (key, val) in iter {
+            ht.insert(key, val.into())
+                .expect("inserting a zval into a new hash table failed");
         }
 
         ht
     }
 }
+
+/// Converts a slice of zvals into a packed hash table with sequential integer keys. Each
+/// element is shallow cloned, see [`Zval::shallow_clone`], so the slice keeps its own
+/// references to the values.
+impl From<&[Zval]> for ZendHashTable {
+    fn from(vals: &[Zval]) -> Self {
+        vals.iter().map(Zval::shallow_clone).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, ptr};
+
+    use super::*;
+    use crate::{
+        bindings::{IS_ARRAY_EX, IS_INDIRECT},
+        php::types::fixtures::{bucket, fake_array, fake_string, hash_table},
+    };
+
+    fn collect(ht: &mut HashTable) -> Vec<(ArrayKey, Option<ZendLong>)> {
+        HashTableRef::from_ptr(ht)
+            .iter()
+            .map(|(key, val)| (key, val.long()))
+            .collect()
+    }
+
+    #[test]
+    fn test_get_null() {
+        let ht = HashTableRef::from_ptr(ptr::null_mut());
+
+        assert!(ht.get("key").is_none());
+        assert!(ht.get_index(0).is_none());
+        assert_eq!(ht.len(), 0);
+        assert!(ht.is_empty());
+        assert_eq!(ht.next_free_index(), 0);
+        assert_eq!(ht.get_as::<ZendLong>("key"), None);
+    }
+
+    #[test]
+    fn test_slot_exists() {
+        let mut null: Zval = unsafe { mem::zeroed() };
+        null.set_null();
+        assert!(slot_exists(&null));
+
+        let undef: Zval = unsafe { mem::zeroed() };
+        assert!(!slot_exists(&undef));
+
+        let mut indirect: Zval = unsafe { mem::zeroed() };
+        indirect.value.zv = &undef as *const Zval as *mut Zval;
+        indirect.u1.type_info = IS_INDIRECT;
+        assert!(!slot_exists(&indirect));
+
+        indirect.value.zv = &null as *const Zval as *mut Zval;
+        assert!(slot_exists(&indirect));
+    }
+
+    #[test]
+    fn test_remove_indirect() {
+        let mut var: Zval = unsafe { mem::zeroed() };
+        var.set_long(5);
+        let mut buckets = [bucket(0, None, None)];
+        buckets[0].val.value.zv = &mut var;
+        buckets[0].val.u1.type_info = IS_INDIRECT;
+        let mut ht = hash_table(&mut buckets, 0);
+        ht.gc.refcount = 1;
+        let first = ht.arData;
+
+        // The variable is left undefined, and the bucket still points to it.
+        let removed = HashTableMut::from_ptr(&mut ht).ht.remove_bucket(first);
+        assert_eq!(removed.and_then(|val| val.long()), Some(5));
+        assert!(var.is_undef());
+        assert!(buckets[0].val.is_indirect());
+        assert_ne!(unsafe { ht.u.flags } & HASH_FLAG_HAS_EMPTY_IND, 0);
+
+        let mut wrapper = HashTableMut::from_ptr(&mut ht);
+        assert!(wrapper.ht.remove_bucket(first).is_none());
+        assert!(wrapper.ht.remove_bucket(ptr::null_mut()).is_none());
+    }
+
+    #[test]
+    fn test_iter_packed() {
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, Some(2))];
+        let mut ht = hash_table(&mut buckets, HASH_FLAG_PACKED);
+
+        assert_eq!(
+            collect(&mut ht),
+            vec![(ArrayKey::Long(0), Some(1)), (ArrayKey::Long(1), Some(2))]
+        );
+
+        let ht = HashTableRef::from_ptr(&mut ht);
+        let vec = Vec::<Zval>::try_from(&*ht).unwrap();
+        assert_eq!(
+            vec.iter().map(Zval::long).collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+    }
+
+    #[test]
+    fn test_merge_persistent() {
+        let mut key = fake_string(b"a", 0, 0);
+        let mut buckets = [bucket(0, Some(&mut key), Some(1))];
+        let mut other = hash_table(&mut buckets, 0);
+        let other = HashTableRef::from_ptr(&mut other);
+
+        let mut persistent = hash_table(&mut [], 0);
+        persistent.gc.u.type_info = IS_ARRAY_PERSISTENT;
+        let mut ht = HashTableMut::from_ptr(&mut persistent);
+
+        assert_eq!(ht.merge(&other, true), Err(Error::NotPersistent));
+        assert_eq!(ht.extend(&other), Err(Error::NotPersistent));
+    }
+
+    #[test]
+    fn test_persistent_nested_arrays() {
+        let mut persistent = hash_table(&mut [], 0);
+        persistent.gc.u.type_info = IS_ARRAY_PERSISTENT;
+        let ht = HashTableRef::from_ptr(&mut persistent);
+
+        let mut nested = hash_table(&mut [], 0);
+        let mut zv = Zval::new();
+        zv.u1.type_info = IS_ARRAY_EX;
+        zv.value.arr = &mut nested;
+
+        // Neither request nor persistent arrays can be nested, only immutable ones.
+        assert_eq!(ht.check_value(&zv), Err(Error::NotPersistent));
+        unsafe { (*zv.value.arr).gc.u.type_info = IS_ARRAY_PERSISTENT };
+        assert_eq!(ht.check_value(&zv), Err(Error::NotPersistent));
+        unsafe { (*zv.value.arr).gc.u.type_info = IS_ARRAY_IMMUTABLE };
+        assert_eq!(ht.check_value(&zv), Ok(()));
+    }
+
+    #[test]
+    fn test_extend_full() {
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, Some(2))];
+        let mut other = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let other = HashTableRef::from_ptr(&mut other);
+
+        // Only the largest index is left, but two elements are appended.
+        let mut full = hash_table(&mut [], 0);
+        full.nNextFreeElement = ZendLong::MAX;
+        let mut ht = HashTableMut::from_ptr(&mut full);
+
+        assert_eq!(ht.extend(&other), Err(Error::NextElementOccupied));
+    }
+
+    #[test]
+    fn test_sort_compare() {
+        let mut a = bucket(0, None, Some(2));
+        let mut b = bucket(1, None, Some(1));
+        let mut c = bucket(2, None, Some(2));
+        a.val.u2.extra = 0;
+        b.val.u2.extra = 1;
+        c.val.u2.extra = 2;
+
+        let mut cmp = |a: &Zval, b: &Zval| a.long().cmp(&b.long());
+        let mut state = SortCompare {
+            cmp: &mut cmp,
+            panic: None,
+        };
+        SORT_COMPARE.with(|current| current.set(&mut state as *mut SortCompare as *mut c_void));
+
+        unsafe {
+            assert_eq!(sort_compare(&mut a, &mut b), 1);
+            assert_eq!(sort_compare(&mut b, &mut a), -1);
+            // Equal values keep their original order.
+            assert_eq!(sort_compare(&mut a, &mut c), -1);
+            assert_eq!(sort_compare(&mut c, &mut a), 1);
+        }
+
+        SORT_COMPARE.with(|current| current.set(ptr::null_mut()));
+    }
+
+    #[test]
+    fn test_sort_compare_panic() {
+        let mut a = bucket(0, None, Some(2));
+        let mut b = bucket(1, None, Some(1));
+
+        let mut calls = 0;
+        let mut cmp = |_: &Zval, _: &Zval| {
+            calls += 1;
+            panic!("comparison failed")
+        };
+        let mut state = SortCompare {
+            cmp: &mut cmp,
+            panic: None,
+        };
+        SORT_COMPARE.with(|current| current.set(&mut state as *mut SortCompare as *mut c_void));
+
+        // The panic is caught rather than unwinding through the engine, and the comparison
+        // function is not called again.
+        unsafe {
+            assert_eq!(sort_compare(&mut a, &mut b), 0);
+            assert_eq!(sort_compare(&mut b, &mut a), 0);
+        }
+
+        SORT_COMPARE.with(|current| current.set(ptr::null_mut()));
+        let payload = state.panic.take().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"comparison failed"));
+        drop(state);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_to_vec_typed() {
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, Some(2))];
+        let mut packed = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let ht = HashTableRef::from_ptr(&mut packed);
+
+        assert!(ht.is_packed());
+        assert_eq!(ht.to_vec_long(), Ok(vec![1, 2]));
+        assert_eq!(ht.to_vec_double(), Ok(vec![1.0, 2.0]));
+        assert_eq!(
+            ht.to_vec_string(),
+            Err(Error::ArrayElementConversion("0".to_string()))
+        );
+
+        buckets[1].val.set_double(2.5);
+        let ht = HashTableRef::from_ptr(&mut packed);
+        assert_eq!(ht.to_vec_double(), Ok(vec![1.0, 2.5]));
+        assert_eq!(
+            ht.to_vec_long(),
+            Err(Error::ArrayElementConversion("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_to_vec_typed_holes() {
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, None)];
+        let mut packed = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let ht = HashTableRef::from_ptr(&mut packed);
+        assert_eq!(ht.to_vec_long(), Ok(vec![1]));
+
+        let mut buckets = [bucket(0, None, None), bucket(1, None, Some(2))];
+        let mut packed = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let ht = HashTableRef::from_ptr(&mut packed);
+        assert_eq!(
+            ht.to_vec_long(),
+            Err(Error::ArrayElementConversion("1".to_string()))
+        );
+
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, Some(2))];
+        let mut hash = hash_table(&mut buckets, 0);
+        let ht = HashTableRef::from_ptr(&mut hash);
+        assert!(!ht.is_packed());
+        assert_eq!(ht.to_vec_long(), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_iter_mixed_keys() {
+        let mut arr = fake_array(&[("a".into(), Some(1)), (5.into(), Some(2))], 0);
+        let ht = &mut arr.ht;
+
+        assert_eq!(
+            collect(ht),
+            vec![
+                (ArrayKey::Str(b"a".to_vec()), Some(1)),
+                (ArrayKey::Long(5), Some(2))
+            ]
+        );
+
+        let ht = HashTableRef::from_ptr(ht);
+        assert_eq!(
+            ht.keys(),
+            vec![ArrayKey::Str(b"a".to_vec()), ArrayKey::Long(5)]
+        );
+        assert_eq!(
+            ht.values().iter().map(|v| v.long()).collect::<Vec<_>>(),
+            vec![Some(1), Some(2)]
+        );
+        assert_eq!(ht.values_owned::<ZendLong>(), Ok(vec![1, 2]));
+        assert_eq!(
+            ht.values_owned::<String>(),
+            Err(Error::ArrayElementConversion("a".to_string()))
+        );
+
+        let map = HashMap::from(&*ht);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["a"].long(), Some(1));
+        assert_eq!(map["5"].long(), Some(2));
+        assert_eq!(
+            ht.to_vec(),
+            Err(Error::ArrayElementConversion("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let entries = [("a".into(), Some(1)), (1.into(), None), (5.into(), Some(2))];
+        let mut arr = fake_array(&entries, 0);
+        let ht = &mut arr.ht;
+        ht.gc.refcount = 1;
+
+        let mut wrapper = HashTableMut::from_ptr(ht);
+        let mut keys = Vec::new();
+        for (key, val) in wrapper.iter_mut() {
+            keys.push(key);
+            val.set_long(val.long().unwrap() * 10);
+        }
+        assert_eq!(keys, vec![ArrayKey::Str(b"a".to_vec()), ArrayKey::Long(5)]);
+
+        assert_eq!(
+            collect(ht),
+            vec![
+                (ArrayKey::Str(b"a".to_vec()), Some(10)),
+                (ArrayKey::Long(5), Some(20))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retain_visits_elements() {
+        let entries = [("a".into(), Some(1)), (1.into(), None), (5.into(), Some(2))];
+        let mut arr = fake_array(&entries, 0);
+        arr.ht.gc.refcount = 1;
+
+        // Keeping every element visits them in order without calling into the engine.
+        let mut wrapper = HashTableMut::from_ptr(&mut arr.ht);
+        let mut visited = Vec::new();
+        wrapper.retain(|key, val| {
+            visited.push((key.clone(), val.long()));
+            true
+        });
+        assert_eq!(
+            visited,
+            vec![
+                (ArrayKey::Str(b"a".to_vec()), Some(1)),
+                (ArrayKey::Long(5), Some(2))
+            ]
+        );
+        assert_eq!(wrapper.len(), 2);
+    }
+
+    #[test]
+    fn test_iter_holes() {
+        let entries = [
+            (0.into(), None),
+            (1.into(), Some(2)),
+            (2.into(), None),
+            (3.into(), Some(4)),
+        ];
+        let mut arr = fake_array(&entries, HASH_FLAG_PACKED);
+        let ht = &mut arr.ht;
+        ht.nInternalPointer = 3;
+        ht.nNextFreeElement = 4;
+
+        let wrapper = HashTableRef::from_ptr(ht);
+        assert_eq!(wrapper.len(), 2);
+        assert_eq!(wrapper.next_free_index(), 4);
+
+        assert_eq!(
+            collect(ht),
+            vec![(ArrayKey::Long(1), Some(2)), (ArrayKey::Long(3), Some(4))]
+        );
+        assert_eq!(ht.nInternalPointer, 3);
+
+        // The owned hash table shares the array with one other reference.
+        ht.gc.refcount = 2;
+        let owned = ZendHashTable { ptr: ht };
+        assert_eq!(
+            owned.into_iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            vec![ArrayKey::Long(1), ArrayKey::Long(3)]
+        );
+        assert_eq!(ht.gc.refcount, 1);
+    }
+
+    #[test]
+    fn test_ownership() {
+        let mut buckets = [bucket(0, None, Some(1))];
+        let mut ht = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        ht.gc.refcount = 1;
+
+        // Borrowing the array never releases it.
+        {
+            let _borrowed = HashTableRef::from_ptr(&mut ht);
+            let _borrowed_mut = HashTableMut::from_ptr(&mut ht);
+        }
+        assert_eq!(ht.gc.refcount, 1);
+
+        // Shallow clones release their reference when dropped.
+        let borrowed = HashTableRef::from_ptr(&mut ht);
+        let clone = borrowed.shallow_clone();
+        assert_eq!(ht.gc.refcount, 2);
+        drop(clone);
+        assert_eq!(ht.gc.refcount, 1);
+
+        // Handing the array to a zval transfers the reference.
+        let mut zv = Zval::from(());
+        zv.set_array(ZendHashTable { ptr: &mut ht }).unwrap();
+        assert_eq!(ht.gc.refcount, 1);
+        assert!(zv.is_array());
+
+        // Immutable arrays are never released.
+        unsafe { ht.gc.u.type_info |= GC_IMMUTABLE };
+        drop(ZendHashTable { ptr: &mut ht });
+        assert_eq!(ht.gc.refcount, 1);
+
+        // Persistent arrays cannot be handed to a zval.
+        unsafe { ht.gc.u.type_info |= IS_ARRAY_PERSISTENT };
+        let mut zv = Zval::from(());
+        assert_eq!(
+            zv.set_array(ZendHashTable { ptr: &mut ht }),
+            Err(Error::NotPersistent)
+        );
+        assert!(zv.is_null());
+    }
+
+    #[test]
+    fn test_array_key() {
+        assert_eq!(ArrayKey::from(5u32), ArrayKey::Long(5));
+        assert_eq!(ArrayKey::from(-1i64), ArrayKey::Long(u64::MAX));
+        assert_eq!(ArrayKey::from("a"), ArrayKey::Str(b"a".to_vec()));
+        assert_eq!(ArrayKey::from(-1i64).to_string(), "-1");
+    }
+
+    #[test]
+    fn test_array_key_bytes() {
+        let key = ArrayKey::from(&b"\x00\x01"[..]);
+        assert_eq!(key, ArrayKey::Str(vec![0, 1]));
+        assert_eq!(key.as_str(), Some("\u{0}\u{1}"));
+
+        let invalid = ArrayKey::from(vec![b'a', 0xff]);
+        assert_eq!(invalid.as_str(), None);
+        assert_eq!(invalid.to_string_lossy(), "a\u{fffd}");
+        assert_eq!(invalid.to_string(), "a\u{fffd}");
+
+        assert_eq!(ArrayKey::from(&b"-5"[..]), ArrayKey::Long(-5i64 as u64));
+        assert_eq!(ArrayKey::Long(5).as_str(), None);
+        assert_eq!(ArrayKey::Long(5).to_string_lossy(), "5");
+
+        // Keys are read from the hash table without replacing invalid UTF-8.
+        let mut key = fake_string(b"\x00\x01\xff", 0, 0);
+        let mut buckets = [bucket(0, Some(&mut key), Some(1))];
+        let mut ht = hash_table(&mut buckets, 0);
+        assert_eq!(
+            collect(&mut ht),
+            vec![(ArrayKey::Str(vec![0, 1, 0xff]), Some(1))]
+        );
+
+        // The owned iterator yields the same keys.
+        ht.gc.refcount = 2;
+        let owned = ZendHashTable { ptr: &mut ht };
+        assert_eq!(
+            owned
+                .into_iter()
+                .map(|(key, val)| (key, val.long()))
+                .collect::<Vec<_>>(),
+            vec![(ArrayKey::Str(vec![0, 1, 0xff]), Some(1))]
+        );
+        assert_eq!(ht.gc.refcount, 1);
+    }
+
+    #[test]
+    fn test_array_key_numeric_strings() {
+        let long = |idx: ZendLong| ArrayKey::Long(idx as u64);
+        let string = |key: &str| ArrayKey::Str(key.as_bytes().to_vec());
+
+        assert_eq!(ArrayKey::from("5"), long(5));
+        assert_eq!(ArrayKey::from("0"), long(0));
+        assert_eq!(ArrayKey::from("-5"), long(-5));
+        assert_eq!(ArrayKey::from("5".to_string()), long(5));
+        assert_eq!(ArrayKey::from(&"-5".to_string()), long(-5));
+
+        for key in &[
+            "05", "-0", "-05", "", "-", "+5", " 5", "5 ", "5.0", "1e3", "0x1A",
+        ] {
+            assert_eq!(ArrayKey::from(*key), string(key));
+        }
+
+        // Keys outside the range of a long are kept as strings.
+        assert_eq!(
+            ArrayKey::from(ZendLong::MAX.to_string()),
+            long(ZendLong::MAX)
+        );
+        assert_eq!(
+            ArrayKey::from(ZendLong::MIN.to_string()),
+            long(ZendLong::MIN)
+        );
+        let above = (ZendLong::MAX as i128 + 1).to_string();
+        assert_eq!(ArrayKey::from(above.as_str()), string(&above));
+        let below = (ZendLong::MIN as i128 - 1).to_string();
+        assert_eq!(ArrayKey::from(below.as_str()), string(&below));
+
+        // On 32-bit platforms, longs are 32 bits wide.
+        if mem::size_of::<ZendLong>() == 4 {
+            assert_eq!(ArrayKey::from("2147483648"), string("2147483648"));
+        } else {
+            assert_eq!(ArrayKey::from("2147483648"), ArrayKey::Long(2147483648));
+        }
+    }
+
+    #[test]
+    fn test_array_key_hash() {
+        let mut keys = HashMap::new();
+        keys.insert(ArrayKey::from("5"), "numeric string");
+        keys.insert(ArrayKey::from(5), "integer");
+        keys.insert(ArrayKey::from("05"), "string");
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[&ArrayKey::Long(5)], "integer");
+    }
+}
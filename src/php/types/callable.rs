@@ -0,0 +1,121 @@
+//! Represents a callable value in PHP, such as a closure, the name of a function or an array
+//! containing an object and a method name.
+
+use std::{convert::TryFrom, ptr};
+
+use crate::{
+    bindings::{_call_user_function_impl, zend_is_callable_ex},
+    errors::{Error, Result},
+    php::errors::ExceptionMark,
+};
+
+use super::{
+    string::ZendString,
+    zval::{IntoZvalDyn, Zval},
+};
+
+/// A zval which has been checked to be callable. The callable borrows the zval it was
+/// created from, and therefore cannot outlive it.
+#[derive(Debug, Clone, Copy)]
+pub struct ZendCallable<'a>(&'a Zval);
+
+impl<'a> ZendCallable<'a> {
+    /// Creates a new callable from a zval.
+    ///
+    /// # Parameters
+    ///
+    /// * `callable` - The zval to call.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(ZendCallable)` - The zval was callable.
+    /// * `None` - The zval was not callable.
+    pub fn new(callable: &'a Zval) -> Option<Self> {
+        if callable.is_callable() {
+            Some(Self(callable))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the name of the callable, as it would be displayed by PHP, e.g. `strlen` or
+    /// `Foo::bar`. Closures are named `Closure::__invoke`.
+    pub fn name(&self) -> String {
+        let ptr: *const Zval = self.0;
+        let mut name = ptr::null_mut();
+
+        unsafe {
+            zend_is_callable_ex(
+                ptr as *mut Zval,
+                ptr::null_mut(),
+                0,
+                &mut name,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+
+        unsafe { ZendString::from_raw(name) }
+            .map(|zs| String::from(&zs))
+            .unwrap_or_default()
+    }
+
+    /// Calls the callable with a list of arguments to pass to the function.
+    ///
+    /// # Parameters
+    ///
+    /// * `params` - A list of parameters to call the function with, e.g. `&[&5, &"hello"]`.
+    ///   The parameters are converted into zvals for the call, which are released afterwards.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The result of the function call.
+    /// * `Err(Error::Exception)` - The callable threw an exception. The exception is left
+    ///   pending, and can be cleared with `ExecutorGlobals::clear_exception` to swallow it.
+    /// * `Err(Error::CallFailed)` - The call failed.
+    /// * `Err(Error)` - A parameter could not be converted into a zval.
+    pub fn call(&self, params: &[&dyn IntoZvalDyn]) -> Result<Zval> {
+        let mut params = params
+            .iter()
+            .map(|param| param.as_zval())
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut retval = Zval::new();
+        let len = params.len();
+        let ptr: *const Zval = self.0;
+        let mark = ExceptionMark::new();
+
+        let result = unsafe {
+            _call_user_function_impl(
+                ptr::null_mut(),
+                ptr as *mut Zval,
+                &mut retval,
+                len as _,
+                params.as_mut_ptr(),
+                ptr::null_mut(),
+            )
+        };
+
+        // The engine takes its own references to the parameters when building the call frame,
+        // so the parameters we created must be released regardless of the result.
+        params.into_iter().for_each(Zval::release);
+
+        if let Some(exception) = mark.thrown() {
+            retval.release();
+            return Err(Error::Exception(exception));
+        }
+
+        if result < 0 {
+            Err(Error::CallFailed)
+        } else {
+            Ok(retval)
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Zval> for ZendCallable<'a> {
+    type Error = Error;
+    fn try_from(value: &'a Zval) -> Result<Self> {
+        Self::new(value).ok_or(Error::Callable)
+    }
+}
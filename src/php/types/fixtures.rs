@@ -0,0 +1,90 @@
+//! Fake engine values shared by the unit tests, which run without the engine. Only the
+//! fields read by the library are filled in.
+
+use std::{mem, ptr};
+
+use crate::{
+    bindings::{_Bucket, zend_string, HashTable, IS_ARRAY, IS_UNDEF},
+    php::types::{array::ArrayKey, long::ZendLong, zval::Zval},
+};
+
+/// Builds the memory layout of a Zend string with the given contents, flags and hash,
+/// returning the buffer backing the string. The buffer must outlive any pointer to the
+/// string.
+pub(crate) fn fake_string(val: &[u8], flags: u32, h: u64) -> Vec<u64> {
+    let words = (mem::size_of::<zend_string>() + val.len()) / mem::size_of::<u64>() + 1;
+    let mut buf = vec![0u64; words];
+    let ptr = buf.as_mut_ptr() as *mut zend_string;
+    unsafe {
+        (*ptr).gc.refcount = 1;
+        (*ptr).gc.u.type_info = flags;
+        (*ptr).h = h;
+        (*ptr).len = val.len() as _;
+        ptr::copy_nonoverlapping(val.as_ptr(), (*ptr).val.as_mut_ptr() as *mut u8, val.len());
+    }
+    buf
+}
+
+/// Builds a bucket containing a long, with a string key if given. The bucket is a hole if no
+/// value is given.
+pub(crate) fn bucket(h: u64, key: Option<&mut Vec<u64>>, val: Option<ZendLong>) -> _Bucket {
+    let mut bucket: _Bucket = unsafe { mem::zeroed() };
+    bucket.h = h;
+    bucket.key = key.map_or(ptr::null_mut(), |buf| buf.as_mut_ptr() as *mut zend_string);
+
+    match val {
+        Some(val) => bucket.val.set_long(val),
+        None => bucket.val.u1.type_info = IS_UNDEF,
+    }
+
+    bucket
+}
+
+/// Builds a hash table over the given buckets. The hash part of the table is not built,
+/// so only iteration is supported.
+pub(crate) fn hash_table(buckets: &mut [_Bucket], flags: u32) -> HashTable {
+    let mut ht: HashTable = unsafe { mem::zeroed() };
+    ht.u.flags = flags;
+    ht.arData = buckets.as_mut_ptr();
+    ht.nNumUsed = buckets.len() as u32;
+    ht.nNumOfElements = buckets.iter().filter(|b| !b.val.is_undef()).count() as u32;
+    ht.nNextFreeElement = ZendLong::MIN;
+    ht
+}
+
+/// Builds a zval containing a fake array. The zval must not be released.
+pub(crate) fn array_zval(ht: &mut HashTable) -> Zval {
+    let mut zv = Zval::new();
+    zv.u1.type_info = IS_ARRAY;
+    zv.value.arr = ht;
+    zv
+}
+
+/// A fake array along with the buckets and string keys it points to, see [`fake_array`].
+pub(crate) struct FakeArray {
+    pub ht: HashTable,
+    _buckets: Vec<_Bucket>,
+    _keys: Vec<Vec<u64>>,
+}
+
+/// Builds a hash table over buckets containing the given keys and longs, in order. Entries
+/// without a value are holes, as left behind by `unset()`.
+pub(crate) fn fake_array(entries: &[(ArrayKey, Option<ZendLong>)], flags: u32) -> FakeArray {
+    let mut keys = Vec::new();
+    let mut buckets: Vec<_Bucket> = entries
+        .iter()
+        .map(|(key, val)| match key {
+            ArrayKey::Long(idx) => bucket(*idx, None, *val),
+            ArrayKey::Str(key) => {
+                keys.push(fake_string(key, 0, 0));
+                bucket(0, keys.last_mut(), *val)
+            }
+        })
+        .collect();
+
+    FakeArray {
+        ht: hash_table(&mut buckets, flags),
+        _buckets: buckets,
+        _keys: keys,
+    }
+}
@@ -2,8 +2,12 @@
 //! Introduces functions for converting between Zend values and Rust values.
 
 pub mod array;
+pub mod callable;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod long;
 pub mod object;
+pub mod smart_str;
 pub mod string;
 pub mod zval;
 
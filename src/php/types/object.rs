@@ -4,19 +4,177 @@
 use std::{
     mem,
     ops::{Deref, DerefMut},
+    os::raw::c_char,
+    ptr,
 };
 
 use crate::{
     bindings::{
         ext_php_rs_zend_object_alloc, ext_php_rs_zend_object_std_init, std_object_handlers,
-        zend_object, zend_object_handlers,
+        zend_object, zend_object_handlers, zend_read_property, zend_update_property,
+    },
+    errors::Result,
+    php::{
+        class::ClassEntry, errors::ExceptionMark, execution_data::ExecutionData,
+        globals::ExecutorGlobals,
     },
-    php::{class::ClassEntry, execution_data::ExecutionData},
 };
 
+use super::zval::{FromZval, IntoZval, Zval};
+
+/// A PHP object. Alias.
 pub type ZendObject = zend_object;
 pub type ZendObjectHandlers = zend_object_handlers;
 
+impl ZendObject {
+    /// Returns the name of the class of the object, e.g. `DateTime`.
+    pub fn class_name(&self) -> String {
+        unsafe { self.ce.as_ref() }
+            .and_then(|ce| unsafe { ce.name.as_ref() })
+            .map(String::from)
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the object is an instance of the given class, or of a class extending
+    /// or implementing it, equivalent to `instanceof` in PHP.
+    ///
+    /// # Parameters
+    ///
+    /// * `class` - The class, or interface, to check against, e.g. as returned by
+    ///   [`ClassEntry::try_find`].
+    pub fn instance_of(&self, class: &ClassEntry) -> bool {
+        match unsafe { self.ce.as_ref() } {
+            Some(ce) => ce.instance_of(class),
+            None => false,
+        }
+    }
+
+    /// Returns the handle of the object, which uniquely identifies the object while it is
+    /// alive. This is the number shown after the `#` by `var_dump`.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Reads a property of the object. The property is read from the scope of the class of
+    /// the object, so protected and private properties can be read as well as dynamic
+    /// properties. Private properties declared by a parent class are not visible.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(T)` - The value of the property.
+    /// * `None` - The property does not exist or could not be converted into `T`.
+    pub fn get_property<T>(&self, name: &str) -> Option<T>
+    where
+        T: for<'a> FromZval<'a>,
+    {
+        self.get_property_in_scope(unsafe { self.ce.as_ref() }?, name)
+    }
+
+    /// Reads a property of the object from the given scope. See [`ZendObject::get_property`].
+    ///
+    /// # Parameters
+    ///
+    /// * `scope` - The class to read the property as, which determines the visibility of
+    ///   protected and private properties.
+    /// * `name` - The name of the property.
+    pub(crate) fn get_property_in_scope<T>(&self, scope: &ClassEntry, name: &str) -> Option<T>
+    where
+        T: for<'a> FromZval<'a>,
+    {
+        let zv = self.read_property(scope, name)?;
+        let val = T::from_zval(&zv).ok();
+
+        zv.release();
+        val
+    }
+
+    /// Reads a property of the object from the given scope, returning a copy of the value
+    /// which holds its own reference. The zval must be handed over to PHP or released with
+    /// [`Zval::release`].
+    ///
+    /// # Parameters
+    ///
+    /// * `scope` - The class to read the property as. See
+    ///   [`ZendObject::get_property_in_scope`].
+    /// * `name` - The name of the property.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Zval)` - The value of the property.
+    /// * `None` - The property does not exist or is not visible from the scope.
+    pub(crate) fn read_property(&self, scope: &ClassEntry, name: &str) -> Option<Zval> {
+        let ptr = self as *const Self as *mut Self;
+        let mut rv = Zval::new();
+
+        let zv = unsafe {
+            zend_read_property(
+                scope as *const ClassEntry as *mut ClassEntry,
+                ptr,
+                name.as_ptr() as *const c_char,
+                name.len() as _,
+                true,
+                &mut rv,
+            )
+        };
+
+        // Properties which are not stored on the object, e.g. those returned by `__get`, are
+        // written into `rv` and owned by us.
+        if ptr::eq(zv, &rv) {
+            return Some(rv);
+        }
+
+        // Missing properties are read as the shared uninitialized zval, rather than null.
+        let val = unsafe { zv.as_ref() }
+            .filter(|zv| !ptr::eq(*zv, &ExecutorGlobals::get().uninitialized_zval))
+            .map(|zv| zv.dereference().shallow_clone());
+
+        rv.release();
+        val
+    }
+
+    /// Writes a property of the object, creating a dynamic property if the class does not
+    /// declare it. As with [`ZendObject::get_property`], protected and private properties can
+    /// be written.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property.
+    /// * `value` - The value to write to the property.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The property was written.
+    /// * `Err(Error::Exception)` - Writing the property threw an exception, e.g. a `TypeError`
+    ///   when the property is typed. The exception is left pending.
+    /// * `Err(Error)` - The value could not be converted into a zval.
+    pub fn set_property<T>(&mut self, name: &str, value: T) -> Result<()>
+    where
+        T: IntoZval,
+    {
+        let mut value = value.into_zval()?;
+        let mark = ExceptionMark::new();
+
+        unsafe {
+            zend_update_property(
+                self.ce,
+                self,
+                name.as_ptr() as *const c_char,
+                name.len() as _,
+                &mut value,
+            )
+        };
+
+        // The object takes its own reference to the value.
+        value.release();
+
+        mark.check(())
+    }
+}
+
 /// Implemented by the [`object_override_handler`] macro on a type T which is used as the T type
 /// for [`ZendClassObject`].
 /// Implements a function `create_object` which is passed to a PHP class entry to instantiate the
@@ -87,10 +245,11 @@ impl<T: Default> ZendClassObject<T> {
     ///
     /// # Parameters
     ///
-    /// * `ex` - The execution data of the function.
-    pub fn get(ex: &ExecutionData) -> Option<&'static mut Self> {
+    /// * `ex` - The execution data of the function. The object is borrowed from it, so it
+    ///   cannot be used once the function returns.
+    pub fn get(ex: &mut ExecutionData) -> Option<&mut Self> {
         // cast to u8 to work in terms of bytes
-        let ptr = ex.This.object()? as *mut u8;
+        let ptr = ex.get_this_mut()? as *mut ZendObject as *mut u8;
         let offset = std::mem::size_of::<T>();
         unsafe {
             let ptr = ptr.offset(0 - offset as isize);
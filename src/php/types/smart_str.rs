@@ -0,0 +1,229 @@
+//! A growable string buffer used by the engine to build strings piece by piece, e.g. when
+//! serializing values or exporting them with `var_export`.
+
+use std::{
+    fmt::{self, Write},
+    os::raw::c_char,
+    ptr,
+};
+
+use crate::{
+    bindings::{
+        ext_php_rs_smart_str_append_bytes, ext_php_rs_smart_str_append_long,
+        ext_php_rs_smart_str_extract, ext_php_rs_smart_str_free, smart_str,
+    },
+    errors::Result,
+};
+
+use super::{
+    long::ZendLong,
+    string::{check_len, ZendString},
+    zval::{IntoZval, Zval},
+};
+
+/// The maximum length of a formatted long, including the sign, `MAX_LENGTH_OF_LONG - 1`.
+const MAX_LONG_LEN: usize = 20;
+
+/// Builds a Zend string by appending to a buffer which grows as needed, as with the
+/// `smart_str` API. Appending avoids the copies made when concatenating strings one at a
+/// time, and the buffer is turned into a [`ZendString`] without copying once it is complete.
+///
+/// The buffer is allocated per request. It is freed when dropped, unless it has been
+/// consumed by [`SmartStr::finish`] or [`SmartStr::finish_zval`].
+///
+/// Values can also be formatted into the buffer with the [`write!`] macro:
+///
+/// ```no_run
+/// use std::fmt::Write;
+/// use ext_php_rs::php::types::smart_str::SmartStr;
+///
+/// let mut buf = SmartStr::new();
+/// write!(buf, "{} + {} = {}", 1, 2, 3).unwrap();
+/// let s = buf.finish();
+/// ```
+pub struct SmartStr {
+    inner: smart_str,
+}
+
+impl SmartStr {
+    /// Creates a new, empty buffer. Nothing is allocated until the first append.
+    pub fn new() -> Self {
+        Self {
+            inner: smart_str {
+                s: ptr::null_mut(),
+                a: 0,
+            },
+        }
+    }
+
+    /// Returns the number of bytes appended to the buffer.
+    pub fn len(&self) -> usize {
+        unsafe { self.inner.s.as_ref() }.map_or(0, |s| s.len as usize)
+    }
+
+    /// Returns whether nothing has been appended to the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the contents of the buffer as a slice of bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { self.inner.s.as_ref() }.map_or(&[], |s| s.as_bytes())
+    }
+
+    /// Appends a string to the buffer.
+    ///
+    /// # Parameters
+    ///
+    /// * `str_` - The string to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The string was appended.
+    /// * `Err(Error::StringTooLong)` - The buffer would exceed [`ZendString::max_len`].
+    pub fn append_str(&mut self, str_: &str) -> Result<()> {
+        self.append_bytes(str_.as_bytes())
+    }
+
+    /// Appends bytes to the buffer, which may contain NUL bytes and bytes which are not
+    /// valid UTF-8.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The bytes to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The bytes were appended.
+    /// * `Err(Error::StringTooLong)` - The buffer would exceed [`ZendString::max_len`].
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        check_len(self.len().saturating_add(bytes.len()))?;
+
+        unsafe {
+            ext_php_rs_smart_str_append_bytes(
+                &mut self.inner,
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as _,
+            )
+        };
+
+        Ok(())
+    }
+
+    /// Appends an integer to the buffer in decimal, as PHP formats integers.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The integer to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The integer was appended.
+    /// * `Err(Error::StringTooLong)` - The buffer would exceed [`ZendString::max_len`].
+    pub fn append_long(&mut self, val: ZendLong) -> Result<()> {
+        check_len(self.len().saturating_add(MAX_LONG_LEN))?;
+        unsafe { ext_php_rs_smart_str_append_long(&mut self.inner, val) };
+
+        Ok(())
+    }
+
+    /// Appends a float to the buffer, formatted in the same way as a `(string)` cast in PHP,
+    /// which depends on the `precision` INI setting. For example, `1.0` is appended as `1`
+    /// and infinity as `INF`.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The float to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The float was appended.
+    /// * `Err(Error::StringTooLong)` - The buffer would exceed [`ZendString::max_len`].
+    pub fn append_double(&mut self, val: f64) -> Result<()> {
+        let mut zv = Zval::new();
+        zv.set_double(val);
+
+        self.append_zval(&zv)
+    }
+
+    /// Appends the value of a zval to the buffer, converted following the same rules as
+    /// [`Zval::coerce_to_string`].
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The value was appended.
+    /// * `Err(Error::Exception)` - The value could not be converted, e.g. it was an object
+    ///   without a `__toString` method. The `Error` thrown by PHP is left pending.
+    /// * `Err(Error::StringTooLong)` - The buffer would exceed [`ZendString::max_len`].
+    pub fn append_zval(&mut self, val: &Zval) -> Result<()> {
+        let zs = val.coerce_to_zend_string()?;
+        self.append_bytes(zs.as_bytes())
+    }
+
+    /// Consumes the buffer, returning its contents as a Zend string without copying them.
+    /// An empty buffer returns the interned empty string.
+    pub fn finish(mut self) -> ZendString {
+        let ptr = unsafe { ext_php_rs_smart_str_extract(&mut self.inner) };
+
+        unsafe { ZendString::from_raw(ptr) }.expect("smart_str_extract never returns null")
+    }
+
+    /// Consumes the buffer, returning its contents as a string zval. See
+    /// [`SmartStr::finish`].
+    pub fn finish_zval(self) -> Zval {
+        self.finish()
+            .into_zval()
+            .expect("Zend strings can always be stored in a zval")
+    }
+}
+
+impl Default for SmartStr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frees the buffer if it was never consumed.
+impl Drop for SmartStr {
+    fn drop(&mut self) {
+        if !self.inner.s.is_null() {
+            unsafe { ext_php_rs_smart_str_free(&mut self.inner) };
+        }
+    }
+}
+
+impl Write for SmartStr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.append_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl From<SmartStr> for ZendString {
+    fn from(buf: SmartStr) -> Self {
+        buf.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let mut buf = SmartStr::new();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_bytes(), b"");
+
+        buf.write_str("").unwrap();
+        assert_eq!(buf.append_bytes(b""), Ok(()));
+        assert_eq!(buf.len(), 0);
+    }
+}
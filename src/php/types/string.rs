@@ -2,65 +2,579 @@
 //! contains the length of the string, meaning the string can contain the NUL character.
 
 use core::slice;
+use std::{
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    mem::{self, ManuallyDrop},
+    ops::Deref,
+    os::raw::c_char,
+    ptr,
+};
 
 use crate::{
-    bindings::{ext_php_rs_zend_string_init, zend_string, zend_string_init_interned},
-    functions::c_str,
+    bindings::{
+        ext_php_rs_zend_string_alloc, ext_php_rs_zend_string_append,
+        ext_php_rs_zend_string_hash_val, ext_php_rs_zend_string_init,
+        ext_php_rs_zend_string_release, zend_known_strings, zend_string, zend_string_concat2,
+        zend_string_concat3, zend_string_init_interned, IS_STR_INTERNED, IS_STR_PERSISTENT,
+    },
+    errors::{Error, Result},
+    php::enums::KnownString,
 };
 
-/// String type used in the Zend internals.
-/// The actual size of the 'string' differs, as the
-/// end of this struct is only 1 char long, but the length
-/// inside the struct defines how many characters are in the string.
-pub type ZendString = zend_string;
+/// A string in the Zend internals, similar to a C string, but reference counted and with a
+/// length, so it may contain NUL bytes and bytes which are not valid UTF-8.
+///
+/// A `ZendString` owns one reference to the string, which is released when it is dropped,
+/// freeing the string once no other references remain. Use [`ZendString::into_raw`] to hand
+/// the reference over to the engine instead, e.g. when storing the string in a zval.
+pub struct ZendString {
+    ptr: *mut zend_string,
+}
 
 impl ZendString {
     /// Creates a new Zend string.
     ///
-    /// Note that this returns a raw pointer, and will not be freed by
-    /// Rust.
-    ///
     /// # Parameters
     ///
     /// * `str_` - The string to create a Zend string from.
     /// * `peresistent` - Whether the request should relive the request boundary.
-    pub fn new<S>(str_: S, persistent: bool) -> *mut Self
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendString)` - The newly allocated string. Empty strings are allocated as
+    ///   regular zero-length strings.
+    /// * `Err(Error)` - The string exceeds [`ZendString::max_len`] or could not be allocated.
+    pub fn new<S>(str_: S, persistent: bool) -> Result<Self>
     where
         S: AsRef<str>,
     {
-        let str_ = str_.as_ref();
-        unsafe { ext_php_rs_zend_string_init(c_str(str_), str_.len() as u64, persistent) }
+        Self::from_bytes(str_.as_ref().as_bytes(), persistent)
     }
 
-    /// Creates a new interned Zend string.
+    /// Creates a new Zend string from a slice of bytes, which are copied into the string
+    /// as-is. The bytes are not required to be valid UTF-8, so binary data or text in other
+    /// encodings can be passed to PHP.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The bytes to create a Zend string from.
+    /// * `peresistent` - Whether the request should relive the request boundary.
+    ///
+    /// # Returns
     ///
-    /// Note that this returns a raw pointer, and will not be freed by
-    /// Rust.
+    /// * `Ok(ZendString)` - The newly allocated string.
+    /// * `Err(Error)` - The string exceeds [`ZendString::max_len`] or could not be allocated.
+    pub fn from_bytes(bytes: &[u8], persistent: bool) -> Result<Self> {
+        check_len(bytes.len())?;
+
+        let ptr = unsafe {
+            ext_php_rs_zend_string_init(
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as u64,
+                persistent,
+            )
+        };
+
+        unsafe { Self::from_raw(ptr) }.ok_or(Error::InvalidPointer)
+    }
+
+    /// Creates a new interned Zend string. Interned strings are not reference counted, so
+    /// dropping the returned string has no effect.
     ///
     /// # Parameters
     ///
     /// * `str_` - The string to create a Zend string from.
-    pub fn new_interned<S>(str_: S) -> *mut Self
+    pub fn new_interned<S>(str_: S) -> Result<Self>
     where
         S: AsRef<str>,
     {
-        let str_ = str_.as_ref();
-        unsafe { zend_string_init_interned.unwrap()(c_str(str_), str_.len() as u64, true) }
+        Self::from_bytes_interned(str_.as_ref().as_bytes())
+    }
+
+    /// Creates a new interned Zend string from a slice of bytes, which are not required to
+    /// be valid UTF-8. See [`ZendString::new_interned`].
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The bytes to create a Zend string from.
+    pub fn from_bytes_interned(bytes: &[u8]) -> Result<Self> {
+        check_len(bytes.len())?;
+
+        let ptr = unsafe {
+            zend_string_init_interned.unwrap()(
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as u64,
+                true,
+            )
+        };
+
+        unsafe { Self::from_raw(ptr) }.ok_or(Error::InvalidPointer)
+    }
+
+    /// Creates a new Zend string containing two slices of bytes one after the other,
+    /// allocating the string once. Equivalent to `zend_string_concat2`.
+    ///
+    /// # Parameters
+    ///
+    /// * `a` - The start of the string.
+    /// * `b` - The end of the string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendString)` - The new string, which is not persistent.
+    /// * `Err(Error)` - The string exceeds [`ZendString::max_len`] or could not be allocated.
+    pub fn concat(a: &[u8], b: &[u8]) -> Result<Self> {
+        Self::concat_all(&[a, b])
+    }
+
+    /// Creates a new Zend string containing the given slices of bytes one after the other,
+    /// allocating the string once.
+    ///
+    /// # Parameters
+    ///
+    /// * `parts` - The parts of the string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendString)` - The new string, which is not persistent.
+    /// * `Err(Error)` - The string exceeds [`ZendString::max_len`] or could not be allocated.
+    pub fn concat_all(parts: &[&[u8]]) -> Result<Self> {
+        let len = parts
+            .iter()
+            .try_fold(0usize, |len, part| len.checked_add(part.len()))
+            .unwrap_or(usize::MAX);
+        check_len(len)?;
+
+        let ptr = unsafe {
+            match parts {
+                [a, b] => zend_string_concat2(
+                    a.as_ptr() as *const c_char,
+                    a.len() as _,
+                    b.as_ptr() as *const c_char,
+                    b.len() as _,
+                ),
+                [a, b, c] => zend_string_concat3(
+                    a.as_ptr() as *const c_char,
+                    a.len() as _,
+                    b.as_ptr() as *const c_char,
+                    b.len() as _,
+                    c.as_ptr() as *const c_char,
+                    c.len() as _,
+                ),
+                _ => {
+                    let ptr = ext_php_rs_zend_string_alloc(len as _, false);
+                    if let Some(zs) = ptr.as_mut() {
+                        let mut dest = zs.val.as_mut_ptr() as *mut u8;
+                        for part in parts {
+                            ptr::copy_nonoverlapping(part.as_ptr(), dest, part.len());
+                            dest = dest.add(part.len());
+                        }
+                    }
+                    ptr
+                }
+            }
+        };
+
+        unsafe { Self::from_raw(ptr) }.ok_or(Error::InvalidPointer)
+    }
+
+    /// Appends bytes to the end of the string. If this is the only reference to the string,
+    /// it is reallocated in place, otherwise the string is copied, as in
+    /// `zend_string_extend`. The string stays persistent if it was persistent, except for
+    /// interned strings, which are always copied into a string allocated for the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The bytes to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendString)` - The string containing the appended bytes.
+    /// * `Err(Error)` - The string would exceed [`ZendString::max_len`]. The string is
+    ///   released.
+    pub fn append(self, bytes: &[u8]) -> Result<Self> {
+        check_len(self.len().saturating_add(bytes.len()))?;
+
+        let ptr = unsafe {
+            ext_php_rs_zend_string_append(
+                self.into_raw(),
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as _,
+            )
+        };
+
+        unsafe { Self::from_raw(ptr) }.ok_or(Error::InvalidPointer)
+    }
+
+    /// Returns one of the strings interned by the engine at startup, equivalent to the
+    /// `ZSTR_KNOWN` macro. Using a known string avoids allocating a new string for names
+    /// the engine already has, e.g. when looking up properties.
+    ///
+    /// The string is borrowed from the engine rather than owned, and must never be released.
+    /// It lives until the engine is shut down.
+    ///
+    /// # Parameters
+    ///
+    /// * `id` - The string to return.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before the engine has started, as the known strings have not been
+    /// interned yet.
+    pub fn known(id: KnownString) -> &'static zend_string {
+        unsafe {
+            zend_known_strings
+                .as_ref()
+                .and_then(|_| zend_known_strings.add(id as usize).read().as_ref())
+                .expect("known strings are interned when the engine starts")
+        }
+    }
+
+    /// Takes ownership of a reference to a Zend string, e.g. one returned by the engine.
+    ///
+    /// # Parameters
+    ///
+    /// * `ptr` - The string to take ownership of.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(ZendString)` - The owned string, which releases the reference when dropped.
+    /// * `None` - The pointer was null.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or point to a valid Zend string. Unless the string is
+    /// interned, the caller must own a reference to it, which is given up by this call.
+    pub unsafe fn from_raw(ptr: *mut zend_string) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Self { ptr })
+        }
+    }
+
+    /// Converts the string into a raw pointer without releasing it, handing the reference
+    /// held by the string over to the caller, e.g. to store it in a zval. The reference must
+    /// be released by the engine or with [`ZendString::from_raw`], otherwise the string is
+    /// leaked.
+    pub fn into_raw(self) -> *mut zend_string {
+        ManuallyDrop::new(self).ptr
+    }
+
+    /// Returns a pointer to the string, which remains owned by this `ZendString`.
+    pub fn as_ptr(&self) -> *mut zend_string {
+        self.ptr
+    }
+
+    /// Returns the contents of the string as a slice of bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { &*self.ptr }.as_bytes()
+    }
+
+    /// Returns the contents of the string if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    /// Returns the length of the string in bytes.
+    pub fn len(&self) -> usize {
+        unsafe { (*self.ptr).len as usize }
+    }
+
+    /// Returns whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the string is interned, equivalent to the `ZSTR_IS_INTERNED` macro.
+    /// Interned strings are not reference counted, and live until the end of the request or
+    /// the process if they are persistent.
+    pub fn is_interned(&self) -> bool {
+        unsafe { &*self.ptr }.is_interned()
+    }
+
+    /// Returns whether the string is persistent, meaning it was allocated to outlive the
+    /// request.
+    pub fn is_persistent(&self) -> bool {
+        unsafe { &*self.ptr }.is_persistent()
+    }
+
+    /// Returns the hash of the string, as used for the keys of hash tables. See
+    /// [`zend_string::hash`].
+    pub fn hash(&self) -> u64 {
+        unsafe { &*self.ptr }.hash()
+    }
+
+    /// Returns whether the string is equal to another, ignoring the case of ASCII letters.
+    /// See [`zend_string::eq_ci`].
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The string to compare with.
+    pub fn eq_ci(&self, other: &ZendString) -> bool {
+        unsafe { (&*self.ptr).eq_ci(&*other.ptr) }
+    }
+
+    /// Returns the maximum length of a Zend string, equivalent to the `ZSTR_MAX_LEN` macro.
+    pub fn max_len() -> usize {
+        // `_ZSTR_HEADER_SIZE`, the offset of the contents of the string within the struct.
+        let header = mem::size_of::<zend_string>() - mem::size_of::<usize>();
+        // The header and NUL terminator, aligned to `ZEND_MM_ALIGNMENT`.
+        let overhead = (header + 1 + 7) & !7;
+
+        usize::MAX - overhead
+    }
+}
+
+/// Checks that a string of the given length can be allocated as a Zend string. The length
+/// calculation inside the engine overflows for strings longer than `ZSTR_MAX_LEN`.
+pub(crate) fn check_len(len: usize) -> Result<()> {
+    if len > ZendString::max_len() {
+        Err(Error::StringTooLong(len))
+    } else {
+        Ok(())
+    }
+}
+
+/// Releases the reference to the string, which frees it once no other references remain.
+/// Persistent strings are freed with the persistent allocator, as recorded in the flags of
+/// the string rather than the arguments it was created with. Interned strings are not
+/// reference counted, so they are never released.
+impl Drop for ZendString {
+    fn drop(&mut self) {
+        if !self.is_interned() {
+            unsafe { ext_php_rs_zend_string_release(self.ptr) };
+        }
+    }
+}
+
+impl Deref for ZendString {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Debug for ZendString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&String::from_utf8_lossy(self.as_bytes()), f)
+    }
+}
+
+/// Strings are compared by their contents, see [`zend_string`].
+impl PartialEq for ZendString {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { *self.ptr == *other.ptr }
+    }
+}
+
+impl Eq for ZendString {}
+
+impl Hash for ZendString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(unsafe { &*self.ptr }, state)
     }
 }
 
 impl From<&ZendString> for String {
     fn from(zs: &ZendString) -> Self {
-        let len = zs.len;
-        let ptr = zs.val.as_ptr() as *const u8;
+        String::from(unsafe { &*zs.ptr })
+    }
+}
 
+/// Methods for strings borrowed from the engine, such as the keys of a hash table.
+impl zend_string {
+    /// Returns the contents of the string as a slice of bytes.
+    pub fn as_bytes(&self) -> &[u8] {
         // SAFETY: Zend strings have a length that we know we can read.
         // By reading this many bytes we will not run into any issues.
         //
         // We can safely cast our *const c_char into a *const u8 as both
         // only occupy one byte.
-        std::str::from_utf8(unsafe { slice::from_raw_parts(ptr, len as usize) })
-            .unwrap()
-            .to_string()
+        unsafe { slice::from_raw_parts(self.val.as_ptr() as *const u8, self.len as usize) }
+    }
+
+    /// Returns whether the string is interned, see [`ZendString::is_interned`].
+    pub fn is_interned(&self) -> bool {
+        unsafe { self.gc.u.type_info & IS_STR_INTERNED != 0 }
+    }
+
+    /// Returns whether the string is persistent, see [`ZendString::is_persistent`].
+    pub fn is_persistent(&self) -> bool {
+        unsafe { self.gc.u.type_info & IS_STR_PERSISTENT != 0 }
+    }
+
+    /// Returns the hash of the string, equivalent to the `zend_string_hash_val` function. The
+    /// hash is computed the first time it is needed and stored in the string, so later calls
+    /// return the stored hash.
+    pub fn hash(&self) -> u64 {
+        if self.h != 0 {
+            return self.h;
+        }
+
+        // The engine stores the hash in shared strings too, as it only depends on the contents.
+        // Interned strings, including those in shared memory, are hashed when interned, so
+        // they are never written to.
+        unsafe { ext_php_rs_zend_string_hash_val(self as *const Self as *mut Self) }
+    }
+
+    /// Returns whether the string is equal to another, ignoring the case of ASCII letters,
+    /// equivalent to the `zend_string_equals_ci` macro. Function and class names are
+    /// compared this way.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The string to compare with.
+    pub fn eq_ci(&self, other: &zend_string) -> bool {
+        self.as_bytes().eq_ignore_ascii_case(other.as_bytes())
+    }
+}
+
+/// Compares the contents of the strings, equivalent to the `zend_string_equals` function.
+/// Strings at the same address, such as two references to an interned string, are equal
+/// without comparing their contents.
+impl PartialEq for zend_string {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self, other) || self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for zend_string {}
+
+/// Hashes the string using the hash stored in the string, see [`zend_string::hash`].
+impl Hash for zend_string {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(zend_string::hash(self))
+    }
+}
+
+impl From<&zend_string> for String {
+    fn from(zs: &zend_string) -> Self {
+        // Invalid UTF-8 sequences are replaced rather than panicking, as PHP strings can
+        // contain arbitrary bytes.
+        String::from_utf8_lossy(zs.as_bytes()).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{hash_map::DefaultHasher, HashSet},
+        hash::{Hash, Hasher},
+        ptr,
+    };
+
+    use super::{check_len, ZendString};
+    use crate::{
+        bindings::{zend_string, IS_STR_INTERNED, IS_STR_PERSISTENT},
+        errors::Error,
+        php::types::fixtures::fake_string,
+    };
+
+    #[test]
+    fn test_check_len() {
+        let max = ZendString::max_len();
+
+        assert_eq!(check_len(0), Ok(()));
+        assert_eq!(check_len(max), Ok(()));
+        assert_eq!(check_len(max + 1), Err(Error::StringTooLong(max + 1)));
+    }
+
+    #[test]
+    fn test_raw() {
+        let mut buf = fake_string(b"a\0b", IS_STR_INTERNED, 0);
+        let ptr = buf.as_mut_ptr() as *mut zend_string;
+
+        assert!(unsafe { ZendString::from_raw(ptr::null_mut()) }.is_none());
+
+        let zs = unsafe { ZendString::from_raw(ptr) }.unwrap();
+        assert_eq!(zs.len(), 3);
+        assert!(!zs.is_empty());
+        assert_eq!(&*zs, b"a\0b");
+        assert_eq!(zs.as_str(), Some("a\0b"));
+        assert_eq!(format!("{:?}", zs), "\"a\\0b\"");
+        assert!(zs.is_interned());
+        assert!(!zs.is_persistent());
+
+        assert_eq!(zs.into_raw(), ptr);
+        assert_eq!(unsafe { (*ptr).gc.refcount }, 1);
+    }
+
+    #[test]
+    fn test_drop_interned() {
+        let mut buf = fake_string(b"", 0, 0);
+        let ptr = buf.as_mut_ptr() as *mut zend_string;
+
+        // Interned strings are never released, whether or not they are persistent.
+        for flags in &[IS_STR_INTERNED, IS_STR_INTERNED | IS_STR_PERSISTENT] {
+            unsafe {
+                (*ptr).gc.refcount = 1;
+                (*ptr).gc.u.type_info = *flags;
+            }
+
+            let zs = unsafe { ZendString::from_raw(ptr) }.unwrap();
+            assert_eq!(zs.is_persistent(), flags & IS_STR_PERSISTENT != 0);
+            drop(zs);
+            assert_eq!(unsafe { (*ptr).gc.refcount }, 1);
+        }
+    }
+
+    #[test]
+    fn test_hash_eq() {
+        let mut interned = fake_string(b"Name", IS_STR_INTERNED, 0x8000_0000_0000_1234);
+        let mut heap = fake_string(b"Name", 0, 0x8000_0000_0000_1234);
+        let mut lower = fake_string(b"name", IS_STR_INTERNED, 0x8000_0000_0000_5678);
+        let mut longer = fake_string(b"Names", IS_STR_INTERNED, 0x8000_0000_0000_9abc);
+
+        // The heap string is converted back into a raw pointer below rather than released.
+        let interned = unsafe { ZendString::from_raw(interned.as_mut_ptr() as _) }.unwrap();
+        let heap = unsafe { ZendString::from_raw(heap.as_mut_ptr() as _) }.unwrap();
+        let lower = unsafe { ZendString::from_raw(lower.as_mut_ptr() as _) }.unwrap();
+        let longer = unsafe { ZendString::from_raw(longer.as_mut_ptr() as _) }.unwrap();
+
+        // The stored hash is returned without hashing the contents again.
+        assert_eq!(interned.hash(), 0x8000_0000_0000_1234);
+        assert_eq!(interned.hash(), interned.hash());
+
+        // Interned and heap strings with the same contents are equal, and hash the same.
+        assert_eq!(interned, heap);
+        assert_eq!(interned, interned);
+        assert_ne!(interned, lower);
+        assert!(interned.eq_ci(&lower));
+        assert!(!interned.eq_ci(&longer));
+
+        let hash_of = |zs: &ZendString| {
+            let mut hasher = DefaultHasher::new();
+            Hash::hash(zs, &mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash_of(&interned), hash_of(&heap));
+
+        let mut set = HashSet::new();
+        assert!(set.insert(&interned));
+        assert!(!set.insert(&heap));
+        assert!(set.insert(&lower));
+        drop(set);
+
+        heap.into_raw();
+    }
+
+    #[test]
+    fn test_hash_stored() {
+        let mut buf = fake_string(b"Name", 0, 0);
+        let zs = unsafe { &*(buf.as_mut_ptr() as *const zend_string) };
+
+        // The hash is computed once and stored in the string, as the engine does.
+        let hash = zs.hash();
+        assert_ne!(hash, 0);
+        assert_eq!(zs.h, hash);
+        assert_eq!(zs.hash(), hash);
+
+        // Equal strings hash the same.
+        let mut other = fake_string(b"Name", 0, 0);
+        let other = unsafe { &*(other.as_mut_ptr() as *const zend_string) };
+        assert_eq!(other.hash(), hash);
     }
 }
@@ -1,21 +1,39 @@
 //! The base value in PHP. A Zval can contain any PHP type, and the type that it contains is
 //! determined by a property inside the struct. The content of the Zval is stored in a union.
 
-use core::slice;
-use std::{convert::TryFrom, ptr};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    ffi::c_void,
+    fmt::{self, Debug, Formatter},
+    iter::FromIterator,
+    os::raw::c_char,
+    ptr,
+};
 
 use crate::bindings::{
-    _call_user_function_impl, _zval_struct__bindgen_ty_1, _zval_struct__bindgen_ty_2,
-    ext_php_rs_zend_string_release, zend_is_callable, zend_object, zend_resource, zend_value, zval,
-    IS_INTERNED_STRING_EX, IS_STRING_EX,
+    _try_convert_to_string, _zval_struct__bindgen_ty_1, _zval_struct__bindgen_ty_2,
+    convert_to_array, convert_to_boolean, convert_to_double, convert_to_long,
+    ext_php_rs_is_numeric_string, ext_php_rs_zend_new_reference, ext_php_rs_zend_string_init,
+    zend_compare, zend_is_callable, zend_is_identical, zend_is_true, zend_object,
+    zend_parse_arg_bool_weak, zend_parse_arg_double_weak, zend_parse_arg_long_weak,
+    zend_parse_arg_str_weak, zend_resource, zend_try_assign_typed_ref, zend_value, zval,
+    zval_get_long_func, zval_ptr_dtor, zval_try_get_string_func, zval_update_constant_ex,
+    GC_IMMUTABLE, IS_ARRAY, IS_ARRAY_EX, IS_CONSTANT_AST, IS_DOUBLE, IS_FALSE, IS_INDIRECT,
+    IS_INTERNED_STRING_EX, IS_LONG, IS_NULL, IS_OBJECT, IS_PTR, IS_REFERENCE, IS_REFERENCE_EX,
+    IS_RESOURCE, IS_STRING, IS_STRING_EX, IS_TRUE, IS_TYPE_REFCOUNTED, IS_UNDEF,
 };
 
+use crate::errors::{Error, Result};
 use crate::php::{
-    enums::DataType,
-    types::{long::ZendLong, string::ZendString},
+    class::ClassEntry,
+    enums::{DataType, NumericValue},
+    errors::ExceptionMark,
+    types::{callable::ZendCallable, long::ZendLong, object::ZendObject, string::ZendString},
 };
 
-use super::array::ZendHashTable;
+use super::array::{ArrayKey, HashTableMut, HashTableRef, ZendHashTable};
 
 /// Zend value. Represents most data types that are in the Zend engine.
 pub type Zval = zval;
@@ -34,52 +52,339 @@ impl<'a> Zval {
         }
     }
 
+    /// Creates a new zval containing a reference to the given value. See
+    /// [`Zval::set_reference`].
+    ///
+    /// # Parameters
+    ///
+    /// * `inner` - The value to wrap in the reference.
+    pub fn new_reference(inner: Zval) -> Zval {
+        let mut zv = Self::new();
+        zv.set_reference(inner);
+        zv
+    }
+
+    /// Creates a new zval containing a list of the values of the iterator. Unlike collecting
+    /// into a zval, the values only need to implement [`IntoZval`], so strings can be
+    /// collected. See [`Zval::set_array_from_iter`].
+    ///
+    /// # Parameters
+    ///
+    /// * `iter` - The values to collect into the array.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The new zval containing the array.
+    /// * `Err(Error)` - A value could not be converted into a zval. Values which were
+    ///   already inserted are released.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::php::types::zval::Zval;
+    ///
+    /// let words = Zval::try_from_iter("a b c".split(' ').map(String::from)).unwrap();
+    /// ```
+    pub fn try_from_iter<I, T>(iter: I) -> Result<Zval>
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoZval,
+    {
+        let mut zv = Self::new();
+        zv.set_array_from_iter(iter)?;
+        Ok(zv)
+    }
+
+    /// Creates a new zval containing an associative array of the key-value pairs of the
+    /// iterator. See [`Zval::try_from_iter`] and [`Zval::set_assoc_array_from_iter`].
+    ///
+    /// # Parameters
+    ///
+    /// * `iter` - The key-value pairs to collect into the array.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The new zval containing the array.
+    /// * `Err(Error)` - A value could not be converted into a zval. Values which were
+    ///   already inserted are released.
+    pub fn try_from_assoc_iter<I, K, V>(iter: I) -> Result<Zval>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<ArrayKey>,
+        V: IntoZval,
+    {
+        let mut zv = Self::new();
+        zv.set_assoc_array_from_iter(iter)?;
+        Ok(zv)
+    }
+
     /// Returns the value of the zval if it is a long.
     pub fn long(&self) -> Option<ZendLong> {
-        if self.is_long() {
-            Some(unsafe { self.value.lval })
+        let zv = self.dereference();
+
+        if zv.is_long() {
+            Some(unsafe { zv.value.lval })
         } else {
             None
         }
     }
 
+    /// Converts the value of the zval into a long, following the same rules as an `(int)` cast
+    /// in PHP. For example, `"42abc"` becomes `42`, `"1e3"` becomes `1000`, `true` becomes `1`
+    /// and `9.9` is truncated to `9`. Infinity and NaN become `0`.
+    ///
+    /// Use [`Zval::long`] to only accept values which are already longs.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ZendLong)` - The converted long.
+    /// * `Err(Error::Exception)` - The conversion threw an exception, e.g. from the cast
+    ///   handler of an object. The exception is left pending.
+    pub fn coerce_to_long(&self) -> Result<ZendLong> {
+        let ptr: *const Self = self.dereference();
+        let mark = ExceptionMark::new();
+        let val = unsafe { zval_get_long_func(ptr as *mut Self) };
+
+        mark.check(val)
+    }
+
     /// Returns the value of the zval if it is a bool.
     pub fn bool(&self) -> Option<bool> {
-        if self.is_true() {
+        let zv = self.dereference();
+
+        if zv.is_true() {
             Some(true)
-        } else if self.is_false() {
+        } else if zv.is_false() {
             Some(false)
         } else {
             None
         }
     }
 
+    /// Returns whether the value of the zval is considered true by PHP, following the same rules
+    /// as a `(bool)` cast. `null`, `false`, `0`, `0.0`, `""`, `"0"` and empty arrays are false,
+    /// while everything else is true, including `NAN`, resources and objects. Use
+    /// [`Zval::bool`] to only accept values which are already booleans.
+    pub fn truthy(&self) -> bool {
+        let zv = self.dereference();
+
+        match zv.get_type() {
+            Some(DataType::True) => true,
+            Some(DataType::Long) => unsafe { zv.value.lval != 0 },
+            Some(DataType::Double) => unsafe { zv.value.dval != 0.0 },
+            Some(DataType::String) => !matches!(zv.str_bytes(), Some(b"") | Some(b"0")),
+            Some(DataType::Array) => unsafe { (*zv.value.arr).nNumOfElements != 0 },
+            Some(DataType::Resource) => true,
+            // Objects are true unless an internal class overrides the cast, such as
+            // `SimpleXMLElement`, so the engine is left to decide.
+            Some(DataType::Object) => {
+                let ptr: *const Self = zv;
+                unsafe { zend_is_true(ptr as *mut Self) != 0 }
+            }
+            _ => false,
+        }
+    }
+
     /// Returns the value of the zval if it is a double.
     pub fn double(&self) -> Option<f64> {
-        if self.is_double() {
-            Some(unsafe { self.value.dval })
+        let zv = self.dereference();
+
+        if zv.is_double() {
+            Some(unsafe { zv.value.dval })
         } else {
             self.long().map(|x| x as f64)
         }
     }
 
-    /// Returns the value of the zval if it is a string.
+    /// Returns the value of the zval if it is a string. Other types are not converted, use
+    /// [`Zval::coerce_to_string`] to convert them as PHP would.
+    ///
+    /// Returns `None` if the string is not valid UTF-8. Use [`Zval::binary`] or
+    /// [`Zval::string_lossy`] to read strings that may contain arbitrary bytes.
     pub fn string(&self) -> Option<String> {
-        if self.is_string() {
-            // SAFETY: Zend strings have a length that we know we can read.
-            // By reading this many bytes we will not run into any issues.
-            //
-            // We can safely cast our *const c_char into a *const u8 as both
-            // only occupy one byte.
-            unsafe {
-                let len = (*self.value.str).len;
-                let ptr = (*self.value.str).val.as_ptr() as *const u8;
-                let _str = std::str::from_utf8(slice::from_raw_parts(ptr, len as usize)).unwrap();
-
-                Some(_str.to_string())
+        std::str::from_utf8(self.str_bytes()?)
+            .ok()
+            .map(|s| s.to_string())
+    }
+
+    /// Converts the value of the zval into a string, following the same rules as a
+    /// `(string)` cast in PHP. For example, floats are formatted according to the
+    /// `precision` INI setting, `null` and `false` become an empty string, and objects are
+    /// converted through `__toString`.
+    ///
+    /// Converting an array emits the same warning as PHP, and returns `"Array"`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The converted string.
+    /// * `Err(Error::Exception)` - The value could not be converted, e.g. it was an object
+    ///   without a `__toString` method. The `Error` thrown by PHP is left pending.
+    /// * `Err(Error::ZvalConversion)` - The converted string was not valid UTF-8.
+    pub fn coerce_to_string(&self) -> Result<String> {
+        self.coerce_to_zend_string()?
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or(Error::ZvalConversion)
+    }
+
+    /// Converts the value of the zval into a Zend string, following the same rules as
+    /// [`Zval::coerce_to_string`], but keeping strings which are not valid UTF-8.
+    pub(crate) fn coerce_to_zend_string(&self) -> Result<ZendString> {
+        let ptr: *const Self = self.dereference();
+        let mark = ExceptionMark::new();
+        let zs = unsafe { zval_try_get_string_func(ptr as *mut Self) };
+
+        unsafe { ZendString::from_raw(zs) }.ok_or_else(|| {
+            mark.thrown()
+                .map_or(Error::ZvalConversion, Error::Exception)
+        })
+    }
+
+    /// Converts the zval into a long in place, following the same rules as
+    /// [`Zval::coerce_to_long`]. The previous value is released. If the zval is a reference,
+    /// the referenced value is converted.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The zval was converted.
+    /// * `Err(Error::Exception)` - The conversion threw an exception, e.g. from the cast
+    ///   handler of an object. The exception is left pending.
+    pub fn convert_to_long(&mut self) -> Result<()> {
+        let mark = ExceptionMark::new();
+        unsafe { convert_to_long(self.dereference_mut()) };
+        mark.check(())
+    }
+
+    /// Converts the zval into a double in place, following the same rules as a `(float)`
+    /// cast in PHP. See [`Zval::convert_to_long`].
+    pub fn convert_to_double(&mut self) -> Result<()> {
+        let mark = ExceptionMark::new();
+        unsafe { convert_to_double(self.dereference_mut()) };
+        mark.check(())
+    }
+
+    /// Converts the zval into a bool in place, following the same rules as
+    /// [`Zval::truthy`]. See [`Zval::convert_to_long`].
+    pub fn convert_to_bool(&mut self) -> Result<()> {
+        let mark = ExceptionMark::new();
+        unsafe { convert_to_boolean(self.dereference_mut()) };
+        mark.check(())
+    }
+
+    /// Converts the zval into a string in place, following the same rules as
+    /// [`Zval::coerce_to_string`]. Converting an array emits the same warning as PHP. See
+    /// [`Zval::convert_to_long`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The zval was converted.
+    /// * `Err(Error::Exception)` - The value could not be converted, e.g. it was an object
+    ///   without a `__toString` method, or the warning was turned into an exception by an
+    ///   error handler. The exception is left pending and the zval is left unchanged.
+    pub fn convert_to_string(&mut self) -> Result<()> {
+        let zv = self.dereference_mut();
+
+        // Strings are left as they are, as the `try_convert_to_string` macro does.
+        if zv.is_string() {
+            return Ok(());
+        }
+
+        let mark = ExceptionMark::new();
+
+        if unsafe { _try_convert_to_string(zv) } {
+            mark.check(())
+        } else {
+            Err(mark
+                .thrown()
+                .map_or(Error::ZvalConversion, Error::Exception))
+        }
+    }
+
+    /// Converts the zval into an array in place, following the same rules as an `(array)`
+    /// cast in PHP. `null` becomes an empty array, objects are converted into an array of
+    /// their properties, and any other value becomes an array containing the value. See
+    /// [`Zval::convert_to_long`].
+    pub fn convert_to_array(&mut self) -> Result<()> {
+        let mark = ExceptionMark::new();
+        unsafe { convert_to_array(self.dereference_mut()) };
+        mark.check(())
+    }
+
+    /// Coerces an argument in place into the given scalar type, following the rules for
+    /// arguments passed by a caller which does not use strict types. For example, `"5"` is
+    /// accepted as an `int` while `"abc"` is not, and `"5 apples"` is accepted with a warning.
+    /// Values which already have the type are left unchanged.
+    ///
+    /// # Returns
+    ///
+    /// Whether the value was accepted and converted. The value is left unchanged if it was
+    /// not accepted.
+    pub(crate) fn coerce_arg_weak(&mut self, ty: DataType) -> bool {
+        let ptr: *mut Self = self;
+
+        match ty {
+            DataType::Long if !self.is_long() => {
+                let mut val = 0;
+                let ok = unsafe { zend_parse_arg_long_weak(ptr, &mut val) };
+                if ok {
+                    self.replace_scalar(|zv| zv.set_long(val));
+                }
+                ok
+            }
+            DataType::Double if !self.is_double() => {
+                let mut val = 0.0;
+                let ok = unsafe { zend_parse_arg_double_weak(ptr, &mut val) };
+                if ok {
+                    self.replace_scalar(|zv| zv.set_double(val));
+                }
+                ok
+            }
+            DataType::Bool if !self.is_bool() => {
+                let mut val = false;
+                let ok = unsafe { zend_parse_arg_bool_weak(ptr, &mut val) };
+                if ok {
+                    self.replace_scalar(|zv| zv.set_bool(val));
+                }
+                ok
+            }
+            // Strings are converted in place by the engine.
+            DataType::String if !self.is_string() => {
+                let mut val = ptr::null_mut();
+                unsafe { zend_parse_arg_str_weak(ptr, &mut val) }
             }
+            _ => true,
+        }
+    }
+
+    /// Sets the zval to a scalar value with `set`, releasing the previous value.
+    fn replace_scalar(&mut self, set: impl FnOnce(&mut Self)) {
+        let old = *self;
+        set(self);
+        old.release();
+    }
+
+    /// Returns the value of the zval if it is a string, replacing any invalid UTF-8
+    /// sequences with the Unicode replacement character.
+    pub fn string_lossy(&self) -> Option<String> {
+        self.str_bytes()
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Returns the raw bytes of the zval if it is a string. Unlike [`Zval::string`], the
+    /// contents of the string are not required to be valid UTF-8, making this suitable for
+    /// binary data.
+    pub fn binary(&self) -> Option<Vec<u8>> {
+        self.str_bytes().map(|bytes| bytes.to_vec())
+    }
+
+    /// Returns a slice over the contents of the zval if it is a string.
+    pub(crate) fn str_bytes(&self) -> Option<&[u8]> {
+        let zv = self.dereference();
+
+        if zv.is_string() {
+            Some(unsafe { &*zv.value.str }.as_bytes())
         } else {
-            self.double().map(|x| x.to_string())
+            None
         }
     }
 
@@ -87,95 +392,438 @@ impl<'a> Zval {
     pub fn resource(&self) -> Option<*mut zend_resource> {
         // TODO: Can we improve this function? I haven't done much research into
         // resources so I don't know if this is the optimal way to return this.
-        if self.is_resource() {
-            Some(unsafe { self.value.res })
+        let zv = self.dereference();
+
+        if zv.is_resource() {
+            Some(unsafe { zv.value.res })
         } else {
             None
         }
     }
 
     /// Returns the value of the zval if it is an array.
-    pub fn array(&self) -> Option<ZendHashTable> {
-        if self.is_array() {
-            Some(ZendHashTable::from_ptr(unsafe { self.value.arr }))
+    ///
+    /// The array may be shared with other PHP variables, or be an immutable array literal, and
+    /// therefore must only be read from. Use [`Zval::array_mut`] to modify the array.
+    pub fn array(&self) -> Option<HashTableRef<'_>> {
+        let zv = self.dereference();
+
+        if zv.is_array() {
+            Some(HashTableRef::from_ptr(unsafe { zv.value.arr }))
         } else {
             None
         }
     }
 
+    /// Returns the value of the zval if it is an array, separating the array so that it can be
+    /// modified without affecting other variables.
+    ///
+    /// PHP arrays are copy-on-write: if the array is shared with other variables or is
+    /// immutable, it is duplicated and the zval is updated to point to the copy, equivalent to
+    /// the `SEPARATE_ARRAY` macro. If the zval is a reference, the referenced array is
+    /// separated, so changes are visible through the reference.
+    pub fn array_mut(&mut self) -> Option<HashTableMut<'_>> {
+        let zv = self.dereference_mut();
+
+        if !zv.is_array() {
+            return None;
+        }
+
+        let mut ht = HashTableMut::from_ptr(unsafe { zv.value.arr });
+        if ht.separate() {
+            zv.value.arr = ht.ptr;
+            zv.u1.type_info = IS_ARRAY_EX;
+        }
+
+        Some(ht)
+    }
+
     /// Returns the value of the zval if it is an object.
-    pub fn object(&self) -> Option<*mut zend_object> {
-        // TODO: Can we improve this function? I haven't done much research into
-        // objects so I don't know if this is the optimal way to return this.
-        if self.is_object() {
-            Some(unsafe { self.value.obj })
+    pub fn object(&self) -> Option<&ZendObject> {
+        let zv = self.dereference();
+
+        if zv.is_object() {
+            unsafe { zv.value.obj.as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value of the zval if it is an object, e.g. to write
+    /// its properties with [`ZendObject::set_property`].
+    pub fn object_mut(&mut self) -> Option<&mut ZendObject> {
+        let zv = self.dereference_mut();
+
+        if zv.is_object() {
+            unsafe { zv.value.obj.as_mut() }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value of the zval if it is a pointer. Pointer zvals are used by the engine
+    /// inside internal hash tables, such as the module registry and the class table, and are
+    /// never exposed to PHP code.
+    pub fn ptr(&self) -> Option<*mut c_void> {
+        if self.is_ptr() {
+            Some(unsafe { self.value.ptr })
         } else {
             None
         }
     }
 
-    /// Returns the value of the zval if it is a reference.
-    pub fn reference(&self) -> Option<Zval> {
-        if self.is_reference() {
-            Some(unsafe { (*self.value.ref_).val })
+    /// Returns the value the zval refers to, following any references (including references
+    /// to references) and indirect zvals until a value is reached. If the zval is neither a
+    /// reference nor indirect, the zval itself is returned.
+    ///
+    /// The getters on [`Zval`] dereference the zval before reading its value, so this is only
+    /// required when inspecting the type of the zval.
+    pub fn dereference(&self) -> &Zval {
+        let mut zv = self;
+
+        loop {
+            zv = if zv.is_reference() {
+                unsafe { &(*zv.value.ref_).val }
+            } else if zv.is_indirect() {
+                unsafe { &*zv.value.zv }
+            } else {
+                return zv;
+            };
+        }
+    }
+
+    /// Returns a mutable borrow of the value the zval refers to, following any references
+    /// and indirect zvals until a value is reached. Writing to the returned zval modifies the
+    /// referenced variable, which allows assigning to parameters passed by reference. If the
+    /// zval is neither a reference nor indirect, the zval itself is returned.
+    pub fn dereference_mut(&mut self) -> &mut Zval {
+        let mut zv = self;
+
+        loop {
+            zv = if zv.is_reference() {
+                unsafe { &mut (*zv.value.ref_).val }
+            } else if zv.is_indirect() {
+                unsafe { &mut *zv.value.zv }
+            } else {
+                return zv;
+            };
+        }
+    }
+
+    /// Returns the pointer to the zval the zval points to if it is indirect. Unlike
+    /// [`Zval::dereference`], only a single level of indirection is followed, and references
+    /// are not followed.
+    pub fn indirect(&self) -> Option<*mut Zval> {
+        if self.is_indirect() {
+            Some(unsafe { self.value.zv })
         } else {
             None
         }
     }
 
-    /// Attempts to call the argument as a callable with a list of arguments to pass to the function.
-    /// Note that a thrown exception inside the callable is not detectable, therefore you should
-    /// check if the return value is valid rather than unwrapping.
+    /// Writes a value into the variable referenced by the zval, releasing the previous value.
+    /// This is used to assign to parameters which were passed by reference, such as `&$out`.
+    ///
+    /// The zval itself is not modified, only the value it references, therefore this only
+    /// requires a shared reference to the zval.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to write into the reference.
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if the zval is not a reference, or the reference is bound to a typed
+    /// property which does not accept the value. In both cases, the value is released.
+    pub fn set_value_of_reference(&self, mut val: Zval) -> Result<()> {
+        if !self.is_reference() {
+            val.release();
+            return Err(Error::ZvalConversion);
+        }
+
+        let ref_ = unsafe { self.value.ref_ };
+
+        // References to typed properties must go through the engine so that the value is
+        // checked (and possibly coerced) against the type of the property.
+        if unsafe { !(*ref_).sources.ptr.is_null() } {
+            let result = unsafe { zend_try_assign_typed_ref(ref_, &mut val) };
+
+            return if result < 0 {
+                Err(Error::ZvalConversion)
+            } else {
+                Ok(())
+            };
+        }
+
+        let old = unsafe { ptr::replace(&mut (*ref_).val, val) };
+        old.release();
+
+        Ok(())
+    }
+
+    /// Returns the zval as a [`ZendCallable`] if it is callable. The callable borrows the
+    /// zval, and can be called any number of times without checking the zval again.
+    pub fn callable(&self) -> Option<ZendCallable<'_>> {
+        ZendCallable::new(self)
+    }
+
+    /// Attempts to call the zval as a callable with a list of arguments to pass to the
+    /// function. See [`ZendCallable::call`].
     ///
     /// You should not call this function directly, rather through the [`call_user_func`] macro.
     ///
     /// # Parameters
     ///
-    /// * `params` - A list of parameters to call the function with.
+    /// * `params` - A list of parameters to call the function with, e.g. `&[&5, &"hello"]`.
     ///
     /// # Returns
     ///
-    /// * `Some(Zval)` - The result of the function call.
-    /// * `None` - The zval was not callable or the call failed.
-    pub fn try_call(&self, params: Vec<Zval>) -> Option<Zval> {
-        let mut retval = Zval::new();
-        let len = params.len();
-        let packed = Box::into_raw(params.into_boxed_slice()) as *mut Self;
-        let ptr: *const Self = self;
+    /// * `Ok(Zval)` - The result of the function call.
+    /// * `Err(Error::Callable)` - The zval was not callable.
+    /// * `Err(Error)` - The call failed or threw an exception, or a parameter could not be
+    ///   converted into a zval.
+    pub fn try_call(&self, params: &[&dyn IntoZvalDyn]) -> Result<Zval> {
+        self.callable().ok_or(Error::Callable)?.call(params)
+    }
 
-        if !self.is_callable() {
-            return None;
+    /// Attempts to call a method on the object contained in the zval, equivalent to
+    /// `$obj->name(...$params)` in PHP. Methods handled by `__call` can also be called.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the method to call.
+    /// * `params` - A list of parameters to call the method with, e.g. `&[&5, &"hello"]`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The result of the method call.
+    /// * `Err(Error::ZvalConversion)` - The zval was not an object.
+    /// * `Err(Error::UndefinedMethod)` - The object has no method with the given name which is
+    ///   callable from the current scope, and does not implement `__call`.
+    /// * `Err(Error)` - The call failed or threw an exception, or a parameter could not be
+    ///   converted into a zval.
+    pub fn try_call_method(&self, name: &str, params: &[&dyn IntoZvalDyn]) -> Result<Zval> {
+        let obj = self.dereference();
+
+        if !obj.is_object() {
+            return Err(Error::ZvalConversion);
         }
 
-        let result = unsafe {
-            _call_user_function_impl(
-                std::ptr::null_mut(),
-                ptr as *mut Self,
-                &mut retval,
-                len as _,
-                packed,
-                std::ptr::null_mut(),
-            )
-        };
+        // Methods are called through a callable array in the form `[$obj, 'name']`.
+        let mut ht = ZendHashTable::with_capacity(2);
+        ht.push(obj.shallow_clone())?;
+        ht.push(name)?;
 
-        // SAFETY: We just boxed this vector, and the `_call_user_function_impl` does not modify the parameters.
-        // We can safely reclaim the memory knowing it will have the same length and size.
-        // If any parameters are zend strings, they must be released.
-        unsafe {
-            let params = Vec::from_raw_parts(packed, len, len);
+        let mut callable = Zval::new();
+        callable.set_request_array(ht);
 
-            for param in params {
-                if param.is_string() {
-                    ext_php_rs_zend_string_release(param.value.str);
-                }
-            }
+        let result = match callable.callable() {
+            Some(callable) => callable.call(params),
+            None => Err(Error::UndefinedMethod(name.to_string())),
         };
 
+        callable.release();
+        result
+    }
+
+    /// Evaluates the constant expression contained in the zval, returning the resulting value.
+    /// The zval itself is left untouched. If the zval is not a constant expression, a shallow
+    /// copy of the zval is returned.
+    ///
+    /// # Parameters
+    ///
+    /// * `scope` - The class used to resolve `self::` and `static::` inside the expression.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Zval)` - The value of the expression.
+    /// * `Err(Error::Exception)` - Evaluating the expression threw an exception, e.g. because it
+    ///   referenced an undefined constant.
+    /// * `Err(Error::ConstantEvaluation)` - The expression could not be evaluated.
+    pub fn eval_constant(&self, scope: Option<&ClassEntry>) -> Result<Zval> {
+        let mut val = self.shallow_clone();
+
+        if !val.is_constant_ast() {
+            return Ok(val);
+        }
+
+        let scope = scope.map_or(ptr::null_mut(), |ce| ce as *const ClassEntry as *mut _);
+        let mark = ExceptionMark::new();
+        let result = unsafe { zval_update_constant_ex(&mut val, scope) };
+
         if result < 0 {
-            None
+            val.release();
+
+            return Err(mark
+                .thrown()
+                .map_or(Error::ConstantEvaluation, Error::Exception));
+        }
+
+        Ok(val)
+    }
+
+    /// Releases the value contained in the zval, decrementing its reference count and freeing
+    /// it if there are no other references to it. Values which are not reference counted, such
+    /// as longs and interned strings, are left untouched.
+    ///
+    /// As [`Zval`] is `Copy`, values are not released when the zval is dropped. A zval which
+    /// was created in Rust (e.g. through one of the `From` implementations) and is not handed
+    /// over to PHP, by returning it or inserting it into an array, must be released with this
+    /// function, otherwise the value it contains will be leaked.
+    pub fn release(mut self) {
+        if self.is_refcounted() {
+            unsafe { zval_ptr_dtor(&mut self) };
+        }
+    }
+
+    /// Creates a shallow copy of the zval. If the zval contains a reference counted value
+    /// (a string, array, object, resource or reference), the reference count of the value is
+    /// incremented, so both zvals hold their own reference to it. Scalars and interned strings
+    /// are simply copied.
+    ///
+    /// The returned zval must be handed over to PHP or released with [`Zval::release`].
+    pub fn shallow_clone(&self) -> Zval {
+        let mut zv = *self;
+        zv.addref();
+        zv
+    }
+
+    /// Returns true if the zval contains a reference counted value, equivalent to the
+    /// `Z_REFCOUNTED` macro. Scalars, interned strings and immutable arrays are not reference
+    /// counted.
+    pub fn is_refcounted(&self) -> bool {
+        unsafe { self.u1.v.type_flags as u32 & IS_TYPE_REFCOUNTED != 0 }
+    }
+
+    /// Returns true if the zval contains an immutable string or array, such as an interned
+    /// string or an array cached by opcache. Immutable values are shared between requests,
+    /// and their reference count must never be modified.
+    pub fn is_immutable(&self) -> bool {
+        if !self.is_string() && !self.is_array() {
+            return false;
+        }
+
+        unsafe { (*self.value.counted).gc.u.type_info & GC_IMMUTABLE != 0 }
+    }
+
+    /// Returns the reference count of the value contained in the zval, or [`None`] if the
+    /// value is not reference counted. A reference zval returns the reference count of the
+    /// reference itself, not of the referenced value.
+    pub fn refcount(&self) -> Option<u32> {
+        if self.is_refcounted() && !self.is_immutable() {
+            Some(unsafe { (*self.value.counted).gc.refcount })
         } else {
-            Some(retval)
+            None
+        }
+    }
+
+    /// Increments the reference count of the value contained in the zval, equivalent to the
+    /// `Z_TRY_ADDREF` macro. Values which are not reference counted are left untouched.
+    ///
+    /// Every call must be balanced by handing a copy of the zval over to PHP, or by a call to
+    /// [`Zval::delref`] or [`Zval::release`], otherwise the value is leaked.
+    pub fn addref(&mut self) {
+        if self.refcount().is_some() {
+            unsafe { (*self.value.counted).gc.refcount += 1 };
+        }
+    }
+
+    /// Decrements the reference count of the value contained in the zval, equivalent to the
+    /// `Z_TRY_DELREF` macro, and returns the new reference count. Returns [`None`] if the
+    /// value is not reference counted.
+    ///
+    /// The value is not freed when the reference count reaches zero, the caller is
+    /// responsible for destroying it. Use [`Zval::release`] to decrement the reference count
+    /// and free the value if it is no longer used.
+    pub fn delref(&mut self) -> Option<u32> {
+        let refcount = self.refcount()?;
+        debug_assert!(refcount > 0, "reference count of zval is already zero");
+
+        unsafe {
+            (*self.value.counted).gc.refcount = refcount - 1;
         }
+
+        Some(refcount - 1)
+    }
+
+    /// Returns whether the zval is identical to another zval, following the same rules as the
+    /// `===` operator in PHP. Both zvals must have the same type and value, and objects must
+    /// be the same instance. This is the comparison used by the `PartialEq` implementation.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The zval to compare against.
+    pub fn identical(&self, other: &Zval) -> bool {
+        let lhs: *const Self = self.dereference();
+        let rhs: *const Self = other.dereference();
+
+        unsafe { zend_is_identical(lhs as *mut Self, rhs as *mut Self) }
+    }
+
+    /// Returns whether the zval is equal to another zval, following the same rules as the `==`
+    /// operator in PHP, e.g. `"1e1" == 10`.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The zval to compare against.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the zvals are equal.
+    /// * `Err(Error::Exception)` - The comparison threw an exception, e.g. from the compare
+    ///   handler of an object. The exception is left pending.
+    pub fn equals(&self, other: &Zval) -> Result<bool> {
+        self.compare(other).map(|ord| ord == Ordering::Equal)
+    }
+
+    /// Compares the zval to another zval, following the same rules as the `<=>` operator in
+    /// PHP. Note that PHP comparisons are not a total order, e.g. when comparing strings to
+    /// numbers, so the result is not suitable for every sorting algorithm.
+    ///
+    /// # Parameters
+    ///
+    /// * `other` - The zval to compare against.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Ordering)` - The ordering of this zval relative to `other`.
+    /// * `Err(Error::Exception)` - The comparison threw an exception, e.g. from the compare
+    ///   handler of an object. The exception is left pending.
+    pub fn compare(&self, other: &Zval) -> Result<Ordering> {
+        let lhs: *const Self = self;
+        let rhs: *const Self = other;
+        let mark = ExceptionMark::new();
+        let result = unsafe { zend_compare(lhs as *mut Self, rhs as *mut Self) };
+
+        mark.check(result.cmp(&0))
+    }
+
+    /// Returns the type of the value contained in the zval, or `None` if the zval contains
+    /// an internal engine type which is not represented by [`DataType`].
+    pub fn get_type(&self) -> Option<DataType> {
+        let ty = unsafe { self.u1.v.type_ } as u32;
+
+        Some(match ty {
+            IS_UNDEF => DataType::Undef,
+            IS_NULL => DataType::Null,
+            IS_FALSE => DataType::False,
+            IS_TRUE => DataType::True,
+            IS_LONG => DataType::Long,
+            IS_DOUBLE => DataType::Double,
+            IS_STRING => DataType::String,
+            IS_ARRAY => DataType::Array,
+            IS_OBJECT => DataType::Object,
+            IS_RESOURCE => DataType::Resource,
+            IS_REFERENCE => DataType::Reference,
+            IS_CONSTANT_AST => DataType::ConstantExpression,
+            _ => return None,
+        })
+    }
+
+    /// Returns true if the zval is undefined, false otherwise. Undefined zvals mark
+    /// uninitialized variables and properties, as well as removed elements of arrays.
+    pub fn is_undef(&self) -> bool {
+        unsafe { self.u1.v.type_ == DataType::Undef as u8 }
     }
 
     /// Returns true if the zval is a long, false otherwise.
@@ -233,24 +881,109 @@ impl<'a> Zval {
         unsafe { self.u1.v.type_ == DataType::Reference as u8 }
     }
 
+    /// Returns true if the zval is a pointer, false otherwise.
+    pub fn is_ptr(&self) -> bool {
+        unsafe { self.u1.v.type_ as u32 == IS_PTR }
+    }
+
+    /// Returns true if the zval is indirect, false otherwise. Indirect zvals are stored in
+    /// symbol tables and property tables, and point to the zval containing the actual value.
+    pub fn is_indirect(&self) -> bool {
+        unsafe { self.u1.v.type_ as u32 == IS_INDIRECT }
+    }
+
+    /// Returns true if the zval is an unevaluated constant expression, false otherwise.
+    /// Default values of properties and class constants are stored as constant expressions
+    /// until they are first used. See [`Zval::eval_constant`].
+    pub fn is_constant_ast(&self) -> bool {
+        unsafe { self.u1.v.type_ == DataType::ConstantExpression as u8 }
+    }
+
     /// Returns true if the zval is callable, false otherwise.
     pub fn is_callable(&self) -> bool {
         let ptr: *const Self = self;
         unsafe { zend_is_callable(ptr as *mut Self, 0, std::ptr::null_mut()) }
     }
 
-    /// Sets the value of the zval as a string.
+    /// Returns true if the zval is a numeric string, equivalent to calling `is_numeric` on a
+    /// string. Leading and trailing whitespace is allowed, but any other trailing data is
+    /// not. Returns false if the zval is not a string.
+    pub fn is_numeric(&self) -> bool {
+        self.parse_numeric().is_some()
+    }
+
+    /// Parses the zval if it is a numeric string. Integers which overflow a [`ZendLong`] are
+    /// returned as doubles. Returns [`None`] if the zval is not a string, or if the string is
+    /// not numeric, including when it contains trailing data.
+    pub fn parse_numeric(&self) -> Option<NumericValue> {
+        match self.numeric_string(false)? {
+            (val, false) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Parses the zval if it is a leading-numeric string such as `"123abc"`, which PHP
+    /// accepts in arithmetic with a warning. Returns the parsed value and whether trailing
+    /// data was present, or [`None`] if the zval is not a string or does not start with a
+    /// number.
+    pub fn parse_numeric_prefix(&self) -> Option<(NumericValue, bool)> {
+        self.numeric_string(true)
+    }
+
+    /// Parses the zval as a numeric string through `is_numeric_string_ex`.
+    ///
+    /// # Parameters
+    ///
+    /// * `allow_errors` - Whether strings with trailing data are accepted.
+    fn numeric_string(&self, allow_errors: bool) -> Option<(NumericValue, bool)> {
+        let bytes = self.str_bytes()?;
+        let mut lval: ZendLong = 0;
+        let mut dval: f64 = 0.0;
+        let mut trailing_data = false;
+
+        let type_ = unsafe {
+            ext_php_rs_is_numeric_string(
+                bytes.as_ptr() as *const c_char,
+                bytes.len() as _,
+                &mut lval,
+                &mut dval,
+                allow_errors,
+                &mut trailing_data,
+            )
+        };
+
+        match type_ as u32 {
+            IS_LONG => Some((NumericValue::Long(lval), trailing_data)),
+            IS_DOUBLE => Some((NumericValue::Double(dval), trailing_data)),
+            _ => None,
+        }
+    }
+
+    /// Sets the value of the zval as a string.
     ///
     /// # Parameters
     ///
     /// * `val` - The value to set the zval as.
-    pub fn set_string<S>(&mut self, val: S)
+    pub fn set_string<S>(&mut self, val: S) -> Result<()>
     where
         S: AsRef<str>,
     {
-        let zend_str = ZendString::new(val, false);
-        self.value.str = zend_str;
-        self.u1.type_info = IS_STRING_EX;
+        self.set_zend_string(ZendString::new(val, false)?);
+        Ok(())
+    }
+
+    /// Sets the value of the zval as a binary string. The bytes are copied into the string
+    /// as-is, and are not required to be valid UTF-8.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to set the zval as.
+    pub fn set_binary<B>(&mut self, val: B) -> Result<()>
+    where
+        B: AsRef<[u8]>,
+    {
+        self.set_zend_string(ZendString::from_bytes(val.as_ref(), false)?);
+        Ok(())
     }
 
     /// Sets the value of the zval as a persistent string.
@@ -260,13 +993,12 @@ impl<'a> Zval {
     /// # Parameters
     ///
     /// * `val` - The value to set the zval as.
-    pub fn set_persistent_string<S>(&mut self, val: S)
+    pub fn set_persistent_string<S>(&mut self, val: S) -> Result<()>
     where
         S: AsRef<str>,
     {
-        let zend_str = ZendString::new(val, true);
-        self.value.str = zend_str;
-        self.u1.type_info = IS_STRING_EX;
+        self.set_zend_string(ZendString::new(val, true)?);
+        Ok(())
     }
 
     /// Sets the value of the zval as a interned string.
@@ -274,13 +1006,61 @@ impl<'a> Zval {
     /// # Parameters
     ///
     /// * `val` - The value to set the zval as.
-    pub fn set_interned_string<S>(&mut self, val: S)
+    pub fn set_interned_string<S>(&mut self, val: S) -> Result<()>
     where
         S: AsRef<str>,
     {
-        let zend_str = ZendString::new_interned(val);
-        self.value.str = zend_str;
-        self.u1.type_info = IS_INTERNED_STRING_EX;
+        self.set_zend_string(ZendString::new_interned(val)?);
+        Ok(())
+    }
+
+    /// Sets the value of the zval as an existing Zend string, without copying it. The zval
+    /// takes over the reference to the string, so the reference count is not incremented.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The string to set the zval as, e.g. as returned by [`ZendString::new`].
+    pub fn set_zend_string(&mut self, val: ZendString) {
+        self.u1.type_info = if val.is_interned() {
+            IS_INTERNED_STRING_EX
+        } else {
+            IS_STRING_EX
+        };
+        self.value.str = val.into_raw();
+    }
+
+    /// Appends a string to the string contained in the zval, equivalent to `.=` in PHP. If
+    /// the zval is a reference, the referenced string is modified.
+    ///
+    /// The string is reallocated in place if it is not shared, see [`ZendString::append`], so
+    /// a string can be built by appending to it repeatedly without copying it each time.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The string to append.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The string was appended.
+    /// * `Err(Error::ZvalConversion)` - The zval does not contain a string.
+    /// * `Err(Error::StringTooLong)` - The string would exceed [`ZendString::max_len`]. The
+    ///   zval is not modified.
+    pub fn append_string(&mut self, val: &str) -> Result<()> {
+        let zv = self.dereference_mut();
+        if !zv.is_string() {
+            return Err(Error::ZvalConversion);
+        }
+
+        // The length is checked before the string is taken from the zval, as it is released
+        // if appending fails.
+        let len = unsafe { (*zv.value.str).len as usize }.saturating_add(val.len());
+        if len > ZendString::max_len() {
+            return Err(Error::StringTooLong(len));
+        }
+
+        let zs = unsafe { ZendString::from_raw(zv.value.str) }.ok_or(Error::InvalidPointer)?;
+        zv.set_zend_string(zs.append(val.as_bytes())?);
+        Ok(())
     }
 
     /// Sets the value of the zval as a long.
@@ -343,17 +1123,243 @@ impl<'a> Zval {
         self.value.obj = val;
     }
 
-    /// Sets the value of the zval as an array.
+    /// Sets the value of the zval as a pointer. This is only useful when inserting into the
+    /// engine's internal hash tables, which expect a specific type behind the pointer, e.g.
+    /// the module registry expects a `zend_module_entry`. Pointer zvals are not reference
+    /// counted, so the memory behind the pointer is not freed when the zval is released.
     ///
     /// # Parameters
     ///
     /// * `val` - The value to set the zval as.
-    pub fn set_array<V>(&mut self, val: V)
+    pub fn set_ptr(&mut self, val: *mut c_void) {
+        self.u1.type_info = IS_PTR;
+        self.value.ptr = val;
+    }
+
+    /// Sets the value of the zval as a reference to the given value. A new reference is
+    /// allocated with a reference count of one, which takes ownership of the value.
+    ///
+    /// # Parameters
+    ///
+    /// * `inner` - The value to wrap in the reference.
+    pub fn set_reference(&mut self, mut inner: Zval) {
+        self.value.ref_ = unsafe { ext_php_rs_zend_new_reference(&mut inner) };
+        self.u1.type_info = IS_REFERENCE_EX;
+    }
+
+    /// Sets the value of the zval as an array. The reference owned by the array is transferred
+    /// to the zval, so the array is no longer freed by Rust.
+    ///
+    /// # Parameters
+    ///
+    /// * `val` - The value to set the zval as, e.g. a hash table or a slice of zvals.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The zval was set as an array.
+    /// * `Err(Error::NotPersistent)` - The array is persistent, see
+    ///   [`ZendHashTable::new_persistent`]. Persistent arrays outlive the request and cannot be
+    ///   handed to PHP. The array is released and the zval is left unchanged.
+    pub fn set_array<T: Into<ZendHashTable>>(&mut self, val: T) -> Result<()> {
+        let val = val.into();
+
+        if val.is_persistent() {
+            return Err(Error::NotPersistent);
+        }
+
+        self.set_request_array(val);
+        Ok(())
+    }
+
+    /// Sets the value of the zval as an array created during the request, which cannot be
+    /// persistent. See [`Zval::set_array`].
+    pub(crate) fn set_request_array(&mut self, val: ZendHashTable) {
+        debug_assert!(
+            !val.is_persistent(),
+            "persistent arrays cannot be set in a zval"
+        );
+        self.u1.type_info = IS_ARRAY_EX;
+        self.value.arr = val.into_ptr();
+    }
+
+    /// Sets the value of the zval as a list containing the values of the iterator. The
+    /// array is allocated using the iterator's size hint, so results can be returned
+    /// without collecting them first.
+    ///
+    /// # Parameters
+    ///
+    /// * `iter` - The values to set the zval as.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The zval was set as an array.
+    /// * `Err(Error)` - A value could not be converted into a zval. Values which were
+    ///   already inserted are released, and the zval is left unchanged.
+    pub fn set_array_from_iter<I, T>(&mut self, iter: I) -> Result<()>
     where
-        V: Into<ZendHashTable>,
+        I: IntoIterator<Item = T>,
+        T: IntoZval,
     {
-        self.u1.type_info = DataType::Array as u32;
-        self.value.arr = val.into().into_ptr();
+        self.set_request_array(ZendHashTable::try_from_iter(iter)?);
+        Ok(())
+    }
+
+    /// Sets the value of the zval as an associative array containing the key-value pairs
+    /// of the iterator. Keys may be strings or integers, see [`ArrayKey`]. Later pairs
+    /// overwrite earlier pairs with the same key.
+    ///
+    /// # Parameters
+    ///
+    /// * `iter` - The key-value pairs to set the zval as.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The zval was set as an array.
+    /// * `Err(Error)` - A value could not be converted into a zval. Values which were
+    ///   already inserted are released, and the zval is left unchanged.
+    pub fn set_assoc_array_from_iter<I, K, V>(&mut self, iter: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<ArrayKey>,
+        V: IntoZval,
+    {
+        let iter = iter.into_iter();
+        let mut ht = ZendHashTable::for_iter(&iter, false);
+
+        for (key, val) in iter {
+            ht.insert(key, val)?;
+        }
+
+        self.set_request_array(ht);
+        Ok(())
+    }
+}
+
+/// Maximum depth of nested arrays and references printed by the [`Debug`] implementation
+/// of [`Zval`]. Prevents self-referential arrays from recursing forever.
+const DEBUG_MAX_DEPTH: usize = 8;
+
+/// Zvals are compared by identity, see [`Zval::identical`].
+impl PartialEq for Zval {
+    fn eq(&self, other: &Self) -> bool {
+        self.identical(other)
+    }
+}
+
+impl Debug for Zval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        DebugZval(self, 0).fmt(f)
+    }
+}
+
+/// Formats a zval at a given depth of nesting.
+struct DebugZval<'a>(&'a Zval, usize);
+
+impl Debug for DebugZval<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let DebugZval(zv, depth) = *self;
+
+        if let Some(ptr) = zv.ptr() {
+            return write!(f, "Ptr({:p})", ptr);
+        }
+
+        // Indirect zvals are transparent, the value they point to is printed instead.
+        if let Some(ptr) = zv.indirect() {
+            return DebugZval(unsafe { &*ptr }, depth).fmt(f);
+        }
+
+        let ty = match zv.get_type() {
+            Some(ty) => ty,
+            None => return write!(f, "Unknown({})", unsafe { zv.u1.v.type_ }),
+        };
+
+        match ty {
+            DataType::Long => f.debug_tuple("Long").field(&zv.long().unwrap()).finish(),
+            DataType::Double => f
+                .debug_tuple("Double")
+                .field(&zv.double().unwrap())
+                .finish(),
+            DataType::String => match zv.string() {
+                Some(val) => f.debug_tuple("String").field(&val).finish(),
+                None => {
+                    let bytes = zv.binary().unwrap_or_default();
+                    let escaped: String = bytes
+                        .iter()
+                        .flat_map(|b| std::ascii::escape_default(*b))
+                        .map(char::from)
+                        .collect();
+
+                    write!(f, "Binary(b\"{}\", len: {})", escaped, bytes.len())
+                }
+            },
+            DataType::Array => {
+                let ht = zv.array().unwrap();
+
+                if depth >= DEBUG_MAX_DEPTH {
+                    return write!(f, "Array([{} elements])", ht.len());
+                }
+
+                f.debug_tuple("Array")
+                    .field(&DebugArray(&ht, depth + 1))
+                    .finish()
+            }
+            DataType::Object => {
+                let obj = unsafe { &*zv.value.obj };
+                let name = unsafe { obj.ce.as_ref() }
+                    .and_then(|ce| unsafe { ce.name.as_ref() })
+                    .map(String::from)
+                    .unwrap_or_default();
+
+                write!(f, "Object({}, #{})", name, obj.handle)
+            }
+            DataType::Resource => {
+                let res = unsafe { &*zv.value.res };
+                write!(f, "Resource(#{})", res.handle)
+            }
+            DataType::Reference => {
+                let inner = unsafe { &(*zv.value.ref_).val };
+
+                if depth >= DEBUG_MAX_DEPTH {
+                    return write!(f, "Ref(..)");
+                }
+
+                f.debug_tuple("Ref")
+                    .field(&DebugZval(inner, depth + 1))
+                    .finish()
+            }
+            _ => write!(f, "{:?}", ty),
+        }
+    }
+}
+
+/// Formats the elements of an array at a given depth of nesting.
+struct DebugArray<'a>(&'a ZendHashTable, usize);
+
+impl Debug for DebugArray<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let DebugArray(ht, depth) = *self;
+        let mut map = f.debug_map();
+
+        for (key, val) in ht.iter() {
+            // In property tables, unset properties are indirect zvals pointing at an undefined
+            // zval.
+            let slot = match val.indirect() {
+                Some(ptr) => unsafe { &*ptr },
+                None => val,
+            };
+
+            if let Some(DataType::Undef) = slot.get_type() {
+                continue;
+            }
+
+            let val = DebugZval(val, depth);
+            match key {
+                ArrayKey::Str(key) => map.entry(&String::from_utf8_lossy(&key), &val),
+                ArrayKey::Long(idx) => map.entry(&(idx as ZendLong), &val),
+            };
+        }
+
+        map.finish()
     }
 }
 
@@ -367,6 +1373,63 @@ impl TryFrom<&Zval> for ZendLong {
     }
 }
 
+/// Implements `TryFrom<&Zval>` for integer types narrower than or differing in sign from
+/// [`ZendLong`], failing if the long does not fit into the target type.
+macro_rules! try_from_zval_int {
+    ($($t: ty),*) => {
+        $(
+            impl TryFrom<&Zval> for $t {
+                type Error = Error;
+                fn try_from(value: &Zval) -> Result<Self> {
+                    let val = value.long().ok_or(Error::ZvalConversion)?;
+
+                    <$t>::try_from(val)
+                        .map_err(|_| Error::IntegerOutOfRange(val.to_string(), stringify!($t)))
+                }
+            }
+        )*
+    };
+}
+
+try_from_zval_int!(i8, i16, i32, isize, u8, u16, u32);
+
+/// Converts a zval into a `u64`. As PHP does not have unsigned integers, large numbers are
+/// often passed as doubles, so a double is accepted if it holds an integer within range.
+impl TryFrom<&Zval> for u64 {
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self> {
+        if let Some(val) = value.long() {
+            return u64::try_from(val)
+                .map_err(|_| Error::IntegerOutOfRange(val.to_string(), "u64"));
+        }
+
+        let zv = value.dereference();
+
+        if !zv.is_double() {
+            return Err(Error::ZvalConversion);
+        }
+
+        let val = unsafe { zv.value.dval };
+
+        // `u64::MAX as f64` rounds up to 2^64, which is itself out of range.
+        if val.fract() == 0.0 && val >= 0.0 && val < u64::MAX as f64 {
+            Ok(val as u64)
+        } else {
+            Err(Error::IntegerOutOfRange(val.to_string(), "u64"))
+        }
+    }
+}
+
+/// Converts a zval into a `usize`, with the same rules as the conversion into `u64`.
+impl TryFrom<&Zval> for usize {
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self> {
+        let val = u64::try_from(value)?;
+
+        usize::try_from(val).map_err(|_| Error::IntegerOutOfRange(val.to_string(), "usize"))
+    }
+}
+
 impl TryFrom<&Zval> for bool {
     type Error = ();
     fn try_from(value: &Zval) -> Result<Self, Self::Error> {
@@ -397,16 +1460,607 @@ impl TryFrom<&Zval> for String {
     }
 }
 
+/// Converts a PHP list (an array with sequential integer keys starting at zero) into a vector.
+/// See the [`FromZval`] implementation.
+impl<T> TryFrom<&Zval> for Vec<T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self> {
+        Self::from_zval(value)
+    }
+}
+
+/// Converts a nullable zval. See the [`FromZval`] implementation.
+impl<T> TryFrom<&Zval> for Option<T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self, Self::Error> {
+        Self::from_zval(value)
+    }
+}
+
+/// Converts a PHP array into a hash map. See the [`FromZval`] implementation.
+impl<T> TryFrom<&Zval> for HashMap<String, T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self, Self::Error> {
+        Self::from_zval(value)
+    }
+}
+
+/// Converts a PHP array into an ordered map. See the [`FromZval`] implementation.
+impl<T> TryFrom<&Zval> for BTreeMap<String, T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    type Error = Error;
+    fn try_from(value: &Zval) -> Result<Self, Self::Error> {
+        Self::from_zval(value)
+    }
+}
+
+/// Returns a new reference to the array, see [`ZendHashTable::shallow_clone`].
 impl<'a, 'b> TryFrom<&'b Zval> for ZendHashTable {
-    type Error = ();
+    type Error = Error;
     fn try_from(value: &'b Zval) -> Result<Self, Self::Error> {
         match value.array() {
-            Some(val) => Ok(val),
-            _ => Err(()),
+            Some(val) => Ok(val.shallow_clone()),
+            _ => Err(Error::ZvalConversion),
+        }
+    }
+}
+
+/// Converts a zval into a Rust value. The value may borrow from the zval, such as a `&str`
+/// pointing into a PHP string, in which case it cannot outlive the zval.
+///
+/// This is the trait used to read the arguments passed to a function, see [`Arg::val`].
+///
+/// [`Arg::val`]: crate::php::args::Arg::val
+pub trait FromZval<'a>: Sized {
+    /// The PHP type accepted by the conversion, e.g. `int`, named in the `TypeError` thrown
+    /// when an argument cannot be converted. See [`Args`].
+    ///
+    /// [`Args`]: crate::php::args::Args
+    const TYPE: &'static str = "mixed";
+
+    /// Whether `null` is accepted by the conversion, in which case the type is prefixed with
+    /// `?` in the `TypeError`.
+    const NULLABLE: bool = false;
+
+    /// The type of argument accepted by the conversion, used to declare the argument, see
+    /// [`Arg::of`]. Arguments declared as `int`, `float`, `string` or `bool` are coerced into
+    /// the type before being converted, unless the caller uses strict types. For example,
+    /// `"5"` is accepted as an `int`. See [`Args`].
+    ///
+    /// [`Arg::of`]: crate::php::args::Arg::of
+    /// [`Args`]: crate::php::args::Args
+    const DATA_TYPE: DataType = DataType::Mixed;
+
+    /// Attempts to convert the zval into the value.
+    ///
+    /// # Parameters
+    ///
+    /// * `zval` - The zval to convert. References are followed to the value they point to.
+    fn from_zval(zval: &'a Zval) -> Result<Self>;
+}
+
+/// Implements [`FromZval`] for types which are converted through `TryFrom<&Zval>`.
+macro_rules! from_zval {
+    ($($t: ty => $type: literal as $data_type: ident),*) => {
+        $(
+            impl FromZval<'_> for $t {
+                const TYPE: &'static str = $type;
+                const DATA_TYPE: DataType = DataType::$data_type;
+
+                fn from_zval(zval: &Zval) -> Result<Self> {
+                    Self::try_from(zval).map_err(|_| Error::ZvalConversion)
+                }
+            }
+        )*
+    };
+}
+
+/// Implements [`FromZval`] for integer types, keeping the range error of the conversion.
+macro_rules! from_zval_int {
+    ($($t: ty),*) => {
+        $(
+            impl FromZval<'_> for $t {
+                const TYPE: &'static str = "int";
+                const DATA_TYPE: DataType = DataType::Long;
+
+                fn from_zval(zval: &Zval) -> Result<Self> {
+                    Self::try_from(zval)
+                }
+            }
+        )*
+    };
+}
+
+from_zval!(
+    ZendLong => "int" as Long,
+    bool => "bool" as Bool,
+    f64 => "float" as Double,
+    String => "string" as String,
+    ZendHashTable => "array" as Array
+);
+from_zval_int!(i8, i16, i32, isize, u8, u16, u32, u64, usize);
+
+impl<'a> FromZval<'a> for &'a Zval {
+    fn from_zval(zval: &'a Zval) -> Result<Self> {
+        Ok(zval)
+    }
+}
+
+/// Borrows the contents of a PHP string, failing if it is not valid UTF-8.
+impl<'a> FromZval<'a> for &'a str {
+    const TYPE: &'static str = "string";
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn from_zval(zval: &'a Zval) -> Result<Self> {
+        let bytes = zval.str_bytes().ok_or(Error::ZvalConversion)?;
+
+        std::str::from_utf8(bytes).map_err(|_| Error::ZvalConversion)
+    }
+}
+
+/// Converts a PHP string containing exactly one Unicode code point into a character. The
+/// string must be valid UTF-8, so `"é"` is accepted even though it is two bytes long, while
+/// a single byte which is not valid UTF-8 on its own, such as `"\xE9"`, is rejected.
+impl FromZval<'_> for char {
+    const TYPE: &'static str = "string";
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn from_zval(zval: &Zval) -> Result<Self> {
+        let mut chars = <&str>::from_zval(zval)?.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::ZvalConversion),
         }
     }
 }
 
+impl<'a> FromZval<'a> for ZendCallable<'a> {
+    const TYPE: &'static str = "callable";
+    const DATA_TYPE: DataType = DataType::Callable;
+
+    fn from_zval(zval: &'a Zval) -> Result<Self> {
+        Self::try_from(zval)
+    }
+}
+
+/// A PHP `null` is converted into `None`, while any other value must be convertable into `T`.
+impl<'a, T> FromZval<'a> for Option<T>
+where
+    T: FromZval<'a>,
+{
+    const TYPE: &'static str = T::TYPE;
+    const NULLABLE: bool = true;
+    const DATA_TYPE: DataType = T::DATA_TYPE;
+
+    fn from_zval(zval: &'a Zval) -> Result<Self> {
+        if zval.is_null() {
+            Ok(None)
+        } else {
+            T::from_zval(zval).map(Some)
+        }
+    }
+}
+
+/// Converts a PHP list (an array with sequential integer keys starting at zero) into a vector.
+/// Fails if the zval is not an array, contains string keys, has holes in its indices or if
+/// any element cannot be converted into `T`.
+impl<T> FromZval<'_> for Vec<T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    const TYPE: &'static str = "array";
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn from_zval(zval: &Zval) -> Result<Self> {
+        let ht = zval.array().ok_or(Error::ZvalConversion)?;
+        let mut vec = Vec::with_capacity(ht.len());
+
+        // Elements are visited in order, so the keys of a list count up from zero. A string
+        // key or a hole in the indices breaks the sequence.
+        for (i, (key, val)) in ht.iter().enumerate() {
+            if key != ArrayKey::Long(i as u64) {
+                return Err(Error::ZvalConversion);
+            }
+
+            vec.push(T::from_zval(val)?);
+        }
+
+        Ok(vec)
+    }
+}
+
+/// Converts a PHP array into a hash map. Numeric keys are converted into their string
+/// representation, the same way PHP does when casting array keys to strings.
+impl<T> FromZval<'_> for HashMap<String, T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    const TYPE: &'static str = "array";
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn from_zval(zval: &Zval) -> Result<Self> {
+        let ht = zval.array().ok_or(Error::ZvalConversion)?;
+        let mut map = HashMap::with_capacity(ht.len());
+
+        for (key, val) in ht.iter() {
+            let key = key.to_string();
+
+            match T::from_zval(val) {
+                Ok(val) => map.insert(key, val),
+                Err(_) => return Err(Error::ArrayElementConversion(key)),
+            };
+        }
+
+        Ok(map)
+    }
+}
+
+/// Converts a PHP array into an ordered map. See the [`HashMap`] implementation.
+impl<T> FromZval<'_> for BTreeMap<String, T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    const TYPE: &'static str = "array";
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn from_zval(zval: &Zval) -> Result<Self> {
+        Ok(HashMap::<String, T>::from_zval(zval)?.into_iter().collect())
+    }
+}
+
+/// Converts a Rust value into a zval, consuming the value. The value can either be converted
+/// into a new zval, or written directly into an existing one, such as the return value of a
+/// function.
+pub trait IntoZval: Sized {
+    /// The type of the zval the value is converted into, used to declare the return type of a
+    /// function, see [`FunctionBuilder::returns_type`].
+    ///
+    /// [`FunctionBuilder::returns_type`]: crate::php::function::FunctionBuilder::returns_type
+    const DATA_TYPE: DataType = DataType::Mixed;
+
+    /// Whether the value may be converted into `null`, in addition to [`IntoZval::DATA_TYPE`].
+    const NULLABLE: bool = false;
+
+    /// Writes the value into the given zval. The previous value of the zval is overwritten
+    /// without being released.
+    ///
+    /// # Parameters
+    ///
+    /// * `zv` - The zval to write the value into.
+    fn set_zval(self, zv: &mut Zval) -> Result<()>;
+
+    /// Converts the value into a new zval. The zval must be handed over to PHP or released
+    /// with [`Zval::release`].
+    fn into_zval(self) -> Result<Zval> {
+        let mut zv = Zval::new();
+        self.set_zval(&mut zv)?;
+        Ok(zv)
+    }
+}
+
+/// Implements [`IntoZval`] for integer types, failing rather than wrapping when the value
+/// does not fit into a [`ZendLong`].
+macro_rules! into_zval_int {
+    ($($t: ty),*) => {
+        $(
+            impl IntoZval for $t {
+                const DATA_TYPE: DataType = DataType::Long;
+
+                #[allow(clippy::useless_conversion)]
+                fn set_zval(self, zv: &mut Zval) -> Result<()> {
+                    let val = ZendLong::try_from(self)
+                        .map_err(|_| Error::IntegerOutOfRange(self.to_string(), "ZendLong"))?;
+                    zv.set_long(val);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+into_zval_int!(ZendLong, i8, i16, i32, isize, u8, u16, u32, u64, usize);
+
+impl IntoZval for bool {
+    const DATA_TYPE: DataType = DataType::Bool;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_bool(self);
+        Ok(())
+    }
+}
+
+impl IntoZval for f64 {
+    const DATA_TYPE: DataType = DataType::Double;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_double(self);
+        Ok(())
+    }
+}
+
+/// The unit type is converted into PHP `null`, allowing functions returning `()` to be
+/// wrapped.
+impl IntoZval for () {
+    const DATA_TYPE: DataType = DataType::Void;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_null();
+        Ok(())
+    }
+}
+
+/// Characters are converted into a string containing the UTF-8 encoding of the character.
+impl IntoZval for char {
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_string(self.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl IntoZval for String {
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_string(self)
+    }
+}
+
+impl IntoZval for ZendString {
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_zend_string(self);
+        Ok(())
+    }
+}
+
+impl IntoZval for &str {
+    const DATA_TYPE: DataType = DataType::String;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_string(self)
+    }
+}
+
+/// Ownership of the value is moved into the target zval, without changing its reference count.
+impl IntoZval for Zval {
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        *zv = self;
+        Ok(())
+    }
+}
+
+impl IntoZval for ZendHashTable {
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_array(self)
+    }
+}
+
+/// `None` is converted into PHP `null`.
+impl<T> IntoZval for Option<T>
+where
+    T: IntoZval,
+{
+    const DATA_TYPE: DataType = T::DATA_TYPE;
+    const NULLABLE: bool = true;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        match self {
+            Some(val) => val.set_zval(zv),
+            None => {
+                zv.set_null();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Converts a vector into a packed PHP array with sequential integer keys.
+impl<T> IntoZval for Vec<T>
+where
+    T: IntoZval,
+{
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_request_array(ZendHashTable::try_from_iter(self)?);
+        Ok(())
+    }
+}
+
+/// Converts a hash map into an associative PHP array. String keys may contain NUL bytes,
+/// and numeric string keys are converted into integers, see [`ArrayKey`].
+impl<K, V> IntoZval for HashMap<K, V>
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_request_array(ZendHashTable::try_from(self)?);
+        Ok(())
+    }
+}
+
+/// Converts an ordered map into an associative PHP array, preserving the order of the keys.
+impl<K, V> IntoZval for BTreeMap<K, V>
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    const DATA_TYPE: DataType = DataType::Array;
+
+    fn set_zval(self, zv: &mut Zval) -> Result<()> {
+        zv.set_request_array(ZendHashTable::try_from(self)?);
+        Ok(())
+    }
+}
+
+/// Implements the conversions between tuples and packed PHP arrays, where the elements of
+/// the tuple are stored at sequential integer keys starting at zero, e.g. `[$host, $port]`.
+///
+/// Converting an array into a tuple fails with [`Error::ArrayElementConversion`] naming the
+/// first index which is missing, cannot be converted or is one past the arity of the tuple.
+macro_rules! tuple_conversions {
+    ($len: literal; $($t: ident $idx: tt),+) => {
+        impl<$($t),+> IntoZval for ($($t,)+)
+        where
+            $($t: IntoZval),+
+        {
+            const DATA_TYPE: DataType = DataType::Array;
+
+            fn set_zval(self, zv: &mut Zval) -> Result<()> {
+                let mut ht = ZendHashTable::with_capacity($len);
+                $(ht.push(self.$idx)?;)+
+                zv.set_request_array(ht);
+                Ok(())
+            }
+        }
+
+        impl<$($t),+> TryFrom<($($t,)+)> for Zval
+        where
+            $($t: IntoZval),+
+        {
+            type Error = Error;
+            fn try_from(val: ($($t,)+)) -> Result<Self> {
+                val.into_zval()
+            }
+        }
+
+        impl<$($t),+> FromZval<'_> for ($($t,)+)
+        where
+            $($t: for<'a> FromZval<'a>),+
+        {
+            const TYPE: &'static str = "array";
+            const DATA_TYPE: DataType = DataType::Array;
+
+            fn from_zval(zval: &Zval) -> Result<Self> {
+                let ht = zval.array().ok_or(Error::ZvalConversion)?;
+
+                if ht.len() > $len {
+                    return Err(Error::ArrayElementConversion($len.to_string()));
+                }
+
+                Ok(($(
+                    ht.get_index($idx)
+                        .ok_or(Error::ZvalConversion)
+                        .and_then(|val| $t::from_zval(val))
+                        .map_err(|_| Error::ArrayElementConversion($idx.to_string()))?,
+                )+))
+            }
+        }
+
+        impl<$($t),+> TryFrom<&Zval> for ($($t,)+)
+        where
+            $($t: for<'a> FromZval<'a>),+
+        {
+            type Error = Error;
+            fn try_from(value: &Zval) -> Result<Self> {
+                Self::from_zval(value)
+            }
+        }
+    };
+}
+
+tuple_conversions!(2; A 0, B 1);
+tuple_conversions!(3; A 0, B 1, C 2);
+tuple_conversions!(4; A 0, B 1, C 2, D 3);
+tuple_conversions!(5; A 0, B 1, C 2, D 3, E 4);
+tuple_conversions!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_conversions!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_conversions!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Converts a borrowed Rust value into a zval. Unlike [`IntoZval`], the trait is object safe,
+/// allowing values of different types to be passed together, e.g. as the parameters of a call
+/// to a PHP function.
+pub trait IntoZvalDyn {
+    /// Converts the value into a new zval. The zval must be handed over to PHP or released
+    /// with [`Zval::release`].
+    fn as_zval(&self) -> Result<Zval>;
+}
+
+/// Implements [`IntoZvalDyn`] for types which can be cloned and converted into a zval.
+macro_rules! into_zval_dyn {
+    ($($t: ty),*) => {
+        $(
+            impl IntoZvalDyn for $t {
+                fn as_zval(&self) -> Result<Zval> {
+                    self.clone().into_zval()
+                }
+            }
+        )*
+    };
+}
+
+into_zval_dyn!(
+    ZendLong,
+    i8,
+    i16,
+    i32,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    bool,
+    f64,
+    char,
+    (),
+    String,
+    &str
+);
+
+/// The value of the zval is shared rather than copied, incrementing its reference count.
+impl IntoZvalDyn for Zval {
+    fn as_zval(&self) -> Result<Zval> {
+        Ok(self.shallow_clone())
+    }
+}
+
+impl<T> IntoZvalDyn for Option<T>
+where
+    T: IntoZvalDyn,
+{
+    fn as_zval(&self) -> Result<Zval> {
+        match self {
+            Some(val) => val.as_zval(),
+            None => Ok(Zval::new()),
+        }
+    }
+}
+
+impl<T> IntoZvalDyn for Vec<T>
+where
+    T: IntoZvalDyn,
+{
+    fn as_zval(&self) -> Result<Zval> {
+        let mut ht = ZendHashTable::with_capacity(self.len() as u32);
+
+        for val in self {
+            ht.push(val.as_zval()?)?;
+        }
+
+        ht.into_zval()
+    }
+}
+
 impl From<ZendLong> for Zval {
     fn from(val: ZendLong) -> Self {
         let mut zv = Self::new();
@@ -415,6 +2069,44 @@ impl From<ZendLong> for Zval {
     }
 }
 
+/// Implements `From` for integer types which always fit into a [`ZendLong`].
+macro_rules! zval_from_int {
+    ($($t: ty),*) => {
+        $(
+            impl From<$t> for Zval {
+                fn from(val: $t) -> Self {
+                    let mut zv = Self::new();
+                    zv.set_long(ZendLong::from(val));
+                    zv
+                }
+            }
+        )*
+    };
+}
+
+/// Implements `TryFrom` for integer types which may not fit into a [`ZendLong`], failing
+/// rather than wrapping when the value is out of range.
+macro_rules! try_zval_from_int {
+    ($($t: ty),*) => {
+        $(
+            impl TryFrom<$t> for Zval {
+                type Error = Error;
+                fn try_from(val: $t) -> Result<Self> {
+                    val.into_zval()
+                }
+            }
+        )*
+    };
+}
+
+zval_from_int!(i8, i16, i32, u8, u16);
+try_zval_from_int!(isize, usize, u64);
+
+#[cfg(target_pointer_width = "64")]
+zval_from_int!(u32);
+#[cfg(not(target_pointer_width = "64"))]
+try_zval_from_int!(u32);
+
 impl From<bool> for Zval {
     fn from(val: bool) -> Self {
         let mut zv = Self::new();
@@ -430,18 +2122,647 @@ impl From<f64> for Zval {
     }
 }
 
-impl From<String> for Zval {
-    fn from(val: String) -> Self {
+impl From<()> for Zval {
+    fn from(_: ()) -> Self {
+        Self::new()
+    }
+}
+
+/// Collects values into a packed PHP array, see the [`FromIterator`] implementation of
+/// [`ZendHashTable`]. Values which can only be converted with [`IntoZval`], such as strings,
+/// are collected with [`Zval::try_from_iter`] instead.
+impl<T> FromIterator<T> for Zval
+where
+    T: Into<Zval>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut zv = Self::new();
+        zv.set_request_array(iter.into_iter().collect());
+        zv
+    }
+}
+
+/// Collects key-value pairs into an associative PHP array, see the [`FromIterator`]
+/// implementation of [`ZendHashTable`] and [`Zval::try_from_assoc_iter`].
+impl<K, V> FromIterator<(K, V)> for Zval
+where
+    K: Into<ArrayKey>,
+    V: Into<Zval>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
         let mut zv = Self::new();
-        zv.set_string(val);
+        zv.set_request_array(iter.into_iter().collect());
         zv
     }
 }
 
-impl From<&str> for Zval {
-    fn from(val: &str) -> Self {
+impl From<char> for Zval {
+    fn from(val: char) -> Self {
+        let mut buf = [0; 4];
+        let bytes = val.encode_utf8(&mut buf).as_bytes();
+
+        // A character is at most four bytes long, so it never exceeds the maximum length of a
+        // string, and the engine bails out rather than returning a null pointer if the
+        // allocation fails.
         let mut zv = Self::new();
-        zv.set_string(val);
+        zv.u1.type_info = IS_STRING_EX;
+        zv.value.str = unsafe {
+            ext_php_rs_zend_string_init(bytes.as_ptr() as *const c_char, bytes.len() as u64, false)
+        };
         zv
     }
 }
+
+impl TryFrom<String> for Zval {
+    type Error = Error;
+    fn try_from(val: String) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+impl TryFrom<&str> for Zval {
+    type Error = Error;
+    fn try_from(val: &str) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+/// Converts an optional value into a zval. See the [`IntoZval`] implementation.
+impl<T> TryFrom<Option<T>> for Zval
+where
+    T: IntoZval,
+{
+    type Error = Error;
+    fn try_from(val: Option<T>) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+/// Converts a hash map into an associative PHP array. See the [`IntoZval`] implementation.
+impl<K, V> TryFrom<HashMap<K, V>> for Zval
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    type Error = Error;
+    fn try_from(val: HashMap<K, V>) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+/// Converts an ordered map into an associative PHP array. See the [`IntoZval`] implementation.
+impl<K, V> TryFrom<BTreeMap<K, V>> for Zval
+where
+    K: Into<ArrayKey>,
+    V: IntoZval,
+{
+    type Error = Error;
+    fn try_from(val: BTreeMap<K, V>) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+/// Converts a vector into a packed PHP array. See the [`IntoZval`] implementation.
+impl<T> TryFrom<Vec<T>> for Zval
+where
+    T: IntoZval,
+{
+    type Error = Error;
+    fn try_from(val: Vec<T>) -> Result<Self> {
+        val.into_zval()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryFrom, ffi::c_void, mem, ptr};
+
+    use super::{FromZval, IntoZval, IntoZvalDyn, Zval};
+    use crate::{
+        bindings::{
+            zend_reference, zend_string, HASH_FLAG_PACKED, IS_INDIRECT, IS_INTERNED_STRING_EX,
+            IS_REFERENCE_EX, IS_STRING_EX, IS_STR_INTERNED,
+        },
+        errors::Error,
+        php::types::{
+            array::ZendHashTable,
+            fixtures::{array_zval, bucket, fake_string, hash_table},
+            long::ZendLong,
+            string::ZendString,
+        },
+    };
+
+    #[test]
+    fn test_vec_from_zval() {
+        let mut empty = hash_table(&mut [], HASH_FLAG_PACKED);
+        let empty = array_zval(&mut empty);
+        assert_eq!(Vec::<ZendLong>::try_from(&empty), Ok(vec![]));
+
+        let mut buckets = [
+            bucket(0, None, Some(1)),
+            bucket(1, None, Some(2)),
+            bucket(2, None, Some(3)),
+        ];
+        let mut list = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let list = array_zval(&mut list);
+        assert_eq!(Vec::<ZendLong>::try_from(&list), Ok(vec![1, 2, 3]));
+        assert_eq!(Vec::<u8>::try_from(&list), Ok(vec![1, 2, 3]));
+
+        // Elements which cannot be converted fail the whole conversion.
+        assert_eq!(Vec::<String>::try_from(&list), Err(Error::ZvalConversion));
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&Zval::from(1)),
+            Err(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_vec_from_mixed_zval() {
+        let mut buf = fake_string(b"two", IS_STR_INTERNED, 0);
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, None, Some(0))];
+        buckets[1].val.u1.type_info = IS_INTERNED_STRING_EX;
+        buckets[1].val.value.str = buf.as_mut_ptr() as *mut zend_string;
+
+        let mut mixed = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let mixed = array_zval(&mut mixed);
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&mixed),
+            Err(Error::ZvalConversion)
+        );
+        assert_eq!(Vec::<String>::try_from(&mixed), Err(Error::ZvalConversion));
+        assert_eq!(
+            Vec::<Option<ZendLong>>::try_from(&mixed),
+            Err(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_vec_from_zval_keys() {
+        // String keys and holes in the indices are rejected.
+        let mut key = fake_string(b"a", 0, 0);
+        let mut buckets = [bucket(0, None, Some(1)), bucket(1, Some(&mut key), Some(2))];
+        let mut assoc = hash_table(&mut buckets, 0);
+        let assoc = array_zval(&mut assoc);
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&assoc),
+            Err(Error::ZvalConversion)
+        );
+
+        let mut buckets = [bucket(0, None, Some(1)), bucket(2, None, Some(3))];
+        let mut holes = hash_table(&mut buckets, 0);
+        let holes = array_zval(&mut holes);
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&holes),
+            Err(Error::ZvalConversion)
+        );
+
+        let mut buckets = [bucket(1, None, Some(1)), bucket(0, None, Some(0))];
+        let mut unordered = hash_table(&mut buckets, 0);
+        let unordered = array_zval(&mut unordered);
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&unordered),
+            Err(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_nested_vec_from_zval() {
+        let mut inner_a = [bucket(0, None, Some(1)), bucket(1, None, Some(2))];
+        let mut inner_a = hash_table(&mut inner_a, HASH_FLAG_PACKED);
+        let mut inner_b = hash_table(&mut [], HASH_FLAG_PACKED);
+
+        let mut buckets = [bucket(0, None, Some(0)), bucket(1, None, Some(0))];
+        buckets[0].val = array_zval(&mut inner_a);
+        buckets[1].val = array_zval(&mut inner_b);
+        let mut outer = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let outer = array_zval(&mut outer);
+
+        assert_eq!(
+            Vec::<Vec<ZendLong>>::try_from(&outer),
+            Ok(vec![vec![1, 2], vec![]])
+        );
+        assert_eq!(
+            Vec::<ZendLong>::try_from(&outer),
+            Err(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_tuple_wrong_arity() {
+        let mut buckets = [
+            bucket(0, None, Some(1)),
+            bucket(1, None, Some(2)),
+            bucket(2, None, Some(3)),
+        ];
+        let mut ht = hash_table(&mut buckets, HASH_FLAG_PACKED);
+        let zv = array_zval(&mut ht);
+
+        // The error names the first index past the arity of the tuple.
+        assert_eq!(
+            <(ZendLong, ZendLong)>::try_from(&zv),
+            Err(Error::ArrayElementConversion("2".to_string()))
+        );
+        assert_eq!(
+            <(ZendLong, ZendLong)>::try_from(&Zval::from(1)),
+            Err(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_string_not_converted() {
+        // Only strings are returned, other types are not converted.
+        assert_eq!(Zval::from(5).string(), None);
+        assert_eq!(Zval::from(1.5).string(), None);
+        assert_eq!(Zval::from(true).string(), None);
+        assert_eq!(Zval::new().string(), None);
+
+        let mut buf = fake_string(b"1.5", IS_STR_INTERNED, 0);
+        let mut zv = Zval::new();
+        zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+        zv.u1.type_info = IS_INTERNED_STRING_EX;
+
+        assert_eq!(zv.string(), Some("1.5".to_string()));
+        assert_eq!(zv.double(), None);
+        assert_eq!(zv.long(), None);
+    }
+
+    #[test]
+    fn test_convert_string_to_string() {
+        let mut buf = fake_string(b"abc", 0, 0);
+        let zs = buf.as_mut_ptr() as *mut zend_string;
+        let mut zv = Zval::new();
+        zv.value.str = zs;
+        zv.u1.type_info = IS_STRING_EX;
+
+        // Strings are not copied, so the string keeps its only reference.
+        assert_eq!(zv.convert_to_string(), Ok(()));
+        assert_eq!(unsafe { zv.value.str }, zs);
+        assert_eq!(unsafe { (*zs).gc.refcount }, 1);
+
+        let mut reference: zend_reference = unsafe { mem::zeroed() };
+        reference.val = zv;
+        let mut outer = Zval::new();
+        outer.value.ref_ = &mut reference;
+        outer.u1.type_info = IS_REFERENCE_EX;
+        assert_eq!(outer.convert_to_string(), Ok(()));
+        assert_eq!(outer.string(), Some("abc".to_string()));
+    }
+
+    #[test]
+    fn test_string_invalid_utf8() {
+        let mut buf = fake_string(&[0xC3, 0x28], 0, 0);
+        let mut zv = Zval::new();
+        zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+        zv.u1.type_info = IS_STRING_EX;
+
+        assert_eq!(zv.string(), None);
+        assert_eq!(zv.string_lossy(), Some("\u{FFFD}(".to_string()));
+        assert_eq!(zv.binary(), Some(vec![0xC3, 0x28]));
+    }
+
+    #[test]
+    fn test_set_zend_string() {
+        let mut buf = fake_string(b"hello", 0, 0);
+        let zs = buf.as_mut_ptr() as *mut zend_string;
+        let mut zv = Zval::new();
+
+        zv.set_zend_string(unsafe { ZendString::from_raw(zs) }.unwrap());
+        assert_eq!(unsafe { zv.u1.type_info }, IS_STRING_EX);
+        assert_eq!(zv.string(), Some("hello".to_string()));
+
+        unsafe { (*zs).gc.u.type_info = IS_STR_INTERNED };
+        zv.set_zend_string(unsafe { ZendString::from_raw(zs) }.unwrap());
+        assert_eq!(unsafe { zv.u1.type_info }, IS_INTERNED_STRING_EX);
+        assert_eq!(unsafe { (*zs).gc.refcount }, 1);
+
+        assert!(unsafe { ZendString::from_raw(ptr::null_mut()) }.is_none());
+    }
+
+    #[test]
+    fn test_append_string_not_string() {
+        let mut zv = Zval::new();
+        zv.set_long(5);
+
+        assert_eq!(zv.append_string("a"), Err(Error::ZvalConversion));
+        assert_eq!(zv.long(), Some(5));
+    }
+
+    #[test]
+    fn test_release_interned() {
+        let mut buf = fake_string(b"Foo::BAR", 0, 0);
+        let zs = buf.as_mut_ptr() as *mut zend_string;
+        unsafe { (*zs).gc.u.type_info = IS_STR_INTERNED };
+
+        let mut zv = Zval::new();
+        zv.set_zend_string(unsafe { ZendString::from_raw(zs) }.unwrap());
+        assert!(!zv.is_refcounted());
+
+        // Neither copying nor releasing an interned string touches its reference count, as
+        // happens to the parameters of `try_call`.
+        let copy = zv.shallow_clone();
+        copy.release();
+        zv.release();
+        assert_eq!(unsafe { (*zs).gc.refcount }, 1);
+    }
+
+    #[test]
+    fn test_shallow_clone() {
+        let mut buf = fake_string(b"hello", 0, 0);
+        let zs = buf.as_mut_ptr() as *mut zend_string;
+        let mut zv = Zval::new();
+        zv.value.str = zs;
+        zv.u1.type_info = IS_STRING_EX;
+
+        let copy = zv.shallow_clone();
+        assert_eq!(copy.string(), Some("hello".to_string()));
+        assert_eq!(unsafe { (*zs).gc.refcount }, 2);
+
+        zv.u1.type_info = IS_INTERNED_STRING_EX;
+        zv.shallow_clone();
+        assert_eq!(unsafe { (*zs).gc.refcount }, 2);
+    }
+
+    #[test]
+    fn test_refcount() {
+        let mut buf = fake_string(b"hello", 0, 0);
+        let zs = buf.as_mut_ptr() as *mut zend_string;
+        let mut zv = Zval::new();
+        zv.value.str = zs;
+        zv.u1.type_info = IS_STRING_EX;
+
+        assert!(zv.is_refcounted());
+        assert!(!zv.is_immutable());
+        assert_eq!(zv.refcount(), Some(1));
+
+        zv.addref();
+        assert_eq!(zv.refcount(), Some(2));
+        assert_eq!(zv.delref(), Some(1));
+
+        unsafe { (*zs).gc.u.type_info = IS_STR_INTERNED };
+        zv.u1.type_info = IS_INTERNED_STRING_EX;
+        assert!(!zv.is_refcounted());
+        assert!(zv.is_immutable());
+        assert_eq!(zv.refcount(), None);
+        zv.addref();
+        assert_eq!(zv.delref(), None);
+        assert_eq!(unsafe { (*zs).gc.refcount }, 1);
+
+        let mut long = Zval::new();
+        long.set_long(5);
+        assert_eq!(long.refcount(), None);
+        assert!(!long.is_immutable());
+    }
+
+    #[test]
+    fn test_dereference() {
+        let mut inner: zend_reference = unsafe { mem::zeroed() };
+        inner.val.set_long(5);
+
+        let mut outer: zend_reference = unsafe { mem::zeroed() };
+        outer.val.value.ref_ = &mut inner;
+        outer.val.u1.type_info = IS_REFERENCE_EX;
+
+        let mut zv = Zval::new();
+        zv.value.ref_ = &mut outer;
+        zv.u1.type_info = IS_REFERENCE_EX;
+
+        assert!(zv.is_reference());
+        assert!(zv.dereference().is_long());
+        assert_eq!(zv.long(), Some(5));
+
+        zv.dereference_mut().set_long(10);
+        assert_eq!(inner.val.long(), Some(10));
+    }
+
+    #[test]
+    fn test_integer_range() {
+        let mut zv = Zval::new();
+
+        zv.set_long(i32::MAX as ZendLong + 1);
+        assert_eq!(
+            i32::try_from(&zv),
+            Err(Error::IntegerOutOfRange("2147483648".to_string(), "i32"))
+        );
+        assert_eq!(u32::try_from(&zv), Ok(i32::MAX as u32 + 1));
+
+        zv.set_long(-1);
+        assert_eq!(
+            u32::try_from(&zv),
+            Err(Error::IntegerOutOfRange("-1".to_string(), "u32"))
+        );
+        assert!(u64::try_from(&zv).is_err());
+
+        zv.set_double(2f64.powi(53));
+        assert_eq!(u64::try_from(&zv), Ok(1 << 53));
+        assert_eq!(i32::try_from(&zv), Err(Error::ZvalConversion));
+
+        zv.set_double(1.5);
+        assert!(u64::try_from(&zv).is_err());
+
+        zv.set_double(2f64.powi(64));
+        assert!(u64::try_from(&zv).is_err());
+    }
+
+    #[test]
+    fn test_integer_into_zval() {
+        assert_eq!(Zval::from(-5i8).long(), Some(-5));
+        assert_eq!(Zval::from(u16::MAX).long(), Some(u16::MAX as ZendLong));
+        assert_eq!(Zval::try_from(5usize).unwrap().long(), Some(5));
+        assert_eq!(
+            Zval::try_from(usize::MAX).unwrap_err(),
+            Error::IntegerOutOfRange(usize::MAX.to_string(), "ZendLong")
+        );
+    }
+
+    #[test]
+    fn test_set_zval() {
+        let mut zv = Zval::new();
+        5u8.set_zval(&mut zv).unwrap();
+        assert_eq!(zv.long(), Some(5));
+
+        Option::<bool>::None.set_zval(&mut zv).unwrap();
+        assert!(zv.is_null());
+
+        assert_eq!(
+            u64::MAX.set_zval(&mut zv).unwrap_err(),
+            Error::IntegerOutOfRange(u64::MAX.to_string(), "ZendLong")
+        );
+    }
+
+    #[test]
+    fn test_from_zval_borrowed() {
+        let mut buf = fake_string(b"hello", 0, 0);
+        let mut zv = Zval::new();
+        zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+        zv.u1.type_info = IS_INTERNED_STRING_EX;
+
+        assert_eq!(<&str>::from_zval(&zv), Ok("hello"));
+        assert_eq!(Option::<&str>::from_zval(&zv), Ok(Some("hello")));
+        assert_eq!(ZendLong::from_zval(&zv), Err(Error::ZvalConversion));
+        assert!(<&Zval>::from_zval(&zv).unwrap().is_string());
+    }
+
+    #[test]
+    fn test_char() {
+        let from_bytes = |bytes: &[u8]| {
+            let mut buf = fake_string(bytes, 0, 0);
+            let mut zv = Zval::new();
+            zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+            zv.u1.type_info = IS_INTERNED_STRING_EX;
+            char::from_zval(&zv)
+        };
+
+        assert_eq!(from_bytes(b"a"), Ok('a'));
+        assert_eq!(from_bytes("é".as_bytes()), Ok('é'));
+        assert_eq!(from_bytes(b"\xE9"), Err(Error::ZvalConversion));
+        assert_eq!(from_bytes(b"ab"), Err(Error::ZvalConversion));
+        assert_eq!(from_bytes(b""), Err(Error::ZvalConversion));
+    }
+
+    #[test]
+    fn test_unit() {
+        let mut zv = Zval::from(5 as ZendLong);
+        ().set_zval(&mut zv).unwrap();
+        assert!(zv.is_null());
+        assert!(Zval::from(()).is_null());
+    }
+
+    #[test]
+    fn test_into_zval_dyn() {
+        let params: &[&dyn IntoZvalDyn] = &[&5, &true, &2.5, &Option::<ZendLong>::None];
+        let zvals = params
+            .iter()
+            .map(|param| param.as_zval().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(zvals[0].long(), Some(5));
+        assert_eq!(zvals[1].bool(), Some(true));
+        assert_eq!(zvals[2].double(), Some(2.5));
+        assert!(zvals[3].is_null());
+    }
+
+    #[test]
+    fn test_indirect() {
+        let mut slot = Zval::new();
+        slot.set_long(5);
+
+        let mut zv = Zval::new();
+        zv.value.zv = &mut slot;
+        zv.u1.type_info = IS_INDIRECT;
+
+        assert!(zv.is_indirect());
+        assert_eq!(zv.indirect(), Some(&mut slot as *mut Zval));
+        assert_eq!(zv.long(), Some(5));
+        assert_eq!(format!("{:?}", zv), "Long(5)");
+    }
+
+    #[test]
+    fn test_ptr() {
+        let mut val = 5;
+        let ptr = &mut val as *mut i32 as *mut c_void;
+
+        let mut zv = Zval::new();
+        assert_eq!(zv.ptr(), None);
+
+        zv.set_ptr(ptr);
+        assert!(zv.is_ptr());
+        assert_eq!(zv.ptr(), Some(ptr));
+        assert_eq!(zv.long(), None);
+        assert_eq!(format!("{:?}", zv), format!("Ptr({:p})", ptr));
+    }
+
+    #[test]
+    fn test_truthy() {
+        let mut zv = Zval::new();
+        assert!(!zv.truthy());
+
+        zv.set_long(0);
+        assert!(!zv.truthy());
+        zv.set_long(-1);
+        assert!(zv.truthy());
+
+        zv.set_double(0.0);
+        assert!(!zv.truthy());
+        zv.set_double(-0.0);
+        assert!(!zv.truthy());
+        zv.set_double(f64::NAN);
+        assert!(zv.truthy());
+        zv.set_double(f64::INFINITY);
+        assert!(zv.truthy());
+
+        zv.set_bool(false);
+        assert!(!zv.truthy());
+        zv.set_bool(true);
+        assert!(zv.truthy());
+
+        for (bytes, expected) in &[
+            (&b""[..], false),
+            (b"0", false),
+            (b"00", true),
+            (b"yes", true),
+        ] {
+            let mut buf = fake_string(bytes, 0, 0);
+            zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+            zv.u1.type_info = IS_STRING_EX;
+            assert_eq!(zv.truthy(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut zv = Zval::new();
+        assert_eq!(format!("{:?}", zv), "Null");
+
+        zv.set_long(5);
+        assert_eq!(format!("{:?}", zv), "Long(5)");
+
+        let mut buf = fake_string(&[0x00, 0xFF], 0, 0);
+        zv.value.str = buf.as_mut_ptr() as *mut zend_string;
+        zv.u1.type_info = IS_STRING_EX;
+        assert_eq!(format!("{:?}", zv), "Binary(b\"\\x00\\xff\", len: 2)");
+    }
+
+    #[test]
+    fn test_option_from_null() {
+        let zv = Zval::new();
+        assert_eq!(Option::<ZendLong>::try_from(&zv), Ok(None));
+    }
+
+    #[test]
+    fn test_option_from_value() {
+        let mut zv = Zval::new();
+        zv.set_long(5);
+        assert_eq!(Option::<ZendLong>::try_from(&zv), Ok(Some(5)));
+
+        zv.set_bool(true);
+        assert_eq!(
+            Option::<ZendLong>::try_from(&zv),
+            Err(Error::ZvalConversion)
+        );
+
+        // The error of the inner conversion is kept.
+        zv.set_long(300);
+        assert_eq!(
+            Option::<u8>::try_from(&zv),
+            Err(Error::IntegerOutOfRange("300".to_string(), "u8"))
+        );
+    }
+
+    #[test]
+    fn test_hash_table_from_value() {
+        let zv = Zval::from(());
+        assert_eq!(
+            ZendHashTable::try_from(&zv).err(),
+            Some(Error::ZvalConversion)
+        );
+    }
+
+    #[test]
+    fn test_option_into_zval() {
+        let zv = Zval::try_from(Option::<ZendLong>::None).unwrap();
+        assert!(zv.is_null());
+
+        let zv = Zval::try_from(Some(5 as ZendLong)).unwrap();
+        assert_eq!(zv.long(), Some(5));
+    }
+}
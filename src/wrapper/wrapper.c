@@ -10,6 +10,63 @@ void ext_php_rs_zend_string_release(zend_string *zs)
     zend_string_release(zs);
 }
 
+zend_string *ext_php_rs_zend_string_alloc(size_t len, bool persistent)
+{
+    zend_string *zs = zend_string_alloc(len, persistent);
+    ZSTR_VAL(zs)[len] = '\0';
+    return zs;
+}
+
+// Takes over the reference to `zs`. The string is reallocated in place if it is not shared,
+// otherwise it is copied and the reference is released. Interned strings are always copied
+// into a request string, even if they were interned persistently.
+zend_string *ext_php_rs_zend_string_append(zend_string *zs, const char *str, size_t len)
+{
+    size_t old_len = ZSTR_LEN(zs);
+    bool persistent = !ZSTR_IS_INTERNED(zs) && (GC_FLAGS(zs) & IS_STR_PERSISTENT);
+
+    zs = zend_string_extend(zs, old_len + len, persistent);
+    memcpy(ZSTR_VAL(zs) + old_len, str, len);
+    ZSTR_VAL(zs)[ZSTR_LEN(zs)] = '\0';
+    return zs;
+}
+
+zend_ulong ext_php_rs_zend_string_hash_val(zend_string *zs)
+{
+    return zend_string_hash_val(zs);
+}
+
+void ext_php_rs_smart_str_append_bytes(smart_str *dest, const char *str, size_t len)
+{
+    smart_str_appendl(dest, str, len);
+}
+
+void ext_php_rs_smart_str_append_long(smart_str *dest, zend_long num)
+{
+    smart_str_append_long(dest, num);
+}
+
+// Takes the string out of the buffer, leaving the buffer empty.
+zend_string *ext_php_rs_smart_str_extract(smart_str *str)
+{
+    zend_string *zs;
+
+    if (!str->s) {
+        return ZSTR_EMPTY_ALLOC();
+    }
+
+    smart_str_0(str);
+    zs = str->s;
+    str->s = NULL;
+    str->a = 0;
+    return zs;
+}
+
+void ext_php_rs_smart_str_free(smart_str *str)
+{
+    smart_str_free(str);
+}
+
 const char *ext_php_rs_php_build_id()
 {
     return ZEND_MODULE_BUILD_ID;
@@ -23,4 +80,262 @@ void *ext_php_rs_zend_object_alloc(size_t obj_size, zend_class_entry *ce)
 void ext_php_rs_zend_object_std_init(zend_object *object, zend_class_entry *ce)
 {
     zend_object_std_init(object, ce);
-}
\ No newline at end of file
+}
+
+zend_reference *ext_php_rs_zend_new_reference(zval *val)
+{
+    zval ref;
+    ZVAL_NEW_REF(&ref, val);
+    return Z_REF(ref);
+}
+
+zend_executor_globals *ext_php_rs_executor_globals()
+{
+#ifdef ZTS
+    return TSRMG_FAST_BULK(executor_globals_offset, zend_executor_globals *);
+#else
+    return &executor_globals;
+#endif
+}
+
+zend_string *ext_php_rs_var_serialize(zval *val)
+{
+    smart_str buf = {0};
+    php_serialize_data_t var_hash;
+
+    PHP_VAR_SERIALIZE_INIT(var_hash);
+    php_var_serialize(&buf, val, &var_hash);
+    PHP_VAR_SERIALIZE_DESTROY(var_hash);
+
+    smart_str_0(&buf);
+    return buf.s ? buf.s : ZSTR_EMPTY_ALLOC();
+}
+
+bool ext_php_rs_var_unserialize(zval *rv, const char *buf, size_t len, HashTable *allowed_classes, size_t *offset)
+{
+    const unsigned char *p = (const unsigned char *)buf;
+    php_unserialize_data_t var_hash;
+    zval *retval;
+    bool result;
+
+    PHP_VAR_UNSERIALIZE_INIT(var_hash);
+    if (allowed_classes) {
+        php_var_unserialize_set_allowed_classes(var_hash, allowed_classes);
+    }
+
+    // The value must be allocated by the unserializer, as back references into it are only
+    // valid until the unserializer is destroyed.
+    retval = var_tmp_var(&var_hash);
+    result = php_var_unserialize(retval, &p, p + len, &var_hash);
+    if (result) {
+        ZVAL_COPY(rv, retval);
+    }
+    *offset = (const char *)p - buf;
+
+    PHP_VAR_UNSERIALIZE_DESTROY(var_hash);
+    return result;
+}
+
+zend_string *ext_php_rs_json_encode(zval *val, int options, zend_long depth, int *error_code)
+{
+    smart_str buf = {0};
+
+    php_json_encode_ex(&buf, val, options, depth);
+    *error_code = JSON_G(error_code);
+
+    smart_str_0(&buf);
+    return buf.s ? buf.s : ZSTR_EMPTY_ALLOC();
+}
+
+int ext_php_rs_json_decode(zval *rv, const char *str, size_t len, zend_long options, zend_long depth)
+{
+    // The error code is only written when decoding fails, so it is reset as `json_decode`
+    // does.
+    JSON_G(error_code) = PHP_JSON_ERROR_NONE;
+    php_json_decode_ex(rv, str, len, options, depth);
+    return JSON_G(error_code);
+}
+
+zend_uchar ext_php_rs_is_numeric_string(const char *str, size_t len, zend_long *lval, double *dval, bool allow_errors, bool *trailing_data)
+{
+    return is_numeric_string_ex(str, len, lval, dval, allow_errors, NULL, trailing_data);
+}
+
+HashTable *ext_php_rs_zend_new_persistent_array(uint32_t size)
+{
+    HashTable *ht = pemalloc(sizeof(HashTable), 1);
+    zend_hash_init(ht, size, NULL, ZVAL_INTERNAL_PTR_DTOR, 1);
+    return ht;
+}
+
+void ext_php_rs_zend_persistent_array_destroy(HashTable *ht)
+{
+    zend_hash_destroy(ht);
+    pefree(ht, 1);
+}
+// The comparison functions below mirror those used by `sort` and `ksort` in
+// `ext/standard/array.c`, which are not exported.
+
+// Compares the original positions of two buckets, which are stored by `zend_hash_sort_ex`,
+// so that elements which compare equal keep their order.
+static int stable_sort_fallback(Bucket *a, Bucket *b)
+{
+    if (Z_EXTRA(a->val) > Z_EXTRA(b->val)) {
+        return 1;
+    } else if (Z_EXTRA(a->val) < Z_EXTRA(b->val)) {
+        return -1;
+    } else {
+        return 0;
+    }
+}
+
+#define RETURN_STABLE_SORT(a, b, result) do { \
+    int _result = (result); \
+    if (EXPECTED(_result)) { \
+        return _result; \
+    } \
+    return stable_sort_fallback((a), (b)); \
+} while (0)
+
+static int data_compare_regular(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, zend_compare(&a->val, &b->val));
+}
+
+static int data_compare_numeric(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, numeric_compare_function(&a->val, &b->val));
+}
+
+static int data_compare_string(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, string_compare_function(&a->val, &b->val));
+}
+
+static int data_compare_string_case(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, string_case_compare_function(&a->val, &b->val));
+}
+
+static int data_compare_locale_string(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, string_locale_compare_function(&a->val, &b->val));
+}
+
+static int natural_compare(Bucket *a, Bucket *b, bool fold_case)
+{
+    zend_string *tmp1, *tmp2;
+    zend_string *str1 = zval_get_tmp_string(&a->val, &tmp1);
+    zend_string *str2 = zval_get_tmp_string(&b->val, &tmp2);
+    int result = strnatcmp_ex(ZSTR_VAL(str1), ZSTR_LEN(str1), ZSTR_VAL(str2), ZSTR_LEN(str2), fold_case);
+
+    zend_tmp_string_release(tmp1);
+    zend_tmp_string_release(tmp2);
+    return result;
+}
+
+static int data_compare_natural(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, natural_compare(a, b, false));
+}
+
+static int data_compare_natural_case(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, natural_compare(a, b, true));
+}
+
+static int key_compare(Bucket *a, Bucket *b)
+{
+    zval first, second;
+
+    if (a->key == NULL && b->key == NULL) {
+        return (zend_long)a->h > (zend_long)b->h ? 1 : -1;
+    } else if (a->key && b->key) {
+        return zendi_smart_strcmp(a->key, b->key);
+    }
+
+    if (a->key) {
+        ZVAL_STR(&first, a->key);
+    } else {
+        ZVAL_LONG(&first, a->h);
+    }
+    if (b->key) {
+        ZVAL_STR(&second, b->key);
+    } else {
+        ZVAL_LONG(&second, b->h);
+    }
+    return zend_compare(&first, &second);
+}
+
+static int data_compare_key(Bucket *a, Bucket *b)
+{
+    RETURN_STABLE_SORT(a, b, key_compare(a, b));
+}
+
+void ext_php_rs_zend_hash_sort_values(HashTable *ht, zend_long flags, bool renumber)
+{
+    bucket_compare_func_t compare;
+
+    switch (flags & ~PHP_SORT_FLAG_CASE) {
+        case PHP_SORT_NUMERIC:
+            compare = data_compare_numeric;
+            break;
+        case PHP_SORT_STRING:
+            compare = flags & PHP_SORT_FLAG_CASE ? data_compare_string_case : data_compare_string;
+            break;
+        case PHP_SORT_NATURAL:
+            compare = flags & PHP_SORT_FLAG_CASE ? data_compare_natural_case : data_compare_natural;
+            break;
+        case PHP_SORT_LOCALE_STRING:
+            compare = data_compare_locale_string;
+            break;
+        case PHP_SORT_REGULAR:
+        default:
+            compare = data_compare_regular;
+            break;
+    }
+
+    zend_hash_sort(ht, compare, renumber);
+}
+
+void ext_php_rs_zend_hash_sort_keys(HashTable *ht)
+{
+    zend_hash_sort(ht, data_compare_key, false);
+}
+
+bool ext_php_rs_phpinfo_as_text()
+{
+    return sapi_module.phpinfo_as_text;
+}
+
+void ext_php_rs_php_info_print(const char *str, size_t len)
+{
+    php_output_write(str, len);
+}
+
+void ext_php_rs_module_set_globals(zend_module_entry *module, int *id, void *globals)
+{
+#ifdef ZTS
+    module->globals_id_ptr = id;
+#else
+    module->globals_ptr = globals;
+#endif
+}
+
+void *ext_php_rs_tsrm_globals(int id)
+{
+#ifdef ZTS
+    return TSRMG_BULK(id, void *);
+#else
+    return NULL;
+#endif
+}
+
+zval *ext_php_rs_property_default(zend_class_entry *ce, zend_property_info *info)
+{
+    if (info->flags & ZEND_ACC_STATIC) {
+        return &ce->default_static_members_table[info->offset];
+    }
+
+    return &ce->default_properties_table[OBJ_PROP_TO_NUM(info->offset)];
+}
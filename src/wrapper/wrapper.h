@@ -1,9 +1,40 @@
 #include "php.h"
+#include "SAPI.h"
 #include "ext/standard/info.h"
 #include "zend_exceptions.h"
+#include "zend_inheritance.h"
+#include "zend_interfaces.h"
+#include "zend_smart_str.h"
+#include "ext/standard/php_var.h"
+#include "ext/json/php_json.h"
+#include "ext/standard/php_array.h"
+#include "ext/standard/php_string.h"
 
 zend_string *ext_php_rs_zend_string_init(const char *str, size_t len, bool persistent);
 void ext_php_rs_zend_string_release(zend_string *zs);
+zend_string *ext_php_rs_zend_string_alloc(size_t len, bool persistent);
+zend_string *ext_php_rs_zend_string_append(zend_string *zs, const char *str, size_t len);
+zend_ulong ext_php_rs_zend_string_hash_val(zend_string *zs);
+void ext_php_rs_smart_str_append_bytes(smart_str *dest, const char *str, size_t len);
+void ext_php_rs_smart_str_append_long(smart_str *dest, zend_long num);
+zend_string *ext_php_rs_smart_str_extract(smart_str *str);
+void ext_php_rs_smart_str_free(smart_str *str);
 const char *ext_php_rs_php_build_id();
 void *ext_php_rs_zend_object_alloc(size_t obj_size, zend_class_entry *ce);
-void ext_php_rs_zend_object_std_init(zend_object *object, zend_class_entry *ce);
\ No newline at end of file
+void ext_php_rs_zend_object_std_init(zend_object *object, zend_class_entry *ce);
+zend_reference *ext_php_rs_zend_new_reference(zval *val);
+zend_executor_globals *ext_php_rs_executor_globals();
+zend_string *ext_php_rs_var_serialize(zval *val);
+bool ext_php_rs_var_unserialize(zval *rv, const char *buf, size_t len, HashTable *allowed_classes, size_t *offset);
+zend_string *ext_php_rs_json_encode(zval *val, int options, zend_long depth, int *error_code);
+int ext_php_rs_json_decode(zval *rv, const char *str, size_t len, zend_long options, zend_long depth);
+zend_uchar ext_php_rs_is_numeric_string(const char *str, size_t len, zend_long *lval, double *dval, bool allow_errors, bool *trailing_data);
+HashTable *ext_php_rs_zend_new_persistent_array(uint32_t size);
+void ext_php_rs_zend_persistent_array_destroy(HashTable *ht);
+void ext_php_rs_zend_hash_sort_values(HashTable *ht, zend_long flags, bool renumber);
+void ext_php_rs_zend_hash_sort_keys(HashTable *ht);
+bool ext_php_rs_phpinfo_as_text();
+void ext_php_rs_php_info_print(const char *str, size_t len);
+void ext_php_rs_module_set_globals(zend_module_entry *module, int *id, void *globals);
+void *ext_php_rs_tsrm_globals(int id);
+zval *ext_php_rs_property_default(zend_class_entry *ce, zend_property_info *info);
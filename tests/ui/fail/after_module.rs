@@ -0,0 +1,13 @@
+use ext_php_rs::{php::module::ModuleBuilder, php_function, php_module};
+
+#[php_module]
+pub fn get_module() -> ModuleBuilder {
+    ModuleBuilder::new("ext-name", "0.1.0")
+}
+
+#[php_function]
+pub fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+fn main() {}
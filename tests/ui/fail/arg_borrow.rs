@@ -0,0 +1,12 @@
+use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+
+// The object could be one of the arguments, so arguments cannot be used while it is borrowed
+// mutably.
+pub extern "C" fn set_name(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    let name = execute_data.get_arg::<&str>(0).unwrap_or_default();
+    if let Some(this) = execute_data.get_this_mut() {
+        let _ = this.set_property("name", name);
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use ext_php_rs::php::{execution_data::ExecutionData, types::zval::Zval};
+
+static mut ARGS: Vec<&Zval> = Vec::new();
+
+// Arguments borrow from the call frame, so they cannot be kept after the handler returns.
+pub extern "C" fn remember(execute_data: &mut ExecutionData, _retval: &mut Zval) {
+    if let Some(arg) = execute_data.get_arg::<&Zval>(0) {
+        unsafe { ARGS.push(arg) };
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use ext_php_rs::{php::args::RefArg, php_function};
+
+#[php_function(by_ref(valeu))]
+pub fn increment(mut value: RefArg) {
+    let _ = value.set(1);
+}
+
+pub fn reset(mut value: RefArg) {
+    let _ = value.set(0);
+}
+
+fn main() {}
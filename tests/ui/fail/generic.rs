@@ -0,0 +1,8 @@
+use ext_php_rs::php_function;
+
+#[php_function]
+pub fn first<T: Clone>(values: Vec<T>) -> Option<T> {
+    values.first().cloned()
+}
+
+fn main() {}
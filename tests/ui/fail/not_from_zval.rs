@@ -0,0 +1,10 @@
+use std::collections::VecDeque;
+
+use ext_php_rs::php_function;
+
+#[php_function]
+pub fn len(values: VecDeque<i64>) -> i64 {
+    values.len() as i64
+}
+
+fn main() {}
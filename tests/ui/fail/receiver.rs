@@ -0,0 +1,12 @@
+use ext_php_rs::php_function;
+
+pub struct Counter(i64);
+
+impl Counter {
+    #[php_function]
+    pub fn get(&self) -> i64 {
+        self.0
+    }
+}
+
+fn main() {}
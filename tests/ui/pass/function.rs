@@ -0,0 +1,27 @@
+use ext_php_rs::{
+    php::{args::RefArg, module::ModuleBuilder},
+    php_function, php_module,
+};
+
+#[php_function]
+pub fn add(a: i64, b: Option<i64>) -> i64 {
+    a + b.unwrap_or(1)
+}
+
+#[php_function(name = "ext_increment", by_ref(value))]
+pub fn increment(mut value: RefArg, by: Option<i64>) {
+    let next = value.val::<i64>().unwrap_or(0) + by.unwrap_or(1);
+    let _ = value.set(next);
+}
+
+#[php_function]
+pub fn names(names: Vec<String>) -> Option<String> {
+    names.into_iter().next()
+}
+
+#[php_module]
+pub fn get_module() -> ModuleBuilder {
+    ModuleBuilder::new("ext-name", "0.1.0")
+}
+
+fn main() {}